@@ -9,6 +9,7 @@ use structopt::StructOpt;
 use trace::trace_command;
 use wonnx::onnx::ModelProto;
 use wonnx::utils::{get_opset_version, OutputTensor, Shape};
+use wonnx::{Session, SessionConfig};
 use wonnx_preprocessing::shape_inference::{apply_dynamic_dimensions, infer_shapes};
 use wonnx_preprocessing::text::{get_lines, EncodedText};
 use wonnx_preprocessing::Tensor;
@@ -100,6 +101,8 @@ async fn run() -> Result<(), NNXError> {
         Command::Prepare(prepare_opt) => prepare_command(prepare_opt).await,
 
         Command::Infer(infer_opt) => infer_command(infer_opt).await,
+
+        Command::Profile(infer_opt) => profile_command(infer_opt).await,
     }
 }
 
@@ -175,6 +178,15 @@ fn print_output(
                         }
                     }
                 }
+                wonnx::utils::OutputTensor::F16(fs) => {
+                    for i in fs {
+                        if print_newlines {
+                            println!("{:.3}", i);
+                        } else {
+                            print!("{:.3} ", i);
+                        }
+                    }
+                }
                 wonnx::utils::OutputTensor::I32(ints) => {
                     for i in ints {
                         if print_newlines {
@@ -202,6 +214,24 @@ fn print_output(
                         }
                     }
                 }
+                wonnx::utils::OutputTensor::I8(ints) => {
+                    for i in ints {
+                        if print_newlines {
+                            println!("{}", i);
+                        } else {
+                            print!("{}", i);
+                        }
+                    }
+                }
+                wonnx::utils::OutputTensor::Bool(bools) => {
+                    for b in bools {
+                        if print_newlines {
+                            println!("{}", b);
+                        } else {
+                            print!("{}", b);
+                        }
+                    }
+                }
             }
         }
     }
@@ -422,6 +452,65 @@ async fn infer_command(infer_opt: InferOptions) -> Result<(), NNXError> {
     Ok(())
 }
 
+async fn profile_command(infer_opt: InferOptions) -> Result<(), NNXError> {
+    // Load the model
+    let model_path = infer_opt
+        .model
+        .clone()
+        .into_os_string()
+        .into_string()
+        .expect("invalid path");
+    let model = ModelProto::parse_from_bytes(
+        &std::fs::read(&model_path).expect("ONNX Model path not found."),
+    )
+    .expect("Could not deserialize the model");
+
+    let inference_input = InferenceInput::new(&infer_opt, &model)?;
+    let outputs = if infer_opt.output_name.is_empty() {
+        None
+    } else {
+        Some(infer_opt.output_name.clone())
+    };
+    let session_config = SessionConfig::new().with_outputs(outputs);
+    let session = Session::from_path_with_config(&model_path, &session_config).await?;
+
+    let input_refs = inference_input
+        .inputs
+        .iter()
+        .map(|(k, v)| (k.clone(), v.input_tensor()))
+        .collect();
+    let report = session.profile(&input_refs).await?;
+
+    let mut table = Table::new();
+    table.add_row(row![b->"Node", b->"Op", b->"Output shape", b->"Dispatch", b->"Time (ms)"]);
+    for node in &report.nodes {
+        let (x, y, z) = node.dispatch;
+        table.add_row(row![
+            node.node_name,
+            node.op_type,
+            node.output_shapes
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+            if node.on_host {
+                "host".to_string()
+            } else {
+                format!("{x}x{y}x{z}")
+            },
+            r->format!("{:.3}", node.duration.as_secs_f64() * 1000.0)
+        ]);
+    }
+    table.printstd();
+    println!(
+        "total: {:.3}ms ({} nodes, timed using {:?})",
+        report.total_duration().as_secs_f64() * 1000.0,
+        report.nodes.len(),
+        report.method
+    );
+    Ok(())
+}
+
 #[cfg(feature = "cpu")]
 mod cpu;
 
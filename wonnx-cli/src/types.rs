@@ -273,6 +273,9 @@ pub enum Command {
     /// Prints the dependencies of a particular output
     Trace(TraceOptions),
 
+    /// Perform inference once and print the time taken by each node on the GPU
+    Profile(InferOptions),
+
     /// Prepare a model by applying user-specified transformations. By default no transformations are applied and the input model is simply written to the output
     Prepare(PrepareOptions),
 }
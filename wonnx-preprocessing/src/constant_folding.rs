@@ -1,10 +1,11 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
 use protobuf::{ProtobufEnum, RepeatedField};
 use thiserror::Error;
 
 use wonnx::{
     constant_of_shape_output,
+    half::f16,
     onnx::{
         GraphProto, NodeProto, TensorProto, TensorShapeProto, TensorShapeProto_Dimension,
         TypeProto, TypeProto_Tensor, ValueInfoProto,
@@ -13,7 +14,7 @@ use wonnx::{
         model_with_opset, DataTypeError, InputTensor, NodeAttributes, OutputTensor, ScalarType,
         Shape,
     },
-    CompileError, GpuError, Session, SessionError,
+    wgpu, CompileError, GpuError, Session, SessionConfig, SessionError,
 };
 
 #[derive(Error, Debug)]
@@ -37,6 +38,7 @@ pub(crate) async fn calculate_constant_node_outputs<'a>(
     output_shapes: &[Shape],
     _initializers: &HashMap<String, Cow<'a, TensorProto>>,
     opset_version: i64,
+    device_queue: Option<&(Arc<wgpu::Device>, Arc<wgpu::Queue>)>,
 ) -> Result<Option<Vec<OutputTensor>>, ConstantFoldingError> {
     Ok(match node.get_op_type() {
         "Identity" | "Unsqueeze" | "Squeeze" | "Reshape" => {
@@ -48,9 +50,31 @@ pub(crate) async fn calculate_constant_node_outputs<'a>(
                     ConstantFoldingError::InvalidNode("to attribute missing for Cast ".to_string())
                 })? as i32)
                 .map_err(ConstantFoldingError::UnsupportedDataType)?;
-            let input_tensor = &inputs[0];
 
-            let output_tensor = match (input_tensor, cast_to_type) {
+            // Float16 values are cast through float32, boolean and int8 values through int32
+            let widened;
+            let input_tensor = match &inputs[0] {
+                InputTensor::F16(v) => {
+                    widened = InputTensor::F32(v.iter().map(|x| x.to_f32()).collect());
+                    &widened
+                }
+                InputTensor::Bool(v) => {
+                    widened = InputTensor::I32(v.iter().map(|x| *x as i32).collect());
+                    &widened
+                }
+                InputTensor::I8(v) => {
+                    widened = InputTensor::I32(v.iter().map(|x| *x as i32).collect());
+                    &widened
+                }
+                other => other,
+            };
+            let target_type = match cast_to_type {
+                ScalarType::F16 | ScalarType::Bool => ScalarType::F32,
+                ScalarType::I8 => ScalarType::I32,
+                other => other,
+            };
+
+            let output_tensor = match (input_tensor, target_type) {
                 (InputTensor::F32(v), ScalarType::F32) => OutputTensor::F32(v.to_vec()),
                 (InputTensor::F32(v), ScalarType::I64) => {
                     OutputTensor::I64(v.iter().map(|x| *x as i64).collect())
@@ -91,6 +115,20 @@ pub(crate) async fn calculate_constant_node_outputs<'a>(
                     OutputTensor::I32(v.iter().map(|x| *x as i32).collect())
                 }
                 (InputTensor::U8(v), ScalarType::U8) => OutputTensor::U8(v.to_vec()),
+                (InputTensor::F16(_) | InputTensor::Bool(_) | InputTensor::I8(_), _)
+                | (_, ScalarType::F16 | ScalarType::Bool | ScalarType::I8) => unreachable!(),
+            };
+            let output_tensor = match (output_tensor, cast_to_type) {
+                (OutputTensor::F32(v), ScalarType::F16) => {
+                    OutputTensor::F16(v.into_iter().map(f16::from_f32).collect())
+                }
+                (OutputTensor::F32(v), ScalarType::Bool) => {
+                    OutputTensor::Bool(v.into_iter().map(|x| x != 0.0).collect())
+                }
+                (OutputTensor::I32(v), ScalarType::I8) => {
+                    OutputTensor::I8(v.into_iter().map(|x| x as i8).collect())
+                }
+                (output_tensor, _) => output_tensor,
             };
 
             Some(vec![output_tensor])
@@ -161,7 +199,13 @@ pub(crate) async fn calculate_constant_node_outputs<'a>(
 
             let model = model_with_opset(graph, opset_version);
 
-            let session = match Session::from_model(model).await {
+            let config = match device_queue {
+                Some((device, queue)) => {
+                    SessionConfig::new().with_device_queue(device.clone(), queue.clone())
+                }
+                None => SessionConfig::new(),
+            };
+            let session = match Session::from_model_with_config(model, &config).await {
                 Ok(v) => v,
                 Err(e) => {
                     if let SessionError::GpuError(GpuError::CompileError {
@@ -0,0 +1,400 @@
+//! Inference sessions for models with dynamic (symbolic) input dimensions. The model is kept in its original form and
+//! prepared (shape inference, optimization and compilation) for each new combination of input shapes that is used for
+//! inference. Prepared sessions are kept in a cache so that subsequent inferences with the same input shapes are fast.
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use thiserror::Error;
+use wonnx::{
+    onnx::{ModelProto, TensorShapeProto_Dimension, TypeProto_oneof_value, ValueInfoProto},
+    request_device_queue,
+    utils::{
+        get_opset_version, DataTypeError, InputTensor, OpsetError, OutputTensor, ScalarType, Shape,
+    },
+    Backend, DeviceError, Session, SessionConfig, SessionError,
+};
+
+use crate::shape_inference::{
+    apply_dynamic_dimensions, infer_shapes_on_device, ShapeInferenceError,
+};
+
+/// The default number of prepared sessions (one per combination of input shapes) kept by a [DynamicSession]
+pub const DEFAULT_CACHE_CAPACITY: usize = 4;
+
+#[derive(Error, Debug)]
+pub enum DynamicSessionError {
+    #[error("session error: {0}")]
+    SessionError(#[from] SessionError),
+
+    #[error("shape inference failed: {0}")]
+    ShapeInferenceError(#[from] ShapeInferenceError),
+
+    #[error("unsupported data type encountered: {0}")]
+    DataTypeError(#[from] DataTypeError),
+
+    #[error("opset error: {0}")]
+    OpsetError(#[from] OpsetError),
+
+    #[error("could not obtain GPU device: {0}")]
+    DeviceError(#[from] DeviceError),
+
+    #[error("the model did not reference a specific version of the ONNX opset")]
+    UnknownOnnxOpsetVersion,
+
+    #[error("input '{0}' has dynamic dimensions, but no shape was provided for it")]
+    MissingInputShape(String),
+
+    #[error("shape {actual:?} provided for input '{input}' does not match the shape in the model ({expected})")]
+    InputShapeMismatch {
+        input: String,
+        expected: String,
+        actual: Vec<i64>,
+    },
+
+    #[error("dimension '{dimension}' is set to {first} by one input and to {second} by another")]
+    ConflictingDimension {
+        dimension: String,
+        first: i64,
+        second: i64,
+    },
+}
+
+/// An inference session for a model with dynamic input dimensions. Each time inference is performed with input shapes
+/// that have not been seen recently, the dynamic dimensions are bound to the provided shapes, shapes are inferred and a
+/// new [Session] is created. The most recently used sessions are cached. All sessions (as well as the sessions used to
+/// fold constants during shape inference) are created on the same GPU device.
+///
+/// # Examples
+///
+/// ```ignore
+/// let session = DynamicSession::from_model(model).await?;
+/// let input_shapes = HashMap::from([("input".to_string(), vec![2, 3, 224, 224])]);
+/// let outputs = session.run(&inputs, &input_shapes).await?;
+/// ```
+pub struct DynamicSession {
+    model: ModelProto,
+    config: SessionConfig,
+    onnx_opset_version: i64,
+    fold_constants: bool,
+    cache: Mutex<SessionCache>,
+
+    /// Held while a session is prepared, so that concurrent inferences with the same new input shapes prepare it once
+    preparing: futures_util::lock::Mutex<()>,
+}
+
+/// The input shapes for which a session was prepared (by input name)
+type ShapeSignature = BTreeMap<String, Vec<i64>>;
+
+/// Least-recently-used cache of prepared sessions
+struct SessionCache {
+    capacity: usize,
+    entries: VecDeque<(ShapeSignature, Arc<Session>)>,
+}
+
+impl SessionCache {
+    fn get(&mut self, signature: &ShapeSignature) -> Option<Arc<Session>> {
+        let index = self.entries.iter().position(|(s, _)| s == signature)?;
+        let entry = self.entries.remove(index)?;
+        let session = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(session)
+    }
+
+    fn insert(&mut self, signature: ShapeSignature, session: Arc<Session>) {
+        self.entries.retain(|(s, _)| s != &signature);
+        self.entries.push_front((signature, session));
+        self.entries.truncate(self.capacity);
+    }
+}
+
+impl DynamicSession {
+    /// Create a dynamic session for the model, using default [session config](SessionConfig).
+    pub async fn from_model(model: ModelProto) -> Result<DynamicSession, DynamicSessionError> {
+        Self::from_model_with_config(model, SessionConfig::new()).await
+    }
+
+    /// Create a dynamic session for the model. The provided [session config](SessionConfig) is used for each of the
+    /// sessions that is prepared. When the GPU backend is used and the config does not specify a device, a device is
+    /// requested here and used for all sessions.
+    pub async fn from_model_with_config(
+        model: ModelProto,
+        config: SessionConfig,
+    ) -> Result<DynamicSession, DynamicSessionError> {
+        let onnx_opset_version =
+            get_opset_version(&model)?.ok_or(DynamicSessionError::UnknownOnnxOpsetVersion)?;
+
+        let config = if config.backend == Backend::Gpu && config.device_queue.is_none() {
+            let (device, queue) = request_device_queue(&config.adapter_options).await?;
+            config.with_device_queue(device, queue)
+        } else {
+            config
+        };
+
+        Ok(DynamicSession {
+            model,
+            // Constant folding during shape inference performs inference on the GPU
+            fold_constants: config.backend == Backend::Gpu,
+            config,
+            onnx_opset_version,
+            cache: Mutex::new(SessionCache {
+                capacity: DEFAULT_CACHE_CAPACITY,
+                entries: VecDeque::new(),
+            }),
+            preparing: futures_util::lock::Mutex::new(()),
+        })
+    }
+
+    /// Sets the maximum number of prepared sessions to keep (at least one) and returns [Self].
+    pub fn with_cache_capacity(self, capacity: usize) -> Self {
+        self.cache.lock().unwrap().capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets whether constant nodes should be folded during shape inference and returns [Self].
+    pub fn with_fold_constants(mut self, fold_constants: bool) -> Self {
+        self.fold_constants = fold_constants;
+        self
+    }
+
+    /// Perform inference given the inputs and their shapes. Shapes only need to be provided for inputs that have dynamic
+    /// dimensions in the model.
+    pub async fn run<'a>(
+        &self,
+        inputs: &HashMap<String, InputTensor<'a>>,
+        input_shapes: &HashMap<String, Vec<i64>>,
+    ) -> Result<HashMap<String, OutputTensor>, DynamicSessionError> {
+        let session = self.session_for(input_shapes).await?;
+        Ok(session.run(inputs).await?)
+    }
+
+    /// Returns a session prepared for the specified input shapes, either from the cache or by preparing a new one
+    pub async fn session_for(
+        &self,
+        input_shapes: &HashMap<String, Vec<i64>>,
+    ) -> Result<Arc<Session>, DynamicSessionError> {
+        let (signature, dimensions) = self.bind_dimensions(input_shapes)?;
+        if let Some(session) = self.cache.lock().unwrap().get(&signature) {
+            return Ok(session);
+        }
+
+        // Another inference may have prepared the session while waiting
+        let _preparing = self.preparing.lock().await;
+        if let Some(session) = self.cache.lock().unwrap().get(&signature) {
+            return Ok(session);
+        }
+
+        log::info!("preparing session for input shapes {:?}", signature);
+        let mut model = self.model.clone();
+        let graph = model.mut_graph();
+        for input in graph.mut_input() {
+            if let Some(dims) = signature.get(input.get_name()) {
+                let data_type =
+                    ScalarType::from_i32(input.get_field_type().get_tensor_type().get_elem_type())?;
+                input.set_shape(&Shape::from(data_type, dims));
+            }
+        }
+        apply_dynamic_dimensions(graph, &dimensions);
+
+        // Shapes that still depend on unbound dimensions are inferred again
+        graph
+            .mut_value_info()
+            .retain(|value_info| value_info.get_shape().is_ok());
+        infer_shapes_on_device(
+            graph,
+            self.fold_constants,
+            self.onnx_opset_version,
+            &self.config.operator_registry,
+            self.config.device_queue.as_ref(),
+        )
+        .await?;
+
+        // Outputs with dynamic dimensions take their shape from the inferred value shapes
+        let inferred_shapes: HashMap<String, Shape> = graph
+            .get_value_info()
+            .iter()
+            .filter_map(|vi| Some((vi.get_name().to_string(), vi.get_shape().ok()?)))
+            .collect();
+        for output in graph.mut_output() {
+            if output.get_shape().is_err() {
+                if let Some(shape) = inferred_shapes.get(output.get_name()) {
+                    output.set_shape(shape);
+                }
+            }
+        }
+
+        let session = Arc::new(Session::from_model_with_config(model, &self.config).await?);
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(signature, session.clone());
+        Ok(session)
+    }
+
+    /// Checks the provided input shapes against the model and determines the values of the dynamic dimensions
+    fn bind_dimensions(
+        &self,
+        input_shapes: &HashMap<String, Vec<i64>>,
+    ) -> Result<(ShapeSignature, HashMap<String, i64>), DynamicSessionError> {
+        let graph = self.model.get_graph();
+        let mut signature = ShapeSignature::new();
+        let mut dimensions = HashMap::<String, i64>::new();
+
+        for input in graph.get_input() {
+            let name = input.get_name();
+
+            // Inputs that have an initializer do not need to be provided
+            if graph.get_initializer().iter().any(|i| i.get_name() == name) {
+                continue;
+            }
+
+            let model_dims = input_dimensions(input);
+            let provided_dims = match input_shapes.get(name) {
+                Some(dims) => dims,
+                None if input.get_shape().is_ok() => continue,
+                None => return Err(DynamicSessionError::MissingInputShape(name.to_string())),
+            };
+
+            let mismatch = || DynamicSessionError::InputShapeMismatch {
+                input: name.to_string(),
+                expected: model_dims
+                    .iter()
+                    .map(|dim| {
+                        if dim.has_dim_param() {
+                            dim.get_dim_param().to_string()
+                        } else {
+                            dim.get_dim_value().to_string()
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join("x"),
+                actual: provided_dims.clone(),
+            };
+
+            if model_dims.len() != provided_dims.len() {
+                return Err(mismatch());
+            }
+
+            for (dim, value) in model_dims.iter().zip(provided_dims.iter()) {
+                if *value <= 0 {
+                    return Err(mismatch());
+                }
+
+                if dim.has_dim_value() && dim.get_dim_value() != *value {
+                    return Err(mismatch());
+                } else if dim.has_dim_param() {
+                    let param = dim.get_dim_param();
+                    match dimensions.get(param) {
+                        Some(first) if first != value => {
+                            return Err(DynamicSessionError::ConflictingDimension {
+                                dimension: param.to_string(),
+                                first: *first,
+                                second: *value,
+                            })
+                        }
+                        _ => {
+                            dimensions.insert(param.to_string(), *value);
+                        }
+                    }
+                }
+            }
+
+            signature.insert(name.to_string(), provided_dims.clone());
+        }
+
+        Ok((signature, dimensions))
+    }
+}
+
+/// Returns the dimensions of a (tensor) value as they are specified in the model
+fn input_dimensions(value_info: &ValueInfoProto) -> Vec<TensorShapeProto_Dimension> {
+    match &value_info.get_field_type().value {
+        Some(TypeProto_oneof_value::tensor_type(tensor_type)) => {
+            tensor_type.get_shape().get_dim().to_vec()
+        }
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use wonnx::{
+        onnx::ModelProto,
+        utils::{graph, model, node, tensor, OutputTensor},
+        Backend, SessionConfig,
+    };
+
+    use super::{DynamicSession, DynamicSessionError};
+
+    /// Returns a model that calculates Y=Relu(X) where X and Y have shape [N, 2]
+    fn dynamic_relu_model() -> ModelProto {
+        let mut model = model(graph(
+            vec![tensor("X", &[1, 2])],
+            vec![tensor("Y", &[1, 2])],
+            vec![],
+            vec![],
+            vec![node(vec!["X"], vec!["Y"], "relu", "Relu", vec![])],
+        ));
+
+        let graph = model.mut_graph();
+        for value_info in graph.input.iter_mut().chain(graph.output.iter_mut()) {
+            let dims = value_info
+                .mut_field_type()
+                .mut_tensor_type()
+                .mut_shape()
+                .mut_dim();
+            dims[0].set_dim_param("N".to_string());
+        }
+        model
+    }
+
+    #[test]
+    fn test_dynamic_session() {
+        let config = SessionConfig::new().with_backend(Backend::Cpu);
+        let session = pollster::block_on(DynamicSession::from_model_with_config(
+            dynamic_relu_model(),
+            config,
+        ))
+        .unwrap()
+        .with_cache_capacity(1);
+
+        for n in [1, 3, 3, 1] {
+            let data: Vec<f32> = (0..(n * 2)).map(|x| x as f32 - 2.0).collect();
+            let inputs = HashMap::from([("X".to_string(), data.as_slice().into())]);
+            let input_shapes = HashMap::from([("X".to_string(), vec![n, 2])]);
+            let result = pollster::block_on(session.run(&inputs, &input_shapes)).unwrap();
+            assert_eq!(
+                result["Y"],
+                OutputTensor::F32(data.iter().map(|x| x.max(0.0)).collect())
+            );
+        }
+
+        assert_eq!(session.cache.lock().unwrap().entries.len(), 1);
+    }
+
+    #[test]
+    fn test_dynamic_session_invalid_shapes() {
+        let config = SessionConfig::new().with_backend(Backend::Cpu);
+        let session = pollster::block_on(DynamicSession::from_model_with_config(
+            dynamic_relu_model(),
+            config,
+        ))
+        .unwrap();
+
+        let result = pollster::block_on(session.session_for(&HashMap::new()));
+        assert!(matches!(
+            result,
+            Err(DynamicSessionError::MissingInputShape(_))
+        ));
+
+        let input_shapes = HashMap::from([("X".to_string(), vec![2, 3])]);
+        let result = pollster::block_on(session.session_for(&input_shapes));
+        assert!(matches!(
+            result,
+            Err(DynamicSessionError::InputShapeMismatch { .. })
+        ));
+    }
+}
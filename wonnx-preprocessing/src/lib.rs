@@ -2,6 +2,7 @@ use ndarray::ArrayBase;
 use wonnx::utils::InputTensor;
 
 pub mod constant_folding;
+pub mod dynamic_session;
 pub mod image;
 pub mod shape_inference;
 pub mod text;
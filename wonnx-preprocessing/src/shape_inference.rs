@@ -1,4 +1,4 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
 use protobuf::ProtobufEnum;
 use thiserror::Error;
@@ -10,6 +10,7 @@ use wonnx::{
     utils::{
         AttributeNotFoundError, DataTypeError, InputTensor, NodeAttributes, ScalarType, Shape,
     },
+    wgpu, CompileError, EinsumEquation, OperatorRegistry,
 };
 
 use crate::constant_folding::{calculate_constant_node_outputs, ConstantFoldingError};
@@ -96,7 +97,7 @@ fn apply_dynamic_dimensions_value(
 
         for (idx, dim) in dims.iter_mut().enumerate() {
             if let Some(new_dim_value) = dynamic_dims.get(dim.get_dim_param()) {
-                println!(
+                log::info!(
                     "Setting dimension param {idx} ({}) to value {new_dim_value} for {name}",
                     dim.get_dim_param()
                 );
@@ -190,6 +191,9 @@ pub enum ShapeInferenceError {
     #[error("constant folding failed: {0}")]
     #[from(ConstantFoldingError)]
     ConstantFoldingError(ConstantFoldingError),
+
+    #[error("shape inference for custom operator in node {0} failed: {1}")]
+    CustomOperatorError(String, CompileError),
 }
 
 /// Replaces nodes of op type Constant with an initializer
@@ -263,6 +267,35 @@ pub async fn infer_shapes(
     graph: &mut GraphProto,
     should_fold_constants: bool,
     opset_version: i64,
+) -> Result<(), ShapeInferenceError> {
+    infer_shapes_with_registry(
+        graph,
+        should_fold_constants,
+        opset_version,
+        &OperatorRegistry::default(),
+    )
+    .await
+}
+
+/// Performs shape inference like [infer_shapes], using the shape inference functions of the custom operators in the
+/// specified registry for nodes they implement. Nodes implemented by a custom operator are never folded.
+pub async fn infer_shapes_with_registry(
+    graph: &mut GraphProto,
+    should_fold_constants: bool,
+    opset_version: i64,
+    registry: &OperatorRegistry,
+) -> Result<(), ShapeInferenceError> {
+    infer_shapes_on_device(graph, should_fold_constants, opset_version, registry, None).await
+}
+
+/// Performs shape inference like [infer_shapes_with_registry], folding constants on the specified GPU device (if any)
+/// rather than on a newly requested one.
+pub(crate) async fn infer_shapes_on_device(
+    graph: &mut GraphProto,
+    should_fold_constants: bool,
+    opset_version: i64,
+    registry: &OperatorRegistry,
+    device_queue: Option<&(Arc<wgpu::Device>, Arc<wgpu::Queue>)>,
 ) -> Result<(), ShapeInferenceError> {
     let mut foldable_nodes: Vec<String> = vec![];
     let mut folded_node_indexes: Vec<usize> = vec![];
@@ -281,6 +314,7 @@ pub async fn infer_shapes(
             .map(|x| (x.get_name().to_string(), Cow::Borrowed(x))),
     );
 
+    let missing_input_shape = Shape::from(ScalarType::F32, &[]);
     for (node_index, node) in graph.node.iter().enumerate() {
         log::debug!(
             "node: {} {} inputs {} -> outputs {}",
@@ -291,24 +325,36 @@ pub async fn infer_shapes(
         );
 
         // Do shape inference if this node has at least one output for which the shape is not yet known
-        if node
-            .get_output()
-            .iter()
-            .any(|output_name| !shapes.contains_key(output_name.as_str()))
-        {
+        if node.get_output().iter().any(|output_name| {
+            !output_name.is_empty() && !shapes.contains_key(output_name.as_str())
+        }) {
             log::debug!("node needs shape inference: {}", node.get_name());
 
+            // Optional inputs that are not provided (i.e. have an empty name) get an empty placeholder shape
             let input_shapes: Vec<&Shape> = node
                 .get_input()
                 .iter()
                 .map(|name| {
+                    if name.is_empty() {
+                        return Ok(&missing_input_shape);
+                    }
                     shapes
                         .get(name)
                         .ok_or_else(|| ShapeInferenceError::MissingInputShape(name.clone()))
                 })
                 .collect::<Result<_, ShapeInferenceError>>()?;
 
-            let output_shapes = infer_output_shapes(node, &input_shapes, &initializers)?;
+            let custom_operator = registry.get(node);
+            let output_shapes = match custom_operator {
+                Some(operator) => {
+                    operator
+                        .infer_output_shapes(node, &input_shapes)
+                        .map_err(|e| {
+                            ShapeInferenceError::CustomOperatorError(node.get_name().to_string(), e)
+                        })?
+                }
+                None => infer_output_shapes(node, &input_shapes, &initializers)?,
+            };
 
             // Check inferred shapes
             for (output_index, shape) in output_shapes.iter().enumerate() {
@@ -335,8 +381,11 @@ pub async fn infer_shapes(
                 panic!("number of outputs inferred does not match node output count");
             }
 
-            // Cache the inferred shapes and write to model
+            // Cache the inferred shapes and write to model (optional outputs that are not used have an empty name)
             for (output_idx, output_name) in node.get_output().iter().enumerate() {
+                if output_name.is_empty() {
+                    continue;
+                }
                 let output_shape = &output_shapes[output_idx];
                 shapes.insert(output_name.clone(), output_shape.clone());
                 let mut vip = ValueInfoProto::new();
@@ -365,14 +414,16 @@ pub async fn infer_shapes(
             }
 
             // Can we fold the node altogether?
-            let can_fold = should_fold_constants && {
+            let can_fold = should_fold_constants && custom_operator.is_none() && {
                 let all_inputs_are_constant = node
                     .input
                     .iter()
                     .all(|input_name| initializers.contains_key(input_name));
                 let is_known_shape_node =
                     node.get_op_type() == "Shape" && shapes.contains_key(&node.input[0]);
-                all_inputs_are_constant || is_known_shape_node
+                // Nodes with optional outputs that are not used (i.e. have an empty name) are not folded
+                let all_outputs_are_named = node.output.iter().all(|name| !name.is_empty());
+                (all_inputs_are_constant || is_known_shape_node) && all_outputs_are_named
             };
 
             if can_fold {
@@ -405,6 +456,7 @@ pub async fn infer_shapes(
                     &output_shapes,
                     &initializers,
                     opset_version,
+                    device_queue,
                 )
                 .await
                 .map_err(ShapeInferenceError::ConstantFoldingError)?
@@ -484,7 +536,7 @@ pub(crate) fn infer_output_shapes(
         | (
             "Identity" | "Sqrt" | "Relu" | "LeakyRelu" | "Abs" | "Acos" | "Acosh" | "Asin" | "Sin"
             | "Asinh" | "Atan" | "Atanh" | "Cos" | "Cosh" | "Elu" | "Erf" | "Exp" | "Log" | "Neg"
-            | "Ceil" | "Floor" | "Reciprocal" | "Celu" | "Sign",
+            | "Ceil" | "Floor" | "Reciprocal" | "Celu" | "Sign" | "Not",
             1,
             1,
         ) => Ok(vec![input_shapes[0].clone()]),
@@ -509,6 +561,25 @@ pub(crate) fn infer_output_shapes(
             Ok(vec![output_shape])
         }
 
+        ("Einsum", 1.., 1) => {
+            // https://github.com/onnx/onnx/blob/main/docs/Operators.md#Einsum
+            let equation: String = node
+                .get_attribute_value("equation", None)
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+            let input_ranks: Vec<usize> = input_shapes.iter().map(|s| s.rank()).collect();
+            let input_dims: Vec<&[u64]> = input_shapes.iter().map(|s| s.dims.as_slice()).collect();
+            let output_dims = EinsumEquation::parse(&equation, &input_ranks)
+                .and_then(|equation| equation.output_dims(&input_dims))
+                .map_err(|e| {
+                    ShapeInferenceError::InvalidNode(
+                        node.get_name().to_string(),
+                        format!("invalid equation '{}' for Einsum: {}", equation, e),
+                    )
+                })?;
+            let output_dims: Vec<i64> = output_dims.iter().map(|d| *d as i64).collect();
+            Ok(vec![Shape::from(input_shapes[0].data_type, &output_dims)])
+        }
+
         ("Flatten", 1, 1) => {
             let axis: usize = {
                 let a = node.get_attribute_value("axis", Some(1)).unwrap();
@@ -591,6 +662,63 @@ pub(crate) fn infer_output_shapes(
             )])
         }
 
+        ("GatherElements", 2, 1) => {
+            // https://github.com/onnx/onnx/blob/main/docs/Operators.md#GatherElements
+            // The output has the shape (but not the data type) of the indices
+            if input_shapes[0].rank() != input_shapes[1].rank() {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    "indices must have the same rank as the data".to_string(),
+                ));
+            }
+            let dims: Vec<i64> = input_shapes[1].dims.iter().map(|d| *d as i64).collect();
+            Ok(vec![Shape::from(input_shapes[0].data_type, &dims)])
+        }
+
+        ("GatherND", 2, 1) => {
+            // https://github.com/onnx/onnx/blob/main/docs/Operators.md#GatherND
+            let (data_shape, indices_shape) = (input_shapes[0], input_shapes[1]);
+            let batch_dims = node
+                .get_attribute_value("batch_dims", Some(0))
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+            let (r, q) = (data_shape.rank(), indices_shape.rank());
+            if batch_dims < 0 || batch_dims as usize >= r.min(q) {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    format!(
+                        "batch_dims ({}) must be less than the rank of the data and indices",
+                        batch_dims
+                    ),
+                ));
+            }
+            let batch_dims = batch_dims as usize;
+            let index_depth = indices_shape.dim(q - 1) as usize;
+            if index_depth < 1 || batch_dims + index_depth > r {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    format!(
+                        "the last dimension of the indices ({}) must be between 1 and the rank of the data minus batch_dims ({})",
+                        index_depth,
+                        r - batch_dims
+                    ),
+                ));
+            }
+
+            // The output holds a slice of the data for each tuple of indices
+            let dims: Vec<i64> = indices_shape.dims[..(q - 1)]
+                .iter()
+                .chain(data_shape.dims[(batch_dims + index_depth)..].iter())
+                .map(|d| *d as i64)
+                .collect();
+            Ok(vec![Shape::from(data_shape.data_type, &dims)])
+        }
+
+        ("ScatterElements" | "ScatterND", 3, 1) => {
+            // https://github.com/onnx/onnx/blob/main/docs/Operators.md#ScatterND
+            // The output has the shape of the data
+            Ok(vec![input_shapes[0].clone()])
+        }
+
         ("Shape", 1, 1) => {
             let rank = input_shapes[0].rank() as i64;
             let mut start: i64 = node.get_attribute_value("start", Some(0)).unwrap();
@@ -764,6 +892,200 @@ pub(crate) fn infer_output_shapes(
             )])
         }
 
+        ("ArgMax" | "ArgMin", 1, 1) => {
+            // https://github.com/onnx/onnx/blob/main/docs/Operators.md#ArgMax
+            let input_shape = input_shapes[0];
+            let rank = input_shape.rank() as i64;
+            let axis = node
+                .get_attribute_value("axis", Some(0))
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+            if axis < -rank || axis >= rank {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    format!("axis {} is out of bounds for input of rank {}", axis, rank),
+                ));
+            }
+            let axis = if axis < 0 { rank + axis } else { axis } as usize;
+            let keep_dims = node
+                .get_attribute_value("keepdims", Some(1))
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+
+            let mut output_dims: Vec<i64> = input_shape.dims.iter().map(|d| *d as i64).collect();
+            if keep_dims == 1 {
+                output_dims[axis] = 1;
+            } else {
+                output_dims.remove(axis);
+            }
+            Ok(vec![Shape::from(ScalarType::I64, &output_dims)])
+        }
+
+        ("TopK", 1..=2, 2) => {
+            // https://github.com/onnx/onnx/blob/main/docs/Operators.md#TopK
+            // Note: up to version 10, k is an attribute; from version 10 it is the second input
+            let input_shape = input_shapes[0];
+            let rank = input_shape.rank() as i64;
+            let k = if node.get_input().len() == 1 {
+                node.get_attribute_value::<i64>("k", None)
+                    .map_err(ShapeInferenceError::MissingAttribute)?
+            } else {
+                match static_initializer_value_i64(initializers, &node.get_input()[1])? {
+                    [k] => *k,
+                    _ => {
+                        return Err(ShapeInferenceError::InvalidNode(
+                            node.get_name().to_string(),
+                            "K input must contain exactly one value".to_string(),
+                        ))
+                    }
+                }
+            };
+            let axis = node
+                .get_attribute_value("axis", Some(-1))
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+            if axis < -rank || axis >= rank {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    format!("axis {} is out of bounds for input of rank {}", axis, rank),
+                ));
+            }
+            let axis = if axis < 0 { rank + axis } else { axis } as usize;
+            if k < 1 || k as usize > input_shape.dim(axis) as usize {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    format!(
+                        "k ({}) must be between 1 and the size of the axis ({})",
+                        k,
+                        input_shape.dim(axis)
+                    ),
+                ));
+            }
+
+            let mut output_dims: Vec<i64> = input_shape.dims.iter().map(|d| *d as i64).collect();
+            output_dims[axis] = k;
+            Ok(vec![
+                Shape::from(input_shape.data_type, &output_dims),
+                Shape::from(ScalarType::I64, &output_dims),
+            ])
+        }
+
+        ("NonMaxSuppression", 2..=5, 1) => {
+            // https://github.com/onnx/onnx/blob/main/docs/Operators.md#NonMaxSuppression
+            // The number of selected boxes depends on the data. The output is sized for the maximum number of boxes that
+            // can be selected (at most max_output_boxes_per_class for each batch and class, when that is known).
+            let (boxes_shape, scores_shape) = (input_shapes[0], input_shapes[1]);
+            if boxes_shape.rank() != 3 || scores_shape.rank() != 3 {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    "boxes and scores must have rank 3".to_string(),
+                ));
+            }
+            let box_count = boxes_shape.dim(1) as i64;
+            let max_boxes_per_class = match node.get_input().get(2) {
+                Some(name) if !name.is_empty() => {
+                    match static_initializer_value_i64(initializers, name) {
+                        Ok([max_boxes]) => (*max_boxes).clamp(0, box_count),
+                        _ => box_count,
+                    }
+                }
+                _ => 0,
+            };
+            let max_selected =
+                (scores_shape.dim(0) * scores_shape.dim(1)) as i64 * max_boxes_per_class;
+            Ok(vec![Shape::from(ScalarType::I64, &[max_selected, 3])])
+        }
+
+        ("RoiAlign", 3, 1) => {
+            // https://github.com/onnx/onnx/blob/main/docs/Operators.md#RoiAlign
+            let (x_shape, rois_shape) = (input_shapes[0], input_shapes[1]);
+            if x_shape.rank() != 4 || rois_shape.rank() != 2 {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    "X must have rank 4 and rois must have rank 2".to_string(),
+                ));
+            }
+            let output_height = node
+                .get_attribute_value("output_height", Some(1))
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+            let output_width = node
+                .get_attribute_value("output_width", Some(1))
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+            Ok(vec![Shape::from(
+                x_shape.data_type,
+                &[
+                    rois_shape.dim(0) as i64,
+                    x_shape.dim(1) as i64,
+                    output_height,
+                    output_width,
+                ],
+            )])
+        }
+
+        (
+            "Equal" | "Greater" | "GreaterOrEqual" | "Less" | "LessOrEqual" | "And" | "Or" | "Xor",
+            2,
+            1,
+        ) => {
+            if let Some(mut output_shape) =
+                Shape::multi_broadcast(&[input_shapes[0].clone(), input_shapes[1].clone()])
+            {
+                output_shape.data_type = ScalarType::Bool;
+                Ok(vec![output_shape])
+            } else {
+                Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    format!(
+                        "two inputs (left {} shape: {}, right {} shape: {}) must be broadcastable",
+                        node.get_input()[0],
+                        node.get_input()[1],
+                        input_shapes[0],
+                        input_shapes[1]
+                    ),
+                ))
+            }
+        }
+
+        ("Where", 3, 1) => {
+            // The condition is broadcast together with the values, but has a different (boolean) type
+            let data_type = input_shapes[1].data_type;
+            let shapes: Vec<Shape> = input_shapes
+                .iter()
+                .map(|shape| {
+                    Shape::from(
+                        data_type,
+                        &shape.dims.iter().map(|d| *d as i64).collect::<Vec<_>>(),
+                    )
+                })
+                .collect();
+            Shape::multi_broadcast(&shapes)
+                .filter(|_| input_shapes[2].data_type == data_type)
+                .map(|output_shape| vec![output_shape])
+                .ok_or_else(|| {
+                    ShapeInferenceError::InvalidNode(
+                        node.get_name().to_string(),
+                        format!(
+                            "inputs (condition shape: {}, X shape: {}, Y shape: {}) must be broadcastable and X and Y must have the same type",
+                            input_shapes[0], input_shapes[1], input_shapes[2]
+                        ),
+                    )
+                })
+        }
+
+        ("QuantizeLinear", 2..=3, 1) => {
+            // The output type is that of the zero point, which defaults to uint8
+            let data_type = input_shapes
+                .get(2)
+                .map(|zero_point| zero_point.data_type)
+                .unwrap_or(ScalarType::U8);
+            let mut output_shape = input_shapes[0].clone();
+            output_shape.data_type = data_type;
+            Ok(vec![output_shape])
+        }
+
+        ("DequantizeLinear", 2..=3, 1) => {
+            let mut output_shape = input_shapes[0].clone();
+            output_shape.data_type = input_shapes[1].data_type;
+            Ok(vec![output_shape])
+        }
+
         ("Sub" | "Pow" | "Add" | "Div" | "Mul" | "Mod", 2, 1) => {
             if let Some(output_shape) =
                 Shape::multi_broadcast(&[input_shapes[0].clone(), input_shapes[1].clone()])
@@ -793,13 +1115,14 @@ pub(crate) fn infer_output_shapes(
             let use_dilation = true;
             let require_kernel_shape = matches!(node.get_op_type(), "MaxPool" | "AveragePool");
             let input_shape = input_shapes[0];
-            if input_shape.rank() < 2 {
+            if input_shape.rank() < 3 {
                 return Err(ShapeInferenceError::InvalidNode(
                     node.get_name().to_string(),
-                    "input shape must have at least two dimensions".to_string(),
+                    "input shape must have at least one spatial dimension".to_string(),
                 ));
             }
 
+            // Any number of spatial dimensions (i.e. 1D, 2D and 3D convolution and pooling)
             let num_input_dims = input_shape.rank() - 2;
 
             // Obtain dilations info
@@ -883,7 +1206,7 @@ pub(crate) fn infer_output_shapes(
                     .get_attribute_value("auto_pad", Some(String::from("VALID")))
                     .unwrap();
 
-                if auto_pad != "VALID" {
+                if auto_pad == "SAME_UPPER" || auto_pad == "SAME_LOWER" {
                     for i in 0..num_input_dims {
                         let mut residual: i64 = 0;
                         let stride = strides[i];
@@ -961,6 +1284,99 @@ pub(crate) fn infer_output_shapes(
                 .collect())
         }
 
+        ("ConvTranspose", 2..=3, 1) => {
+            // https://github.com/onnx/onnx/blob/main/docs/Operators.md#ConvTranspose
+            let input_shape = input_shapes[0];
+            let weight_shape = input_shapes[1];
+            if input_shape.rank() < 3 || weight_shape.rank() != input_shape.rank() {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    "input and weights must have the same rank of at least three".to_string(),
+                ));
+            }
+            let num_input_dims = input_shape.rank() - 2;
+
+            // Obtain the spatial attributes, which all have one value per spatial dimension
+            let spatial_attribute = |name: &str, default: i64| {
+                let values: Vec<i64> = node
+                    .get_attribute_value(name, Some(vec![default; num_input_dims]))
+                    .map_err(ShapeInferenceError::MissingAttribute)?;
+                if values.len() != num_input_dims {
+                    return Err(ShapeInferenceError::InvalidNode(
+                        node.get_name().to_string(),
+                        format!("attribute {name} has incorrect size"),
+                    ));
+                }
+                Ok(values)
+            };
+            let strides = spatial_attribute("strides", 1)?;
+            let dilations = spatial_attribute("dilations", 1)?;
+            let output_padding = spatial_attribute("output_padding", 0)?;
+            let kernel_shape: Vec<i64> = node
+                .get_attribute_value(
+                    "kernel_shape",
+                    Some(weight_shape.dims[2..].iter().map(|d| *d as i64).collect()),
+                )
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+            if kernel_shape.len() != num_input_dims {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    "kernel shape rank must be equal to input rank".to_string(),
+                ));
+            }
+
+            // The weights are shaped [C, M / group, k1, k2, ...]
+            let group = node.get_attribute_value("group", Some(1)).unwrap();
+            let mut output_shape: Vec<i64> = vec![
+                input_shape.dim(0) as i64,
+                weight_shape.dim(1) as i64 * group,
+            ];
+
+            let auto_pad = node
+                .get_attribute_value("auto_pad", Some(String::from("NOTSET")))
+                .unwrap();
+            if node.has_attribute("output_shape") {
+                // The output shape may be specified with or without the batch and channel dimensions
+                let explicit_shape: Vec<i64> = node
+                    .get_attribute_value("output_shape", None)
+                    .map_err(ShapeInferenceError::MissingAttribute)?;
+                if explicit_shape.len() < num_input_dims {
+                    return Err(ShapeInferenceError::InvalidNode(
+                        node.get_name().to_string(),
+                        "attribute output_shape has incorrect size".to_string(),
+                    ));
+                }
+                output_shape.extend(&explicit_shape[(explicit_shape.len() - num_input_dims)..]);
+            } else if auto_pad == "SAME_UPPER" || auto_pad == "SAME_LOWER" {
+                output_shape.extend(
+                    (0..num_input_dims).map(|i| input_shape.dim(2 + i) as i64 * strides[i]),
+                );
+            } else {
+                let pads = if auto_pad == "VALID" {
+                    vec![0; num_input_dims * 2]
+                } else {
+                    node.get_attribute_value("pads", Some(vec![0; num_input_dims * 2]))
+                        .map_err(ShapeInferenceError::MissingAttribute)?
+                };
+                if pads.len() != num_input_dims * 2 {
+                    return Err(ShapeInferenceError::InvalidNode(
+                        node.get_name().to_string(),
+                        "pads attribute has incorrect size".to_string(),
+                    ));
+                }
+                output_shape.extend((0..num_input_dims).map(|i| {
+                    strides[i] * (input_shape.dim(2 + i) as i64 - 1)
+                        + output_padding[i]
+                        + (kernel_shape[i] - 1) * dilations[i]
+                        + 1
+                        - pads[i]
+                        - pads[i + num_input_dims]
+                }));
+            }
+
+            Ok(vec![Shape::from(input_shape.data_type, &output_shape)])
+        }
+
         ("ConstantOfShape", 1, 1) => {
             let shape = static_initializer_value_i64(initializers, &node.get_input()[0])?;
 
@@ -1047,6 +1463,58 @@ pub(crate) fn infer_output_shapes(
             }
         }
 
+        ("Expand", 2, 1) => {
+            let shape_tensor_name = &node.get_input()[1];
+            if let Some(shape_tensor) = initializers.get(shape_tensor_name) {
+                // The input is broadcast to the specified shape in both directions
+                let target_shape =
+                    Shape::from(input_shapes[0].data_type, shape_tensor.get_int64_data());
+                Shape::multi_broadcast(&[input_shapes[0].clone(), target_shape])
+                    .map(|output_shape| vec![output_shape])
+                    .ok_or_else(|| {
+                        ShapeInferenceError::InvalidNode(
+                            node.get_name().to_string(),
+                            format!(
+                                "input shape {} cannot be broadcast to {:?}",
+                                input_shapes[0],
+                                shape_tensor.get_int64_data()
+                            ),
+                        )
+                    })
+            } else {
+                Err(ShapeInferenceError::Unsupported(format!(
+                    "Expand with dynamic shape tensor (input name is {shape_tensor_name})"
+                )))
+            }
+        }
+
+        ("Tile", 2, 1) => {
+            let repeats_tensor_name = &node.get_input()[1];
+            if let Some(repeats_tensor) = initializers.get(repeats_tensor_name) {
+                let repeats = repeats_tensor.get_int64_data();
+                if repeats.len() != input_shapes[0].rank() || repeats.iter().any(|r| *r < 0) {
+                    return Err(ShapeInferenceError::InvalidNode(
+                        node.get_name().to_string(),
+                        format!(
+                            "repeats {:?} must contain a non-negative value for each dimension of input shape {}",
+                            repeats, input_shapes[0]
+                        ),
+                    ));
+                }
+                let output_shape: Vec<i64> = input_shapes[0]
+                    .dims
+                    .iter()
+                    .zip(repeats.iter())
+                    .map(|(dim, repeat)| (*dim as i64) * repeat)
+                    .collect();
+                Ok(vec![Shape::from(input_shapes[0].data_type, &output_shape)])
+            } else {
+                Err(ShapeInferenceError::Unsupported(format!(
+                    "Tile with dynamic repeats tensor (input name is {repeats_tensor_name})"
+                )))
+            }
+        }
+
         ("Concat", 1.., 1) => {
             let axis = node
                 .get_attribute_value::<i64>("axis", None)
@@ -1192,6 +1660,99 @@ pub(crate) fn infer_output_shapes(
             Ok(vec![input_shapes[0].clone()])
         }
 
+        ("InstanceNormalization" | "GroupNormalization", 3, 1) => Ok(vec![input_shapes[0].clone()]),
+
+        ("LayerNormalization", 2..=3, num_outputs @ 1..=3) => {
+            // The mean and inverse standard deviation have the normalized dimensions reduced to one
+            let rank = input_shapes[0].rank() as i64;
+            let mut axis = node.get_attribute_value("axis", Some(-1)).unwrap();
+            if axis < 0 {
+                axis += rank;
+            }
+            if axis < 0 || axis >= rank {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    format!("axis {axis} is out of range for input of rank {rank}"),
+                ));
+            }
+
+            let stash_type = node.get_attribute_value("stash_type", Some(1)).unwrap();
+            let statistics_type = if stash_type == 1 {
+                ScalarType::F32
+            } else {
+                input_shapes[0].data_type
+            };
+            let statistics_dims: Vec<i64> = input_shapes[0]
+                .dims
+                .iter()
+                .enumerate()
+                .map(|(index, dim)| {
+                    if (index as i64) < axis {
+                        *dim as i64
+                    } else {
+                        1
+                    }
+                })
+                .collect();
+            let statistics_shape = Shape::from(statistics_type, &statistics_dims);
+
+            let mut output_shapes = vec![input_shapes[0].clone()];
+            output_shapes.resize(num_outputs, statistics_shape);
+            Ok(output_shapes)
+        }
+
+        ("LayerNormalization" | "InstanceNormalization" | "GroupNormalization", _, _) => {
+            Err(ShapeInferenceError::InvalidNode(
+                node.get_name().to_string(),
+                format!(
+                    "invalid number of inputs ({}) or outputs ({})",
+                    node.get_input().len(),
+                    node.get_output().len()
+                ),
+            ))
+        }
+
+        ("LSTM", 3..=8, num_outputs @ 1..=3) | ("GRU" | "RNN", 3..=6, num_outputs @ 1..=2) => {
+            // Y has shape [seq_length, num_directions, batch_size, hidden_size] and Y_h (as well as Y_c for LSTM) has
+            // shape [num_directions, batch_size, hidden_size]. With layout=1, the batch dimension comes first instead.
+            let input_shape = input_shapes[0];
+            if input_shape.rank() != 3 {
+                return Err(ShapeInferenceError::InvalidNode(
+                    node.get_name().to_string(),
+                    format!("input shape {input_shape} must have rank 3"),
+                ));
+            }
+            let direction = node
+                .get_attribute_value("direction", Some("forward".to_string()))
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+            let num_directions = if direction == "bidirectional" { 2 } else { 1 };
+            let hidden_size = node
+                .get_attribute_value("hidden_size", Some(input_shapes[2].dim(2) as i64))
+                .map_err(ShapeInferenceError::MissingAttribute)?;
+            let layout = node.get_attribute_value("layout", Some(0)).unwrap();
+
+            let (sequence_length, batch_size) = if layout == 0 {
+                (input_shape.dim(0) as i64, input_shape.dim(1) as i64)
+            } else {
+                (input_shape.dim(1) as i64, input_shape.dim(0) as i64)
+            };
+            let (y_dims, state_dims) = if layout == 0 {
+                (
+                    vec![sequence_length, num_directions, batch_size, hidden_size],
+                    vec![num_directions, batch_size, hidden_size],
+                )
+            } else {
+                (
+                    vec![batch_size, sequence_length, num_directions, hidden_size],
+                    vec![batch_size, num_directions, hidden_size],
+                )
+            };
+
+            let mut output_shapes = vec![Shape::from(input_shape.data_type, &y_dims)];
+            output_shapes.resize(num_outputs, Shape::from(input_shape.data_type, &state_dims));
+            Ok(output_shapes)
+        }
+
         (
             "ReduceMean" | "ReduceSum" | "ReduceMin" | "ReduceMax" | "ReduceSumSquare"
             | "ReduceLogSumExp" | "ReduceLogSum" | "ReduceL2" | "ReduceL1" | "ReduceProd",
@@ -1210,7 +1771,11 @@ pub(crate) fn infer_output_shapes(
             | "Atan" | "Atanh" | "Cos" | "Cosh" | "Elu" | "Erf" | "Exp" | "Log" | "Neg" | "Ceil"
             | "Reciprocal" | "Floor" | "Mod" | "Celu" | "ReduceSum" | "ReduceMin" | "ReduceMax"
             | "ReduceSumSquare" | "ReduceLogSumExp" | "ReduceLogSum" | "ReduceL2" | "ReduceL1"
-            | "ReduceProd" | "Size" | "Sign",
+            | "ReduceProd" | "Size" | "Sign" | "Not" | "Equal" | "Greater" | "GreaterOrEqual"
+            | "Less" | "LessOrEqual" | "And" | "Or" | "Xor" | "Where" | "QuantizeLinear"
+            | "DequantizeLinear" | "Expand" | "Tile" | "ConvTranspose" | "LSTM" | "GRU" | "RNN"
+            | "TopK" | "ArgMax" | "ArgMin" | "GatherElements" | "GatherND" | "ScatterElements"
+            | "ScatterND" | "Einsum" | "NonMaxSuppression" | "RoiAlign",
             _,
             _,
         ) => Err(ShapeInferenceError::InvalidNode(
@@ -1274,9 +1839,12 @@ fn fix_raw_tensor(tensor: &mut TensorProto) -> Result<(), ShapeInferenceError> {
             .map_err(ShapeInferenceError::UnsupportedDataType)?
         {
             ScalarType::F32 => tensor.set_float_data(bytemuck::cast_slice(&raw_data[..]).to_vec()),
+            ScalarType::F16 => tensor.set_raw_data(raw_data),
             ScalarType::I64 => tensor.set_int64_data(bytemuck::cast_slice(&raw_data[..]).to_vec()),
             ScalarType::I32 => tensor.set_int32_data(bytemuck::cast_slice(&raw_data[..]).to_vec()),
             ScalarType::U8 => tensor.set_raw_data(bytemuck::cast_slice(&raw_data[..]).to_vec()),
+            ScalarType::I8 => tensor.set_raw_data(raw_data),
+            ScalarType::Bool => tensor.set_raw_data(raw_data),
         }
     }
     Ok(())
@@ -1,4 +1,4 @@
-use ::wonnx::utils::OutputTensor;
+use ::wonnx::utils::{OutputTensor, ScalarType, TypedOutputTensor};
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
 use std::collections::HashMap;
@@ -16,13 +16,44 @@ impl IntoPy<PyObject> for PyOutputTensor {
     fn into_py(self, py: Python) -> PyObject {
         match self.0 {
             OutputTensor::F32(fs) => fs.into_py(py),
+            OutputTensor::F16(fs) => fs
+                .iter()
+                .map(|x| x.to_f32())
+                .collect::<Vec<f32>>()
+                .into_py(py),
             OutputTensor::I32(fs) => fs.into_py(py),
             OutputTensor::I64(fs) => fs.into_py(py),
             OutputTensor::U8(fs) => fs.into_py(py),
+            OutputTensor::I8(fs) => fs.into_py(py),
+            OutputTensor::Bool(fs) => fs.into_py(py),
         }
     }
 }
 
+/// An output tensor together with its shape, converted to a dict with keys `data` (flat list of values), `shape` (list of
+/// dimensions) and `dtype` (name of the NumPy data type)
+pub struct PyTypedOutputTensor(TypedOutputTensor);
+
+impl IntoPy<PyObject> for PyTypedOutputTensor {
+    fn into_py(self, py: Python) -> PyObject {
+        let dtype = match self.0.shape.data_type {
+            ScalarType::F32 => "float32",
+            ScalarType::F16 => "float16",
+            ScalarType::I32 => "int32",
+            ScalarType::I64 => "int64",
+            ScalarType::U8 => "uint8",
+            ScalarType::I8 => "int8",
+            ScalarType::Bool => "bool",
+        };
+        let dict = PyDict::new(py);
+        dict.set_item("data", PyOutputTensor(self.0.data).into_py(py))
+            .unwrap();
+        dict.set_item("shape", self.0.shape.dims).unwrap();
+        dict.set_item("dtype", dtype).unwrap();
+        dict.into_py(py)
+    }
+}
+
 #[pymethods]
 impl PySession {
     #[staticmethod]
@@ -49,6 +80,19 @@ impl PySession {
             .map(|(k, v)| (k, PyOutputTensor(v)))
             .collect())
     }
+
+    pub fn run_typed(&self, dict: &PyDict) -> PyResult<HashMap<String, PyTypedOutputTensor>> {
+        let map: HashMap<String, Vec<f32>> = dict.extract().unwrap();
+        let mut inputs = HashMap::new();
+        for (key, value) in map.iter() {
+            inputs.insert(key.clone(), value.as_slice().into());
+        }
+        let result = pollster::block_on(self.session.run_typed(&inputs)).unwrap();
+        Ok(result
+            .into_iter()
+            .map(|(k, v)| (k, PyTypedOutputTensor(v)))
+            .collect())
+    }
 }
 
 /// This module is implemented in Rust.
@@ -47,12 +47,12 @@ class DummyRep(BackendRep):
                 np.reshape(tmp_v, self.outputs_shape[k])
                 dicts[k] = tmp_v
 
-        results = self.session.run(dicts)
+        results = self.session.run_typed(dicts)
 
         outputs = []
         for item in results.items():
-            tmp_v = np.array(item[1])
-            tmp_v = np.reshape(tmp_v, self.outputs_shape[item[0]])
+            tmp_v = np.array(item[1]["data"])
+            tmp_v = np.reshape(tmp_v, item[1]["shape"])
             if tmp_v.dtype == "float64":
                 tmp_v = tmp_v.astype("float32")
             outputs.append(tmp_v)
@@ -7,7 +7,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::future_to_promise;
-use wonnx::utils::{InputTensor, OutputTensor};
+use wonnx::utils::{InputTensor, OutputTensor, ScalarType, TypedOutputTensor};
 
 #[wasm_bindgen(start)]
 pub fn main() {
@@ -88,14 +88,73 @@ impl Session {
             Ok(serde_wasm_bindgen::to_value(&result).unwrap())
         })
     }
+
+    /// Like `run`, but each output is an object with keys `data` (array of numbers), `shape` (array of dimensions) and
+    /// `dataType` (name of the data type)
+    #[wasm_bindgen(js_name = "runTyped")]
+    pub fn run_typed(&self, input: &Input) -> Promise {
+        let input_copy = input.clone();
+        let engine = self.session.clone();
+
+        future_to_promise(async move {
+            let input_data: HashMap<String, InputTensor<'_>> = input_copy
+                .input_data
+                .iter()
+                .map(|(k, v)| (k.clone(), v.as_slice().into()))
+                .collect();
+            let result = engine.run_typed(&input_data).await.map_err(SessionError)?;
+            drop(input_copy);
+
+            let outputs = js_sys::Object::new();
+            for (name, tensor) in result {
+                js_sys::Reflect::set(
+                    &outputs,
+                    &JsValue::from_str(&name),
+                    &typed_tensor_to_js_value(tensor),
+                )?;
+            }
+            Ok(outputs.into())
+        })
+    }
 }
 
 /// Convert an OutputTensor to a JsValue (we cannot implement Into<JsValue> for OutputTensor here)
 pub fn tensor_to_js_value(tensor: OutputTensor) -> JsValue {
     match tensor {
         OutputTensor::F32(fs) => serde_wasm_bindgen::to_value(&fs).unwrap(),
+        // JavaScript has no float16 arrays, so these are returned as float32
+        OutputTensor::F16(fs) => {
+            let fs: Vec<f32> = fs.iter().map(|x| x.to_f32()).collect();
+            serde_wasm_bindgen::to_value(&fs).unwrap()
+        }
         OutputTensor::I32(ints) => serde_wasm_bindgen::to_value(&ints).unwrap(),
         OutputTensor::I64(ints) => serde_wasm_bindgen::to_value(&ints).unwrap(),
         OutputTensor::U8(ints) => serde_wasm_bindgen::to_value(&ints).unwrap(),
+        OutputTensor::I8(ints) => serde_wasm_bindgen::to_value(&ints).unwrap(),
+        OutputTensor::Bool(bools) => serde_wasm_bindgen::to_value(&bools).unwrap(),
     }
 }
+
+/// Convert a TypedOutputTensor to a JS object with the keys `data`, `shape` and `dataType`
+pub fn typed_tensor_to_js_value(tensor: TypedOutputTensor) -> JsValue {
+    let data_type = match tensor.shape.data_type {
+        ScalarType::F32 => "float32",
+        ScalarType::F16 => "float16",
+        ScalarType::I32 => "int32",
+        ScalarType::I64 => "int64",
+        ScalarType::U8 => "uint8",
+        ScalarType::I8 => "int8",
+        ScalarType::Bool => "bool",
+    };
+
+    let object = js_sys::Object::new();
+    js_sys::Reflect::set(&object, &"data".into(), &tensor_to_js_value(tensor.data)).unwrap();
+    js_sys::Reflect::set(
+        &object,
+        &"shape".into(),
+        &serde_wasm_bindgen::to_value(&tensor.shape.dims).unwrap(),
+    )
+    .unwrap();
+    js_sys::Reflect::set(&object, &"dataType".into(), &data_type.into()).unwrap();
+    object.into()
+}
@@ -0,0 +1,463 @@
+//! Pre-allocated input and output buffers that can be re-used for repeated inference
+use std::{collections::HashMap, sync::Arc};
+
+use bytemuck::NoUninit;
+use wgpu::{Buffer, BufferUsages};
+
+use crate::{
+    float16,
+    gpu::{map_read, write_input_tensor, GpuError, GpuModel, GpuTensor, PaddingSource},
+    padding::{produced_rows, remove_padding},
+    resource,
+    utils::{InputTensor, OutputTensor, ScalarType, Shape},
+    validate_input, SessionError,
+};
+
+/// An IO binding holds on to the GPU buffers used for the inputs and outputs of a [Session](crate::Session), so that
+/// inference can be performed repeatedly without allocating buffers on each run. Inputs are written to buffers owned by
+/// the binding when they are bound, and copied to the input buffers of the model at the start of each run (so that other
+/// runs of the session, with or without another binding, do not affect them). Outputs are either copied to a
+/// caller-provided buffer (which stays on the GPU) or read back to host memory through a staging buffer that is allocated
+/// once when the output is bound.
+///
+/// An IO binding is created using [Session::create_io_binding](crate::Session::create_io_binding) and can only be used
+/// with the session that created it.
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut binding = session.create_io_binding()?;
+/// binding.bind_output_to_host("output")?;
+/// loop {
+///     binding.bind_input("input", &frame.as_slice().into())?;
+///     session.run_with_binding(&mut binding).await?;
+///     let output = binding.output("output").unwrap();
+/// }
+/// ```
+pub struct IoBinding {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    inputs: HashMap<String, BoundInput>,
+    available_outputs: HashMap<String, (GpuTensor, Option<PaddingSource>)>,
+    outputs: Vec<BoundOutput>,
+    strict_int64: bool,
+}
+
+struct BoundInput {
+    /// The buffer owned by this binding that holds the bound data
+    tensor: GpuTensor,
+
+    /// The input buffer of the model, to which the bound data is copied before each run
+    model_buffer: Arc<Buffer>,
+
+    /// Whether data was written to this input, or the input receives data from the output of another session
+    written: bool,
+}
+
+struct BoundOutput {
+    name: String,
+    source: GpuTensor,
+    destination: OutputDestination,
+}
+
+enum OutputDestination {
+    /// The output is copied to a buffer provided by the caller
+    Buffer(Arc<Buffer>),
+
+    /// The output is copied to a staging buffer and then read into host memory. When the output contains rows of
+    /// padding (see `padding`), these are removed after reading.
+    Host {
+        staging: Buffer,
+        data: OutputTensor,
+        padding: Option<Box<HostPadding>>,
+    },
+}
+
+/// The output from which the number of produced rows of a padded output follows, which is read back along with it
+struct HostPadding {
+    source: PaddingSource,
+    staging: Buffer,
+    rows: OutputTensor,
+}
+
+impl IoBinding {
+    pub(crate) fn new(model: &GpuModel) -> IoBinding {
+        IoBinding {
+            device: model.device().clone(),
+            queue: model.queue().clone(),
+            inputs: model
+                .input_tensors()
+                .iter()
+                .map(|(name, tensor)| {
+                    let buffer = resource::buffer(
+                        model.device(),
+                        tensor.buffer.size() as usize,
+                        name,
+                        BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                    );
+                    (
+                        name.clone(),
+                        BoundInput {
+                            tensor: GpuTensor {
+                                buffer: Arc::new(buffer),
+                                shape: tensor.shape.clone(),
+                            },
+                            model_buffer: tensor.buffer.clone(),
+                            written: false,
+                        },
+                    )
+                })
+                .collect(),
+            available_outputs: model.output_tensors(),
+            outputs: vec![],
+            strict_int64: model.strict_int64(),
+        }
+    }
+
+    /// Write data for the specified input to the input buffer of this binding. The data is used for all subsequent runs
+    /// until the input is bound again.
+    pub fn bind_input(&mut self, name: &str, data: &InputTensor) -> Result<(), SessionError> {
+        let input = self
+            .inputs
+            .get_mut(name)
+            .ok_or_else(|| SessionError::InvalidInput(name.to_string()))?;
+
+        // When the device does not support float16, float16 inputs have been widened to float32
+        let widened;
+        let data = if data.data_type() == ScalarType::F16
+            && input.tensor.shape.data_type == ScalarType::F32
+        {
+            widened = float16::widen_input(data);
+            &widened
+        } else {
+            data
+        };
+        validate_input(
+            name,
+            data,
+            input.tensor.shape.data_type,
+            Some(input.tensor.shape.clone()),
+        )?;
+        write_input_tensor(
+            &self.queue,
+            &input.tensor.buffer,
+            name,
+            data,
+            self.strict_int64,
+        )?;
+        input.written = true;
+        Ok(())
+    }
+
+    /// The shape of the specified input, or None if the model has no input with this name
+    pub fn input_shape(&self, name: &str) -> Option<&Shape> {
+        self.inputs.get(name).map(|input| &input.tensor.shape)
+    }
+
+    /// The shape of the specified output, or None if the model has no output with this name. For outputs with a
+    /// data-dependent number of rows (e.g. the output of `NonMaxSuppression`) this is the maximum shape.
+    pub fn output_shape(&self, name: &str) -> Option<&Shape> {
+        self.available_outputs
+            .get(name)
+            .map(|(tensor, _)| &tensor.shape)
+    }
+
+    /// Have the specified output copied to the provided buffer after each run. The buffer must have been created on the
+    /// same device as the session, must have `COPY_DST` usage and must be at least [Self::output_size_bytes] bytes in
+    /// size. Note that the data is written in the internal representation used on the GPU (i.e. int64 values are stored
+    /// as int32, unless [SessionConfig::strict_int64](crate::SessionConfig::strict_int64) is set). Outputs with a
+    /// data-dependent number of rows (see [Self::output_shape]) are written at their maximum size, including the rows of
+    /// padding.
+    pub fn bind_output_to_buffer(
+        &mut self,
+        name: &str,
+        buffer: Arc<Buffer>,
+    ) -> Result<(), SessionError> {
+        let source = self.output_tensor(name)?;
+        if !buffer.usage().contains(BufferUsages::COPY_DST) {
+            return Err(SessionError::OutputBufferUsage(name.to_string()));
+        }
+        let required = copy_size(&source);
+        if buffer.size() < required {
+            return Err(SessionError::OutputBufferTooSmall {
+                name: name.to_string(),
+                expected: required,
+                actual: buffer.size(),
+            });
+        }
+        self.outputs.push(BoundOutput {
+            name: name.to_string(),
+            source,
+            destination: OutputDestination::Buffer(buffer),
+        });
+        Ok(())
+    }
+
+    /// Have the specified output read back to host memory after each run. The data can be obtained using
+    /// [Self::output]. The staging buffer and host memory needed for this are allocated once, here.
+    pub fn bind_output_to_host(&mut self, name: &str) -> Result<(), SessionError> {
+        let source = self.output_tensor(name)?;
+        let padding = self.available_outputs[name].1.clone().map(|source| {
+            let staging = resource::buffer(
+                &self.device,
+                copy_size(&source.rows) as usize,
+                name,
+                BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            );
+            let rows = empty_output_tensor(&source.rows.shape);
+            Box::new(HostPadding {
+                source,
+                staging,
+                rows,
+            })
+        });
+        let staging = resource::buffer(
+            &self.device,
+            copy_size(&source) as usize,
+            name,
+            BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        );
+        let data = empty_output_tensor(&source.shape);
+        self.outputs.retain(|output| {
+            output.name != name || !matches!(output.destination, OutputDestination::Host { .. })
+        });
+        self.outputs.push(BoundOutput {
+            name: name.to_string(),
+            source,
+            destination: OutputDestination::Host {
+                staging,
+                data,
+                padding,
+            },
+        });
+        Ok(())
+    }
+
+    /// The number of bytes that will be copied to a buffer bound to the specified output
+    pub fn output_size_bytes(&self, name: &str) -> Result<u64, SessionError> {
+        Ok(copy_size(&self.output_tensor(name)?))
+    }
+
+    /// The data read back for the specified output during the last run, if the output was bound to host memory. For
+    /// outputs with a data-dependent number of rows, only the rows that were produced are returned.
+    pub fn output(&self, name: &str) -> Option<&OutputTensor> {
+        self.outputs
+            .iter()
+            .filter(|output| output.name == name)
+            .find_map(|output| match &output.destination {
+                OutputDestination::Host { data, .. } => Some(data),
+                OutputDestination::Buffer(_) => None,
+            })
+    }
+
+    /// Feed the specified output of the session this binding belongs to directly into an input of the session that
+    /// `target` belongs to. After each run of this binding, the output is copied to the input buffer of the target
+    /// binding on the GPU, so the data does not have to be read back to host memory. As the target binding owns this
+    /// buffer, runs of the target session in between (without the target binding) do not affect it. Both sessions must have been
+    /// created on the same device (see [SessionConfig::with_device_queue](crate::SessionConfig::with_device_queue)), and
+    /// this binding needs to be run before the target binding.
+    ///
+    /// An output can be bound to multiple inputs (as well as to host memory). Outputs with a data-dependent number of rows
+    /// cannot be bound to inputs, as the target session would use the rows of padding as data.
+    pub fn bind_output_to_input(
+        &mut self,
+        output_name: &str,
+        target: &mut IoBinding,
+        input_name: &str,
+    ) -> Result<(), SessionError> {
+        if !Arc::ptr_eq(&self.device, &target.device) {
+            return Err(SessionError::DeviceMismatch);
+        }
+
+        let source = self.output_tensor(output_name)?;
+        if self.available_outputs[output_name].1.is_some() {
+            return Err(SessionError::DataDependentOutputChained {
+                output: output_name.to_string(),
+                input: input_name.to_string(),
+            });
+        }
+        let input = target
+            .inputs
+            .get_mut(input_name)
+            .ok_or_else(|| SessionError::InvalidInput(input_name.to_string()))?;
+        let expected = &input.tensor.shape;
+        if source.shape.data_type != expected.data_type {
+            return Err(SessionError::InputTypeMismatch {
+                name: input_name.to_string(),
+                expected: expected.data_type,
+                actual: source.shape.data_type,
+            });
+        }
+        if source.shape.element_count() != expected.element_count() {
+            return Err(SessionError::InputSizeMismatch {
+                name: input_name.to_string(),
+                expected: expected.clone(),
+                actual: source.shape.element_count() as usize,
+            });
+        }
+
+        input.written = true;
+        let buffer = input.tensor.buffer.clone();
+        self.outputs.push(BoundOutput {
+            name: output_name.to_string(),
+            source,
+            destination: OutputDestination::Buffer(buffer),
+        });
+        Ok(())
+    }
+
+    fn output_tensor(&self, name: &str) -> Result<GpuTensor, SessionError> {
+        self.available_outputs
+            .get(name)
+            .map(|(tensor, _)| tensor.clone())
+            .ok_or_else(|| SessionError::InvalidOutput(name.to_string()))
+    }
+
+    /// Perform inference using the bound inputs, then copy the outputs to their bound destinations.
+    pub(crate) async fn run(&mut self, model: &GpuModel) -> Result<(), SessionError> {
+        if let Some((name, _)) = self.inputs.iter().find(|(_, input)| !input.written) {
+            return Err(GpuError::InferenceInputMissing(name.clone()).into());
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        for input in self.inputs.values() {
+            encoder.copy_buffer_to_buffer(
+                &input.tensor.buffer,
+                0,
+                &input.model_buffer,
+                0,
+                copy_size(&input.tensor),
+            );
+        }
+        let mut encoder = model.encode_steps(encoder).await?;
+        for output in &self.outputs {
+            let destination = match &output.destination {
+                OutputDestination::Buffer(buffer) => buffer.as_ref(),
+                OutputDestination::Host { staging, .. } => staging,
+            };
+            encoder.copy_buffer_to_buffer(
+                &output.source.buffer,
+                0,
+                destination,
+                0,
+                copy_size(&output.source),
+            );
+            if let OutputDestination::Host {
+                padding: Some(padding),
+                ..
+            } = &output.destination
+            {
+                encoder.copy_buffer_to_buffer(
+                    &padding.source.rows.buffer,
+                    0,
+                    &padding.staging,
+                    0,
+                    copy_size(&padding.source.rows),
+                );
+            }
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        for output in &mut self.outputs {
+            if let OutputDestination::Host {
+                staging,
+                data,
+                padding,
+            } = &mut output.destination
+            {
+                map_read(&self.device, staging).await?;
+                {
+                    let bytes = staging.slice(..).get_mapped_range();
+                    read_bytes_into(&bytes, &output.source.shape, data, self.strict_int64);
+                }
+                staging.unmap();
+
+                if let Some(padding) = padding {
+                    let rows_shape = &padding.source.rows.shape;
+                    map_read(&self.device, &padding.staging).await?;
+                    {
+                        let bytes = padding.staging.slice(..).get_mapped_range();
+                        read_bytes_into(&bytes, rows_shape, &mut padding.rows, self.strict_int64);
+                    }
+                    padding.staging.unmap();
+                    let row_count = produced_rows(&padding.rows, rows_shape);
+                    remove_padding(data, &output.source.shape, padding.source.axis, row_count);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The number of bytes that need to be copied out of the buffer of a tensor to obtain all its data
+fn copy_size(tensor: &GpuTensor) -> u64 {
+    u64::min(
+        tensor.shape.buffer_bytes_aligned() as u64,
+        tensor.buffer.size(),
+    )
+}
+
+fn empty_output_tensor(shape: &Shape) -> OutputTensor {
+    let capacity = shape.element_count() as usize;
+    match shape.data_type {
+        ScalarType::F32 => OutputTensor::F32(Vec::with_capacity(capacity)),
+        ScalarType::F16 => OutputTensor::F16(Vec::with_capacity(capacity)),
+        ScalarType::I32 => OutputTensor::I32(Vec::with_capacity(capacity)),
+        ScalarType::I64 => OutputTensor::I64(Vec::with_capacity(capacity)),
+        ScalarType::U8 => OutputTensor::U8(Vec::with_capacity(capacity)),
+        ScalarType::I8 => OutputTensor::I8(Vec::with_capacity(capacity)),
+        ScalarType::Bool => OutputTensor::Bool(Vec::with_capacity(capacity)),
+    }
+}
+
+/// Read tensor data from a mapped buffer into an existing output tensor, re-using its allocation. Follows the conversions
+/// performed when reading outputs in `GpuModel::infer`.
+fn read_bytes_into<A: NoUninit>(
+    output_data: &[A],
+    shape: &Shape,
+    target: &mut OutputTensor,
+    strict_int64: bool,
+) {
+    let element_count = shape.element_count() as usize;
+    match target {
+        OutputTensor::F32(v) => {
+            v.clear();
+            v.extend_from_slice(&bytemuck::cast_slice(output_data)[..element_count]);
+        }
+        OutputTensor::F16(v) => {
+            v.clear();
+            v.extend_from_slice(&bytemuck::cast_slice(output_data)[..element_count]);
+        }
+        OutputTensor::I32(v) => {
+            v.clear();
+            v.extend_from_slice(&bytemuck::cast_slice(output_data)[..element_count]);
+        }
+        OutputTensor::U8(v) => {
+            // 8-bit integers are packed four to a u32, so the bytes can be read directly
+            v.clear();
+            v.extend_from_slice(&bytemuck::cast_slice(output_data)[..element_count]);
+        }
+        OutputTensor::I8(v) => {
+            v.clear();
+            v.extend_from_slice(&bytemuck::cast_slice(output_data)[..element_count]);
+        }
+        OutputTensor::Bool(v) => {
+            // Booleans are stored as u32
+            let ints: &[u32] = &bytemuck::cast_slice(output_data)[..element_count];
+            v.clear();
+            v.extend(ints.iter().map(|i| *i != 0));
+        }
+        OutputTensor::I64(v) if strict_int64 => {
+            v.clear();
+            v.extend_from_slice(&bytemuck::cast_slice(output_data)[..element_count]);
+        }
+        OutputTensor::I64(v) => {
+            // Internally int64 scalars are stored as int32
+            let ints: &[i32] = &bytemuck::cast_slice(output_data)[..element_count];
+            v.clear();
+            v.extend(ints.iter().map(|i| *i as i64));
+        }
+    }
+}
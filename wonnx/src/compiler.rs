@@ -1,6 +1,8 @@
 //! Compiles individual ONNX ops to a WebGPU shader using WGSL templates
 use std::sync::OnceLock;
 
+use crate::einsum::{EinsumEquation, EinsumError};
+use crate::registry::{CustomOperator, OperatorRegistry, ShaderSource};
 use crate::utils::{
     ceil, AttributeNotFoundError, DataTypeError, MultiType, NodeAttributes, ScalarType, Shape,
 };
@@ -17,6 +19,9 @@ pub const MAX_WORKGROUP_SIZE_X: u32 = 256;
 pub const MAX_WORKGROUP_SIZE_Y: u32 = 256;
 // pub const MAX_WORKGROUP_SIZE_Z: u32 = 64;
 
+/// The maximum number of bindings in a binding group (defined by wgpu)
+pub(crate) const MAX_BINDINGS_PER_GROUP: usize = 4;
+
 static TEMPLATES: OnceLock<Tera> = OnceLock::new();
 
 fn get_templates() -> &'static Tera {
@@ -57,6 +62,11 @@ fn get_templates() -> &'static Tera {
             include_str!("../templates/matrix/concat.wgsl"),
         )
         .unwrap();
+        tera.add_raw_template(
+            "matrix/einsum.wgsl",
+            include_str!("../templates/matrix/einsum.wgsl"),
+        )
+        .unwrap();
         tera.add_raw_template(
             "matrix/gemm_1.wgsl",
             include_str!("../templates/matrix/gemm_1.wgsl"),
@@ -72,6 +82,11 @@ fn get_templates() -> &'static Tera {
             include_str!("../templates/matrix/pad.wgsl"),
         )
         .unwrap();
+        tera.add_raw_template(
+            "matrix/slice.wgsl",
+            include_str!("../templates/matrix/slice.wgsl"),
+        )
+        .unwrap();
         tera.add_raw_template(
             "matrix/resize.wgsl",
             include_str!("../templates/matrix/resize.wgsl"),
@@ -102,11 +117,26 @@ fn get_templates() -> &'static Tera {
             include_str!("../templates/pool/conv_kernel_3.wgsl"),
         )
         .unwrap();
+        tera.add_raw_template(
+            "pool/conv_transpose.wgsl",
+            include_str!("../templates/pool/conv_transpose.wgsl"),
+        )
+        .unwrap();
         tera.add_raw_template(
             "pool/conv.wgsl",
             include_str!("../templates/pool/conv.wgsl"),
         )
         .unwrap();
+        tera.add_raw_template(
+            "pool/topk.wgsl",
+            include_str!("../templates/pool/topk.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "pool/roialign.wgsl",
+            include_str!("../templates/pool/roialign.wgsl"),
+        )
+        .unwrap();
         tera.add_raw_template(
             "pool/reduce.wgsl",
             include_str!("../templates/pool/reduce.wgsl"),
@@ -129,16 +159,101 @@ fn get_templates() -> &'static Tera {
             include_str!("../templates/endomorphism/gather.wgsl"),
         )
         .unwrap();
+        tera.add_raw_template(
+            "endomorphism/gather_elements.wgsl",
+            include_str!("../templates/endomorphism/gather_elements.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "endomorphism/gather_nd.wgsl",
+            include_str!("../templates/endomorphism/gather_nd.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "endomorphism/scatter_elements.wgsl",
+            include_str!("../templates/endomorphism/scatter_elements.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "endomorphism/scatter_nd.wgsl",
+            include_str!("../templates/endomorphism/scatter_nd.wgsl"),
+        )
+        .unwrap();
         tera.add_raw_template(
             "endomorphism/onehot.wgsl",
             include_str!("../templates/endomorphism/onehot.wgsl"),
         )
         .unwrap();
+        tera.add_raw_template(
+            "endomorphism/normalization.wgsl",
+            include_str!("../templates/endomorphism/normalization.wgsl"),
+        )
+        .unwrap();
         tera.add_raw_template(
             "endomorphism/broadcast.wgsl",
             include_str!("../templates/endomorphism/broadcast.wgsl"),
         )
         .unwrap();
+        tera.add_raw_template(
+            "endomorphism/where.wgsl",
+            include_str!("../templates/endomorphism/where.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "snippets/int64.wgsl",
+            include_str!("../templates/snippets/int64.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "int64/arithmetic.wgsl",
+            include_str!("../templates/int64/arithmetic.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "int64/cast.wgsl",
+            include_str!("../templates/int64/cast.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "int64/concat.wgsl",
+            include_str!("../templates/int64/concat.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "int64/gather.wgsl",
+            include_str!("../templates/int64/gather.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "snippets/quantization.wgsl",
+            include_str!("../templates/snippets/quantization.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "quantization/conv.wgsl",
+            include_str!("../templates/quantization/conv.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "quantization/dequantize.wgsl",
+            include_str!("../templates/quantization/dequantize.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "quantization/matmul.wgsl",
+            include_str!("../templates/quantization/matmul.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "quantization/quantize.wgsl",
+            include_str!("../templates/quantization/quantize.wgsl"),
+        )
+        .unwrap();
+        tera.add_raw_template(
+            "sequence/recurrent.wgsl",
+            include_str!("../templates/sequence/recurrent.wgsl"),
+        )
+        .unwrap();
         tera
     })
 }
@@ -162,6 +277,12 @@ pub enum CompileError {
     #[error("op {0} is not implemented yet! Check the README if you want to implement it")]
     UnimplementedOp(String),
 
+    #[error("could not render the shader template for custom op {op}: {error}")]
+    CustomTemplateError { op: String, error: String },
+
+    #[error("custom op {op} failed: {message}")]
+    CustomOperatorError { op: String, message: String },
+
     #[error("'{variant}' is not yet implemented for op {op}")]
     UnimplementedVariant { variant: String, op: String },
 
@@ -190,6 +311,9 @@ pub enum CompileError {
     #[error("cannot infer data type to use")]
     TypeUnderspecified,
 
+    #[error("expected an 8-bit integer type, but got {0}")]
+    NotQuantizedType(ScalarType),
+
     #[error("invalid type encountered: {0}")]
     InvalidType(#[from] DataTypeError),
 
@@ -201,6 +325,12 @@ pub enum CompileError {
         input_shapes: Vec<Shape>,
         output_shape: Shape,
     },
+
+    #[error("invalid equation '{equation}' for Einsum: {error}")]
+    InvalidEinsumEquation {
+        equation: String,
+        error: EinsumError,
+    },
 }
 
 struct NodeTemplate {
@@ -252,6 +382,8 @@ pub fn compile(
     input_shapes: &[&Shape],
     output_shapes: &[&Shape],
     opset_version: i64,
+    registry: &OperatorRegistry,
+    strict_int64: bool,
 ) -> Result<CompiledNode, CompileError> {
     let input_lengths = input_shapes
         .iter()
@@ -278,6 +410,39 @@ pub fn compile(
     context.insert("op_type", &node.get_op_type());
     context.insert("opset_version", &opset_version);
 
+    if let Some(operator) = registry.get(node) {
+        return compile_custom(
+            operator.as_ref(),
+            node,
+            input_shapes,
+            output_shapes,
+            context,
+            strict_int64,
+        );
+    }
+
+    if strict_int64
+        && input_shapes
+            .iter()
+            .chain(output_shapes.iter())
+            .any(|shape| shape.data_type == ScalarType::I64)
+    {
+        return compile_int64(node, input_shapes, output_shapes, opset_version, context);
+    }
+
+    // 8-bit integer tensors are stored packed on the GPU, and can only be handled by the quantization ops
+    if !is_quantization_op(node.get_op_type())
+        && input_shapes
+            .iter()
+            .chain(output_shapes.iter())
+            .any(|shape| matches!(shape.data_type, ScalarType::U8 | ScalarType::I8))
+    {
+        return Err(CompileError::UnimplementedVariant {
+            variant: "with 8-bit integer tensors".to_string(),
+            op: node.get_op_type().to_string(),
+        });
+    }
+
     let node_template: NodeTemplate = match node.get_op_type() {
         op @ ("Reshape" | "Dropout" | "Identity" | "Flatten" | "Squeeze" | "Unsqueeze") => {
             // These ops should all be optimized away earlier
@@ -287,7 +452,7 @@ pub fn compile(
         // Map simple function
         "Abs" | "Acos" | "Asin" | "Atan" | "Ceil" | "Cos" | "Cosh" | "Exp" | "Floor" | "Log"
         | "Round" | "Sign" | "Sin" | "Sinh" | "Sqrt" | "Tan" | "Tanh" | "Reciprocal" | "Acosh"
-        | "Asinh" | "Atanh" | "Neg" => {
+        | "Asinh" | "Atanh" | "Neg" | "Not" => {
             let (x_threads, workgroup_size_x) = workgroup_size(
                 ceil(output_lengths[0], 4),
                 MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
@@ -444,6 +609,38 @@ pub fn compile(
             }
         }
 
+        "GatherElements" | "GatherND" | "ScatterElements" | "ScatterND" | "ScatterNDInPlace" => {
+            // Outside of strict int64 mode, int64 values (including the indices) are stored as 32-bit integers
+            let scalar_type = match input_shapes[0].data_type {
+                ScalarType::I64 => ScalarType::I32,
+                data_type => data_type,
+            };
+            context.insert("data_type", scalar_type.wgsl_type_name());
+            context.insert("data_is_int64", &false);
+            context.insert("indices_type", "i32");
+            context.insert("indices_are_int64", &false);
+            let (template, invocations) = insert_gather_scatter_context(
+                &mut context,
+                node,
+                input_shapes,
+                output_shapes,
+                opset_version,
+            )?;
+
+            let (x_threads, workgroup_size_x) = workgroup_size(
+                invocations,
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_X,
+            )?;
+            context.insert("workgroup_size_x", &workgroup_size_x);
+
+            NodeTemplate {
+                scalar_type,
+                template,
+                threads: (x_threads, 1, 1),
+            }
+        }
+
         "Cast" => {
             let cast_to_type =
                 ScalarType::from_i32(node.get_attribute_value::<i64>("to", None)? as i32)?;
@@ -456,6 +653,7 @@ pub fn compile(
             }
 
             context.insert("cast_to_type", cast_to_type.wgsl_type_name());
+            context.insert("cast_to_bool", &(cast_to_type == ScalarType::Bool));
 
             let (x_threads, workgroup_size_x) = workgroup_size(
                 ceil(output_lengths[0], 4),
@@ -548,7 +746,7 @@ pub fn compile(
 
         // Arithmetic operation
         op @ ("Add" | "And" | "Div" | "Equal" | "Greater" | "GreaterOrEqual" | "Less"
-        | "LessOrEqual" | "Mod" | "Mul" | "Or" | "Sub" | "Pow" | "PRelu") => {
+        | "LessOrEqual" | "Mod" | "Mul" | "Or" | "Xor" | "Sub" | "Pow" | "PRelu") => {
             let broadcast = node.get_attribute_value("broadcast", Some(0))?;
             if broadcast != 0 {
                 return Err(CompileError::UnimplementedVariant {
@@ -572,6 +770,7 @@ pub fn compile(
                     "Mod" => "%",
                     "Mul" => "*",
                     "Or" => "|",
+                    "Xor" => "^",
                     "Sub" => "-",
                     "Pow" => "Pow",
                     "PRelu" => "PRelu",
@@ -583,8 +782,25 @@ pub fn compile(
                 },
             );
 
+            // Comparison ops produce booleans, the other ops produce values of the same type as their inputs
+            let comparison = matches!(
+                op,
+                "Equal" | "Greater" | "GreaterOrEqual" | "Less" | "LessOrEqual"
+            );
+            let scalar_type = if comparison {
+                context.insert(
+                    "output_type",
+                    agreed_type(&[], output_shapes)?.wgsl_type_name(),
+                );
+                agreed_type(input_shapes, &[])?
+            } else {
+                agreed_type(input_shapes, output_shapes)?
+            };
+            context.insert("comparison", &comparison);
+
             if input_shapes.len() == 2
-                && (input_shapes[0] != output_shapes[0] || input_shapes[1] != output_shapes[0])
+                && (input_shapes[0].dims != output_shapes[0].dims
+                    || input_shapes[1].dims != output_shapes[0].dims)
             {
                 // We are likely broadcasting; check if the broadcast is valid. Compute the possible broadcast output shape
                 let out_shape =
@@ -597,7 +813,7 @@ pub fn compile(
                             output_shape: output_shapes[0].clone(),
                         })?;
 
-                if &out_shape != output_shapes[0] {
+                if out_shape.dims != output_shapes[0].dims {
                     return Err(CompileError::InvalidBroadcast {
                         input_shapes: input_shapes
                             .iter()
@@ -629,11 +845,11 @@ pub fn compile(
                 context.insert("workgroup_size_x", &workgroup_size_x);
 
                 NodeTemplate {
-                    scalar_type: agreed_type(input_shapes, output_shapes)?,
+                    scalar_type,
                     template: "endomorphism/broadcast.wgsl",
                     threads: (x_threads, 1, 1),
                 }
-            } else if input_shapes[0] != output_shapes[0] {
+            } else if input_shapes[0].dims != output_shapes[0].dims {
                 // If we are not broadcasting, the input shape needs to be equal to the output shape
                 return Err(CompileError::InvalidInputShape {
                     input_index: 0,
@@ -652,12 +868,147 @@ pub fn compile(
                 context.insert("workgroup_size_x", &workgroup_size_x);
 
                 NodeTemplate {
-                    scalar_type: agreed_type(input_shapes, output_shapes)?,
+                    scalar_type,
                     template: "endomorphism/arithmetic.wgsl",
                     threads: (x_threads, 1, 1),
                 }
             }
         }
+        "Where" => {
+            if input_shapes.len() != 3 {
+                return Err(CompileError::InvalidInputCount {
+                    expected: 3,
+                    actual: input_shapes.len(),
+                });
+            }
+            if input_shapes[0].data_type != ScalarType::Bool {
+                return Err(CompileError::TypesDisagree(
+                    ScalarType::Bool,
+                    input_shapes[0].data_type,
+                ));
+            }
+
+            // All three inputs are broadcast to the output shape
+            let out_rank = output_shapes[0].rank();
+            for (input_index, input_shape) in input_shapes.iter().enumerate() {
+                let padded_shape = input_shape.left_padded_to(1, out_rank);
+                let broadcastable = input_shape.rank() <= out_rank
+                    && padded_shape
+                        .dims
+                        .iter()
+                        .zip(output_shapes[0].dims.iter())
+                        .all(|(dim, out_dim)| dim == out_dim || *dim == 1);
+                if !broadcastable {
+                    return Err(CompileError::InvalidBroadcast {
+                        input_shapes: input_shapes.iter().map(|x| (*x).clone()).collect(),
+                        output_shape: output_shapes[0].clone(),
+                    });
+                }
+
+                let name = ["condition", "x", "y"][input_index];
+                context.insert(format!("{name}_padded_shape"), &padded_shape.dims);
+                context.insert(format!("{name}_padded_chunks"), &padded_shape.chunks());
+            }
+
+            let (x_threads, workgroup_size_x) = workgroup_size(
+                output_lengths[0],
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_X,
+            )?;
+            context.insert("workgroup_size_x", &workgroup_size_x);
+
+            NodeTemplate {
+                scalar_type: agreed_type(&input_shapes[1..3], output_shapes)?,
+                template: "endomorphism/where.wgsl",
+                threads: (x_threads, 1, 1),
+            }
+        }
+
+        op @ ("QuantizeLinear" | "DequantizeLinear") => {
+            if input_shapes.len() < 2 {
+                return Err(CompileError::InvalidInputCount {
+                    expected: 2,
+                    actual: input_shapes.len(),
+                });
+            }
+
+            // The scale and zero point either apply to the whole tensor, or to slices along the specified axis
+            let scale_shape = input_shapes[1];
+            let per_axis = scale_shape.element_count() > 1;
+            context.insert("per_axis", &per_axis);
+            if per_axis {
+                let rank = input_shapes[0].rank() as i64;
+                let mut axis = node.get_attribute_value("axis", Some(1))?;
+                if axis < 0 {
+                    axis += rank;
+                }
+                if axis < 0
+                    || axis >= rank
+                    || scale_shape.rank() != 1
+                    || scale_shape.dim(0) != input_shapes[0].dim(axis as usize)
+                {
+                    return Err(CompileError::InvalidInputShape {
+                        input_index: 1,
+                        input_shape: scale_shape.clone(),
+                    });
+                }
+                context.insert("axis_dim", &input_shapes[0].dim(axis as usize));
+                context.insert("axis_chunk", &input_shapes[0].chunks()[axis as usize]);
+            }
+
+            if op == "QuantizeLinear" {
+                if input_shapes[0].data_type != ScalarType::F32 {
+                    return Err(CompileError::UnimplementedVariant {
+                        variant: format!("with input of type {}", input_shapes[0].data_type),
+                        op: op.to_string(),
+                    });
+                }
+                insert_quantized_type(&mut context, "output", output_shapes[0].data_type)?;
+
+                // Each thread produces four values, which are packed together in a single output element
+                let packed_length = ceil(output_lengths[0], 4);
+                let (x_threads, workgroup_size_x) = workgroup_size(
+                    packed_length,
+                    MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                    MAX_WORKGROUP_SIZE_X,
+                )?;
+                context.insert("packed_length", &packed_length);
+                context.insert("workgroup_size_x", &workgroup_size_x);
+
+                NodeTemplate {
+                    scalar_type: ScalarType::F32,
+                    template: "quantization/quantize.wgsl",
+                    threads: (x_threads, 1, 1),
+                }
+            } else {
+                if output_shapes[0].data_type != ScalarType::F32 {
+                    return Err(CompileError::UnimplementedVariant {
+                        variant: format!("with output of type {}", output_shapes[0].data_type),
+                        op: op.to_string(),
+                    });
+                }
+
+                // Besides 8-bit integers, DequantizeLinear also accepts int32 input (typically used for biases)
+                match input_shapes[0].data_type {
+                    ScalarType::I32 => context.insert("input_type", "i32"),
+                    input_type => insert_quantized_type(&mut context, "input", input_type)?,
+                }
+
+                let (x_threads, workgroup_size_x) = workgroup_size(
+                    output_lengths[0],
+                    MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                    MAX_WORKGROUP_SIZE_X,
+                )?;
+                context.insert("workgroup_size_x", &workgroup_size_x);
+
+                NodeTemplate {
+                    scalar_type: ScalarType::F32,
+                    template: "quantization/dequantize.wgsl",
+                    threads: (x_threads, 1, 1),
+                }
+            }
+        }
+
         // Not taking into account attributes
         "BatchNormalization" => {
             /* Prior to version 9, BatchNormalization supported a 'spatial' mode where input mean/variance are of shape
@@ -814,14 +1165,29 @@ pub fn compile(
             }
         }
         op @ ("MaxPool" | "AveragePool" | "Conv" | "ConvRelu" | "ConvLeakyRelu" | "ConvMish"
-        | "GlobalAveragePool") => {
-            // TODO: Conv only support NxCxHxW for the moment.
-            if input_shapes[0].rank() != 4 {
+        | "GlobalAveragePool" | "QLinearConv") => {
+            // QLinearConv takes the data, weight and bias inputs at different positions (the other inputs are the scales
+            // and zero points). Select these so the shape checks below apply to all convolutions.
+            let all_input_shapes = input_shapes;
+            let input_shapes: Vec<&Shape> = if op == "QLinearConv" {
+                [0, 3, 8]
+                    .iter()
+                    .filter_map(|index| all_input_shapes.get(*index).copied())
+                    .collect()
+            } else {
+                all_input_shapes.to_vec()
+            };
+
+            // Convolution and pooling support one, two or three spatial dimensions (NxCxW, NxCxHxW and NxCxDxHxW).
+            // QLinearConv only supports NxCxHxW for the moment.
+            let rank = input_shapes[0].rank();
+            if !(3..=5).contains(&rank) || (op == "QLinearConv" && rank != 4) {
                 return Err(CompileError::InvalidInputShape {
                     input_index: 0,
                     input_shape: input_shapes[0].clone(),
                 });
             }
+            let spatial_rank = rank - 2;
 
             // GlobalAveragePool is equivalent to AveragePool, with the kernel shape set to the size of the input tensor
             // See https://github.com/onnx/onnx/blob/main/docs/Operators.md#globalaveragepool
@@ -833,32 +1199,68 @@ pub fn compile(
             }
 
             let auto_pad = node.get_attribute_value("auto_pad", Some("NOTSET".to_string()))?;
-            let dilations = node.get_attribute_value("dilations", Some(vec![1, 1]))?;
+            let dilations = node.get_attribute_value("dilations", Some(vec![1; spatial_rank]))?;
             let kernel_shape = if is_global_average_pool {
-                vec![input_shapes[0].dim(2) as i64, input_shapes[0].dim(3) as i64]
+                input_shapes[0].dims[2..]
+                    .iter()
+                    .map(|d| *d as i64)
+                    .collect()
+            } else if op == "QLinearConv" {
+                // If not specified, the kernel shape is inferred from the weights
+                let weight_dims = input_shapes[1].dims[2..]
+                    .iter()
+                    .map(|d| *d as i64)
+                    .collect();
+                node.get_attribute_value::<Vec<i64>>("kernel_shape", Some(weight_dims))?
             } else {
                 node.get_attribute_value::<Vec<i64>>("kernel_shape", None)?
             };
-            let strides = node.get_attribute_value("strides", Some(vec![1, 1]))?;
-            let pads = node.get_attribute_value("pads", Some(vec![0, 0, 0, 0]))?;
+            let strides = node.get_attribute_value("strides", Some(vec![1; spatial_rank]))?;
+            let pads = node.get_attribute_value("pads", Some(vec![0; spatial_rank * 2]))?;
             let count_include_pad = node.get_attribute_value("count_include_pad", Some(0))?;
             let group = node.get_attribute_value("group", Some(1))? as u64;
 
+            if kernel_shape.len() != spatial_rank
+                || strides.len() != spatial_rank
+                || dilations.len() != spatial_rank
+                || pads.len() != spatial_rank * 2
+                || kernel_shape.iter().any(|k| *k < 0)
+            {
+                return Err(CompileError::InvalidInputShape {
+                    input_index: 0,
+                    input_shape: input_shapes[0].clone(),
+                });
+            }
+
             let pads = match auto_pad.as_str() {
                 "NOTSET" => pads.to_vec(),
-                "SAME_UPPER" => {
-                    let pad_0 = (output_shapes[0].dim(2) as i64 - 1) * strides[0] + kernel_shape[0]
-                        - input_shapes[0].dim(3) as i64;
-                    let pad_1 = (output_shapes[0].dim(2) as i64 - 1) * strides[1] + kernel_shape[1]
-                        - input_shapes[0].dim(3) as i64;
-                    vec![pad_0 / 2, pad_1 / 2]
-                }
-                "SAME_LOWER" => {
-                    let pad_0 = (output_shapes[0].dim(2) as i64 - 1) * strides[0] + kernel_shape[0]
-                        - input_shapes[0].dim(3) as i64;
-                    let pad_1 = (output_shapes[0].dim(2) as i64 - 1) * strides[1] + kernel_shape[1]
-                        - input_shapes[0].dim(3) as i64;
-                    vec![pad_0 - pad_0 / 2, pad_1 - pad_1 / 2]
+                "SAME_UPPER" | "SAME_LOWER" => {
+                    // The total padding is split over the beginning and end of each spatial dimension. When it is odd,
+                    // SAME_UPPER adds the extra padding at the end and SAME_LOWER at the beginning.
+                    let total_pads: Vec<i64> = (0..spatial_rank)
+                        .map(|d| {
+                            let effective_kernel = (kernel_shape[d] - 1) * dilations[d] + 1;
+                            ((output_shapes[0].dim(d + 2) as i64 - 1) * strides[d]
+                                + effective_kernel
+                                - input_shapes[0].dim(d + 2) as i64)
+                                .max(0)
+                        })
+                        .collect();
+                    let begin_pads = total_pads.iter().map(|total| {
+                        if auto_pad == "SAME_UPPER" {
+                            total / 2
+                        } else {
+                            total - total / 2
+                        }
+                    });
+                    let end_pads = total_pads.iter().map(|total| {
+                        if auto_pad == "SAME_UPPER" {
+                            total - total / 2
+                        } else {
+                            total / 2
+                        }
+                    });
+                    begin_pads.chain(end_pads).collect()
                 }
                 _ => {
                     return Err(CompileError::UnimplementedVariant {
@@ -870,8 +1272,6 @@ pub fn compile(
 
             let input_shape = &input_shapes[0];
             let output_shape = &output_shapes[0];
-            assert!(kernel_shape.len() >= 2);
-            assert!(kernel_shape[0] >= 0 && kernel_shape[1] >= 0);
 
             let channels_per_group = input_shape.dim(1) / group;
             if channels_per_group * group != input_shape.dim(1) {
@@ -906,18 +1306,29 @@ pub fn compile(
                 });
             }
 
-            context.insert("original_width", &input_shape.dim(3));
-            context.insert("width", &output_shape.dim(3));
-            context.insert("original_height", &input_shape.dim(2));
+            // The templates for arbitrary spatial rank index the spatial dimensions and their strides per dimension
+            let kernel_length: i64 = kernel_shape.iter().product();
+            let kernel_chunks: Vec<i64> = (0..spatial_rank)
+                .map(|d| kernel_shape[(d + 1)..].iter().product())
+                .collect();
+            context.insert("original_shape", &input_shape.dims[2..]);
+            context.insert("i_spatial_chunks", &input_shape.chunks()[2..]);
+            context.insert("o_spatial_chunks", &output_shape.chunks()[2..]);
+            context.insert("kernel_chunks", &kernel_chunks);
+            if spatial_rank == 2 {
+                context.insert("original_width", &input_shape.dim(3));
+                context.insert("width", &output_shape.dim(3));
+                context.insert("original_height", &input_shape.dim(2));
+            }
             context.insert("channel", &input_shape.dim(1));
             context.insert("groups", &group);
             context.insert("channels_per_group", &channels_per_group);
             context.insert("stride", &strides);
             context.insert("kernel_shape", &kernel_shape);
-            context.insert("kernel_length", &(kernel_shape[0] * kernel_shape[1]));
+            context.insert("kernel_length", &kernel_length);
             context.insert(
                 "kernel_channel_len",
-                &((kernel_shape[0] as u64) * (kernel_shape[1] as u64) * channels_per_group),
+                &((kernel_length as u64) * channels_per_group),
             );
             context.insert("pad", &pads);
             context.insert("count_include_pad", &count_include_pad);
@@ -928,13 +1339,13 @@ pub fn compile(
                 "MaxPool" | "AveragePool" | "GlobalAveragePool" => {
                     if channels_per_group % 4 == 0 {
                         NodeTemplate {
-                            scalar_type: agreed_type(input_shapes, &output_shapes[0..1])?,
+                            scalar_type: agreed_type(&input_shapes, &output_shapes[0..1])?,
                             template: "pool/aggregate.wgsl",
                             threads: (ceil(output_lengths[0], 1024) as _, 1, 1),
                         }
                     } else {
                         NodeTemplate {
-                            scalar_type: agreed_type(input_shapes, &output_shapes[0..1])?,
+                            scalar_type: agreed_type(&input_shapes, &output_shapes[0..1])?,
                             template: "pool/aggregate.wgsl",
                             threads: (ceil(output_lengths[0], 256) as _, 1, 1),
                         }
@@ -945,7 +1356,7 @@ pub fn compile(
                     let alpha = node.get_attribute_value("alpha", Some(0.01))?;
                     context.insert("alpha", &alpha);
 
-                    let scalar_type = agreed_type(input_shapes, output_shapes)?;
+                    let scalar_type = agreed_type(&input_shapes, output_shapes)?;
 
                     // WGSL shader for convolution computation
                     // Matrixes in WGSL are only supported for floating point types, so we can only use these (faster) shader
@@ -959,7 +1370,7 @@ pub fn compile(
                         && group == 1
                     {
                         NodeTemplate {
-                            scalar_type: agreed_type(input_shapes, output_shapes)?,
+                            scalar_type: agreed_type(&input_shapes, output_shapes)?,
                             template: "pool/conv_kernel_1.wgsl",
                             threads: (ceil(output_lengths[0], 1024) as _, 1, 1),
                         }
@@ -977,17 +1388,134 @@ pub fn compile(
                         }
                     } else {
                         NodeTemplate {
-                            scalar_type: agreed_type(input_shapes, output_shapes)?,
+                            scalar_type: agreed_type(&input_shapes, output_shapes)?,
                             template: "pool/conv.wgsl",
                             threads: (ceil(output_lengths[0], 256) as _, 1, 1),
                         }
                     }
                 }
+                "QLinearConv" => {
+                    if all_input_shapes.len() < 8 {
+                        return Err(CompileError::InvalidInputCount {
+                            expected: 8,
+                            actual: all_input_shapes.len(),
+                        });
+                    }
+                    insert_quantized_type(&mut context, "x", all_input_shapes[0].data_type)?;
+                    insert_quantized_type(&mut context, "w", all_input_shapes[3].data_type)?;
+                    insert_quantized_type(&mut context, "output", output_shapes[0].data_type)?;
+
+                    // The scale and zero point of the weights are either per tensor or per output channel
+                    context.insert(
+                        "per_channel_weights",
+                        &(all_input_shapes[4].element_count() > 1),
+                    );
+
+                    // Each thread produces four values, which are packed together in a single output element
+                    let packed_length = ceil(output_lengths[0], 4);
+                    context.insert("packed_length", &packed_length);
+
+                    NodeTemplate {
+                        scalar_type: ScalarType::F32,
+                        template: "quantization/conv.wgsl",
+                        threads: (ceil(packed_length, 256) as _, 1, 1),
+                    }
+                }
                 _ => return Err(CompileError::InvalidOperation(op.to_string())),
             }
         }
-        op @ ("Gemm" | "MatMul") => {
+        "ConvTranspose" | "ConvTransposeRelu" | "ConvTransposeLeakyRelu" => {
+            // TODO: ConvTranspose only supports NxCxHxW for the moment.
+            if input_shapes.len() < 2 {
+                return Err(CompileError::InvalidInputCount {
+                    expected: 2,
+                    actual: input_shapes.len(),
+                });
+            }
+            let input_shape = input_shapes[0];
+            let weight_shape = input_shapes[1];
+            let output_shape = output_shapes[0];
+            if input_shape.rank() != 4 {
+                return Err(CompileError::InvalidInputShape {
+                    input_index: 0,
+                    input_shape: input_shape.clone(),
+                });
+            }
+
+            // The weights are shaped [C, M / group, kH, kW] (unlike Conv, where these are [M, C / group, kH, kW])
+            let group = node.get_attribute_value("group", Some(1))? as u64;
+            let channels_per_group = input_shape.dim(1) / group.max(1);
+            if group == 0 || channels_per_group * group != input_shape.dim(1) {
+                return Err(CompileError::InvalidInputShape {
+                    input_index: 0,
+                    input_shape: input_shape.clone(),
+                });
+            }
+            if weight_shape.rank() != 4
+                || weight_shape.dim(0) != input_shape.dim(1)
+                || weight_shape.dim(1) * group != output_shape.dim(1)
+            {
+                return Err(CompileError::InvalidInputShape {
+                    input_index: 1,
+                    input_shape: weight_shape.clone(),
+                });
+            }
+            if input_shapes.len() > 2 && input_shapes[2].element_count() != output_shape.dim(1) {
+                return Err(CompileError::InvalidInputShape {
+                    input_index: 2,
+                    input_shape: input_shapes[2].clone(),
+                });
+            }
+
+            let kernel_shape = node.get_attribute_value::<Vec<i64>>(
+                "kernel_shape",
+                Some(vec![weight_shape.dim(2) as i64, weight_shape.dim(3) as i64]),
+            )?;
+            let strides = node.get_attribute_value("strides", Some(vec![1, 1]))?;
+            let dilations = node.get_attribute_value("dilations", Some(vec![1, 1]))?;
+            let pads = conv_transpose_pads(
+                node,
+                &input_shape.dims[2..],
+                &kernel_shape,
+                &output_shape.dims[2..],
+                opset_version,
+            )?;
+            let maps_per_group = weight_shape.dim(1);
+            let kernel_length = (kernel_shape[0] * kernel_shape[1]) as u64;
+
+            // Alpha is the Leaky Relu attribute
+            let alpha = node.get_attribute_value("alpha", Some(0.01))?;
+            context.insert("alpha", &alpha);
+            context.insert("original_width", &input_shape.dim(3));
+            context.insert("original_height", &input_shape.dim(2));
+            context.insert("groups", &group);
+            context.insert("channels_per_group", &channels_per_group);
+            context.insert("maps_per_group", &maps_per_group);
+            context.insert("stride", &strides);
+            context.insert("kernel_shape", &kernel_shape);
+            context.insert("kernel_length", &kernel_length);
+            context.insert("kernel_channel_len", &(kernel_length * maps_per_group));
+            context.insert("pad", &pads);
+            context.insert("dilation", &dilations);
+
+            NodeTemplate {
+                scalar_type: agreed_type(input_shapes, output_shapes)?,
+                template: "pool/conv_transpose.wgsl",
+                threads: (ceil(output_lengths[0], 256) as _, 1, 1),
+            }
+        }
+        op @ ("Gemm" | "MatMul" | "QLinearMatMul" | "MatMulInteger") => {
             // Generic matrix multiplication; outputs an M*N matrix from inputs A (size M*K) and B (size K*N)
+            // The quantized variants behave like MatMul, but take the B matrix as fourth input (QLinearMatMul) or have
+            // zero points as additional inputs (MatMulInteger).
+            let is_quantized = op == "QLinearMatMul" || op == "MatMulInteger";
+            let right_index = if op == "QLinearMatMul" { 3 } else { 1 };
+            if input_shapes.len() <= right_index {
+                return Err(CompileError::InvalidInputCount {
+                    expected: right_index + 1,
+                    actual: input_shapes.len(),
+                });
+            }
 
             // MatMul behaves "like numpy.matmul" (https://docs.scipy.org/doc/numpy-1.13.0/reference/generated/numpy.matmul.html)
             // If both arguments are 2-D they are multiplied like conventional matrices. If they are not, special rules are
@@ -996,13 +1524,13 @@ pub fn compile(
             // set a 'stack count' and 'stack stride'.
             let mut stack_count = 1;
             let mut input_left_shape = input_shapes[0].clone();
-            let mut input_right_shape = input_shapes[1].clone();
+            let mut input_right_shape = input_shapes[right_index].clone();
             let mut output_shape = output_shapes[0].clone();
             let mut stack_left_stride: u64 = 0;
             let mut stack_right_stride: u64 = 0;
             let mut stack_output_stride: u64 = 0;
 
-            if op == "MatMul" {
+            if op != "Gemm" {
                 // - If either argument is N-D, N > 2, it is treated as a stack of matrices residing in the last two indexes
                 //   and broadcast accordingly.
                 if input_left_shape.rank() > 2 || input_right_shape.rank() > 2 {
@@ -1131,16 +1659,25 @@ pub fn compile(
             }
 
             // Due to a limitation in WGSL, we currently only support floating-point matrix multiplication
-            // See https://github.com/gfx-rs/naga/issues/1896
-            let scalar_type = agreed_type(input_shapes, output_shapes)?;
+            // See https://github.com/gfx-rs/naga/issues/1896 (the quantized variants are implemented using scalar
+            // integer arithmetic)
+            let scalar_type = if is_quantized {
+                ScalarType::F32
+            } else {
+                agreed_type(input_shapes, output_shapes)?
+            };
             match scalar_type {
-                ScalarType::I32 | ScalarType::I64 | ScalarType::U8 => {
+                ScalarType::I32
+                | ScalarType::I64
+                | ScalarType::U8
+                | ScalarType::I8
+                | ScalarType::Bool => {
                     return Err(CompileError::UnimplementedVariant {
                         variant: "with integers".to_string(),
                         op: op.to_string(),
                     })
                 }
-                ScalarType::F32 => (),
+                ScalarType::F32 | ScalarType::F16 => (),
             }
 
             // Obtain alpha and beta coefficients
@@ -1157,13 +1694,62 @@ pub fn compile(
             )?;
             context.insert("workgroup_size_y", &workgroup_size_y);
 
-            if dim_m == 1 {
-                let n_elements = output_shapes[0].dim(1);
-                let (x_threads, workgroup_size_x) = workgroup_size(
-                    n_elements,
-                    MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
-                    MAX_WORKGROUP_SIZE_X,
-                )?;
+            if is_quantized {
+                // Only a single scale and zero point per matrix is supported
+                for (input_index, input_shape) in input_shapes.iter().enumerate() {
+                    if input_index != 0
+                        && input_index != right_index
+                        && input_shape.element_count() != 1
+                    {
+                        return Err(CompileError::UnimplementedVariant {
+                            variant: "with per-row or per-column scales or zero points".to_string(),
+                            op: op.to_string(),
+                        });
+                    }
+                }
+
+                insert_quantized_type(&mut context, "a", input_shapes[0].data_type)?;
+                insert_quantized_type(&mut context, "b", input_shapes[right_index].data_type)?;
+                if op == "QLinearMatMul" {
+                    if input_shapes.len() < 8 {
+                        return Err(CompileError::InvalidInputCount {
+                            expected: 8,
+                            actual: input_shapes.len(),
+                        });
+                    }
+                    insert_quantized_type(&mut context, "output", output_shapes[0].data_type)?;
+                } else if output_shapes[0].data_type != ScalarType::I32 {
+                    return Err(CompileError::TypesDisagree(
+                        ScalarType::I32,
+                        output_shapes[0].data_type,
+                    ));
+                }
+
+                // Each thread produces four consecutive values (which are packed together for 8-bit outputs)
+                let packed_length = ceil(output_lengths[0], 4);
+                let (x_threads, workgroup_size_x) = workgroup_size(
+                    packed_length,
+                    MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                    MAX_WORKGROUP_SIZE_X,
+                )?;
+                context.insert("packed_length", &packed_length);
+                context.insert("workgroup_size_x", &workgroup_size_x);
+                context.insert("dim_m", &dim_m);
+                context.insert("dim_n", &dim_n);
+                context.insert("dim_k", &dim_k);
+
+                NodeTemplate {
+                    scalar_type,
+                    template: "quantization/matmul.wgsl",
+                    threads: (x_threads, 1, 1),
+                }
+            } else if dim_m == 1 {
+                let n_elements = output_shapes[0].dim(1);
+                let (x_threads, workgroup_size_x) = workgroup_size(
+                    n_elements,
+                    MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                    MAX_WORKGROUP_SIZE_X,
+                )?;
 
                 context.insert("workgroup_size_x", &workgroup_size_x);
                 NodeTemplate {
@@ -1365,6 +1951,238 @@ pub fn compile(
                 threads: (ceil(output_lengths[0], 256) as u32, 1, 1),
             }
         }
+        op @ ("LayerNormalization" | "InstanceNormalization" | "GroupNormalization") => {
+            // The input is divided into groups of contiguous elements that are each normalized by a single workgroup
+            let groups = normalization_groups(node, input_shapes, opset_version)?;
+
+            let has_bias = input_shapes.len() > 2;
+            if op != "LayerNormalization" && output_shapes.len() > 1 {
+                return Err(CompileError::UnimplementedVariant {
+                    op: op.to_string(),
+                    variant: format!("with {} outputs", output_shapes.len()),
+                });
+            }
+
+            let scalar_type = agreed_type(&input_shapes[0..1], &output_shapes[0..1])?;
+
+            // With stash_type=1 (the default) the mean and variance are computed in float32
+            let stash_type = node.get_attribute_value("stash_type", Some(1))?;
+            let accumulator_type = if stash_type == 1 {
+                ScalarType::F32
+            } else {
+                scalar_type
+            };
+            if !accumulator_type.is_float() {
+                return Err(CompileError::UnimplementedVariant {
+                    op: op.to_string(),
+                    variant: format!("with {} values", scalar_type),
+                });
+            }
+            if let Some(statistics_shape) = output_shapes.get(1) {
+                context.insert(
+                    "statistics_type",
+                    statistics_shape.data_type.wgsl_type_name(),
+                );
+            }
+
+            let epsilon = node.get_attribute_value("epsilon", Some(1e-05))?;
+            context.insert("epsilon", &epsilon);
+            context.insert("group_size", &groups.group_size);
+            context.insert("affine_groups", &groups.affine_groups);
+            context.insert("affine_group_stride", &groups.affine_group_stride);
+            context.insert("affine_divisor", &groups.affine_divisor);
+            context.insert("scale_length", &input_shapes[1].element_count());
+            context.insert("has_bias", &has_bias);
+            if has_bias {
+                context.insert("bias_length", &input_shapes[2].element_count());
+            }
+            context.insert("accumulator_type", accumulator_type.wgsl_type_name());
+            context.insert(
+                "workgroup_size_x",
+                &groups.group_size.next_power_of_two().clamp(1, 256),
+            );
+
+            NodeTemplate {
+                scalar_type,
+                template: "endomorphism/normalization.wgsl",
+                threads: (groups.group_count as u32, 1, 1),
+            }
+        }
+        op @ ("Expand" | "Tile") => {
+            // Both ops use the broadcast template with a single input. Expand broadcasts the input to the output shape
+            // (multidirectionally) whereas Tile repeats the input along each axis.
+            let input_padded_shape = if op == "Expand" {
+                let shape: Vec<i64> = node.get_attribute_value("shape", None)?;
+                let target_shape = Shape::from(input_shapes[0].data_type, &shape);
+                Shape::multi_broadcast(&[input_shapes[0].clone(), target_shape])
+                    .filter(|out_shape| out_shape.dims == output_shapes[0].dims)
+                    .ok_or_else(|| CompileError::InvalidBroadcast {
+                        input_shapes: vec![input_shapes[0].clone()],
+                        output_shape: output_shapes[0].clone(),
+                    })?;
+                input_shapes[0].left_padded_to(1, output_shapes[0].rank())
+            } else {
+                let repeats: Vec<i64> = node.get_attribute_value("repeats", None)?;
+                if repeats.len() != input_shapes[0].rank()
+                    || repeats
+                        .iter()
+                        .zip(input_shapes[0].dims.iter())
+                        .zip(output_shapes[0].dims.iter())
+                        .any(|((repeat, dim), out_dim)| {
+                            *repeat < 0 || (*repeat as u64) * dim != *out_dim
+                        })
+                {
+                    return Err(CompileError::InvalidAttributeValue {
+                        attribute: "repeats".to_string(),
+                        value: format!("{:?}", repeats),
+                        opset_version,
+                    });
+                }
+                input_shapes[0].clone()
+            };
+            context.insert("lhs_padded_shape", &input_padded_shape.dims);
+            context.insert("lhs_padded_chunks", &input_padded_shape.chunks());
+
+            let (x_threads, workgroup_size_x) = workgroup_size(
+                output_lengths[0],
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_X,
+            )?;
+            context.insert("workgroup_size_x", &workgroup_size_x);
+
+            NodeTemplate {
+                scalar_type: agreed_type(&input_shapes[0..1], &output_shapes[0..1])?,
+                template: "endomorphism/broadcast.wgsl",
+                threads: (x_threads, 1, 1),
+            }
+        }
+        "Slice" => {
+            // Each selected input element is at a fixed offset plus a (possibly negative) stride for each output dimension
+            let ranges = slice_ranges(node, input_shapes[0], output_shapes[0], opset_version)?;
+            let slice_offset: i64 = ranges
+                .iter()
+                .zip(input_chunks[0].iter())
+                .map(|((start, _), chunk)| start * (*chunk as i64))
+                .sum();
+            let slice_strides: Vec<i64> = ranges
+                .iter()
+                .zip(input_chunks[0].iter())
+                .map(|((_, step), chunk)| step * (*chunk as i64))
+                .collect();
+            context.insert("slice_offset", &slice_offset);
+            context.insert("slice_strides", &slice_strides);
+
+            NodeTemplate {
+                scalar_type: agreed_type(&input_shapes[0..1], &output_shapes[0..1])?,
+                template: "matrix/slice.wgsl",
+                threads: (ceil(output_lengths[0], 256) as u32, 1, 1),
+            }
+        }
+        op @ ("TopK" | "ArgMax" | "ArgMin") => {
+            // Indices are stored as 32-bit integers (unless in strict int64 mode, see `compile_int64`)
+            let row_count = insert_top_k_context(
+                &mut context,
+                node,
+                input_shapes,
+                output_shapes,
+                opset_version,
+            )?;
+            context.insert("indices_are_int64", &false);
+            context.insert("indices_type", "i32");
+
+            let (x_threads, workgroup_size_x) = workgroup_size(
+                row_count,
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_X,
+            )?;
+            context.insert("workgroup_size_x", &workgroup_size_x);
+
+            let value_outputs = if op == "TopK" {
+                &output_shapes[0..1]
+            } else {
+                &[]
+            };
+            NodeTemplate {
+                scalar_type: agreed_type(&input_shapes[0..1], value_outputs)?,
+                template: "pool/topk.wgsl",
+                threads: (x_threads, 1, 1),
+            }
+        }
+        "RoiAlign" => {
+            // Batch indices are stored as 32-bit integers (unless in strict int64 mode, see `compile_int64`)
+            insert_roi_align_context(
+                &mut context,
+                node,
+                input_shapes,
+                output_shapes,
+                opset_version,
+            )?;
+            context.insert("indices_are_int64", &false);
+            context.insert("indices_type", "i32");
+
+            let (x_threads, workgroup_size_x) = workgroup_size(
+                output_lengths[0],
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_X,
+            )?;
+            context.insert("workgroup_size_x", &workgroup_size_x);
+
+            NodeTemplate {
+                scalar_type: agreed_type(&input_shapes[0..2], output_shapes)?,
+                template: "pool/roialign.wgsl",
+                threads: (x_threads, 1, 1),
+            }
+        }
+        "Einsum" => {
+            // Each output element is the sum of the products of the inputs over all values of the summed labels. The
+            // offsets in each input are determined by the strides of each label in that input (see `label_strides`).
+            let equation = einsum_equation(node, input_shapes, output_shapes)?;
+            let scalar_type = agreed_type(input_shapes, output_shapes)?;
+            if scalar_type == ScalarType::Bool {
+                return Err(CompileError::UnimplementedVariant {
+                    variant: format!("with {} tensors", scalar_type),
+                    op: "Einsum".to_string(),
+                });
+            }
+
+            let input_dims: Vec<&[u64]> = input_shapes.iter().map(|s| s.dims.as_slice()).collect();
+            let sizes = equation
+                .label_sizes(&input_dims)
+                .map_err(|error| invalid_einsum_equation(node, error))?;
+            let summed_labels = equation.summed_labels();
+            let summed_dims: Vec<u64> = summed_labels.iter().map(|label| sizes[label]).collect();
+
+            // Shape::chunks is not used here, because it returns a stride for a scalar
+            let chunks = |dims: &[u64]| {
+                (0..dims.len())
+                    .map(|axis| dims[(axis + 1)..].iter().product())
+                    .collect::<Vec<u64>>()
+            };
+            context.insert("output_chunks", &chunks(&output_shapes[0].dims));
+            context.insert(
+                "output_strides",
+                &equation.label_strides(&input_dims, &equation.output),
+            );
+            context.insert("sum_chunks", &chunks(&summed_dims));
+            context.insert(
+                "sum_strides",
+                &equation.label_strides(&input_dims, &summed_labels),
+            );
+            context.insert("sum_count", &summed_dims.iter().product::<u64>());
+
+            let (x_threads, workgroup_size_x) = workgroup_size(
+                output_lengths[0],
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_X,
+            )?;
+            context.insert("workgroup_size_x", &workgroup_size_x);
+
+            NodeTemplate {
+                scalar_type,
+                template: "matrix/einsum.wgsl",
+                threads: (x_threads, 1, 1),
+            }
+        }
         "Transpose" => {
             let n_dims: i64 = input_shapes[0].rank() as i64;
             let default = (0..n_dims).rev().collect::<Vec<i64>>();
@@ -1396,48 +2214,114 @@ pub fn compile(
                 threads: (ceil(output_lengths[0], 256) as _, 1, 1),
             }
         }
+        op @ ("LSTM" | "GRU" | "RNN") => {
+            // The shader performs a single step of the sequence for all directions, batches and hidden units. It is
+            // dispatched once for each step (and phase), and keeps the hidden (and cell) state in a separate state buffer.
+            let recurrence = recurrence(node, input_shapes, opset_version)?;
+            let activations =
+                recurrent_activations(node, recurrence.num_directions as usize, opset_version)?;
+            let scalar_type = agreed_type(&input_shapes[0..3], output_shapes)?;
+            if !scalar_type.is_float() {
+                return Err(CompileError::UnimplementedVariant {
+                    op: op.to_string(),
+                    variant: format!("with {} tensors", scalar_type),
+                });
+            }
+
+            #[derive(serde::Serialize)]
+            struct Binding {
+                group: usize,
+                binding: usize,
+                name: String,
+                access: &'static str,
+                array_type: &'static str,
+            }
+
+            let input_names: &[&str] = match op {
+                "LSTM" => &[
+                    "x",
+                    "w",
+                    "r",
+                    "b",
+                    "sequence_lens",
+                    "initial_h",
+                    "initial_c",
+                    "p",
+                ],
+                _ => &["x", "w", "r", "b", "sequence_lens", "initial_h"],
+            };
+            let output_names = ["y", "y_h", "y_c"];
+            let mut bindings = vec![];
+            let mut add_binding = |name: String, access: &'static str, array_type: &'static str| {
+                let index = bindings.len();
+                bindings.push(Binding {
+                    group: index / MAX_BINDINGS_PER_GROUP,
+                    binding: index % MAX_BINDINGS_PER_GROUP,
+                    name,
+                    access,
+                    array_type,
+                });
+            };
+
+            for (position, name) in input_names.iter().enumerate() {
+                let provided = provided_input_index(node, position).is_some();
+                context.insert(format!("has_{}", name), &provided);
+                if provided {
+                    let array_type = if *name == "sequence_lens" {
+                        "IntArray"
+                    } else {
+                        "Array"
+                    };
+                    add_binding(format!("input_{}", name), "read", array_type);
+                }
+            }
+            for (position, name) in output_names.iter().enumerate() {
+                let provided = position < output_shapes.len();
+                context.insert(format!("has_{}", name), &provided);
+                if provided {
+                    add_binding(format!("output_{}", name), "read_write", "Array");
+                }
+            }
+            add_binding("state".to_string(), "read_write", "Array");
+            context.insert(
+                "step_group",
+                &ceil(bindings.len() as u64, MAX_BINDINGS_PER_GROUP as u64),
+            );
+            context.insert("bindings", &bindings);
+
+            let direction = node.get_attribute_value("direction", Some("forward".to_string()))?;
+            let clip: Option<f32> = node.get_attribute_value("clip", None).ok();
+            context.insert("has_clip", &clip.is_some());
+            context.insert("clip", &clip.unwrap_or_default());
+            context.insert(
+                "input_forget",
+                &node.get_attribute_value("input_forget", Some(0))?,
+            );
+            context.insert(
+                "linear_before_reset",
+                &node.get_attribute_value("linear_before_reset", Some(0))?,
+            );
+            context.insert("direction", &direction);
+            context.insert("activations", &activations);
+            context.insert("phases", &recurrence.phases);
+            context.insert("sequence_length", &recurrence.sequence_length);
+            context.insert("batch_size", &recurrence.batch_size);
+            context.insert("input_size", &recurrence.input_size);
+            context.insert("hidden_size", &recurrence.hidden_size);
+            context.insert("gate_count", &recurrence.gate_count);
+            context.insert("state_length", &recurrence.state_length());
+
+            NodeTemplate {
+                scalar_type,
+                template: "sequence/recurrent.wgsl",
+                threads: (ceil(recurrence.state_length(), 256) as u32, 1, 1),
+            }
+        }
         op => return Err(CompileError::UnimplementedOp(op.to_string())),
     };
 
-    // Check if we remain within the limits of the thread count allowed by WebGPU
-    if node_template.threads.0 > MAX_COMPUTE_WORKGROUPS_PER_DIMENSION {
-        return Err(CompileError::ComputeLimitExceeded(
-            String::from("X threads"),
-            node_template.threads.0 as _,
-            MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
-        ));
-    }
-    if node_template.threads.1 > MAX_COMPUTE_WORKGROUPS_PER_DIMENSION {
-        return Err(CompileError::ComputeLimitExceeded(
-            String::from("Y threads"),
-            node_template.threads.1 as _,
-            MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
-        ));
-    }
-    if node_template.threads.2 > MAX_COMPUTE_WORKGROUPS_PER_DIMENSION {
-        return Err(CompileError::ComputeLimitExceeded(
-            String::from("Z threads"),
-            node_template.threads.2 as _,
-            MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
-        ));
-    }
-
-    // Determine (default) scalar data type to use
-    context.insert("scalar_type", node_template.scalar_type.wgsl_type_name());
-    context.insert(
-        "scalar_type_is_float",
-        &node_template.scalar_type.is_float(),
-    );
-    context.insert("scalar_stride", &node_template.scalar_type.stride());
-    context.insert(
-        "vec4_stride",
-        &(MultiType::Vec(node_template.scalar_type, 4).stride()),
-    );
-    context.insert(
-        "mat4x4_stride",
-        &(MultiType::Mat(node_template.scalar_type, 4, 4).stride()),
-    );
-    context.insert("mat3x3_stride", &(48));
+    check_thread_limits(node_template.threads)?;
+    insert_scalar_type(&mut context, node_template.scalar_type);
 
     // Render template
     let shader = get_templates()
@@ -1450,44 +2334,1459 @@ pub fn compile(
     })
 }
 
-/// Determines the appropriate number of threads and workgroup size given a number of times the entry point of the shader should be run
-fn workgroup_size(
-    x: u64,
-    max_threads: u32,
-    max_workgroup_size: u32,
-) -> Result<(u32, u32), CompileError> {
-    let max_x = max_threads as u64;
+/// Compile a node that is implemented by a custom operator
+fn compile_custom(
+    operator: &dyn CustomOperator,
+    node: &crate::onnx::NodeProto,
+    input_shapes: &[&Shape],
+    output_shapes: &[&Shape],
+    mut context: Context,
+    strict_int64: bool,
+) -> Result<CompiledNode, CompileError> {
+    let custom_shader = operator.compile(node, input_shapes, output_shapes)?;
+    check_thread_limits(custom_shader.threads)?;
+
+    let shader = match custom_shader.source {
+        ShaderSource::Wgsl(source) => source,
+        ShaderSource::Template(template) => {
+            // Use the type of the first output (or input) as default scalar type, like the built-in ops do
+            if let Some(shape) = output_shapes.first().or_else(|| input_shapes.first()) {
+                match shape.data_type {
+                    ScalarType::I64 if strict_int64 => insert_int64_scalar_type(&mut context),
+                    ScalarType::I64 => insert_scalar_type(&mut context, ScalarType::I32),
+                    data_type => insert_scalar_type(&mut context, data_type),
+                }
+            }
 
-    Ok(if x > max_x {
-        let workgroup_size = ceil(x, max_x) as _;
-        let threads = ceil(x, workgroup_size as u64) as _;
-        log::debug!(
-            "number of items ({}) exceeds maximum number of threads ({}); adjusting workgroup size={} and threads={} (this will compute {} items)",
-            x,
-            max_x,
-            workgroup_size,
-            threads,
-            workgroup_size * threads
-        );
+            // Add the template to (a copy of) the built-in templates so that it can include these
+            let mut tera = get_templates().clone();
+            let template_name = format!("custom/{}.wgsl", node.get_op_type());
+            tera.add_raw_template(&template_name, &template)
+                .and_then(|_| tera.render(&template_name, &context))
+                .map_err(|error| CompileError::CustomTemplateError {
+                    op: node.get_op_type().to_string(),
+                    error: error.to_string(),
+                })?
+        }
+    };
 
-        if threads > max_threads {
-            return Err(CompileError::ComputeLimitExceeded(
-                String::from("threads"),
-                threads,
-                max_threads,
-            ));
+    Ok(CompiledNode {
+        shader,
+        threads: custom_shader.threads,
+    })
+}
+
+/// Compile a node that has int64 inputs or outputs in strict int64 mode. In this mode int64 values are stored as
+/// vec2<u32> (low and high 32 bits) on the GPU and only a subset of the operators is supported.
+fn compile_int64(
+    node: &crate::onnx::NodeProto,
+    input_shapes: &[&Shape],
+    output_shapes: &[&Shape],
+    opset_version: i64,
+    mut context: Context,
+) -> Result<CompiledNode, CompileError> {
+    let op_type = node.get_op_type();
+    let unsupported = || CompileError::UnimplementedVariant {
+        variant: String::from("int64 (strict)"),
+        op: op_type.to_string(),
+    };
+
+    let (x_threads, workgroup_size_x) = workgroup_size(
+        output_shapes[0].element_count(),
+        MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+        MAX_WORKGROUP_SIZE_X,
+    )?;
+    context.insert("workgroup_size_x", &workgroup_size_x);
+
+    let (template, threads) = match op_type {
+        "Add" | "Sub" | "Mul" | "Div" | "Mod" | "Equal" | "Greater" | "GreaterOrEqual" | "Less"
+        | "LessOrEqual" => {
+            if input_shapes.len() != 2 {
+                return Err(CompileError::InvalidInputCount {
+                    expected: 2,
+                    actual: input_shapes.len(),
+                });
+            }
+            if input_shapes
+                .iter()
+                .any(|shape| shape.data_type != ScalarType::I64)
+            {
+                return Err(CompileError::TypesDisagree(
+                    input_shapes[0].data_type,
+                    input_shapes[1].data_type,
+                ));
+            }
+
+            let out_shape =
+                Shape::multi_broadcast(&[input_shapes[0].clone(), input_shapes[1].clone()])
+                    .filter(|out_shape| out_shape.dims == output_shapes[0].dims)
+                    .ok_or_else(|| CompileError::InvalidBroadcast {
+                        input_shapes: input_shapes.iter().map(|x| (*x).clone()).collect(),
+                        output_shape: output_shapes[0].clone(),
+                    })?;
+
+            let lhs_padded_shape = input_shapes[0].left_padded_to(1, out_shape.rank());
+            let rhs_padded_shape = input_shapes[1].left_padded_to(1, out_shape.rank());
+            context.insert("lhs_padded_shape", &lhs_padded_shape.dims);
+            context.insert("rhs_padded_shape", &rhs_padded_shape.dims);
+            context.insert("lhs_padded_chunks", &lhs_padded_shape.chunks());
+            context.insert("rhs_padded_chunks", &rhs_padded_shape.chunks());
+
+            let output_type = output_shapes[0].data_type;
+            if !matches!(op_type, "Add" | "Sub" | "Mul" | "Div" | "Mod")
+                && !matches!(
+                    output_type,
+                    ScalarType::Bool | ScalarType::I64 | ScalarType::I32 | ScalarType::F32
+                )
+            {
+                return Err(unsupported());
+            }
+            context.insert("output_type", int64_wgsl_type_name(output_type));
+            context.insert("output_is_int64", &(output_type == ScalarType::I64));
+            context.insert("fmod", &(node.get_attribute_value("fmod", Some(0))? != 0));
+            ("int64/arithmetic.wgsl", (x_threads, 1, 1))
         }
 
-        if workgroup_size > max_workgroup_size {
-            return Err(CompileError::ComputeLimitExceeded(
-                String::from("workgroup size"),
-                workgroup_size,
-                max_workgroup_size,
-            ));
+        "Gather" => {
+            let axis = node.get_attribute_value("axis", Some(0))?;
+            if axis != 0 {
+                return Err(CompileError::UnimplementedVariant {
+                    variant: format!("axis={}", axis),
+                    op: String::from("Gather"),
+                });
+            }
+
+            let indices_type = input_shapes[1].data_type;
+            if !matches!(indices_type, ScalarType::I64 | ScalarType::I32) {
+                return Err(unsupported());
+            }
+            context.insert("data_type", int64_wgsl_type_name(input_shapes[0].data_type));
+            context.insert("indices_type", int64_wgsl_type_name(indices_type));
+            context.insert("indices_are_int64", &(indices_type == ScalarType::I64));
+
+            // The X dimension represents the indexes, the Y dimension the elements to copy for each index
+            let (x_threads, workgroup_size_x) = workgroup_size(
+                input_shapes[1].element_count(),
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_X,
+            )?;
+            let (y_threads, workgroup_size_y) = workgroup_size(
+                input_shapes[0].chunks()[0],
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_Y,
+            )?;
+            context.insert("workgroup_size_x", &workgroup_size_x);
+            context.insert("workgroup_size_y", &workgroup_size_y);
+            ("int64/gather.wgsl", (x_threads, y_threads, 1))
         }
 
-        (threads, workgroup_size)
-    } else {
-        (x as u32, 1)
+        "Cast" => {
+            let input_type = input_shapes[0].data_type;
+            let output_type = output_shapes[0].data_type;
+            if input_type == ScalarType::U8 || output_type == ScalarType::U8 {
+                return Err(unsupported());
+            }
+            context.insert("input_type", int64_wgsl_type_name(input_type));
+            context.insert("output_type", int64_wgsl_type_name(output_type));
+            context.insert("input_is_int64", &(input_type == ScalarType::I64));
+            context.insert("output_is_int64", &(output_type == ScalarType::I64));
+            context.insert("input_is_float", &input_type.is_float());
+            context.insert("output_is_float", &output_type.is_float());
+            context.insert("output_is_bool", &(output_type == ScalarType::Bool));
+            ("int64/cast.wgsl", (x_threads, 1, 1))
+        }
+
+        "Concat" => {
+            if input_shapes
+                .iter()
+                .chain(output_shapes.iter())
+                .any(|shape| shape.data_type != ScalarType::I64)
+            {
+                return Err(unsupported());
+            }
+
+            let rank = output_shapes[0].rank() as i64;
+            let axis = node.get_attribute_value::<i64>("axis", None)?;
+            let axis = if axis < 0 { axis + rank } else { axis };
+            if axis < 0 || axis >= rank {
+                return Err(CompileError::InvalidAttributeValue {
+                    attribute: String::from("axis"),
+                    value: axis.to_string(),
+                    opset_version,
+                });
+            }
+            let axis = axis as usize;
+
+            let mut axis_offsets = vec![0];
+            for shape in input_shapes {
+                axis_offsets.push(axis_offsets.last().unwrap() + shape.dim(axis));
+            }
+            let inner_size: u64 = output_shapes[0].dims[(axis + 1)..].iter().product();
+            context.insert("axis_offsets", &axis_offsets);
+            context.insert("inner_size", &inner_size);
+            ("int64/concat.wgsl", (x_threads, 1, 1))
+        }
+
+        "TopK" | "ArgMax" | "ArgMin" => {
+            // Only the indices are 64-bit integers
+            let input_type = input_shapes[0].data_type;
+            if input_type == ScalarType::I64 {
+                return Err(unsupported());
+            }
+            let row_count = insert_top_k_context(
+                &mut context,
+                node,
+                input_shapes,
+                output_shapes,
+                opset_version,
+            )?;
+            context.insert("indices_are_int64", &true);
+            context.insert("indices_type", "Int64");
+            insert_scalar_type(&mut context, input_type);
+
+            let (x_threads, workgroup_size_x) = workgroup_size(
+                row_count,
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_X,
+            )?;
+            context.insert("workgroup_size_x", &workgroup_size_x);
+            ("pool/topk.wgsl", (x_threads, 1, 1))
+        }
+
+        "RoiAlign" => {
+            // Only the batch indices are 64-bit integers
+            let input_type = input_shapes[0].data_type;
+            if !input_type.is_float() {
+                return Err(unsupported());
+            }
+            insert_roi_align_context(
+                &mut context,
+                node,
+                input_shapes,
+                output_shapes,
+                opset_version,
+            )?;
+            context.insert("indices_are_int64", &true);
+            context.insert("indices_type", "Int64");
+            insert_scalar_type(&mut context, input_type);
+            ("pool/roialign.wgsl", (x_threads, 1, 1))
+        }
+
+        "GatherElements" | "GatherND" | "ScatterElements" | "ScatterND" | "ScatterNDInPlace" => {
+            // Int64 data can only be copied, not combined with updates
+            let data_type = input_shapes[0].data_type;
+            let indices_type = input_shapes[1].data_type;
+            if matches!(data_type, ScalarType::U8 | ScalarType::I8)
+                || (data_type == ScalarType::I64
+                    && scatter_reduction(node, opset_version)? != ScatterReduction::None)
+            {
+                return Err(unsupported());
+            }
+            context.insert("data_type", int64_wgsl_type_name(data_type));
+            context.insert("data_is_int64", &(data_type == ScalarType::I64));
+            context.insert("indices_type", int64_wgsl_type_name(indices_type));
+            context.insert("indices_are_int64", &(indices_type == ScalarType::I64));
+            let (template, invocations) = insert_gather_scatter_context(
+                &mut context,
+                node,
+                input_shapes,
+                output_shapes,
+                opset_version,
+            )?;
+
+            let (x_threads, workgroup_size_x) = workgroup_size(
+                invocations,
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+                MAX_WORKGROUP_SIZE_X,
+            )?;
+            context.insert("workgroup_size_x", &workgroup_size_x);
+            (template, (x_threads, 1, 1))
+        }
+
+        _ => return Err(unsupported()),
+    };
+
+    check_thread_limits(threads)?;
+    let shader = get_templates()
+        .render(template, &context)
+        .expect("failed to render shader");
+
+    Ok(CompiledNode { shader, threads })
+}
+
+/// Returns whether the op is one of the quantization ops, which are the only ops that can access 8-bit integer tensors
+fn is_quantization_op(op_type: &str) -> bool {
+    matches!(
+        op_type,
+        "QuantizeLinear" | "DequantizeLinear" | "QLinearConv" | "QLinearMatMul" | "MatMulInteger"
+    )
+}
+
+/// Inserts the name of an 8-bit integer type ('u8' or 'i8') as `<name>_type` into the context. For outputs, the range of
+/// values the type can represent is inserted as well (as `lowest` and `highest`), for use when saturating.
+fn insert_quantized_type(
+    context: &mut Context,
+    name: &str,
+    scalar_type: ScalarType,
+) -> Result<(), CompileError> {
+    let (type_name, lowest, highest) = match scalar_type {
+        ScalarType::U8 => ("u8", u8::MIN as i32, u8::MAX as i32),
+        ScalarType::I8 => ("i8", i8::MIN as i32, i8::MAX as i32),
+        _ => return Err(CompileError::NotQuantizedType(scalar_type)),
+    };
+    context.insert(format!("{name}_type"), type_name);
+    if name == "output" {
+        context.insert("lowest", &lowest);
+        context.insert("highest", &highest);
+    }
+    Ok(())
+}
+
+/// The WGSL type used for elements of a tensor of the specified type in strict int64 mode
+fn int64_wgsl_type_name(scalar_type: ScalarType) -> &'static str {
+    match scalar_type {
+        ScalarType::I64 => "Int64",
+        // 8 bit unsigned integers are stored as 32 bit ints
+        ScalarType::U8 => "i32",
+        scalar_type => scalar_type.wgsl_type_name(),
+    }
+}
+
+/// Check if we remain within the limits of the thread count allowed by WebGPU
+fn check_thread_limits(threads: (u32, u32, u32)) -> Result<(), CompileError> {
+    for (name, count) in [
+        ("X threads", threads.0),
+        ("Y threads", threads.1),
+        ("Z threads", threads.2),
+    ] {
+        if count > MAX_COMPUTE_WORKGROUPS_PER_DIMENSION {
+            return Err(CompileError::ComputeLimitExceeded(
+                String::from(name),
+                count as _,
+                MAX_COMPUTE_WORKGROUPS_PER_DIMENSION,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Describes how the input of a LayerNormalization, InstanceNormalization or GroupNormalization node is divided into
+/// groups of contiguous elements that are normalized together, and how the scale and bias are selected for each element.
+/// For element `i` of group `g` the scale and bias are at index `(g % affine_groups) * affine_group_stride + i /
+/// affine_divisor` (modulo their length, as they may be broadcast).
+pub(crate) struct NormalizationGroups {
+    pub group_count: u64,
+    pub group_size: u64,
+    pub affine_groups: u64,
+    pub affine_group_stride: u64,
+    pub affine_divisor: u64,
+}
+
+pub(crate) fn normalization_groups(
+    node: &crate::onnx::NodeProto,
+    input_shapes: &[&Shape],
+    opset_version: i64,
+) -> Result<NormalizationGroups, CompileError> {
+    let op = node.get_op_type();
+    if input_shapes.len() < 2 {
+        return Err(CompileError::InvalidInputCount {
+            expected: 2,
+            actual: input_shapes.len(),
+        });
+    }
+    let input_shape = input_shapes[0];
+    let rank = input_shape.rank();
+    if op != "LayerNormalization" && rank < 2 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: input_shape.clone(),
+        });
+    }
+    let spatial_size = input_shape.dims.iter().skip(2).product::<u64>();
+
+    // The scale and bias are either specified per element of a group (LayerNormalization), per channel or per group
+    let scale_length = input_shapes[1].element_count();
+    let (group_size, affine_groups, affine_group_stride, affine_divisor) = match op {
+        "LayerNormalization" => {
+            let mut axis = node.get_attribute_value("axis", Some(-1))?;
+            if axis < 0 {
+                axis += rank as i64;
+            }
+            if axis < 0 || axis >= rank as i64 {
+                return Err(CompileError::InvalidAttributeValue {
+                    attribute: "axis".to_string(),
+                    value: format!("{}", axis),
+                    opset_version,
+                });
+            }
+
+            // The scale and bias are broadcast to the normalized dimensions
+            for (input_index, shape) in input_shapes.iter().enumerate().skip(1) {
+                let first_non_one = shape.dims.iter().position(|d| *d != 1);
+                let trailing_dims = &shape.dims[first_non_one.unwrap_or(shape.rank())..];
+                if !input_shape.dims[(axis as usize)..].ends_with(trailing_dims) {
+                    return Err(CompileError::InvalidInputShape {
+                        input_index,
+                        input_shape: (*shape).clone(),
+                    });
+                }
+            }
+            let group_size = input_shape.dims[(axis as usize)..].iter().product::<u64>();
+            (group_size, 1, 0, 1)
+        }
+        "InstanceNormalization" => {
+            let channels = input_shape.dim(1);
+            if scale_length != channels {
+                return Err(CompileError::InvalidInputShape {
+                    input_index: 1,
+                    input_shape: input_shapes[1].clone(),
+                });
+            }
+            (spatial_size, channels, 1, spatial_size)
+        }
+        "GroupNormalization" => {
+            let num_groups: i64 = node.get_attribute_value("num_groups", None)?;
+            let channels = input_shape.dim(1);
+            if num_groups <= 0 || channels % (num_groups as u64) != 0 {
+                return Err(CompileError::InvalidAttributeValue {
+                    attribute: "num_groups".to_string(),
+                    value: format!("{}", num_groups),
+                    opset_version,
+                });
+            }
+            let num_groups = num_groups as u64;
+            let channels_per_group = channels / num_groups;
+
+            // Before opset 21, the scale and bias are specified per group instead of per channel
+            let group_size = channels_per_group * spatial_size;
+            if scale_length == num_groups {
+                (group_size, num_groups, 1, group_size)
+            } else if scale_length == channels {
+                (group_size, num_groups, channels_per_group, spatial_size)
+            } else {
+                return Err(CompileError::InvalidInputShape {
+                    input_index: 1,
+                    input_shape: input_shapes[1].clone(),
+                });
+            }
+        }
+        _ => unreachable!(),
+    };
+
+    let group_count = input_shape
+        .element_count()
+        .checked_div(group_size)
+        .unwrap_or(0);
+
+    // Apart from LayerNormalization (where it is broadcast), the bias has the same shape as the scale
+    if op != "LayerNormalization"
+        && input_shapes.len() > 2
+        && input_shapes[2].element_count() != scale_length
+    {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 2,
+            input_shape: input_shapes[2].clone(),
+        });
+    }
+
+    Ok(NormalizationGroups {
+        group_count,
+        group_size,
+        affine_groups,
+        affine_group_stride,
+        affine_divisor,
+    })
+}
+
+/// Determines the padding at the start of each spatial axis for a ConvTranspose node. When the output shape is specified
+/// (either explicitly or through auto_pad), the total padding follows from the output shape and is divided between the
+/// start and end of each axis as specified by ONNX. Otherwise the padding is taken from the pads attribute.
+pub(crate) fn conv_transpose_pads(
+    node: &crate::onnx::NodeProto,
+    input_spatial_dims: &[u64],
+    kernel_shape: &[i64],
+    output_spatial_dims: &[u64],
+    opset_version: i64,
+) -> Result<Vec<i64>, CompileError> {
+    let spatial_rank = input_spatial_dims.len();
+    let ones = vec![1; spatial_rank];
+    let strides = node.get_attribute_value("strides", Some(ones.clone()))?;
+    let dilations = node.get_attribute_value("dilations", Some(ones))?;
+    let output_padding = node.get_attribute_value("output_padding", Some(vec![0; spatial_rank]))?;
+    let auto_pad = node.get_attribute_value("auto_pad", Some("NOTSET".to_string()))?;
+    for (attribute, values) in [
+        ("kernel_shape", kernel_shape),
+        ("strides", &strides),
+        ("dilations", &dilations),
+        ("output_padding", &output_padding),
+    ] {
+        if values.len() != spatial_rank {
+            return Err(CompileError::InvalidAttributeValue {
+                attribute: attribute.to_string(),
+                value: format!("{:?}", values),
+                opset_version,
+            });
+        }
+    }
+
+    if node.has_attribute("output_shape") || auto_pad.starts_with("SAME_") {
+        Ok((0..spatial_rank)
+            .map(|d| {
+                let total_padding = strides[d] * (input_spatial_dims[d] as i64 - 1)
+                    + output_padding[d]
+                    + (kernel_shape[d] - 1) * dilations[d]
+                    + 1
+                    - output_spatial_dims[d] as i64;
+                // The total padding may be negative, in which case it is rounded down like in the ONNX reference
+                if auto_pad == "SAME_UPPER" {
+                    total_padding.div_euclid(2)
+                } else {
+                    total_padding - total_padding.div_euclid(2)
+                }
+            })
+            .collect())
+    } else {
+        match auto_pad.as_str() {
+            "NOTSET" => {
+                let pads = node.get_attribute_value("pads", Some(vec![0; spatial_rank * 2]))?;
+                Ok(pads[0..spatial_rank].to_vec())
+            }
+            "VALID" => Ok(vec![0; spatial_rank]),
+            _ => Err(CompileError::UnimplementedVariant {
+                op: node.get_op_type().to_string(),
+                variant: format!("auto_pad={}", auto_pad),
+            }),
+        }
+    }
+}
+
+/// Returns the index of the input at the specified position among the inputs of a node that are provided, or `None` if the
+/// optional input at that position is not provided (i.e. it has an empty name or lies beyond the end of the input list).
+/// Inputs that are not provided are not passed to the node implementations, so this is where provided inputs end up.
+pub(crate) fn provided_input_index(
+    node: &crate::onnx::NodeProto,
+    position: usize,
+) -> Option<usize> {
+    let inputs = node.get_input();
+    match inputs.get(position) {
+        Some(name) if !name.is_empty() => {
+            Some(inputs[..position].iter().filter(|n| !n.is_empty()).count())
+        }
+        _ => None,
+    }
+}
+
+/// The dimensions of the sequence processed by a recurrent (LSTM, GRU or RNN) node
+pub(crate) struct Recurrence {
+    pub sequence_length: u64,
+    pub batch_size: u64,
+    pub input_size: u64,
+    pub hidden_size: u64,
+    pub num_directions: u64,
+    /// The number of gates, each of which has its own rows in the weight and bias tensors (LSTM: i, o, f, c; GRU: z, r, h;
+    /// RNN: i)
+    pub gate_count: u64,
+    /// Whether the sequence is processed in reverse, for each direction
+    pub reversed: Vec<bool>,
+    /// The number of dispatches needed for each step. GRU applies the reset gate to the hidden state before multiplying it
+    /// with the recurrence weights (unless linear_before_reset is set), which requires a separate first phase.
+    pub phases: u32,
+}
+
+impl Recurrence {
+    /// The number of values in the hidden state (one for each direction, batch and hidden unit)
+    pub fn state_length(&self) -> u64 {
+        self.num_directions * self.batch_size * self.hidden_size
+    }
+}
+
+/// Determines the dimensions of the sequence processed by a recurrent node from its input (X: [seq_length, batch_size,
+/// input_size]) and weights (W: [num_directions, gate_count * hidden_size, input_size]).
+pub(crate) fn recurrence(
+    node: &crate::onnx::NodeProto,
+    input_shapes: &[&Shape],
+    opset_version: i64,
+) -> Result<Recurrence, CompileError> {
+    let op = node.get_op_type();
+    let gate_count = match op {
+        "LSTM" => 4,
+        "GRU" => 3,
+        "RNN" => 1,
+        _ => return Err(CompileError::InvalidOperation(op.to_string())),
+    };
+
+    let provided_inputs = node.get_input().iter().filter(|n| !n.is_empty()).count();
+    if input_shapes.len() < 3 || input_shapes.len() != provided_inputs {
+        return Err(CompileError::InvalidInputCount {
+            expected: provided_inputs.max(3),
+            actual: input_shapes.len(),
+        });
+    }
+
+    let layout = node.get_attribute_value("layout", Some(0))?;
+    if layout != 0 {
+        return Err(CompileError::UnimplementedVariant {
+            op: op.to_string(),
+            variant: format!("layout={}", layout),
+        });
+    }
+
+    let direction = node.get_attribute_value("direction", Some("forward".to_string()))?;
+    let reversed = match direction.as_str() {
+        "forward" => vec![false],
+        "reverse" => vec![true],
+        "bidirectional" => vec![false, true],
+        _ => {
+            return Err(CompileError::InvalidAttributeValue {
+                attribute: "direction".to_string(),
+                value: direction,
+                opset_version,
+            })
+        }
+    };
+    let num_directions = reversed.len() as u64;
+
+    let (x, w, r) = (input_shapes[0], input_shapes[1], input_shapes[2]);
+    if x.rank() != 3 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: x.clone(),
+        });
+    }
+    let hidden_size = r.dims.last().copied().unwrap_or(0);
+    if w.dims != [num_directions, gate_count * hidden_size, x.dim(2)] {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: w.clone(),
+        });
+    }
+    if r.dims != [num_directions, gate_count * hidden_size, hidden_size] {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 2,
+            input_shape: r.clone(),
+        });
+    }
+
+    let phases = if op == "GRU" && node.get_attribute_value("linear_before_reset", Some(0))? == 0 {
+        2
+    } else {
+        1
+    };
+
+    Ok(Recurrence {
+        sequence_length: x.dim(0),
+        batch_size: x.dim(1),
+        input_size: x.dim(2),
+        hidden_size,
+        num_directions,
+        gate_count,
+        reversed,
+        phases,
+    })
+}
+
+/// An activation function applied by a recurrent node, with its (optional) parameters
+#[derive(serde::Serialize)]
+pub(crate) struct RecurrentActivation {
+    pub name: String,
+    pub alpha: f32,
+    pub beta: f32,
+}
+
+/// Returns the activation functions of a recurrent node for each of its activation slots (LSTM: f, g, h; GRU: f, g; RNN:
+/// f), and for each direction within a slot. The alpha and beta parameters are consumed in order by the functions that use
+/// them; when not specified the defaults of the corresponding ONNX operators are used.
+pub(crate) fn recurrent_activations(
+    node: &crate::onnx::NodeProto,
+    num_directions: usize,
+    opset_version: i64,
+) -> Result<Vec<Vec<RecurrentActivation>>, CompileError> {
+    let defaults: &[&str] = match node.get_op_type() {
+        "LSTM" => &["Sigmoid", "Tanh", "Tanh"],
+        "GRU" => &["Sigmoid", "Tanh"],
+        _ => &["Tanh"],
+    };
+    let slot_count = defaults.len();
+
+    let default_names = defaults.repeat(num_directions);
+    let default_names: Vec<String> = default_names.iter().map(|s| s.to_string()).collect();
+    let mut names = node.get_attribute_value("activations", Some(default_names))?;
+    // The same functions are used for both directions when specified only once
+    if names.len() == slot_count {
+        names = names
+            .iter()
+            .cycle()
+            .take(slot_count * num_directions)
+            .cloned()
+            .collect();
+    }
+    if names.len() != slot_count * num_directions {
+        return Err(CompileError::InvalidAttributeValue {
+            attribute: "activations".to_string(),
+            value: format!("{:?}", names),
+            opset_version,
+        });
+    }
+
+    let alphas: Vec<f32> = node.get_attribute_value("activation_alpha", Some(vec![]))?;
+    let betas: Vec<f32> = node.get_attribute_value("activation_beta", Some(vec![]))?;
+    let mut alphas = alphas.into_iter();
+    let mut betas = betas.into_iter();
+
+    let mut activations: Vec<RecurrentActivation> = Vec::with_capacity(names.len());
+    for name in names {
+        let (default_alpha, default_beta) = match name.as_str() {
+            "Relu" | "Tanh" | "Sigmoid" | "Softsign" | "Softplus" => (None, None),
+            "Affine" => (Some(1.0), Some(0.0)),
+            "LeakyRelu" => (Some(0.01), None),
+            "ThresholdedRelu" | "Elu" => (Some(1.0), None),
+            "ScaledTanh" => (Some(1.0), Some(1.0)),
+            "HardSigmoid" => (Some(0.2), Some(0.5)),
+            _ => {
+                return Err(CompileError::UnimplementedVariant {
+                    op: node.get_op_type().to_string(),
+                    variant: format!("activation {}", name),
+                })
+            }
+        };
+        let alpha = default_alpha.map(|d| alphas.next().unwrap_or(d));
+        let beta = default_beta.map(|d| betas.next().unwrap_or(d));
+        activations.push(RecurrentActivation {
+            name,
+            alpha: alpha.unwrap_or(0.0),
+            beta: beta.unwrap_or(0.0),
+        });
+    }
+
+    // Activations are specified per direction, but are returned per slot
+    let mut slots: Vec<Vec<RecurrentActivation>> = (0..slot_count).map(|_| vec![]).collect();
+    for (index, activation) in activations.into_iter().enumerate() {
+        slots[index % slot_count].push(activation);
+    }
+    Ok(slots)
+}
+
+/// The elements selected by a TopK node (or an ArgMax or ArgMin node, which select a single index) along its axis
+pub(crate) struct TopKSelection {
+    /// The number of elements that are selected
+    pub k: u64,
+    /// Whether elements with larger values come first
+    pub largest: bool,
+    /// Whether elements with equal values are ordered by descending (instead of ascending) index
+    pub prefer_last_index: bool,
+    /// The number of elements along the axis
+    pub axis_length: u64,
+    /// The number of elements in the dimensions following the axis
+    pub inner_size: u64,
+    /// The number of positions in the other dimensions, for each of which elements are selected
+    pub row_count: u64,
+}
+
+/// Determines which elements are selected by a TopK, ArgMax or ArgMin node. For opset 10 and up, K is an input to TopK,
+/// which the optimizer moves to an attribute named 'K' when static (opset 1 has a single integer attribute 'k' instead).
+pub(crate) fn top_k_selection(
+    node: &crate::onnx::NodeProto,
+    input_shape: &Shape,
+    opset_version: i64,
+) -> Result<TopKSelection, CompileError> {
+    let op = node.get_op_type();
+    let rank = input_shape.rank() as i64;
+    let default_axis = if op == "TopK" { -1 } else { 0 };
+    let axis = node.get_attribute_value("axis", Some(default_axis))?;
+    let axis = if axis < 0 { axis + rank } else { axis };
+    if axis < 0 || axis >= rank {
+        return Err(CompileError::InvalidAttributeValue {
+            attribute: "axis".to_string(),
+            value: axis.to_string(),
+            opset_version,
+        });
+    }
+    let axis = axis as usize;
+    let axis_length = input_shape.dim(axis);
+
+    let (k, largest, prefer_last_index) = match op {
+        "TopK" => {
+            let k = if opset_version < 10 {
+                node.get_attribute_value::<i64>("k", None)?
+            } else {
+                let k: Vec<i64> = node.get_attribute_value("K", None)?;
+                if k.len() != 1 {
+                    return Err(CompileError::InvalidAttributeValue {
+                        attribute: "K".to_string(),
+                        value: format!("{:?}", k),
+                        opset_version,
+                    });
+                }
+                k[0]
+            };
+            let largest = node.get_attribute_value("largest", Some(1))? != 0;
+            (k, largest, false)
+        }
+        "ArgMax" | "ArgMin" => {
+            let select_last_index = node.get_attribute_value("select_last_index", Some(0))?;
+            (1, op == "ArgMax", select_last_index != 0)
+        }
+        _ => return Err(CompileError::InvalidOperation(op.to_string())),
+    };
+    if k < 1 || k as u64 > axis_length {
+        return Err(CompileError::InvalidAttributeValue {
+            attribute: "k".to_string(),
+            value: k.to_string(),
+            opset_version,
+        });
+    }
+
+    let inner_size = input_shape.dims[(axis + 1)..].iter().product::<u64>();
+    Ok(TopKSelection {
+        k: k as u64,
+        largest,
+        prefer_last_index,
+        axis_length,
+        inner_size,
+        row_count: input_shape.element_count() / axis_length.max(1),
     })
 }
+
+/// Inserts the variables used by the TopK template (which also implements ArgMax and ArgMin) into the context, and returns
+/// the number of rows for which elements are selected (each by a single invocation)
+fn insert_top_k_context(
+    context: &mut Context,
+    node: &crate::onnx::NodeProto,
+    input_shapes: &[&Shape],
+    output_shapes: &[&Shape],
+    opset_version: i64,
+) -> Result<u64, CompileError> {
+    let selection = top_k_selection(node, input_shapes[0], opset_version)?;
+    let has_values = node.get_op_type() == "TopK";
+    let indices_shape = output_shapes[if has_values { 1 } else { 0 }];
+    if indices_shape.element_count() != selection.row_count * selection.k {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: input_shapes[0].clone(),
+        });
+    }
+
+    context.insert("has_values", &has_values);
+    context.insert("k", &selection.k);
+    context.insert("largest", &selection.largest);
+    context.insert("prefer_last_index", &selection.prefer_last_index);
+    context.insert("axis_length", &selection.axis_length);
+    context.insert("inner_size", &selection.inner_size);
+    context.insert("row_count", &selection.row_count);
+    Ok(selection.row_count)
+}
+
+/// The parameters of a RoiAlign node, which pools a region of interest (RoI) of the input into a fixed size output
+pub(crate) struct RoiAlignment {
+    pub channels: u64,
+    pub height: u64,
+    pub width: u64,
+    pub output_height: u64,
+    pub output_width: u64,
+    /// The number of points sampled in each direction of an output bin. When zero, this is determined for each RoI from
+    /// the size of its bins (rounded up).
+    pub sampling_ratio: u64,
+    pub spatial_scale: f32,
+    /// Whether pixel coordinates are shifted by half a pixel ('half_pixel' mode) before sampling
+    pub half_pixel: bool,
+    /// Whether the maximum of the sampled values is taken instead of the average
+    pub max: bool,
+}
+
+/// Reads the attributes of a RoiAlign node and checks the shapes of its inputs (X, rois and batch_indices) and output.
+/// Before opset 16, there is no 'coordinate_transformation_mode' attribute and coordinates are not shifted.
+pub(crate) fn roi_alignment(
+    node: &crate::onnx::NodeProto,
+    input_shapes: &[&Shape],
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<RoiAlignment, CompileError> {
+    if input_shapes.len() < 3 {
+        return Err(CompileError::InvalidInputCount {
+            expected: 3,
+            actual: input_shapes.len(),
+        });
+    }
+    let (x, rois, batch_indices) = (input_shapes[0], input_shapes[1], input_shapes[2]);
+    if x.rank() != 4 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: x.clone(),
+        });
+    }
+    if rois.rank() != 2 || rois.dim(1) != 4 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: rois.clone(),
+        });
+    }
+    if batch_indices.dims != [rois.dim(0)] {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 2,
+            input_shape: batch_indices.clone(),
+        });
+    }
+
+    let output_height = node.get_attribute_value("output_height", Some(1))?;
+    let output_width = node.get_attribute_value("output_width", Some(1))?;
+    if output_shape.dims
+        != [
+            rois.dim(0),
+            x.dim(1),
+            output_height as u64,
+            output_width as u64,
+        ]
+    {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: x.clone(),
+        });
+    }
+
+    let sampling_ratio = node.get_attribute_value("sampling_ratio", Some(0))?;
+    if sampling_ratio < 0 {
+        return Err(CompileError::InvalidAttributeValue {
+            attribute: "sampling_ratio".to_string(),
+            value: sampling_ratio.to_string(),
+            opset_version,
+        });
+    }
+
+    let half_pixel = if opset_version >= 16 {
+        let mode = node.get_attribute_value(
+            "coordinate_transformation_mode",
+            Some("half_pixel".to_string()),
+        )?;
+        match mode.as_str() {
+            "half_pixel" => true,
+            "output_half_pixel" => false,
+            _ => {
+                return Err(CompileError::InvalidAttributeValue {
+                    attribute: "coordinate_transformation_mode".to_string(),
+                    value: mode,
+                    opset_version,
+                })
+            }
+        }
+    } else {
+        false
+    };
+
+    let mode = node.get_attribute_value("mode", Some("avg".to_string()))?;
+    let max = match mode.as_str() {
+        "avg" => false,
+        "max" => true,
+        _ => {
+            return Err(CompileError::InvalidAttributeValue {
+                attribute: "mode".to_string(),
+                value: mode,
+                opset_version,
+            })
+        }
+    };
+
+    Ok(RoiAlignment {
+        channels: x.dim(1),
+        height: x.dim(2),
+        width: x.dim(3),
+        output_height: output_height as u64,
+        output_width: output_width as u64,
+        sampling_ratio: sampling_ratio as u64,
+        spatial_scale: node.get_attribute_value("spatial_scale", Some(1.0))?,
+        half_pixel,
+        max,
+    })
+}
+
+/// Insert the parameters of a RoiAlign node into the context for the RoiAlign shader
+fn insert_roi_align_context(
+    context: &mut Context,
+    node: &crate::onnx::NodeProto,
+    input_shapes: &[&Shape],
+    output_shapes: &[&Shape],
+    opset_version: i64,
+) -> Result<(), CompileError> {
+    let alignment = roi_alignment(node, input_shapes, output_shapes[0], opset_version)?;
+    context.insert("channels", &alignment.channels);
+    context.insert("height", &alignment.height);
+    context.insert("width", &alignment.width);
+    context.insert("output_height", &alignment.output_height);
+    context.insert("output_width", &alignment.output_width);
+    context.insert("sampling_ratio", &alignment.sampling_ratio);
+    context.insert("spatial_scale", &alignment.spatial_scale);
+    context.insert("half_pixel", &alignment.half_pixel);
+    context.insert("max", &alignment.max);
+    Ok(())
+}
+
+/// How a ScatterND or ScatterElements node combines the updates with the values of the data input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ScatterReduction {
+    None,
+    Add,
+    Mul,
+    Max,
+    Min,
+}
+
+impl ScatterReduction {
+    /// The name of the reduction, as used in the 'reduction' attribute
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScatterReduction::None => "none",
+            ScatterReduction::Add => "add",
+            ScatterReduction::Mul => "mul",
+            ScatterReduction::Max => "max",
+            ScatterReduction::Min => "min",
+        }
+    }
+}
+
+/// Reads the 'reduction' attribute of a ScatterND or ScatterElements node. The 'add' and 'mul' reductions were added in
+/// opset 16, 'max' and 'min' in opset 18. Nodes without the attribute (including the Gather ops) simply replace values.
+pub(crate) fn scatter_reduction(
+    node: &crate::onnx::NodeProto,
+    opset_version: i64,
+) -> Result<ScatterReduction, CompileError> {
+    let reduction = node.get_attribute_value("reduction", Some("none".to_string()))?;
+    match (reduction.as_str(), opset_version) {
+        ("none", _) => Ok(ScatterReduction::None),
+        ("add", 16..) => Ok(ScatterReduction::Add),
+        ("mul", 16..) => Ok(ScatterReduction::Mul),
+        ("max", 18..) => Ok(ScatterReduction::Max),
+        ("min", 18..) => Ok(ScatterReduction::Min),
+        _ => Err(CompileError::InvalidAttributeValue {
+            attribute: "reduction".to_string(),
+            value: reduction,
+            opset_version,
+        }),
+    }
+}
+
+/// Inserts the variables used by the GatherElements, GatherND, ScatterElements and ScatterND templates into the context,
+/// and returns the template to render along with the number of invocations it needs. The data and indices types are
+/// inserted by the caller, as these depend on whether strict int64 mode is used.
+fn insert_gather_scatter_context(
+    context: &mut Context,
+    node: &crate::onnx::NodeProto,
+    input_shapes: &[&Shape],
+    output_shapes: &[&Shape],
+    opset_version: i64,
+) -> Result<(&'static str, u64), CompileError> {
+    let op = node.get_op_type();
+    let is_scatter = op.starts_with("Scatter");
+    let expected_inputs = if is_scatter { 3 } else { 2 };
+    if input_shapes.len() != expected_inputs {
+        return Err(CompileError::InvalidInputCount {
+            expected: expected_inputs,
+            actual: input_shapes.len(),
+        });
+    }
+
+    let (data_shape, indices_shape, output_shape) =
+        (input_shapes[0], input_shapes[1], output_shapes[0]);
+    if !matches!(indices_shape.data_type, ScalarType::I32 | ScalarType::I64) {
+        return Err(CompileError::UnimplementedVariant {
+            variant: format!("with indices of type {}", indices_shape.data_type),
+            op: op.to_string(),
+        });
+    }
+    let invalid_shape = |input_index: usize| CompileError::InvalidInputShape {
+        input_index,
+        input_shape: input_shapes[input_index].clone(),
+    };
+    if is_scatter {
+        context.insert("reduction", scatter_reduction(node, opset_version)?.name());
+        if output_shape.dims != data_shape.dims {
+            return Err(invalid_shape(0));
+        }
+    }
+
+    match op {
+        "GatherElements" | "ScatterElements" => {
+            let rank = data_shape.rank() as i64;
+            let axis = node.get_attribute_value("axis", Some(0))?;
+            let normalized_axis = if axis < 0 { axis + rank } else { axis };
+            if normalized_axis < 0 || normalized_axis >= rank {
+                return Err(CompileError::InvalidAttributeValue {
+                    attribute: "axis".to_string(),
+                    value: axis.to_string(),
+                    opset_version,
+                });
+            }
+
+            // The indices (and updates) have the same rank as the data, and the output of GatherElements has their shape
+            let shapes_match = if is_scatter {
+                input_shapes[2].dims == indices_shape.dims
+            } else {
+                output_shape.dims == indices_shape.dims
+            };
+            if indices_shape.rank() != data_shape.rank() || !shapes_match {
+                return Err(invalid_shape(1));
+            }
+
+            context.insert("axis", &normalized_axis);
+            let template = if is_scatter {
+                "endomorphism/scatter_elements.wgsl"
+            } else {
+                "endomorphism/gather_elements.wgsl"
+            };
+            Ok((template, output_shape.element_count()))
+        }
+
+        _ => {
+            // The last dimension of the indices holds tuples of indices into the leading dimensions of the data (following
+            // the batch dimensions, for GatherND). Each tuple selects a slice of the data.
+            let batch_dims = if is_scatter {
+                0
+            } else {
+                node.get_attribute_value("batch_dims", Some(0))?
+            };
+            let index_rank = indices_shape.rank();
+            if index_rank == 0 {
+                return Err(invalid_shape(1));
+            }
+            if batch_dims < 0 || batch_dims as usize >= index_rank.min(data_shape.rank()) {
+                return Err(CompileError::InvalidAttributeValue {
+                    attribute: "batch_dims".to_string(),
+                    value: batch_dims.to_string(),
+                    opset_version,
+                });
+            }
+            let batch_dims = batch_dims as usize;
+            let index_depth = indices_shape.dim(index_rank - 1) as usize;
+            if index_depth == 0
+                || batch_dims + index_depth > data_shape.rank()
+                || indices_shape.dims[..batch_dims] != data_shape.dims[..batch_dims]
+            {
+                return Err(invalid_shape(1));
+            }
+
+            let data_chunks = data_shape.chunks();
+            let tuple_count: u64 = indices_shape.dims[batch_dims..(index_rank - 1)]
+                .iter()
+                .product();
+            let slice_size: u64 = data_shape.dims[(batch_dims + index_depth)..]
+                .iter()
+                .product();
+            let mut sliced_dims = indices_shape.dims[..(index_rank - 1)].to_vec();
+            sliced_dims.extend_from_slice(&data_shape.dims[(batch_dims + index_depth)..]);
+            context.insert("index_depth", &index_depth);
+            context.insert(
+                "index_dims",
+                &data_shape.dims[batch_dims..(batch_dims + index_depth)],
+            );
+            context.insert(
+                "index_strides",
+                &data_chunks[batch_dims..(batch_dims + index_depth)],
+            );
+            context.insert("slice_size", &slice_size);
+
+            if is_scatter {
+                // The updates hold a slice for each index tuple
+                if input_shapes[2].dims != sliced_dims {
+                    return Err(invalid_shape(2));
+                }
+
+                // In place, only the updated slices are visited (see the optimizer)
+                let in_place = op == "ScatterNDInPlace";
+                context.insert("tuple_count", &tuple_count);
+                context.insert("in_place", &in_place);
+                let invocations = if in_place {
+                    tuple_count * slice_size
+                } else {
+                    output_shape.element_count()
+                };
+                Ok(("endomorphism/scatter_nd.wgsl", invocations))
+            } else {
+                if output_shape.dims != sliced_dims {
+                    return Err(invalid_shape(1));
+                }
+                context.insert("tuples_per_batch", &tuple_count);
+                context.insert(
+                    "batch_stride",
+                    &data_shape.dims[batch_dims..].iter().product::<u64>(),
+                );
+                Ok(("endomorphism/gather_nd.wgsl", output_shape.element_count()))
+            }
+        }
+    }
+}
+
+/// Determines the first element (start) and the distance between elements (step) selected by a Slice node along each
+/// axis of the input. Out-of-range starts and ends are clamped as specified by ONNX. The starts, ends, axes and steps are
+/// read from attributes; for opset 10 and up these are inputs, which the optimizer moves to attributes when static.
+pub(crate) fn invalid_einsum_equation(
+    node: &crate::onnx::NodeProto,
+    error: EinsumError,
+) -> CompileError {
+    CompileError::InvalidEinsumEquation {
+        equation: node
+            .get_attribute_value("equation", Some(String::new()))
+            .unwrap_or_default(),
+        error,
+    }
+}
+
+/// Parses the equation of an Einsum node for inputs of the given shapes, and checks that the output has the shape the
+/// equation describes.
+pub(crate) fn einsum_equation(
+    node: &crate::onnx::NodeProto,
+    input_shapes: &[&Shape],
+    output_shapes: &[&Shape],
+) -> Result<EinsumEquation, CompileError> {
+    let equation_text: String = node.get_attribute_value("equation", None)?;
+    let ranks: Vec<usize> = input_shapes.iter().map(|shape| shape.rank()).collect();
+    let equation = EinsumEquation::parse(&equation_text, &ranks)
+        .map_err(|error| invalid_einsum_equation(node, error))?;
+
+    let input_dims: Vec<&[u64]> = input_shapes.iter().map(|s| s.dims.as_slice()).collect();
+    let output_dims = equation
+        .output_dims(&input_dims)
+        .map_err(|error| invalid_einsum_equation(node, error))?;
+    if output_shapes[0].dims != output_dims {
+        return Err(CompileError::InvalidBroadcast {
+            input_shapes: input_shapes.iter().map(|s| (*s).clone()).collect(),
+            output_shape: output_shapes[0].clone(),
+        });
+    }
+    Ok(equation)
+}
+
+pub(crate) fn slice_ranges(
+    node: &crate::onnx::NodeProto,
+    input_shape: &Shape,
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<Vec<(i64, i64)>, CompileError> {
+    let rank = input_shape.rank();
+    let invalid = |attribute: &str, value: &[i64]| CompileError::InvalidAttributeValue {
+        attribute: attribute.to_string(),
+        value: format!("{:?}", value),
+        opset_version,
+    };
+
+    let starts: Vec<i64> = node.get_attribute_value("starts", None)?;
+    let ends: Vec<i64> = node.get_attribute_value("ends", None)?;
+    let axes: Vec<i64> =
+        node.get_attribute_value("axes", Some((0..(starts.len() as i64)).collect()))?;
+    let steps: Vec<i64> = node.get_attribute_value("steps", Some(vec![1; starts.len()]))?;
+    if ends.len() != starts.len() {
+        return Err(invalid("ends", &ends));
+    }
+    if axes.len() != starts.len() {
+        return Err(invalid("axes", &axes));
+    }
+    if steps.len() != starts.len() || steps.contains(&0) {
+        return Err(invalid("steps", &steps));
+    }
+
+    let mut ranges: Vec<(i64, i64)> = vec![(0, 1); rank];
+    let mut sliced = vec![false; rank];
+    for (index, axis) in axes.iter().enumerate() {
+        let axis_index = if *axis < 0 {
+            *axis + rank as i64
+        } else {
+            *axis
+        };
+        if axis_index < 0 || axis_index >= rank as i64 || sliced[axis_index as usize] {
+            return Err(invalid("axes", &axes));
+        }
+        let axis_index = axis_index as usize;
+        sliced[axis_index] = true;
+
+        let dim = input_shape.dim(axis_index) as i64;
+        let step = steps[index];
+        let (start, end) = (starts[index], ends[index]);
+        let start = if start < 0 {
+            start.saturating_add(dim)
+        } else {
+            start
+        };
+        let end = if end < 0 {
+            end.saturating_add(dim)
+        } else {
+            end
+        };
+        let (start, end) = if step > 0 {
+            (start.clamp(0, dim), end.clamp(0, dim))
+        } else {
+            (start.clamp(0, dim - 1), end.clamp(-1, dim - 1))
+        };
+
+        // The number of selected elements must match the output shape
+        let length = ((end - start) + step - step.signum()) / step;
+        if length.max(0) != output_shape.dim(axis_index) as i64 {
+            return Err(invalid("ends", &ends));
+        }
+        ranges[axis_index] = (start, step);
+    }
+    Ok(ranges)
+}
+
+/// Determine (default) scalar data type to use in templates
+fn insert_scalar_type(context: &mut Context, scalar_type: ScalarType) {
+    context.insert("scalar_type", scalar_type.wgsl_type_name());
+    context.insert("scalar_type_is_float", &scalar_type.is_float());
+    context.insert("scalar_stride", &scalar_type.stride());
+    context.insert("vec4_stride", &(MultiType::Vec(scalar_type, 4).stride()));
+    context.insert(
+        "mat4x4_stride",
+        &(MultiType::Mat(scalar_type, 4, 4).stride()),
+    );
+    // The columns of a 3x3 matrix are aligned like vec4
+    context.insert("mat3x3_stride", &(12 * scalar_type.stride()));
+    context.insert("scalar_type_is_int64", &false);
+}
+
+/// Sets the scalar type to the representation of int64 values in strict int64 mode (see `snippets/int64.wgsl`). There
+/// are no vectors or matrices of this type.
+fn insert_int64_scalar_type(context: &mut Context) {
+    context.insert("scalar_type", "vec2<u32>");
+    context.insert("scalar_type_is_float", &false);
+    context.insert("scalar_type_is_int64", &true);
+    context.insert("scalar_stride", &ScalarType::I64.stride());
+}
+
+/// Determines the appropriate number of threads and workgroup size given a number of times the entry point of the shader should be run
+fn workgroup_size(
+    x: u64,
+    max_threads: u32,
+    max_workgroup_size: u32,
+) -> Result<(u32, u32), CompileError> {
+    let max_x = max_threads as u64;
+
+    Ok(if x > max_x {
+        let workgroup_size = ceil(x, max_x) as _;
+        let threads = ceil(x, workgroup_size as u64) as _;
+        log::debug!(
+            "number of items ({}) exceeds maximum number of threads ({}); adjusting workgroup size={} and threads={} (this will compute {} items)",
+            x,
+            max_x,
+            workgroup_size,
+            threads,
+            workgroup_size * threads
+        );
+
+        if threads > max_threads {
+            return Err(CompileError::ComputeLimitExceeded(
+                String::from("threads"),
+                threads,
+                max_threads,
+            ));
+        }
+
+        if workgroup_size > max_workgroup_size {
+            return Err(CompileError::ComputeLimitExceeded(
+                String::from("workgroup size"),
+                workgroup_size,
+                max_workgroup_size,
+            ));
+        }
+
+        (threads, workgroup_size)
+    } else {
+        (x as u32, 1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compile;
+    use crate::{
+        registry::OperatorRegistry,
+        utils::{attribute, node, ScalarType, Shape},
+    };
+
+    #[test]
+    fn test_compile_strict_int64() {
+        let registry = OperatorRegistry::default();
+        let lhs = Shape::from(ScalarType::I64, &[2, 3]);
+        let rhs = Shape::from(ScalarType::I64, &[3]);
+        let add = node(vec!["A", "B"], vec!["C"], "add", "Add", vec![]);
+
+        // In strict mode int64 values are represented as vec2<u32>
+        let compiled = compile(&add, &[&lhs, &rhs], &[&lhs], 13, &registry, true).unwrap();
+        assert!(compiled.shader.contains("i64_add(lhs, rhs)"));
+        assert!(compiled.shader.contains("array<Int64>"));
+
+        // Otherwise they are truncated to i32
+        let compiled = compile(&add, &[&lhs, &rhs], &[&lhs], 13, &registry, false).unwrap();
+        assert!(!compiled.shader.contains("Int64"));
+
+        // Casting from int64 to float
+        let float = Shape::from(ScalarType::F32, &[2, 3]);
+        let cast = node(
+            vec!["A"],
+            vec!["C"],
+            "cast",
+            "Cast",
+            vec![attribute("to", 1)],
+        );
+        let compiled = compile(&cast, &[&lhs], &[&float], 13, &registry, true).unwrap();
+        assert!(compiled.shader.contains("i64_to_f32(value)"));
+
+        // Ops that are not supported for int64 in strict mode fail to compile
+        let relu = node(vec!["A"], vec!["C"], "relu", "Relu", vec![]);
+        assert!(compile(&relu, &[&lhs], &[&lhs], 13, &registry, true).is_err());
+    }
+
+    #[test]
+    fn test_compile_slice() {
+        let registry = OperatorRegistry::default();
+        let input = Shape::from(ScalarType::F32, &[3, 4]);
+        let output = Shape::from(ScalarType::F32, &[2, 2]);
+        let slice = node(
+            vec!["X"],
+            vec!["Y"],
+            "slice",
+            "Slice",
+            vec![
+                attribute("starts", vec![-1, 1]),
+                attribute("ends", vec![-10, 1000]),
+                attribute("axes", vec![0, -1]),
+                attribute("steps", vec![-2, 2]),
+            ],
+        );
+
+        // The first selected element is at row 2, column 1 and rows are traversed in reverse
+        let compiled = compile(&slice, &[&input], &[&output], 13, &registry, false).unwrap();
+        assert!(compiled.shader.contains("var index: i32 = 9;"));
+        assert!(compiled.shader.contains("* (-8)"));
+
+        // The output shape must match the number of selected elements
+        let wrong_output = Shape::from(ScalarType::F32, &[3, 2]);
+        assert!(compile(&slice, &[&input], &[&wrong_output], 13, &registry, false).is_err());
+    }
+
+    #[test]
+    fn test_compile_expand_tile() {
+        let registry = OperatorRegistry::default();
+        let input = Shape::from(ScalarType::F32, &[2, 2]);
+        let output = Shape::from(ScalarType::F32, &[4, 6]);
+        let tile = node(
+            vec!["X"],
+            vec!["Y"],
+            "tile",
+            "Tile",
+            vec![attribute("repeats", vec![2, 3])],
+        );
+        let compiled = compile(&tile, &[&input], &[&output], 13, &registry, false).unwrap();
+        assert!(compiled.shader.contains("out_index % 2u"));
+
+        // The repeats must match the output shape
+        let wrong_output = Shape::from(ScalarType::F32, &[4, 4]);
+        assert!(compile(&tile, &[&input], &[&wrong_output], 13, &registry, false).is_err());
+
+        let expand = node(
+            vec!["X"],
+            vec!["Y"],
+            "expand",
+            "Expand",
+            vec![attribute("shape", vec![3, 1, 2])],
+        );
+        let expanded = Shape::from(ScalarType::F32, &[3, 2, 2]);
+        let compiled = compile(&expand, &[&input], &[&expanded], 13, &registry, false).unwrap();
+        assert!(compiled.shader.contains("output_0.data[gidx] = lhs;"));
+    }
+}
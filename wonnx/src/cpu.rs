@@ -0,0 +1,3544 @@
+//! Executes the IR graph on the CPU using plain Rust implementations of each op. This is much slower than running on the
+//! GPU, but it does not require a GPU to be present and serves as a reference for the shader implementations.
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    sync::Arc,
+};
+
+use half::f16;
+use num::NumCast;
+use thiserror::Error;
+
+use crate::{
+    compiler::{
+        conv_transpose_pads, einsum_equation, invalid_einsum_equation, normalization_groups,
+        provided_input_index, recurrence, recurrent_activations, roi_alignment, scatter_reduction,
+        slice_ranges, top_k_selection, CompileError, RecurrentActivation, ScatterReduction,
+    },
+    gpu::op_forwards_input,
+    ir::{Node, NodeDefinition, NodeIdentifier},
+    onnx::{NodeProto, TensorProto},
+    padding::{padded_values, produced_rows, without_padding, PaddingError},
+    registry::OperatorRegistry,
+    utils::{
+        bool_data, float16_data, int8_data, uint8_data, AttributeNotFoundError, DataTypeError,
+        InputTensor, NodeAttributes, OutputTensor, ScalarType, Shape, TypedOutputTensor,
+    },
+};
+
+pub struct CpuModel {
+    onnx_opset_version: i64,
+    steps: Vec<CpuStep>,
+    value_count: usize,
+    inference_outputs: HashMap<String, InferenceOutput>,
+}
+
+/// An operation that is performed on the CPU as part of inference. Intermediate values are kept in 'slots', which are
+/// identified by their index.
+enum CpuStep {
+    /// Places a tensor with static contents (an initializer) in a slot
+    Initializer { value: usize, tensor: CpuTensor },
+
+    /// Places tensor data that is obtained from inference input in a slot
+    Input {
+        name: String,
+        value: usize,
+        shape: Shape,
+    },
+
+    /// Executes an op on tensors from the input slots, placing the results in the output slots. Slots listed in
+    /// `release` are not used by any later step and are emptied afterwards.
+    Operator {
+        proto: Box<NodeProto>,
+        inputs: Vec<usize>,
+        outputs: Vec<usize>,
+        output_shapes: Vec<Shape>,
+        release: Vec<usize>,
+    },
+}
+
+/// A tensor that resides in main memory. The data is reference counted so ops that only change the shape can share it.
+#[derive(Clone)]
+struct CpuTensor {
+    data: Arc<OutputTensor>,
+    shape: Shape,
+}
+
+enum InferenceOutput {
+    InferenceInput(String, Shape),
+    Value(usize),
+    /// A value that contains rows of padding (see `padding`), which are removed when it is read. `rows` is the value from
+    /// which the number of produced rows follows.
+    PaddedValue {
+        value: usize,
+        rows: usize,
+        axis: usize,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum CpuError {
+    #[error("executing node '{node}' failed: {error}")]
+    OperatorError { node: String, error: CompileError },
+
+    #[error("inference input not found: '{0}'")]
+    InferenceInputMissing(String),
+
+    #[error("inference input '{name}' has {actual} elements, but its shape requires {expected}")]
+    InferenceInputSizeMismatch {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("node output not found: index {0}")]
+    OutputMissing(usize),
+
+    #[error("scalar type error: {0}")]
+    ScalarType(#[from] DataTypeError),
+
+    #[error("value out of bounds in node '{0}'")]
+    OutOfBoundsError(String),
+
+    #[error("integer division by zero in node '{0}'")]
+    DivisionByZero(String),
+
+    #[error("node '{node}' uses custom operator '{op_type}', but custom operators are only supported by the GPU backend")]
+    CustomOperatorUnsupported { node: String, op_type: String },
+
+    #[error("padding error: {0}")]
+    PaddingError(#[from] PaddingError),
+}
+
+/// Errors raised by the kernels. These are turned into a [CpuError] that names the node that failed.
+#[derive(Debug)]
+enum KernelError {
+    Operator(CompileError),
+    OutOfBounds,
+    DivisionByZero,
+}
+
+impl From<CompileError> for KernelError {
+    fn from(error: CompileError) -> Self {
+        KernelError::Operator(error)
+    }
+}
+
+impl From<AttributeNotFoundError> for KernelError {
+    fn from(error: AttributeNotFoundError) -> Self {
+        KernelError::Operator(CompileError::AttributeNotFound(error))
+    }
+}
+
+impl From<DataTypeError> for KernelError {
+    fn from(error: DataTypeError) -> Self {
+        KernelError::Operator(CompileError::InvalidType(error))
+    }
+}
+
+impl KernelError {
+    fn for_node(self, proto: &NodeProto) -> CpuError {
+        let node = if proto.has_name() {
+            proto.get_name().to_string()
+        } else {
+            proto.get_op_type().to_string()
+        };
+
+        match self {
+            KernelError::Operator(error) => CpuError::OperatorError { node, error },
+            KernelError::OutOfBounds => CpuError::OutOfBoundsError(node),
+            KernelError::DivisionByZero => CpuError::DivisionByZero(node),
+        }
+    }
+}
+
+impl CpuModel {
+    /// Create a version of the specified model for which inference can be performed on the CPU
+    #[allow(clippy::mutable_key_type)]
+    pub fn from(
+        root: Arc<Node>,
+        onnx_opset_version: i64,
+        capture_intermediates: bool,
+        operator_registry: &OperatorRegistry,
+    ) -> Result<CpuModel, CpuError> {
+        let mut nodes = vec![];
+        let mut nodes_seen = HashSet::new();
+        Node::topological_sort(root.clone(), &mut nodes_seen, &mut nodes);
+        drop(nodes_seen);
+        let padded = padded_values(&nodes, onnx_opset_version)?;
+
+        // Assign a slot to each node output and translate nodes into steps
+        let mut node_values = HashMap::<NodeIdentifier, Vec<usize>>::new();
+        let mut value_count = 0;
+        let mut steps = vec![];
+
+        for node in &nodes {
+            let values = match &node.definition {
+                NodeDefinition::Operator(op_def) => {
+                    let proto = &op_def.proto;
+                    if operator_registry.get(proto).is_some() {
+                        let node = if proto.has_name() {
+                            proto.get_name()
+                        } else {
+                            proto.get_op_type()
+                        };
+                        return Err(CpuError::CustomOperatorUnsupported {
+                            node: node.to_string(),
+                            op_type: proto.get_op_type().to_string(),
+                        });
+                    }
+
+                    // Optional inputs that are not provided are not passed to the op
+                    let inputs = node
+                        .inputs
+                        .iter()
+                        .filter(|input| {
+                            !matches!(input.source_node.definition, NodeDefinition::Missing)
+                        })
+                        .map(|input| {
+                            node_values[&input.source_node.identifier()]
+                                .get(input.output_index)
+                                .copied()
+                                .ok_or(CpuError::OutputMissing(input.output_index))
+                        })
+                        .collect::<Result<Vec<usize>, CpuError>>()?;
+                    let outputs: Vec<usize> =
+                        (value_count..(value_count + op_def.output_shapes.len())).collect();
+                    value_count += outputs.len();
+
+                    steps.push(CpuStep::Operator {
+                        proto: Box::new(op_def.proto.clone().into_owned()),
+                        inputs,
+                        outputs: outputs.clone(),
+                        output_shapes: op_def.output_shapes.clone(),
+                        release: vec![],
+                    });
+                    outputs
+                }
+                NodeDefinition::Tensor(tensor_def) => {
+                    let value = value_count;
+                    value_count += 1;
+                    steps.push(CpuStep::Initializer {
+                        value,
+                        tensor: CpuTensor::from_proto(tensor_def)?,
+                    });
+                    vec![value]
+                }
+                NodeDefinition::Input(input_def) => {
+                    let value = value_count;
+                    value_count += 1;
+                    steps.push(CpuStep::Input {
+                        name: input_def.get_name().to_string(),
+                        value,
+                        shape: input_def.get_shape()?,
+                    });
+                    vec![value]
+                }
+                NodeDefinition::Missing | NodeDefinition::Outputs { .. } => vec![],
+            };
+            node_values.insert(node.identifier(), values);
+        }
+
+        // Find out which outputs we should return as inference outputs
+        let mut inference_outputs = HashMap::new();
+        if let NodeDefinition::Outputs { names } = &root.definition {
+            for (output_index, output_name) in names.iter().enumerate() {
+                let input = &root.inputs[output_index];
+                let output = match &input.source_node.definition {
+                    NodeDefinition::Input(proto) => InferenceOutput::InferenceInput(
+                        proto.get_name().to_string(),
+                        proto.get_shape()?,
+                    ),
+                    _ => {
+                        let source = (input.source_node.identifier(), input.output_index);
+                        let value = node_values[&source.0]
+                            .get(input.output_index)
+                            .copied()
+                            .ok_or(CpuError::OutputMissing(input.output_index))?;
+                        match padded.get(&source) {
+                            Some(rows) => InferenceOutput::PaddedValue {
+                                value,
+                                rows: node_values[&rows.source.0][rows.source.1],
+                                axis: rows.axis,
+                            },
+                            None => InferenceOutput::Value(value),
+                        }
+                    }
+                };
+                inference_outputs.insert(output_name.to_string(), output);
+            }
+        } else {
+            unimplemented!("reading from non-outputs IR node")
+        }
+
+        // When capturing intermediate values, also return the outputs of all other nodes (by ONNX value name)
+        if capture_intermediates {
+            for node in &nodes {
+                if let NodeDefinition::Operator(op_def) = &node.definition {
+                    let values = &node_values[&node.identifier()];
+                    for (output_index, (output_name, value)) in
+                        op_def.proto.get_output().iter().zip(values).enumerate()
+                    {
+                        if !output_name.is_empty() {
+                            inference_outputs.entry(output_name.to_string()).or_insert(
+                                match padded.get(&(node.identifier(), output_index)) {
+                                    Some(rows) => InferenceOutput::PaddedValue {
+                                        value: *value,
+                                        rows: node_values[&rows.source.0][rows.source.1],
+                                        axis: rows.axis,
+                                    },
+                                    None => InferenceOutput::Value(*value),
+                                },
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        // Determine after which step each intermediate value can be dropped
+        let mut last_use = HashMap::<usize, usize>::new();
+        for (step_index, step) in steps.iter().enumerate() {
+            if let CpuStep::Operator { inputs, .. } = step {
+                for input in inputs {
+                    last_use.insert(*input, step_index);
+                }
+            }
+        }
+
+        for output in inference_outputs.values() {
+            match output {
+                InferenceOutput::Value(value) => {
+                    last_use.remove(value);
+                }
+                InferenceOutput::PaddedValue { value, rows, .. } => {
+                    last_use.remove(value);
+                    last_use.remove(rows);
+                }
+                InferenceOutput::InferenceInput(..) => {}
+            }
+        }
+
+        for (value, step_index) in last_use {
+            if let CpuStep::Operator { release, .. } = &mut steps[step_index] {
+                release.push(value);
+            }
+        }
+
+        Ok(CpuModel {
+            onnx_opset_version,
+            steps,
+            value_count,
+            inference_outputs,
+        })
+    }
+
+    /// Perform inference using this model and the specified inference inputs.
+    pub fn infer(
+        &self,
+        inference_inputs: &HashMap<String, InputTensor>,
+    ) -> Result<HashMap<String, TypedOutputTensor>, CpuError> {
+        let mut values: Vec<Option<CpuTensor>> = vec![None; self.value_count];
+
+        for step in &self.steps {
+            match step {
+                CpuStep::Initializer { value, tensor } => {
+                    values[*value] = Some(tensor.clone());
+                }
+                CpuStep::Input { name, value, shape } => {
+                    let input_data = inference_inputs
+                        .get(name)
+                        .ok_or_else(|| CpuError::InferenceInputMissing(name.to_string()))?;
+                    let data = OutputTensor::from(input_data);
+                    let expected = shape.element_count() as usize;
+                    if element_count(&data) != expected {
+                        return Err(CpuError::InferenceInputSizeMismatch {
+                            name: name.to_string(),
+                            expected,
+                            actual: element_count(&data),
+                        });
+                    }
+
+                    let data = if scalar_type(&data) != shape.data_type {
+                        cast(&data, shape.data_type)
+                            .map_err(|_| CpuError::OutOfBoundsError(name.to_string()))?
+                    } else {
+                        data
+                    };
+
+                    values[*value] = Some(CpuTensor {
+                        data: Arc::new(data),
+                        shape: shape.clone(),
+                    });
+                }
+                CpuStep::Operator {
+                    proto,
+                    inputs,
+                    outputs,
+                    output_shapes,
+                    release,
+                } => {
+                    log::debug!("execute {} ({})", proto.get_name(), proto.get_op_type());
+                    let input_tensors: Vec<CpuTensor> = inputs
+                        .iter()
+                        .map(|input| {
+                            values[*input]
+                                .clone()
+                                .expect("value should be computed before it is used")
+                        })
+                        .collect();
+
+                    let output_tensors = execute(
+                        proto,
+                        &input_tensors,
+                        output_shapes,
+                        self.onnx_opset_version,
+                    )
+                    .map_err(|e| e.for_node(proto))?;
+
+                    for (value, tensor) in outputs.iter().zip(output_tensors) {
+                        values[*value] = Some(tensor);
+                    }
+
+                    for value in release {
+                        values[*value] = None;
+                    }
+                }
+            }
+        }
+
+        let mut output_data = HashMap::new();
+        for (output_name, output_source) in &self.inference_outputs {
+            output_data.insert(
+                output_name.to_string(),
+                match output_source {
+                    InferenceOutput::InferenceInput(input_name, shape) => TypedOutputTensor {
+                        shape: shape.clone(),
+                        data: (&inference_inputs[input_name]).into(),
+                    },
+                    InferenceOutput::Value(value) | InferenceOutput::PaddedValue { value, .. } => {
+                        let tensor = values[*value]
+                            .as_ref()
+                            .expect("output value should have been computed");
+                        let output = TypedOutputTensor {
+                            shape: tensor.shape.clone(),
+                            data: tensor.data.as_ref().clone(),
+                        };
+                        if let InferenceOutput::PaddedValue { rows, axis, .. } = output_source {
+                            let rows = values[*rows]
+                                .as_ref()
+                                .expect("padding source should have been computed");
+                            let row_count = produced_rows(&rows.data, &rows.shape);
+                            without_padding(output, *axis, row_count)
+                        } else {
+                            output
+                        }
+                    }
+                },
+            );
+        }
+        Ok(output_data)
+    }
+}
+
+impl CpuTensor {
+    /// Create a tensor containing the data of an initializer
+    fn from_proto(proto: &TensorProto) -> Result<CpuTensor, CpuError> {
+        let scalar_type = ScalarType::from_i32(proto.get_data_type())?;
+        let raw = proto.get_raw_data();
+        let data = match scalar_type {
+            ScalarType::F32 if proto.get_float_data().is_empty() => {
+                OutputTensor::F32(bytemuck::pod_collect_to_vec(raw))
+            }
+            ScalarType::F32 => OutputTensor::F32(proto.get_float_data().to_vec()),
+            ScalarType::F16 => OutputTensor::F16(float16_data(proto)),
+            ScalarType::I32 if proto.get_int32_data().is_empty() => {
+                OutputTensor::I32(bytemuck::pod_collect_to_vec(raw))
+            }
+            ScalarType::I32 => OutputTensor::I32(proto.get_int32_data().to_vec()),
+            ScalarType::I64 if proto.get_int64_data().is_empty() => {
+                OutputTensor::I64(bytemuck::pod_collect_to_vec(raw))
+            }
+            ScalarType::I64 => OutputTensor::I64(proto.get_int64_data().to_vec()),
+            ScalarType::U8 => OutputTensor::U8(uint8_data(proto)),
+            ScalarType::I8 => OutputTensor::I8(int8_data(proto)),
+            ScalarType::Bool => OutputTensor::Bool(bool_data(proto)),
+        };
+
+        Ok(CpuTensor {
+            data: Arc::new(data),
+            shape: Shape::from(scalar_type, proto.get_dims()),
+        })
+    }
+}
+
+/// Run the kernel for a single op on tensors that are read back from the GPU. This is used for ops that the GPU cannot
+/// perform (see `GpuStep::Host`).
+pub(crate) fn execute_on_host(
+    proto: &NodeProto,
+    inputs: Vec<TypedOutputTensor>,
+    output_shapes: &[Shape],
+    opset_version: i64,
+) -> Result<Vec<OutputTensor>, CpuError> {
+    let inputs: Vec<CpuTensor> = inputs
+        .into_iter()
+        .map(|input| CpuTensor {
+            data: Arc::new(input.data),
+            shape: input.shape,
+        })
+        .collect();
+    let outputs =
+        execute(proto, &inputs, output_shapes, opset_version).map_err(|e| e.for_node(proto))?;
+    Ok(outputs
+        .into_iter()
+        .map(|output| output.data.as_ref().clone())
+        .collect())
+}
+
+/// Run the kernel for a single op
+fn execute(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shapes: &[Shape],
+    opset_version: i64,
+) -> Result<Vec<CpuTensor>, KernelError> {
+    let op = proto.get_op_type();
+
+    // Some ops do nothing but forward their input
+    if op_forwards_input(op) {
+        expect_inputs(inputs, 1)?;
+        return Ok(vec![CpuTensor {
+            data: inputs[0].data.clone(),
+            shape: output_shapes[0].clone(),
+        }]);
+    }
+
+    let outputs: Vec<OutputTensor> = match op {
+        "Abs" | "Acos" | "Asin" | "Atan" | "Ceil" | "Cos" | "Cosh" | "Exp" | "Floor" | "Log"
+        | "Round" | "Sign" | "Sin" | "Sinh" | "Sqrt" | "Tan" | "Tanh" | "Reciprocal" | "Acosh"
+        | "Asinh" | "Atanh" | "Neg" => {
+            expect_inputs(inputs, 1)?;
+            vec![map(op, &inputs[0], &output_shapes[0])?]
+        }
+
+        "ReduceMean" | "ReduceSum" | "ReduceMax" | "ReduceMin" | "ReduceProd" | "ReduceL1"
+        | "ReduceL2" | "ReduceLogSum" | "ReduceLogSumExp" | "ReduceSumSquare" => {
+            expect_inputs(inputs, 1)?;
+            vec![reduce(proto, &inputs[0], &output_shapes[0])?]
+        }
+
+        "OneHot" => {
+            expect_inputs(inputs, 3)?;
+            vec![one_hot(proto, inputs, &output_shapes[0])?]
+        }
+
+        "Gather" => {
+            expect_inputs(inputs, 2)?;
+            vec![gather(proto, &inputs[0], &inputs[1], &output_shapes[0])?]
+        }
+
+        "GatherElements" => {
+            expect_inputs(inputs, 2)?;
+            let offsets = element_offsets(proto, &inputs[0], &inputs[1], opset_version)?;
+            vec![select(&inputs[0].data, &offsets)]
+        }
+
+        "GatherND" => {
+            expect_inputs(inputs, 2)?;
+            let offsets = slice_element_offsets(proto, &inputs[0], &inputs[1], opset_version)?;
+            vec![select(&inputs[0].data, &offsets)]
+        }
+
+        "ScatterElements" | "ScatterND" => {
+            expect_inputs(inputs, 3)?;
+            let offsets = if proto.get_op_type() == "ScatterElements" {
+                element_offsets(proto, &inputs[0], &inputs[1], opset_version)?
+            } else {
+                slice_element_offsets(proto, &inputs[0], &inputs[1], opset_version)?
+            };
+            if offsets.len() != element_count(&inputs[2].data) {
+                return Err(CompileError::InvalidInputShape {
+                    input_index: 2,
+                    input_shape: inputs[2].shape.clone(),
+                }
+                .into());
+            }
+            vec![scatter(
+                &inputs[0],
+                &inputs[2],
+                &offsets,
+                scatter_reduction(proto, opset_version)?,
+            )?]
+        }
+
+        "Cast" => {
+            expect_inputs(inputs, 1)?;
+            let cast_to_type =
+                ScalarType::from_i32(proto.get_attribute_value::<i64>("to", None)? as i32)?;
+            vec![cast(&inputs[0].data, cast_to_type)?]
+        }
+
+        "Softmax" => {
+            expect_inputs(inputs, 1)?;
+            vec![softmax(
+                proto,
+                &inputs[0],
+                &output_shapes[0],
+                opset_version,
+            )?]
+        }
+
+        "Add" | "And" | "Div" | "Equal" | "Greater" | "GreaterOrEqual" | "Less" | "LessOrEqual"
+        | "Mod" | "Mul" | "Or" | "Xor" | "Sub" | "Pow" | "PRelu" => {
+            let broadcast = proto.get_attribute_value("broadcast", Some(0))?;
+            if broadcast != 0 {
+                return Err(CompileError::UnimplementedVariant {
+                    op: op.to_string(),
+                    variant: "broadcast".to_string(),
+                }
+                .into());
+            }
+            vec![binary(op, proto, inputs, &output_shapes[0])?]
+        }
+
+        "Not" => {
+            expect_inputs(inputs, 1)?;
+            let values = to_i64(&inputs[0].data)
+                .into_iter()
+                .map(|x| (x == 0) as i64)
+                .collect();
+            vec![from_i64(values, ScalarType::Bool)?]
+        }
+
+        "Where" => {
+            expect_inputs(inputs, 3)?;
+            vec![where_(inputs, &output_shapes[0])?]
+        }
+
+        "LayerNormalization" | "InstanceNormalization" | "GroupNormalization" => {
+            expect_inputs(inputs, 2)?;
+            normalization(proto, inputs, output_shapes, opset_version)?
+        }
+
+        "BatchNormalization" => {
+            expect_inputs(inputs, 5)?;
+            vec![batch_normalization(
+                proto,
+                inputs,
+                &output_shapes[0],
+                opset_version,
+            )?]
+        }
+
+        "Relu" | "Sigmoid" | "Softsign" | "Softplus" | "Clip" | "Celu" | "Elu" | "LeakyRelu"
+        | "HardSigmoid" | "Erf" => {
+            expect_inputs(inputs, 1)?;
+            let activation = Activation::from_node(op, proto)?;
+            let values = to_f64(&inputs[0].data)
+                .into_iter()
+                .map(|x| activation.apply(x))
+                .collect();
+            vec![from_f64(values, output_shapes[0].data_type)?]
+        }
+
+        "Concat" => vec![concat(proto, inputs, &output_shapes[0])?],
+
+        "MaxPool" | "AveragePool" | "GlobalAveragePool" => {
+            expect_inputs(inputs, 1)?;
+            if output_shapes.len() > 1 {
+                return Err(CompileError::UnimplementedVariant {
+                    op: op.to_string(),
+                    variant: "with indices output".to_string(),
+                }
+                .into());
+            }
+            vec![pool(op, proto, &inputs[0], &output_shapes[0])?]
+        }
+
+        "Conv" | "ConvRelu" | "ConvLeakyRelu" | "ConvMish" => {
+            vec![conv(op, proto, inputs, &output_shapes[0])?]
+        }
+
+        "ConvTranspose" | "ConvTransposeRelu" | "ConvTransposeLeakyRelu" => vec![conv_transpose(
+            op,
+            proto,
+            inputs,
+            &output_shapes[0],
+            opset_version,
+        )?],
+
+        "Gemm" => vec![gemm(proto, inputs, &output_shapes[0])?],
+
+        "MatMul" => {
+            expect_inputs(inputs, 2)?;
+            vec![matmul(&inputs[0], &inputs[1], &output_shapes[0])?]
+        }
+
+        "QuantizeLinear" => {
+            expect_inputs(inputs, 2)?;
+            vec![quantize_linear(
+                proto,
+                inputs,
+                &output_shapes[0],
+                opset_version,
+            )?]
+        }
+
+        "DequantizeLinear" => {
+            expect_inputs(inputs, 2)?;
+            vec![dequantize_linear(
+                proto,
+                inputs,
+                &output_shapes[0],
+                opset_version,
+            )?]
+        }
+
+        "MatMulInteger" => {
+            expect_inputs(inputs, 2)?;
+            let a = shifted_by_zero_point(&inputs[0], inputs.get(2), 0)?;
+            let b = shifted_by_zero_point(&inputs[1], inputs.get(3), 0)?;
+            vec![matmul(&a, &b, &output_shapes[0])?]
+        }
+
+        "QLinearMatMul" => {
+            expect_inputs(inputs, 8)?;
+            let a = shifted_by_zero_point(&inputs[0], Some(&inputs[2]), 0)?;
+            let b = shifted_by_zero_point(&inputs[3], Some(&inputs[5]), 0)?;
+            let accumulated = matmul(&a, &b, &accumulator_shape(&output_shapes[0]))?;
+            let scales = to_f64(&inputs[1].data)[0] * to_f64(&inputs[4].data)[0];
+            vec![requantize(
+                accumulated,
+                &vec![scales; output_shapes[0].element_count() as usize],
+                &inputs[6],
+                &inputs[7],
+                &output_shapes[0],
+            )?]
+        }
+
+        "QLinearConv" => {
+            expect_inputs(inputs, 8)?;
+            // The zero point of the weights may be specified per output channel (axis 0)
+            let mut conv_inputs = vec![
+                shifted_by_zero_point(&inputs[0], Some(&inputs[2]), 1)?,
+                shifted_by_zero_point(&inputs[3], Some(&inputs[5]), 0)?,
+            ];
+            conv_inputs.extend(inputs.get(8).cloned());
+            let accumulated = conv(
+                "Conv",
+                proto,
+                &conv_inputs,
+                &accumulator_shape(&output_shapes[0]),
+            )?;
+            let x_scale = to_f64(&inputs[1].data)[0];
+            let scales = quantization_parameter(Some(&inputs[4]), &output_shapes[0], 1)?
+                .into_iter()
+                .map(|w_scale| w_scale * x_scale)
+                .collect::<Vec<f64>>();
+            vec![requantize(
+                accumulated,
+                &scales,
+                &inputs[6],
+                &inputs[7],
+                &output_shapes[0],
+            )?]
+        }
+
+        "Resize" => {
+            expect_inputs(inputs, 1)?;
+            vec![resize(proto, &inputs[0], &output_shapes[0])?]
+        }
+
+        "Split" => {
+            expect_inputs(inputs, 1)?;
+            split(proto, &inputs[0], output_shapes)?
+        }
+
+        "Pad" => {
+            expect_inputs(inputs, 1)?;
+            vec![pad(proto, &inputs[0], &output_shapes[0], opset_version)?]
+        }
+
+        "Expand" => {
+            expect_inputs(inputs, 1)?;
+            vec![expand(&inputs[0], &output_shapes[0])?]
+        }
+
+        "Tile" => {
+            expect_inputs(inputs, 1)?;
+            vec![tile(proto, &inputs[0], &output_shapes[0], opset_version)?]
+        }
+
+        "TopK" | "ArgMax" | "ArgMin" => {
+            expect_inputs(inputs, 1)?;
+            top_k(proto, &inputs[0], output_shapes, opset_version)?
+        }
+
+        "Slice" => {
+            expect_inputs(inputs, 1)?;
+            vec![slice(proto, &inputs[0], &output_shapes[0], opset_version)?]
+        }
+
+        "NonMaxSuppression" => {
+            expect_inputs(inputs, 2)?;
+            vec![non_max_suppression(proto, inputs, &output_shapes[0])?]
+        }
+
+        "RoiAlign" => {
+            expect_inputs(inputs, 3)?;
+            vec![roi_align(proto, inputs, &output_shapes[0], opset_version)?]
+        }
+
+        "LSTM" | "GRU" | "RNN" => recurrent(proto, inputs, output_shapes, opset_version)?,
+
+        "Einsum" => {
+            expect_inputs(inputs, 1)?;
+            vec![einsum(proto, inputs, &output_shapes[0])?]
+        }
+
+        "Transpose" => {
+            expect_inputs(inputs, 1)?;
+            vec![transpose(
+                proto,
+                &inputs[0],
+                &output_shapes[0],
+                opset_version,
+            )?]
+        }
+
+        op => return Err(CompileError::UnimplementedOp(op.to_string()).into()),
+    };
+
+    outputs
+        .into_iter()
+        .zip(output_shapes.iter())
+        .map(|(data, shape)| {
+            let data = if scalar_type(&data) != shape.data_type {
+                cast(&data, shape.data_type)?
+            } else {
+                data
+            };
+            debug_assert_eq!(element_count(&data), shape.element_count() as usize);
+            Ok(CpuTensor {
+                data: Arc::new(data),
+                shape: shape.clone(),
+            })
+        })
+        .collect()
+}
+
+fn expect_inputs(inputs: &[CpuTensor], expected: usize) -> Result<(), KernelError> {
+    if inputs.len() < expected {
+        return Err(CompileError::InvalidInputCount {
+            expected,
+            actual: inputs.len(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/* Helpers for dealing with the data of tensors of different scalar types. Arithmetic is performed on f64 (for floating
+point tensors) or i64 (for integer tensors); data movement is performed on the tensors' own types. */
+
+fn scalar_type(data: &OutputTensor) -> ScalarType {
+    match data {
+        OutputTensor::F32(_) => ScalarType::F32,
+        OutputTensor::F16(_) => ScalarType::F16,
+        OutputTensor::I32(_) => ScalarType::I32,
+        OutputTensor::I64(_) => ScalarType::I64,
+        OutputTensor::U8(_) => ScalarType::U8,
+        OutputTensor::I8(_) => ScalarType::I8,
+        OutputTensor::Bool(_) => ScalarType::Bool,
+    }
+}
+
+fn element_count(data: &OutputTensor) -> usize {
+    match data {
+        OutputTensor::F32(v) => v.len(),
+        OutputTensor::F16(v) => v.len(),
+        OutputTensor::I32(v) => v.len(),
+        OutputTensor::I64(v) => v.len(),
+        OutputTensor::U8(v) => v.len(),
+        OutputTensor::I8(v) => v.len(),
+        OutputTensor::Bool(v) => v.len(),
+    }
+}
+
+fn to_f64(data: &OutputTensor) -> Vec<f64> {
+    match data {
+        OutputTensor::F32(v) => v.iter().map(|x| *x as f64).collect(),
+        OutputTensor::F16(v) => v.iter().map(|x| x.to_f64()).collect(),
+        OutputTensor::I32(v) => v.iter().map(|x| *x as f64).collect(),
+        OutputTensor::I64(v) => v.iter().map(|x| *x as f64).collect(),
+        OutputTensor::U8(v) => v.iter().map(|x| *x as f64).collect(),
+        OutputTensor::I8(v) => v.iter().map(|x| *x as f64).collect(),
+        OutputTensor::Bool(v) => v.iter().map(|x| *x as u8 as f64).collect(),
+    }
+}
+
+/// Returns the data as i64 (floating point values are truncated)
+fn to_i64(data: &OutputTensor) -> Vec<i64> {
+    match data {
+        OutputTensor::F32(v) => v.iter().map(|x| *x as i64).collect(),
+        OutputTensor::F16(v) => v.iter().map(|x| x.to_f64() as i64).collect(),
+        OutputTensor::I32(v) => v.iter().map(|x| *x as i64).collect(),
+        OutputTensor::I64(v) => v.clone(),
+        OutputTensor::U8(v) => v.iter().map(|x| *x as i64).collect(),
+        OutputTensor::I8(v) => v.iter().map(|x| *x as i64).collect(),
+        OutputTensor::Bool(v) => v.iter().map(|x| *x as i64).collect(),
+    }
+}
+
+fn from_f64(values: Vec<f64>, data_type: ScalarType) -> Result<OutputTensor, KernelError> {
+    fn convert<T: NumCast>(values: Vec<f64>) -> Result<Vec<T>, KernelError> {
+        values
+            .into_iter()
+            .map(|x| T::from(x).ok_or(KernelError::OutOfBounds))
+            .collect()
+    }
+
+    Ok(match data_type {
+        ScalarType::F32 => OutputTensor::F32(values.into_iter().map(|x| x as f32).collect()),
+        ScalarType::F16 => OutputTensor::F16(values.into_iter().map(f16::from_f64).collect()),
+        ScalarType::I32 => OutputTensor::I32(convert(values)?),
+        ScalarType::I64 => OutputTensor::I64(convert(values)?),
+        ScalarType::U8 => OutputTensor::U8(convert(values)?),
+        ScalarType::I8 => OutputTensor::I8(convert(values)?),
+        ScalarType::Bool => OutputTensor::Bool(values.into_iter().map(|x| x != 0.0).collect()),
+    })
+}
+
+fn from_i64(values: Vec<i64>, data_type: ScalarType) -> Result<OutputTensor, KernelError> {
+    fn convert<T: TryFrom<i64>>(values: Vec<i64>) -> Result<Vec<T>, KernelError> {
+        values
+            .into_iter()
+            .map(|x| T::try_from(x).map_err(|_| KernelError::OutOfBounds))
+            .collect()
+    }
+
+    Ok(match data_type {
+        ScalarType::F32 => OutputTensor::F32(values.into_iter().map(|x| x as f32).collect()),
+        ScalarType::F16 => OutputTensor::F16(
+            values
+                .into_iter()
+                .map(|x| f16::from_f64(x as f64))
+                .collect(),
+        ),
+        ScalarType::I32 => OutputTensor::I32(convert(values)?),
+        ScalarType::I64 => OutputTensor::I64(values),
+        ScalarType::U8 => OutputTensor::U8(convert(values)?),
+        ScalarType::I8 => OutputTensor::I8(convert(values)?),
+        ScalarType::Bool => OutputTensor::Bool(values.into_iter().map(|x| x != 0).collect()),
+    })
+}
+
+fn cast(data: &OutputTensor, data_type: ScalarType) -> Result<OutputTensor, KernelError> {
+    if scalar_type(data).is_float() {
+        from_f64(to_f64(data), data_type)
+    } else {
+        from_i64(to_i64(data), data_type)
+    }
+}
+
+/// Creates a tensor of the same type as `data` containing the elements at the specified indices
+fn select(data: &OutputTensor, indices: &[usize]) -> OutputTensor {
+    match data {
+        OutputTensor::F32(v) => OutputTensor::F32(indices.iter().map(|i| v[*i]).collect()),
+        OutputTensor::F16(v) => OutputTensor::F16(indices.iter().map(|i| v[*i]).collect()),
+        OutputTensor::I32(v) => OutputTensor::I32(indices.iter().map(|i| v[*i]).collect()),
+        OutputTensor::I64(v) => OutputTensor::I64(indices.iter().map(|i| v[*i]).collect()),
+        OutputTensor::U8(v) => OutputTensor::U8(indices.iter().map(|i| v[*i]).collect()),
+        OutputTensor::I8(v) => OutputTensor::I8(indices.iter().map(|i| v[*i]).collect()),
+        OutputTensor::Bool(v) => OutputTensor::Bool(indices.iter().map(|i| v[*i]).collect()),
+    }
+}
+
+/// Like [select], but uses the specified value for elements that do not have an index
+fn select_or(
+    data: &OutputTensor,
+    indices: &[Option<usize>],
+    value: f64,
+) -> Result<OutputTensor, KernelError> {
+    fn pick<T: Copy + NumCast>(
+        v: &[T],
+        indices: &[Option<usize>],
+        value: f64,
+    ) -> Result<Vec<T>, KernelError> {
+        let value = T::from(value).ok_or(KernelError::OutOfBounds)?;
+        Ok(indices
+            .iter()
+            .map(|i| i.map(|i| v[i]).unwrap_or(value))
+            .collect())
+    }
+
+    Ok(match data {
+        OutputTensor::F32(v) => OutputTensor::F32(pick(v, indices, value)?),
+        OutputTensor::F16(v) => OutputTensor::F16(pick(v, indices, value)?),
+        OutputTensor::I32(v) => OutputTensor::I32(pick(v, indices, value)?),
+        OutputTensor::I64(v) => OutputTensor::I64(pick(v, indices, value)?),
+        OutputTensor::U8(v) => OutputTensor::U8(pick(v, indices, value)?),
+        OutputTensor::I8(v) => OutputTensor::I8(pick(v, indices, value)?),
+        OutputTensor::Bool(v) => OutputTensor::Bool(
+            indices
+                .iter()
+                .map(|i| i.map(|i| v[i]).unwrap_or(value != 0.0))
+                .collect(),
+        ),
+    })
+}
+
+/// Concatenates ranges (tensor index, start, length) of the specified tensors, which must all have the same type
+fn join(
+    tensors: &[&OutputTensor],
+    ranges: &[(usize, usize, usize)],
+) -> Result<OutputTensor, KernelError> {
+    macro_rules! join_variant {
+        ($variant: ident) => {{
+            let mut joined = vec![];
+            for (tensor_index, start, length) in ranges {
+                match tensors[*tensor_index] {
+                    OutputTensor::$variant(v) => {
+                        joined.extend_from_slice(&v[*start..(*start + *length)])
+                    }
+                    other => {
+                        return Err(CompileError::TypesDisagree(
+                            scalar_type(tensors[0]),
+                            scalar_type(other),
+                        )
+                        .into())
+                    }
+                }
+            }
+            OutputTensor::$variant(joined)
+        }};
+    }
+
+    Ok(match tensors[0] {
+        OutputTensor::F32(_) => join_variant!(F32),
+        OutputTensor::F16(_) => join_variant!(F16),
+        OutputTensor::I32(_) => join_variant!(I32),
+        OutputTensor::I64(_) => join_variant!(I64),
+        OutputTensor::U8(_) => join_variant!(U8),
+        OutputTensor::I8(_) => join_variant!(I8),
+        OutputTensor::Bool(_) => join_variant!(Bool),
+    })
+}
+
+/* Helpers for working with multi-dimensional indices */
+
+fn dims_of(shape: &Shape) -> Vec<usize> {
+    shape.dims.iter().map(|d| *d as usize).collect()
+}
+
+/// Returns the number of elements to skip in a flat array to advance one step in each dimension (row-major)
+fn strides(dims: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; dims.len()];
+    for i in (0..dims.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * dims[i + 1];
+    }
+    strides
+}
+
+/// Calls `f` for each element in a tensor of the specified dimensions (in row-major order) with its flat index and its
+/// multi-dimensional index.
+fn for_each_index(dims: &[usize], mut f: impl FnMut(usize, &[usize])) {
+    let count: usize = dims.iter().product();
+    let mut index = vec![0; dims.len()];
+    for flat_index in 0..count {
+        f(flat_index, &index);
+        for d in (0..dims.len()).rev() {
+            index[d] += 1;
+            if index[d] < dims[d] {
+                break;
+            }
+            index[d] = 0;
+        }
+    }
+}
+
+/// Returns the index of the source element for each element of a tensor with dimensions `output_dims` when a tensor with
+/// dimensions `input_dims` is broadcast to it (following numpy rules).
+fn broadcast_indices(input_dims: &[usize], output_dims: &[usize]) -> Option<Vec<usize>> {
+    if input_dims.len() > output_dims.len() {
+        return None;
+    }
+
+    let padding = output_dims.len() - input_dims.len();
+    let input_strides = strides(input_dims);
+    let mut broadcast_strides = vec![0; output_dims.len()];
+    for (d, dim) in input_dims.iter().enumerate() {
+        if *dim == output_dims[d + padding] {
+            broadcast_strides[d + padding] = input_strides[d];
+        } else if *dim != 1 {
+            return None;
+        }
+    }
+
+    let mut indices = Vec::with_capacity(output_dims.iter().product());
+    for_each_index(output_dims, |_, index| {
+        indices.push(
+            index
+                .iter()
+                .zip(broadcast_strides.iter())
+                .map(|(i, s)| i * s)
+                .sum(),
+        );
+    });
+    Some(indices)
+}
+
+fn normalized_axis(axis: i64, rank: usize, opset_version: i64) -> Result<usize, KernelError> {
+    let normalized = if axis < 0 { axis + rank as i64 } else { axis };
+    if normalized < 0 || normalized >= rank as i64 {
+        return Err(CompileError::InvalidAttributeValue {
+            attribute: "axis".to_string(),
+            value: axis.to_string(),
+            opset_version,
+        }
+        .into());
+    }
+    Ok(normalized as usize)
+}
+
+/// Reads a numeric attribute that may either be a scalar or a single-element list of floats or ints (the latter is the
+/// case for attributes that were moved from inputs by the optimizer)
+fn numeric_attribute(proto: &NodeProto, name: &str) -> Option<f64> {
+    let attribute = proto
+        .get_attribute()
+        .iter()
+        .find(|attr| attr.get_name() == name)?;
+    if let Some(f) = attribute.get_floats().first() {
+        Some(*f as f64)
+    } else if let Some(i) = attribute.get_ints().first() {
+        Some(*i as f64)
+    } else if attribute.has_f() {
+        Some(attribute.get_f() as f64)
+    } else if attribute.has_i() {
+        Some(attribute.get_i() as f64)
+    } else {
+        None
+    }
+}
+
+/* Kernels */
+
+fn map(op: &str, input: &CpuTensor, output_shape: &Shape) -> Result<OutputTensor, KernelError> {
+    let f: fn(f64) -> f64 = match op {
+        "Abs" => f64::abs,
+        "Acos" => f64::acos,
+        "Asin" => f64::asin,
+        "Atan" => f64::atan,
+        "Ceil" => f64::ceil,
+        "Cos" => f64::cos,
+        "Cosh" => f64::cosh,
+        "Exp" => f64::exp,
+        "Floor" => f64::floor,
+        "Log" => f64::ln,
+        "Round" => round_half_to_even,
+        "Sign" => |x| {
+            if x > 0.0 {
+                1.0
+            } else if x < 0.0 {
+                -1.0
+            } else {
+                0.0
+            }
+        },
+        "Sin" => f64::sin,
+        "Sinh" => f64::sinh,
+        "Sqrt" => f64::sqrt,
+        "Tan" => f64::tan,
+        "Tanh" => f64::tanh,
+        "Reciprocal" => |x| 1.0 / x,
+        "Acosh" => f64::acosh,
+        "Asinh" => f64::asinh,
+        "Atanh" => f64::atanh,
+        "Neg" => |x| -x,
+        _ => return Err(CompileError::InvalidOperation(op.to_string()).into()),
+    };
+
+    let values = to_f64(&input.data).into_iter().map(f).collect();
+    from_f64(values, output_shape.data_type)
+}
+
+/// Rounds to the nearest integer, rounding halfway cases to the nearest even integer (as required by the Round op)
+fn round_half_to_even(x: f64) -> f64 {
+    let rounded = x.round();
+    if (x - x.trunc()).abs() == 0.5 {
+        2.0 * (x / 2.0).round()
+    } else {
+        rounded
+    }
+}
+
+/// Approximation of the error function (Abramowitz and Stegun, formula 7.1.26; maximum error is 1.5e-7)
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let y = 1.0 - polynomial * (-x * x).exp();
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+/// Elementwise activation functions, shared between the activation ops and the fused Conv ops
+#[derive(Clone, Copy, Debug)]
+enum Activation {
+    Identity,
+    Relu,
+    Sigmoid,
+    Softsign,
+    Softplus,
+    Clip { min: f64, max: f64 },
+    Celu { alpha: f64 },
+    Elu { alpha: f64 },
+    LeakyRelu { alpha: f64 },
+    HardSigmoid { alpha: f64, beta: f64 },
+    Erf,
+    Mish,
+    Tanh,
+    Affine { alpha: f64, beta: f64 },
+    ThresholdedRelu { alpha: f64 },
+    ScaledTanh { alpha: f64, beta: f64 },
+}
+
+impl Activation {
+    fn from_node(activation_type: &str, proto: &NodeProto) -> Result<Activation, KernelError> {
+        Ok(match activation_type {
+            "" => Activation::Identity,
+            "Relu" => Activation::Relu,
+            "Sigmoid" => Activation::Sigmoid,
+            "Softsign" => Activation::Softsign,
+            "Softplus" => Activation::Softplus,
+            "Clip" => Activation::Clip {
+                min: numeric_attribute(proto, "min").unwrap_or(f64::NEG_INFINITY),
+                max: numeric_attribute(proto, "max").unwrap_or(f64::INFINITY),
+            },
+            "Celu" => Activation::Celu {
+                alpha: proto.get_attribute_value("alpha", Some(1.0))? as f64,
+            },
+            "Elu" => Activation::Elu {
+                alpha: proto.get_attribute_value("alpha", Some(1.0))? as f64,
+            },
+            "LeakyRelu" => Activation::LeakyRelu {
+                alpha: proto.get_attribute_value("alpha", Some(0.01))? as f64,
+            },
+            "HardSigmoid" => Activation::HardSigmoid {
+                alpha: proto.get_attribute_value("alpha", Some(0.2))? as f64,
+                beta: proto.get_attribute_value("beta", Some(0.5))? as f64,
+            },
+            "Erf" => Activation::Erf,
+            "Mish" => Activation::Mish,
+            op => return Err(CompileError::InvalidOperation(op.to_string()).into()),
+        })
+    }
+
+    /// The activation function of a recurrent node, with parameters as determined by the compiler
+    fn from_recurrent(activation: &RecurrentActivation) -> Result<Activation, KernelError> {
+        let (alpha, beta) = (activation.alpha as f64, activation.beta as f64);
+        Ok(match activation.name.as_str() {
+            "Relu" => Activation::Relu,
+            "Tanh" => Activation::Tanh,
+            "Sigmoid" => Activation::Sigmoid,
+            "Softsign" => Activation::Softsign,
+            "Softplus" => Activation::Softplus,
+            "Affine" => Activation::Affine { alpha, beta },
+            "LeakyRelu" => Activation::LeakyRelu { alpha },
+            "ThresholdedRelu" => Activation::ThresholdedRelu { alpha },
+            "ScaledTanh" => Activation::ScaledTanh { alpha, beta },
+            "HardSigmoid" => Activation::HardSigmoid { alpha, beta },
+            "Elu" => Activation::Elu { alpha },
+            name => return Err(CompileError::InvalidOperation(name.to_string()).into()),
+        })
+    }
+
+    fn apply(&self, x: f64) -> f64 {
+        match *self {
+            Activation::Identity => x,
+            Activation::Relu => x.max(0.0),
+            Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+            Activation::Softsign => x / (1.0 + x.abs()),
+            Activation::Softplus => x.exp().ln_1p(),
+            Activation::Clip { min, max } => x.max(min).min(max),
+            Activation::Celu { alpha } => x.max(0.0) + (alpha * ((x / alpha).exp() - 1.0)).min(0.0),
+            Activation::Elu { alpha } => {
+                if x > 0.0 {
+                    x
+                } else {
+                    alpha * (x.exp() - 1.0)
+                }
+            }
+            Activation::LeakyRelu { alpha } => {
+                if x >= 0.0 {
+                    x
+                } else {
+                    alpha * x
+                }
+            }
+            Activation::HardSigmoid { alpha, beta } => (alpha * x + beta).clamp(0.0, 1.0),
+            Activation::Erf => erf(x),
+            Activation::Mish => x * x.exp().ln_1p().tanh(),
+            Activation::Tanh => x.tanh(),
+            Activation::Affine { alpha, beta } => alpha * x + beta,
+            Activation::ThresholdedRelu { alpha } => {
+                if x > alpha {
+                    x
+                } else {
+                    0.0
+                }
+            }
+            Activation::ScaledTanh { alpha, beta } => alpha * (beta * x).tanh(),
+        }
+    }
+}
+
+fn reduce(
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    let op = proto.get_op_type();
+    let input_dims = dims_of(&input.shape);
+    let rank = input_dims.len();
+    let all_axes: Vec<i64> = (0..(rank as i64)).collect();
+    let mut axes: Vec<i64> = proto.get_attribute_value("axes", Some(all_axes.clone()))?;
+    if axes.is_empty() {
+        axes = all_axes;
+    }
+    let axes: Vec<usize> = axes
+        .into_iter()
+        .map(|axis| if axis < 0 { axis + rank as i64 } else { axis } as usize)
+        .collect();
+
+    // The reduced dimensions are set to one, which makes the output index easy to compute
+    let dims_preserved: Vec<usize> = input_dims
+        .iter()
+        .enumerate()
+        .map(|(d, dim)| if axes.contains(&d) { 1 } else { *dim })
+        .collect();
+    let output_strides = strides(&dims_preserved);
+    let output_length: usize = dims_preserved.iter().product();
+    let count = (input.shape.element_count() as usize / output_length.max(1)) as f64;
+
+    let initial = match op {
+        "ReduceProd" => 1.0,
+        "ReduceMin" => f64::INFINITY,
+        "ReduceMax" => f64::NEG_INFINITY,
+        _ => 0.0,
+    };
+
+    // For ReduceLogSumExp, subtract the largest value before exponentiation for numerical stability
+    let values = to_f64(&input.data);
+    let mut maxima = vec![f64::NEG_INFINITY; output_length];
+    if op == "ReduceLogSumExp" {
+        for_each_index(&input_dims, |flat_index, index| {
+            let output_index = reduced_index(index, &axes, &output_strides);
+            maxima[output_index] = maxima[output_index].max(values[flat_index]);
+        });
+    }
+
+    let mut accumulators = vec![initial; output_length];
+    for_each_index(&input_dims, |flat_index, index| {
+        let output_index = reduced_index(index, &axes, &output_strides);
+        let accumulator = &mut accumulators[output_index];
+        let value = values[flat_index];
+        *accumulator = match op {
+            "ReduceL1" => *accumulator + value.abs(),
+            "ReduceL2" | "ReduceSumSquare" => *accumulator + value * value,
+            "ReduceLogSumExp" => *accumulator + (value - maxima[output_index]).exp(),
+            "ReduceProd" => *accumulator * value,
+            "ReduceMin" => accumulator.min(value),
+            "ReduceMax" => accumulator.max(value),
+            _ => *accumulator + value,
+        };
+    });
+
+    let results = accumulators
+        .into_iter()
+        .enumerate()
+        .map(|(output_index, accumulator)| match op {
+            "ReduceMean" => accumulator / count,
+            "ReduceL2" => accumulator.sqrt(),
+            "ReduceLogSum" => accumulator.ln(),
+            "ReduceLogSumExp" => accumulator.ln() + maxima[output_index],
+            _ => accumulator,
+        })
+        .collect();
+
+    from_f64(results, output_shape.data_type)
+}
+
+/// Returns the flat output index for a reduction, given the input index and the strides of the output (with the reduced
+/// dimensions preserved)
+fn reduced_index(index: &[usize], axes: &[usize], output_strides: &[usize]) -> usize {
+    index
+        .iter()
+        .enumerate()
+        .filter(|(d, _)| !axes.contains(d))
+        .map(|(d, i)| i * output_strides[d])
+        .sum()
+}
+
+fn one_hot(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    let (indices, depth, values) = (&inputs[0], &inputs[1], &inputs[2]);
+    let output_dims = dims_of(output_shape);
+    let axis = normalized_axis(
+        proto.get_attribute_value("axis", Some(-1))?,
+        output_dims.len(),
+        0,
+    )?;
+
+    // Depth tensor must have exactly one element
+    if depth.shape.element_count() != 1 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: depth.shape.clone(),
+        }
+        .into());
+    }
+
+    // Values tensor must have exactly two elements
+    if values.shape.element_count() != 2 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 2,
+            input_shape: values.shape.clone(),
+        }
+        .into());
+    }
+
+    let depth = to_i64(&depth.data)[0];
+    let index_values = to_i64(&indices.data);
+    let index_strides = strides(&dims_of(&indices.shape));
+
+    let mut selection = Vec::with_capacity(output_shape.element_count() as usize);
+    for_each_index(&output_dims, |_, index| {
+        let index_of_index: usize = index
+            .iter()
+            .enumerate()
+            .filter(|(d, _)| *d != axis)
+            .zip(index_strides.iter())
+            .map(|((_, i), s)| i * s)
+            .sum();
+        let mut hot = index_values[index_of_index];
+        if hot < 0 {
+            hot += depth;
+        }
+        selection.push((hot == index[axis] as i64) as usize);
+    });
+
+    Ok(select(&values.data, &selection))
+}
+
+fn gather(
+    proto: &NodeProto,
+    data: &CpuTensor,
+    indices: &CpuTensor,
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    let data_dims = dims_of(&data.shape);
+    let data_strides = strides(&data_dims);
+    let axis = normalized_axis(
+        proto.get_attribute_value("axis", Some(0))?,
+        data_dims.len(),
+        0,
+    )?;
+
+    let index_values = to_i64(&indices.data);
+    let index_strides = strides(&dims_of(&indices.shape));
+    let index_rank = indices.shape.rank();
+    let axis_size = data_dims[axis] as i64;
+
+    // The output index consists of the data index before the axis, the index of the index and the data index after the axis
+    let mut selection = Vec::with_capacity(output_shape.element_count() as usize);
+    let mut out_of_bounds = false;
+    for_each_index(&dims_of(output_shape), |_, index| {
+        let index_of_index: usize = index[axis..(axis + index_rank)]
+            .iter()
+            .zip(index_strides.iter())
+            .map(|(i, s)| i * s)
+            .sum();
+
+        // Negative indexing is allowed
+        let mut gathered_index = index_values[index_of_index];
+        if gathered_index < 0 {
+            gathered_index += axis_size;
+        }
+        if gathered_index < 0 || gathered_index >= axis_size {
+            out_of_bounds = true;
+            gathered_index = 0;
+        }
+
+        let outer: usize = index[0..axis]
+            .iter()
+            .zip(data_strides.iter())
+            .map(|(i, s)| i * s)
+            .sum();
+        let inner: usize = index[(axis + index_rank)..]
+            .iter()
+            .zip(data_strides[(axis + 1)..].iter())
+            .map(|(i, s)| i * s)
+            .sum();
+        selection.push(outer + (gathered_index as usize) * data_strides[axis] + inner);
+    });
+
+    if out_of_bounds {
+        return Err(KernelError::OutOfBounds);
+    }
+
+    Ok(select(&data.data, &selection))
+}
+
+/// Returns the position along a dimension of the specified size that an index refers to (negative indices count back
+/// from the end of the dimension)
+fn index_position(index: i64, size: usize) -> Result<usize, KernelError> {
+    let position = if index < 0 {
+        index + size as i64
+    } else {
+        index
+    };
+    if position < 0 || position >= size as i64 {
+        return Err(KernelError::OutOfBounds);
+    }
+    Ok(position as usize)
+}
+
+/// Returns the offset in the data of the element referred to by each index of a GatherElements or ScatterElements node.
+/// This is the element at the position of the index, except along the axis, where the index determines the position.
+fn element_offsets(
+    proto: &NodeProto,
+    data: &CpuTensor,
+    indices: &CpuTensor,
+    opset_version: i64,
+) -> Result<Vec<usize>, KernelError> {
+    let data_dims = dims_of(&data.shape);
+    let data_strides = strides(&data_dims);
+    let index_dims = dims_of(&indices.shape);
+    if index_dims.len() != data_dims.len() {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: indices.shape.clone(),
+        }
+        .into());
+    }
+    let axis = normalized_axis(
+        proto.get_attribute_value("axis", Some(0))?,
+        data_dims.len(),
+        opset_version,
+    )?;
+
+    let index_values = to_i64(&indices.data);
+    let mut offsets = Vec::with_capacity(index_values.len());
+    let mut out_of_bounds = false;
+    for_each_index(&index_dims, |flat_index, index| {
+        let mut offset = 0;
+        for (d, i) in index.iter().enumerate() {
+            let position = if d == axis {
+                index_position(index_values[flat_index], data_dims[d]).unwrap_or_else(|_| {
+                    out_of_bounds = true;
+                    0
+                })
+            } else {
+                *i
+            };
+            offset += position * data_strides[d];
+        }
+        offsets.push(offset);
+    });
+
+    if out_of_bounds
+        || offsets
+            .iter()
+            .any(|offset| *offset >= data.shape.element_count() as usize)
+    {
+        return Err(KernelError::OutOfBounds);
+    }
+    Ok(offsets)
+}
+
+/// Returns the offset in the data of each element of the slices referred to by the tuples of indices (in the last
+/// dimension of the indices) of a GatherND or ScatterND node. For GatherND, the tuples index the data of their batch.
+fn slice_element_offsets(
+    proto: &NodeProto,
+    data: &CpuTensor,
+    indices: &CpuTensor,
+    opset_version: i64,
+) -> Result<Vec<usize>, KernelError> {
+    let data_dims = dims_of(&data.shape);
+    let data_strides = strides(&data_dims);
+    let index_dims = dims_of(&indices.shape);
+    let batch_dims = if proto.get_op_type() == "GatherND" {
+        proto.get_attribute_value("batch_dims", Some(0))?
+    } else {
+        0
+    };
+    if batch_dims < 0 || batch_dims as usize >= index_dims.len().min(data_dims.len()) {
+        return Err(CompileError::InvalidAttributeValue {
+            attribute: "batch_dims".to_string(),
+            value: batch_dims.to_string(),
+            opset_version,
+        }
+        .into());
+    }
+    let batch_dims = batch_dims as usize;
+    let index_depth = index_dims[index_dims.len() - 1];
+    if index_depth == 0 || batch_dims + index_depth > data_dims.len() {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: indices.shape.clone(),
+        }
+        .into());
+    }
+
+    let index_values = to_i64(&indices.data);
+    let batch_size: usize = data_dims[batch_dims..].iter().product();
+    let tuples_per_batch: usize = index_dims[batch_dims..(index_dims.len() - 1)]
+        .iter()
+        .product();
+    let slice_size: usize = data_dims[(batch_dims + index_depth)..].iter().product();
+    let mut offsets = Vec::with_capacity(index_values.len() / index_depth * slice_size);
+    for (tuple_index, tuple) in index_values.chunks(index_depth).enumerate() {
+        let mut start = (tuple_index / tuples_per_batch) * batch_size;
+        for (k, index) in tuple.iter().enumerate() {
+            let d = batch_dims + k;
+            start += index_position(*index, data_dims[d])? * data_strides[d];
+        }
+        offsets.extend(start..(start + slice_size));
+    }
+    Ok(offsets)
+}
+
+/// Writes the updates to the data at the specified offsets (in order), combining them with the values already there as
+/// specified by the reduction
+fn scatter(
+    data: &CpuTensor,
+    updates: &CpuTensor,
+    offsets: &[usize],
+    reduction: ScatterReduction,
+) -> Result<OutputTensor, KernelError> {
+    fn apply<T>(
+        mut values: Vec<T>,
+        updates: Vec<T>,
+        offsets: &[usize],
+        reduction: ScatterReduction,
+    ) -> Vec<T>
+    where
+        T: Copy + PartialOrd + std::ops::Add<Output = T> + std::ops::Mul<Output = T>,
+    {
+        for (offset, update) in offsets.iter().zip(updates) {
+            let current = values[*offset];
+            values[*offset] = match reduction {
+                ScatterReduction::None => update,
+                ScatterReduction::Add => current + update,
+                ScatterReduction::Mul => current * update,
+                ScatterReduction::Max if update > current => update,
+                ScatterReduction::Min if update < current => update,
+                ScatterReduction::Max | ScatterReduction::Min => current,
+            };
+        }
+        values
+    }
+
+    let data_type = scalar_type(&data.data);
+    if data_type.is_float() {
+        let values = apply(
+            to_f64(&data.data),
+            to_f64(&updates.data),
+            offsets,
+            reduction,
+        );
+        from_f64(values, data_type)
+    } else {
+        let values = apply(
+            to_i64(&data.data),
+            to_i64(&updates.data),
+            offsets,
+            reduction,
+        );
+        from_i64(values, data_type)
+    }
+}
+
+fn softmax(
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    let default_axis = if opset_version < 13 { 1 } else { -1 };
+    let dims = dims_of(&input.shape);
+    let axis = normalized_axis(
+        proto.get_attribute_value("axis", Some(default_axis))?,
+        dims.len(),
+        opset_version,
+    )?;
+
+    // Before opset 13, the input is coerced to 2D and softmax is calculated over all elements to the right of the axis.
+    // From opset 13 onwards, softmax is calculated along the axis only.
+    let (groups, n_elements, element_stride) = if opset_version < 13 {
+        (
+            dims[0..axis].iter().product::<usize>(),
+            dims[axis..].iter().product::<usize>(),
+            1,
+        )
+    } else {
+        (
+            dims[0..axis].iter().product::<usize>() * dims[(axis + 1)..].iter().product::<usize>(),
+            dims[axis],
+            dims[(axis + 1)..].iter().product::<usize>(),
+        )
+    };
+
+    let values = to_f64(&input.data);
+    let mut results = vec![0.0; values.len()];
+    for group in 0..groups {
+        let start = (group / element_stride) * n_elements * element_stride + group % element_stride;
+        let indices: Vec<usize> = (0..n_elements)
+            .map(|k| start + k * element_stride)
+            .collect();
+
+        // Softmax = exp(input - max(input)) / sum(exp(input - max(input)))
+        let max = indices
+            .iter()
+            .map(|i| values[*i])
+            .fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = indices.iter().map(|i| (values[*i] - max).exp()).sum();
+        for i in indices {
+            results[i] = (values[i] - max).exp() / sum;
+        }
+    }
+
+    from_f64(results, output_shape.data_type)
+}
+
+fn binary(
+    op: &str,
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    let output_dims = dims_of(output_shape);
+
+    // With a single input, the operation is performed with the 'coefficient' attribute as right hand side
+    let (lhs, rhs, lhs_indices, rhs_indices) = match inputs {
+        [lhs] => {
+            let coefficient: f32 = proto.get_attribute_value("coefficient", Some(1.0))?;
+            let rhs = cast(&OutputTensor::F32(vec![coefficient]), lhs.shape.data_type)?;
+            (
+                lhs.data.as_ref().clone(),
+                rhs,
+                (0..element_count(&lhs.data)).collect(),
+                vec![0; element_count(&lhs.data)],
+            )
+        }
+        [lhs, rhs] => {
+            let invalid_broadcast = || CompileError::InvalidBroadcast {
+                input_shapes: vec![lhs.shape.clone(), rhs.shape.clone()],
+                output_shape: output_shape.clone(),
+            };
+            let lhs_indices = broadcast_indices(&dims_of(&lhs.shape), &output_dims)
+                .ok_or_else(invalid_broadcast)?;
+            let rhs_indices = broadcast_indices(&dims_of(&rhs.shape), &output_dims)
+                .ok_or_else(invalid_broadcast)?;
+            (
+                lhs.data.as_ref().clone(),
+                rhs.data.as_ref().clone(),
+                lhs_indices,
+                rhs_indices,
+            )
+        }
+        _ => {
+            return Err(CompileError::InvalidInputCount {
+                expected: 2,
+                actual: inputs.len(),
+            }
+            .into())
+        }
+    };
+
+    if scalar_type(&lhs).is_float() {
+        let (lhs, rhs) = (to_f64(&lhs), to_f64(&rhs));
+        let f: fn(f64, f64) -> f64 = match op {
+            "Add" => |a, b| a + b,
+            "Sub" => |a, b| a - b,
+            "Mul" => |a, b| a * b,
+            "Div" => |a, b| a / b,
+            "Mod" => |a, b| a % b,
+            "Pow" => f64::powf,
+            "PRelu" => |a, b| if a < 0.0 { a * b } else { a },
+            "Equal" => |a, b| (a == b) as u8 as f64,
+            "Greater" => |a, b| (a > b) as u8 as f64,
+            "GreaterOrEqual" => |a, b| (a >= b) as u8 as f64,
+            "Less" => |a, b| (a < b) as u8 as f64,
+            "LessOrEqual" => |a, b| (a <= b) as u8 as f64,
+            _ => {
+                return Err(CompileError::UnimplementedVariant {
+                    variant: "with floating point inputs".to_string(),
+                    op: op.to_string(),
+                }
+                .into())
+            }
+        };
+
+        let results = lhs_indices
+            .iter()
+            .zip(rhs_indices.iter())
+            .map(|(l, r)| f(lhs[*l], rhs[*r]))
+            .collect();
+        from_f64(results, output_shape.data_type)
+    } else {
+        let (lhs, rhs) = (to_i64(&lhs), to_i64(&rhs));
+        let fmod = proto.get_attribute_value("fmod", Some(0))? != 0;
+        let f = |a: i64, b: i64| -> Result<i64, KernelError> {
+            Ok(match op {
+                "Add" => a.wrapping_add(b),
+                "Sub" => a.wrapping_sub(b),
+                "Mul" => a.wrapping_mul(b),
+                "Div" => a.checked_div(b).ok_or(KernelError::DivisionByZero)?,
+                "Mod" => {
+                    let remainder = a.checked_rem(b).ok_or(KernelError::DivisionByZero)?;
+                    // Without fmod, the result takes the sign of the divisor
+                    if !fmod && remainder != 0 && ((remainder < 0) != (b < 0)) {
+                        remainder + b
+                    } else {
+                        remainder
+                    }
+                }
+                "Pow" => (a as f64).powf(b as f64) as i64,
+                "PRelu" => {
+                    if a < 0 {
+                        a.wrapping_mul(b)
+                    } else {
+                        a
+                    }
+                }
+                "And" => a & b,
+                "Or" => a | b,
+                "Xor" => a ^ b,
+                "Equal" => (a == b) as i64,
+                "Greater" => (a > b) as i64,
+                "GreaterOrEqual" => (a >= b) as i64,
+                "Less" => (a < b) as i64,
+                "LessOrEqual" => (a <= b) as i64,
+                _ => return Err(CompileError::UnimplementedOp(op.to_string()).into()),
+            })
+        };
+
+        let results = lhs_indices
+            .iter()
+            .zip(rhs_indices.iter())
+            .map(|(l, r)| f(lhs[*l], rhs[*r]))
+            .collect::<Result<Vec<i64>, KernelError>>()?;
+        from_i64(results, output_shape.data_type)
+    }
+}
+
+/// Where: selects elements from the second input where the condition (first input) is true, and from the third otherwise
+fn where_(inputs: &[CpuTensor], output_shape: &Shape) -> Result<OutputTensor, KernelError> {
+    let output_dims = dims_of(output_shape);
+    let broadcast = |tensor: &CpuTensor| {
+        broadcast_indices(&dims_of(&tensor.shape), &output_dims).ok_or_else(|| {
+            CompileError::InvalidBroadcast {
+                input_shapes: inputs.iter().map(|input| input.shape.clone()).collect(),
+                output_shape: output_shape.clone(),
+            }
+        })
+    };
+
+    let condition = to_i64(&select(&inputs[0].data, &broadcast(&inputs[0])?));
+    let x = select(&inputs[1].data, &broadcast(&inputs[1])?);
+    let y = select(&inputs[2].data, &broadcast(&inputs[2])?);
+    let ranges: Vec<(usize, usize, usize)> = condition
+        .iter()
+        .enumerate()
+        .map(|(index, c)| (if *c != 0 { 0 } else { 1 }, index, 1))
+        .collect();
+    join(&[&x, &y], &ranges)
+}
+
+/// Implements LayerNormalization, InstanceNormalization and GroupNormalization, which differ only in how the input is
+/// divided into groups of values that are normalized together (see [normalization_groups])
+fn normalization(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shapes: &[Shape],
+    opset_version: i64,
+) -> Result<Vec<OutputTensor>, KernelError> {
+    let input_shapes: Vec<&Shape> = inputs.iter().map(|input| &input.shape).collect();
+    let groups = normalization_groups(proto, &input_shapes, opset_version)?;
+    let epsilon = proto.get_attribute_value("epsilon", Some(1e-05))? as f64;
+
+    let x = to_f64(&inputs[0].data);
+    let scale = to_f64(&inputs[1].data);
+    let bias = inputs.get(2).map(|bias| to_f64(&bias.data));
+    let group_size = groups.group_size as usize;
+
+    let mut results = Vec::with_capacity(x.len());
+    let mut means = Vec::with_capacity(groups.group_count as usize);
+    let mut inv_std_devs = Vec::with_capacity(groups.group_count as usize);
+    for (group, values) in x.chunks(group_size.max(1)).enumerate() {
+        let mean = values.iter().sum::<f64>() / (group_size as f64);
+        let variance =
+            values.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (group_size as f64);
+        let inv_std_dev = 1.0 / (variance + epsilon).sqrt();
+
+        // Y = (X - mean) * inv_std_dev * scale + B
+        let affine_group_start =
+            (group % groups.affine_groups as usize) * groups.affine_group_stride as usize;
+        for (i, x) in values.iter().enumerate() {
+            let affine_index = affine_group_start + i / groups.affine_divisor as usize;
+            let mut y = (x - mean) * inv_std_dev * scale[affine_index % scale.len()];
+            if let Some(bias) = &bias {
+                y += bias[affine_index % bias.len()];
+            }
+            results.push(y);
+        }
+        means.push(mean);
+        inv_std_devs.push(inv_std_dev);
+    }
+
+    // LayerNormalization optionally also outputs the mean and inverse standard deviation
+    vec![results, means, inv_std_devs]
+        .into_iter()
+        .zip(output_shapes.iter())
+        .map(|(values, shape)| from_f64(values, shape.data_type))
+        .collect()
+}
+
+fn batch_normalization(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    /* Prior to version 9, BatchNormalization supported a 'spatial' mode where input mean/variance are of shape
+    [C,W,H] instead of just [C]. This mode is not supported. */
+    if let Ok(spatial_value) = proto.get_attribute_value::<i64>("spatial", None) {
+        if opset_version >= 9 {
+            return Err(CompileError::InvalidAttributeValue {
+                attribute: "spatial".to_string(),
+                opset_version,
+                value: spatial_value.to_string(),
+            }
+            .into());
+        } else if spatial_value != 1 {
+            return Err(CompileError::UnimplementedVariant {
+                op: "BatchNormalization".to_string(),
+                variant: "spatial".to_string(),
+            }
+            .into());
+        }
+    }
+
+    // [N,C,D1,D2,...] where the values in each channel C are normalized using the statistics for that channel
+    let input = &inputs[0];
+    if input.shape.rank() < 2 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: input.shape.clone(),
+        }
+        .into());
+    }
+    let channels = input.shape.dim(1) as usize;
+    let channel_size = input.shape.dims[2..].iter().product::<u64>() as usize;
+    let epsilon = proto.get_attribute_value("epsilon", Some(1e-05))? as f64;
+
+    let x = to_f64(&input.data);
+    let scale = to_f64(&inputs[1].data);
+    let bias = to_f64(&inputs[2].data);
+    let mean = to_f64(&inputs[3].data);
+    let variance = to_f64(&inputs[4].data);
+
+    // Y = (X - input_mean) / sqrt(input_var + epsilon) * scale + B
+    let results = x
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let c = (i / channel_size) % channels;
+            (x - mean[c]) / (variance[c] + epsilon).sqrt() * scale[c] + bias[c]
+        })
+        .collect();
+    from_f64(results, output_shape.data_type)
+}
+
+fn concat(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    expect_inputs(inputs, 1)?;
+    let axis = normalized_axis(
+        proto.get_attribute_value("axis", Some(0))?,
+        output_shape.rank(),
+        0,
+    )?;
+
+    // The output consists of blocks (one for each index in the dimensions before the axis) which are concatenations of
+    // the corresponding blocks of the inputs
+    let outer: usize = dims_of(output_shape)[0..axis].iter().product();
+    let block_lengths: Vec<usize> = inputs
+        .iter()
+        .map(|input| dims_of(&input.shape)[axis..].iter().product())
+        .collect();
+
+    let mut ranges = Vec::with_capacity(outer * inputs.len());
+    for block in 0..outer {
+        for (input_index, block_length) in block_lengths.iter().enumerate() {
+            ranges.push((input_index, block * block_length, *block_length));
+        }
+    }
+
+    let tensors: Vec<&OutputTensor> = inputs.iter().map(|input| input.data.as_ref()).collect();
+    join(&tensors, &ranges)
+}
+
+/// The geometry of a sliding window (kernel) over the spatial dimensions of an input for Conv and pooling ops
+struct Window {
+    kernel_shape: Vec<usize>,
+    strides: Vec<usize>,
+    dilations: Vec<usize>,
+    pads_begin: Vec<i64>,
+}
+
+impl Window {
+    fn from_node(
+        proto: &NodeProto,
+        kernel_shape: Vec<usize>,
+        input_spatial_dims: &[usize],
+        output_spatial_dims: &[usize],
+    ) -> Result<Window, KernelError> {
+        let spatial_rank = input_spatial_dims.len();
+        let ones = vec![1; spatial_rank];
+        let to_usize = |v: Vec<i64>| v.into_iter().map(|x| x as usize).collect::<Vec<usize>>();
+        let strides = to_usize(proto.get_attribute_value("strides", Some(ones.clone()))?);
+        let dilations = to_usize(proto.get_attribute_value("dilations", Some(ones))?);
+        let auto_pad = proto.get_attribute_value("auto_pad", Some("NOTSET".to_string()))?;
+
+        let pads_begin = match auto_pad.as_str() {
+            "NOTSET" => {
+                let pads = proto.get_attribute_value("pads", Some(vec![0; spatial_rank * 2]))?;
+                pads[0..spatial_rank].to_vec()
+            }
+            "VALID" => vec![0; spatial_rank],
+            "SAME_UPPER" | "SAME_LOWER" => (0..spatial_rank)
+                .map(|d| {
+                    let total = ((output_spatial_dims[d] as i64 - 1) * strides[d] as i64
+                        + (kernel_shape[d] as i64 - 1) * dilations[d] as i64
+                        + 1
+                        - input_spatial_dims[d] as i64)
+                        .max(0);
+                    if auto_pad == "SAME_UPPER" {
+                        total / 2
+                    } else {
+                        total - total / 2
+                    }
+                })
+                .collect(),
+            _ => {
+                return Err(CompileError::UnimplementedVariant {
+                    op: proto.get_op_type().to_string(),
+                    variant: format!("auto_pad={}", auto_pad),
+                }
+                .into())
+            }
+        };
+
+        Ok(Window {
+            kernel_shape,
+            strides,
+            dilations,
+            pads_begin,
+        })
+    }
+
+    /// Returns the flat indices (relative to the start of a spatial 'plane' of the input) of the elements in the window
+    /// for the output element at the specified spatial index, together with the index of the kernel element. Elements
+    /// that fall in the padding are not returned.
+    fn positions(
+        &self,
+        output_index: &[usize],
+        input_spatial_dims: &[usize],
+        input_spatial_strides: &[usize],
+    ) -> Vec<(usize, usize)> {
+        let mut positions = vec![];
+        for_each_index(&self.kernel_shape, |kernel_flat_index, kernel_index| {
+            let mut input_flat_index = 0;
+            for d in 0..kernel_index.len() {
+                let position = (output_index[d] * self.strides[d]
+                    + kernel_index[d] * self.dilations[d]) as i64
+                    - self.pads_begin[d];
+                if position < 0 || position >= input_spatial_dims[d] as i64 {
+                    return;
+                }
+                input_flat_index += position as usize * input_spatial_strides[d];
+            }
+            positions.push((input_flat_index, kernel_flat_index));
+        });
+        positions
+    }
+}
+
+/// Checks the input is shaped [N, C, D1, D2, ...] and returns the dimensions
+fn spatial_input_dims(input: &CpuTensor) -> Result<Vec<usize>, KernelError> {
+    if input.shape.rank() < 3 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: input.shape.clone(),
+        }
+        .into());
+    }
+    Ok(dims_of(&input.shape))
+}
+
+fn pool(
+    op: &str,
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    let input_dims = spatial_input_dims(input)?;
+    let output_dims = dims_of(output_shape);
+    let (batches, channels) = (input_dims[0], input_dims[1]);
+    let input_spatial_dims = &input_dims[2..];
+    let output_spatial_dims = &output_dims[2..];
+    let input_spatial_strides = strides(input_spatial_dims);
+    let input_plane: usize = input_spatial_dims.iter().product();
+
+    // GlobalAveragePool is equivalent to AveragePool, with the kernel shape set to the size of the input tensor
+    let window = if op == "GlobalAveragePool" {
+        Window {
+            kernel_shape: input_spatial_dims.to_vec(),
+            strides: vec![1; input_spatial_dims.len()],
+            dilations: vec![1; input_spatial_dims.len()],
+            pads_begin: vec![0; input_spatial_dims.len()],
+        }
+    } else {
+        let kernel_shape: Vec<i64> = proto.get_attribute_value("kernel_shape", None)?;
+        Window::from_node(
+            proto,
+            kernel_shape.into_iter().map(|k| k as usize).collect(),
+            input_spatial_dims,
+            output_spatial_dims,
+        )?
+    };
+    let count_include_pad = proto.get_attribute_value("count_include_pad", Some(0))? != 0;
+    let kernel_length: usize = window.kernel_shape.iter().product();
+
+    let values = to_f64(&input.data);
+    let mut results = Vec::with_capacity(output_shape.element_count() as usize);
+    for plane in 0..(batches * channels) {
+        let plane_values = &values[(plane * input_plane)..((plane + 1) * input_plane)];
+        for_each_index(output_spatial_dims, |_, output_index| {
+            let positions =
+                window.positions(output_index, input_spatial_dims, &input_spatial_strides);
+            let result = if op == "MaxPool" {
+                positions
+                    .iter()
+                    .map(|(i, _)| plane_values[*i])
+                    .fold(f64::NEG_INFINITY, f64::max)
+            } else {
+                let sum: f64 = positions.iter().map(|(i, _)| plane_values[*i]).sum();
+                if count_include_pad {
+                    sum / kernel_length as f64
+                } else {
+                    sum / positions.len() as f64
+                }
+            };
+            results.push(result);
+        });
+    }
+
+    from_f64(results, output_shape.data_type)
+}
+
+fn conv(
+    op: &str,
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    expect_inputs(inputs, 2)?;
+    let (input, weights) = (&inputs[0], &inputs[1]);
+    let input_dims = spatial_input_dims(input)?;
+    let weight_dims = dims_of(&weights.shape);
+    let output_dims = dims_of(output_shape);
+    let (batches, channels) = (input_dims[0], input_dims[1]);
+    let input_spatial_dims = &input_dims[2..];
+    let output_spatial_dims = &output_dims[2..];
+    let input_spatial_strides = strides(input_spatial_dims);
+    let input_plane: usize = input_spatial_dims.iter().product();
+
+    let kernel_shape: Vec<i64> = proto.get_attribute_value(
+        "kernel_shape",
+        Some(weight_dims[2..].iter().map(|d| *d as i64).collect()),
+    )?;
+    let window = Window::from_node(
+        proto,
+        kernel_shape.into_iter().map(|k| k as usize).collect(),
+        input_spatial_dims,
+        output_spatial_dims,
+    )?;
+    let kernel_length: usize = window.kernel_shape.iter().product();
+
+    let group = proto.get_attribute_value("group", Some(1))? as usize;
+    let feature_maps = output_dims[1];
+    let channels_per_group = channels / group;
+    if group == 0 || channels_per_group * group != channels {
+        // Input channel count must be divisible by the group count.
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: input.shape.clone(),
+        }
+        .into());
+    }
+
+    if weight_dims[0] != feature_maps
+        || weight_dims[1] != channels_per_group
+        || weight_dims[2..].iter().product::<usize>() != kernel_length
+    {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: weights.shape.clone(),
+        }
+        .into());
+    }
+
+    let bias = match inputs.get(2) {
+        Some(bias) if bias.shape.element_count() as usize != feature_maps => {
+            return Err(CompileError::InvalidInputShape {
+                input_index: 2,
+                input_shape: bias.shape.clone(),
+            }
+            .into())
+        }
+        Some(bias) => to_f64(&bias.data),
+        None => vec![0.0; feature_maps],
+    };
+
+    // Conv ops fused with an activation function (see the optimizer) apply the activation to the result
+    let activation = Activation::from_node(&op["Conv".len()..], proto)?;
+    let activation = match activation {
+        // For ConvLeakyRelu, the default alpha is the same as for LeakyRelu
+        Activation::LeakyRelu { .. } => Activation::LeakyRelu {
+            alpha: proto.get_attribute_value("alpha", Some(0.01))? as f64,
+        },
+        a => a,
+    };
+
+    let x = to_f64(&input.data);
+    let w = to_f64(&weights.data);
+    let maps_per_group = feature_maps / group;
+    let mut results = Vec::with_capacity(output_shape.element_count() as usize);
+    for batch in 0..batches {
+        for (m, map_bias) in bias.iter().enumerate() {
+            let current_group = m / maps_per_group;
+            for_each_index(output_spatial_dims, |_, output_index| {
+                let positions =
+                    window.positions(output_index, input_spatial_dims, &input_spatial_strides);
+                let mut result = *map_bias;
+                for c in 0..channels_per_group {
+                    let input_channel = current_group * channels_per_group + c;
+                    let input_base = (batch * channels + input_channel) * input_plane;
+                    let kernel_base = (m * channels_per_group + c) * kernel_length;
+                    for (input_index, kernel_index) in &positions {
+                        result += x[input_base + input_index] * w[kernel_base + kernel_index];
+                    }
+                }
+                results.push(activation.apply(result));
+            });
+        }
+    }
+
+    from_f64(results, output_shape.data_type)
+}
+
+fn conv_transpose(
+    op: &str,
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    expect_inputs(inputs, 2)?;
+    let (input, weights) = (&inputs[0], &inputs[1]);
+    let input_dims = spatial_input_dims(input)?;
+    let weight_dims = dims_of(&weights.shape);
+    let output_dims = dims_of(output_shape);
+    let (batches, channels) = (input_dims[0], input_dims[1]);
+    let input_spatial_dims = &input_dims[2..];
+    let output_spatial_dims = &output_dims[2..];
+    let output_spatial_strides = strides(output_spatial_dims);
+    let output_plane: usize = output_spatial_dims.iter().product();
+
+    // The weights are shaped [C, M / group, k1, k2, ...]
+    let group = proto.get_attribute_value("group", Some(1))? as usize;
+    let feature_maps = output_dims[1];
+    if group == 0 || channels % group != 0 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: input.shape.clone(),
+        }
+        .into());
+    }
+    let channels_per_group = channels / group;
+    let maps_per_group = feature_maps / group;
+    if weight_dims.len() != input_dims.len()
+        || weight_dims[0] != channels
+        || weight_dims[1] * group != feature_maps
+    {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: weights.shape.clone(),
+        }
+        .into());
+    }
+
+    let kernel_shape: Vec<i64> = proto.get_attribute_value(
+        "kernel_shape",
+        Some(weight_dims[2..].iter().map(|d| *d as i64).collect()),
+    )?;
+    let pads_begin = conv_transpose_pads(
+        proto,
+        &input.shape.dims[2..],
+        &kernel_shape,
+        &output_shape.dims[2..],
+        opset_version,
+    )?;
+    let ones = vec![1; input_spatial_dims.len()];
+    let strides_attr: Vec<i64> = proto.get_attribute_value("strides", Some(ones.clone()))?;
+    let dilations: Vec<i64> = proto.get_attribute_value("dilations", Some(ones))?;
+    let kernel_shape: Vec<usize> = kernel_shape.into_iter().map(|k| k as usize).collect();
+    let kernel_length: usize = kernel_shape.iter().product();
+
+    let bias = match inputs.get(2) {
+        Some(bias) if bias.shape.element_count() as usize != feature_maps => {
+            return Err(CompileError::InvalidInputShape {
+                input_index: 2,
+                input_shape: bias.shape.clone(),
+            }
+            .into())
+        }
+        Some(bias) => to_f64(&bias.data),
+        None => vec![0.0; feature_maps],
+    };
+
+    // ConvTranspose ops fused with an activation function (see the optimizer) apply the activation to the result
+    let activation = Activation::from_node(&op["ConvTranspose".len()..], proto)?;
+
+    // Each input element is multiplied with the kernel and added to the output at (input position * stride + kernel
+    // position * dilation - pad)
+    let x = to_f64(&input.data);
+    let w = to_f64(&weights.data);
+    let mut results = vec![0.0; output_shape.element_count() as usize];
+    for batch in 0..batches {
+        for c in 0..channels {
+            let current_group = c / channels_per_group;
+            let input_base = (batch * channels + c) * input_spatial_dims.iter().product::<usize>();
+            for_each_index(input_spatial_dims, |input_flat_index, input_index| {
+                let value = x[input_base + input_flat_index];
+                for_each_index(&kernel_shape, |kernel_flat_index, kernel_index| {
+                    let mut output_flat_index = 0;
+                    for d in 0..kernel_index.len() {
+                        let position = (input_index[d] as i64) * strides_attr[d]
+                            + (kernel_index[d] as i64) * dilations[d]
+                            - pads_begin[d];
+                        if position < 0 || position >= output_spatial_dims[d] as i64 {
+                            return;
+                        }
+                        output_flat_index += position as usize * output_spatial_strides[d];
+                    }
+                    for map in 0..maps_per_group {
+                        let m = current_group * maps_per_group + map;
+                        let kernel_base = (c * maps_per_group + map) * kernel_length;
+                        results[(batch * feature_maps + m) * output_plane + output_flat_index] +=
+                            value * w[kernel_base + kernel_flat_index];
+                    }
+                });
+            });
+        }
+    }
+
+    for (index, result) in results.iter_mut().enumerate() {
+        let m = (index / output_plane) % feature_maps;
+        *result = activation.apply(*result + bias[m]);
+    }
+
+    from_f64(results, output_shape.data_type)
+}
+
+fn gemm(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    expect_inputs(inputs, 2)?;
+    let transpose_a = proto.get_attribute_value("transA", Some(0))? != 0;
+    let transpose_b = proto.get_attribute_value("transB", Some(0))? != 0;
+    let alpha = proto.get_attribute_value("alpha", Some(1.0))? as f64;
+    let beta = proto.get_attribute_value("beta", Some(1.0))? as f64;
+
+    let (a_dims, b_dims) = (dims_of(&inputs[0].shape), dims_of(&inputs[1].shape));
+    if a_dims.len() != 2 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: inputs[0].shape.clone(),
+        }
+        .into());
+    }
+    let (m, k) = if transpose_a {
+        (a_dims[1], a_dims[0])
+    } else {
+        (a_dims[0], a_dims[1])
+    };
+    if b_dims.len() != 2 || b_dims[if transpose_b { 1 } else { 0 }] != k {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: inputs[1].shape.clone(),
+        }
+        .into());
+    }
+    let n = if transpose_b { b_dims[0] } else { b_dims[1] };
+
+    // The bias should be "unidirectionally broadcastable to M*N"
+    let bias = match inputs.get(2) {
+        Some(c) => {
+            let indices = broadcast_indices(&dims_of(&c.shape), &[m, n]).ok_or_else(|| {
+                CompileError::InvalidInputShape {
+                    input_index: 2,
+                    input_shape: c.shape.clone(),
+                }
+            })?;
+            let values = to_f64(&c.data);
+            indices.into_iter().map(|i| values[i]).collect()
+        }
+        None => vec![0.0; m * n],
+    };
+
+    let a = to_f64(&inputs[0].data);
+    let b = to_f64(&inputs[1].data);
+    let a_at = |i: usize, p: usize| {
+        if transpose_a {
+            a[p * m + i]
+        } else {
+            a[i * k + p]
+        }
+    };
+    let b_at = |p: usize, j: usize| {
+        if transpose_b {
+            b[j * k + p]
+        } else {
+            b[p * n + j]
+        }
+    };
+
+    let mut results = Vec::with_capacity(m * n);
+    for i in 0..m {
+        for j in 0..n {
+            let product: f64 = (0..k).map(|p| a_at(i, p) * b_at(p, j)).sum();
+            results.push(alpha * product + beta * bias[i * n + j]);
+        }
+    }
+
+    from_f64(results, output_shape.data_type)
+}
+
+/// Recurrent (LSTM, GRU and RNN) nodes, which process each sequence in a batch step by step in each direction. The gates
+/// of each op are ordered as in the weight and bias tensors (LSTM: i, o, f, c; GRU: z, r, h; RNN: i).
+fn recurrent(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shapes: &[Shape],
+    opset_version: i64,
+) -> Result<Vec<OutputTensor>, KernelError> {
+    let op = proto.get_op_type();
+    let input_shapes: Vec<&Shape> = inputs.iter().map(|input| &input.shape).collect();
+    let recurrence = recurrence(proto, &input_shapes, opset_version)?;
+    let activations =
+        recurrent_activations(proto, recurrence.num_directions as usize, opset_version)?
+            .iter()
+            .map(|slot| slot.iter().map(Activation::from_recurrent).collect())
+            .collect::<Result<Vec<Vec<Activation>>, KernelError>>()?;
+
+    let (sequence_length, batch_size, input_size, hidden_size, gate_count) = (
+        recurrence.sequence_length as usize,
+        recurrence.batch_size as usize,
+        recurrence.input_size as usize,
+        recurrence.hidden_size as usize,
+        recurrence.gate_count as usize,
+    );
+    let state_length = recurrence.state_length() as usize;
+    let optional_input = |position: usize| {
+        provided_input_index(proto, position).map(|index| to_f64(&inputs[index].data))
+    };
+    let x = to_f64(&inputs[0].data);
+    let w = to_f64(&inputs[1].data);
+    let r = to_f64(&inputs[2].data);
+    let b = optional_input(3);
+    let sequence_lens = provided_input_index(proto, 4).map(|index| to_i64(&inputs[index].data));
+    let p = if op == "LSTM" {
+        optional_input(7)
+    } else {
+        None
+    };
+
+    let clip = proto
+        .get_attribute_value::<f32>("clip", None)
+        .ok()
+        .map(|clip| clip as f64);
+    let clipped = |value: f64| match clip {
+        Some(clip) => value.clamp(-clip, clip),
+        None => value,
+    };
+    let input_forget = proto.get_attribute_value("input_forget", Some(0))? != 0;
+    let linear_before_reset = proto.get_attribute_value("linear_before_reset", Some(0))? != 0;
+
+    let mut y = vec![0.0; sequence_length * state_length];
+    let mut y_h = optional_input(5).unwrap_or_else(|| vec![0.0; state_length]);
+    let mut y_c = if op == "LSTM" {
+        optional_input(6)
+    } else {
+        None
+    }
+    .unwrap_or_else(|| vec![0.0; state_length]);
+
+    for direction in 0..recurrence.num_directions as usize {
+        let f = &activations[0][direction];
+        let weights_start = direction * gate_count * hidden_size;
+        for batch in 0..batch_size {
+            let length = sequence_lens
+                .as_ref()
+                .map(|lens| (lens[batch].max(0) as usize).min(sequence_length))
+                .unwrap_or(sequence_length);
+            let state_start = (direction * batch_size + batch) * hidden_size;
+            let mut hidden = y_h[state_start..(state_start + hidden_size)].to_vec();
+            let mut cell = y_c[state_start..(state_start + hidden_size)].to_vec();
+
+            for step in 0..length {
+                let t = if recurrence.reversed[direction] {
+                    length - 1 - step
+                } else {
+                    step
+                };
+
+                // Input and recurrence terms of the specified row of the weights (and bias)
+                let x_start = (t * batch_size + batch) * input_size;
+                let input_term = |row: usize| {
+                    let w_start = (weights_start + row) * input_size;
+                    let bias = b.as_ref().map(|b| b[2 * weights_start + row]);
+                    (0..input_size)
+                        .map(|k| x[x_start + k] * w[w_start + k])
+                        .sum::<f64>()
+                        + bias.unwrap_or(0.0)
+                };
+                let recurrence_term = |row: usize, state: &[f64]| {
+                    let r_start = (weights_start + row) * hidden_size;
+                    let bias = b
+                        .as_ref()
+                        .map(|b| b[2 * weights_start + gate_count * hidden_size + row]);
+                    (0..hidden_size)
+                        .map(|k| state[k] * r[r_start + k])
+                        .sum::<f64>()
+                        + bias.unwrap_or(0.0)
+                };
+                let gate =
+                    |row: usize, state: &[f64]| input_term(row) + recurrence_term(row, state);
+
+                hidden = match op {
+                    "LSTM" => {
+                        let (g, h) = (&activations[1][direction], &activations[2][direction]);
+                        let peephole = |gate_index: usize, unit: usize| {
+                            p.as_ref().map_or(0.0, |p| {
+                                p[(direction * 3 + gate_index) * hidden_size + unit]
+                            })
+                        };
+                        let mut next_hidden = vec![0.0; hidden_size];
+                        for unit in 0..hidden_size {
+                            let cell_before = cell[unit];
+                            let gate_i = f.apply(clipped(
+                                gate(unit, &hidden) + peephole(0, unit) * cell_before,
+                            ));
+                            let gate_f = if input_forget {
+                                1.0 - gate_i
+                            } else {
+                                f.apply(clipped(
+                                    gate(unit + 2 * hidden_size, &hidden)
+                                        + peephole(2, unit) * cell_before,
+                                ))
+                            };
+                            let gate_c = g.apply(clipped(gate(unit + 3 * hidden_size, &hidden)));
+                            cell[unit] = gate_f * cell_before + gate_i * gate_c;
+                            let gate_o = f.apply(clipped(
+                                gate(unit + hidden_size, &hidden) + peephole(1, unit) * cell[unit],
+                            ));
+                            next_hidden[unit] = gate_o * h.apply(cell[unit]);
+                        }
+                        next_hidden
+                    }
+                    "GRU" => {
+                        let g = &activations[1][direction];
+                        let gate_r: Vec<f64> = (0..hidden_size)
+                            .map(|unit| f.apply(clipped(gate(unit + hidden_size, &hidden))))
+                            .collect();
+                        let reset_hidden: Vec<f64> = hidden
+                            .iter()
+                            .zip(gate_r.iter())
+                            .map(|(h, r)| h * r)
+                            .collect();
+                        (0..hidden_size)
+                            .map(|unit| {
+                                let row = unit + 2 * hidden_size;
+                                let gate_h = if linear_before_reset {
+                                    g.apply(clipped(
+                                        input_term(row)
+                                            + gate_r[unit] * recurrence_term(row, &hidden),
+                                    ))
+                                } else {
+                                    g.apply(clipped(gate(row, &reset_hidden)))
+                                };
+                                let gate_z = f.apply(clipped(gate(unit, &hidden)));
+                                (1.0 - gate_z) * gate_h + gate_z * hidden[unit]
+                            })
+                            .collect()
+                    }
+                    _ => (0..hidden_size)
+                        .map(|unit| f.apply(clipped(gate(unit, &hidden))))
+                        .collect(),
+                };
+
+                // Y has shape [seq_length, num_directions, batch_size, hidden_size]
+                let y_start = t * state_length + state_start;
+                y[y_start..(y_start + hidden_size)].copy_from_slice(&hidden);
+            }
+
+            y_h[state_start..(state_start + hidden_size)].copy_from_slice(&hidden);
+            y_c[state_start..(state_start + hidden_size)].copy_from_slice(&cell);
+        }
+    }
+
+    vec![y, y_h, y_c]
+        .into_iter()
+        .zip(output_shapes.iter())
+        .map(|(values, shape)| from_f64(values, shape.data_type))
+        .collect()
+}
+
+/// Matrix multiplication that behaves like numpy.matmul (1-D inputs are promoted to matrices, stacks of matrices are
+/// broadcast)
+fn matmul(a: &CpuTensor, b: &CpuTensor, output_shape: &Shape) -> Result<OutputTensor, KernelError> {
+    let mut a_dims = dims_of(&a.shape);
+    let mut b_dims = dims_of(&b.shape);
+    if a_dims.is_empty() || b_dims.is_empty() {
+        return Err(CompileError::InvalidInputShape {
+            input_index: if a_dims.is_empty() { 0 } else { 1 },
+            input_shape: if a_dims.is_empty() { a } else { b }.shape.clone(),
+        }
+        .into());
+    }
+
+    if a_dims.len() == 1 {
+        a_dims.insert(0, 1);
+    }
+    if b_dims.len() == 1 {
+        b_dims.push(1);
+    }
+
+    let (m, k) = (a_dims[a_dims.len() - 2], a_dims[a_dims.len() - 1]);
+    let n = b_dims[b_dims.len() - 1];
+    if b_dims[b_dims.len() - 2] != k {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: b.shape.clone(),
+        }
+        .into());
+    }
+
+    // Determine the (broadcast) stack dimensions
+    let a_stack = &a_dims[0..(a_dims.len() - 2)];
+    let b_stack = &b_dims[0..(b_dims.len() - 2)];
+    let stack_rank = a_stack.len().max(b_stack.len());
+    let padded = |dims: &[usize]| {
+        let mut padded = vec![1; stack_rank - dims.len()];
+        padded.extend_from_slice(dims);
+        padded
+    };
+    let stack: Vec<usize> = padded(a_stack)
+        .iter()
+        .zip(padded(b_stack).iter())
+        .map(|(x, y)| if *x == 1 { *y } else { *x })
+        .collect();
+    let invalid_broadcast = || CompileError::InvalidBroadcast {
+        input_shapes: vec![a.shape.clone(), b.shape.clone()],
+        output_shape: output_shape.clone(),
+    };
+    let a_stack_indices = broadcast_indices(a_stack, &stack).ok_or_else(invalid_broadcast)?;
+    let b_stack_indices = broadcast_indices(b_stack, &stack).ok_or_else(invalid_broadcast)?;
+
+    let a_values = to_f64(&a.data);
+    let b_values = to_f64(&b.data);
+    let mut results = Vec::with_capacity(a_stack_indices.len() * m * n);
+    for (a_matrix, b_matrix) in a_stack_indices.iter().zip(b_stack_indices.iter()) {
+        let a_offset = a_matrix * m * k;
+        let b_offset = b_matrix * k * n;
+        for i in 0..m {
+            for j in 0..n {
+                results.push(
+                    (0..k)
+                        .map(|p| a_values[a_offset + i * k + p] * b_values[b_offset + p * n + j])
+                        .sum(),
+                );
+            }
+        }
+    }
+
+    from_f64(results, output_shape.data_type)
+}
+
+/* Quantization: 8-bit integer values q represent real values (q - zero_point) * scale. The scale and zero point are
+either specified for a whole tensor, or for each slice along a specific axis. */
+
+/// Returns the value of a quantization parameter (scale or zero point) for each element of a tensor of the given shape
+fn quantization_parameter(
+    parameter: Option<&CpuTensor>,
+    shape: &Shape,
+    axis: usize,
+) -> Result<Vec<f64>, KernelError> {
+    let element_count = shape.element_count() as usize;
+    let values = match parameter {
+        Some(parameter) => to_f64(&parameter.data),
+        None => vec![0.0],
+    };
+    if values.len() == 1 {
+        return Ok(vec![values[0]; element_count]);
+    }
+
+    let dims = dims_of(shape);
+    if axis >= dims.len() || values.len() != dims[axis] {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: parameter.unwrap().shape.clone(),
+        }
+        .into());
+    }
+    let chunk: usize = dims[(axis + 1)..].iter().product();
+    Ok((0..element_count)
+        .map(|index| values[(index / chunk) % dims[axis]])
+        .collect())
+}
+
+/// Returns the (int32) tensor containing the values of a quantized tensor minus the zero point
+fn shifted_by_zero_point(
+    input: &CpuTensor,
+    zero_point: Option<&CpuTensor>,
+    axis: usize,
+) -> Result<CpuTensor, KernelError> {
+    let zero_points = quantization_parameter(zero_point, &input.shape, axis)?;
+    let values = to_f64(&input.data)
+        .into_iter()
+        .zip(zero_points)
+        .map(|(value, zero_point)| value - zero_point)
+        .collect();
+    let mut shape = input.shape.clone();
+    shape.data_type = ScalarType::I32;
+    Ok(CpuTensor {
+        data: Arc::new(from_f64(values, ScalarType::I32)?),
+        shape,
+    })
+}
+
+/// The shape of a tensor that holds (exact) integer accumulators for the specified quantized output
+fn accumulator_shape(output_shape: &Shape) -> Shape {
+    let mut shape = output_shape.clone();
+    shape.data_type = ScalarType::I64;
+    shape
+}
+
+/// Rounds and saturates real values to the range of the quantized output type
+fn saturate(values: Vec<f64>, data_type: ScalarType) -> Result<OutputTensor, KernelError> {
+    let (lowest, highest) = match data_type {
+        ScalarType::U8 => (u8::MIN as f64, u8::MAX as f64),
+        ScalarType::I8 => (i8::MIN as f64, i8::MAX as f64),
+        _ => return Err(CompileError::NotQuantizedType(data_type).into()),
+    };
+    from_f64(
+        values
+            .into_iter()
+            .map(|x| x.clamp(lowest, highest))
+            .collect(),
+        data_type,
+    )
+}
+
+/// Converts integer accumulators (with the specified scale per element) to the quantized output
+fn requantize(
+    accumulated: OutputTensor,
+    scales: &[f64],
+    y_scale: &CpuTensor,
+    y_zero_point: &CpuTensor,
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    let y_scale = to_f64(&y_scale.data)[0];
+    let y_zero_point = to_f64(&y_zero_point.data)[0];
+    let values = to_f64(&accumulated)
+        .into_iter()
+        .zip(scales.iter())
+        .map(|(value, scale)| round_half_to_even(value * scale / y_scale) + y_zero_point)
+        .collect();
+    saturate(values, output_shape.data_type)
+}
+
+/// The axis along which the scale and zero point of QuantizeLinear/DequantizeLinear apply (only relevant when these
+/// are specified per axis)
+fn quantization_axis(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    opset_version: i64,
+) -> Result<usize, KernelError> {
+    if inputs[1].shape.element_count() == 1 {
+        return Ok(0);
+    }
+    normalized_axis(
+        proto.get_attribute_value("axis", Some(1))?,
+        inputs[0].shape.rank(),
+        opset_version,
+    )
+}
+
+fn quantize_linear(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    let input = &inputs[0];
+    let axis = quantization_axis(proto, inputs, opset_version)?;
+    let scales = quantization_parameter(Some(&inputs[1]), &input.shape, axis)?;
+    let zero_points = quantization_parameter(inputs.get(2), &input.shape, axis)?;
+    let values = to_f64(&input.data)
+        .into_iter()
+        .zip(scales.iter().zip(zero_points.iter()))
+        .map(|(x, (scale, zero_point))| round_half_to_even(x / scale) + zero_point)
+        .collect();
+    saturate(values, output_shape.data_type)
+}
+
+fn dequantize_linear(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    let input = &inputs[0];
+    let axis = quantization_axis(proto, inputs, opset_version)?;
+    let scales = quantization_parameter(Some(&inputs[1]), &input.shape, axis)?;
+    let zero_points = quantization_parameter(inputs.get(2), &input.shape, axis)?;
+    let values = to_f64(&input.data)
+        .into_iter()
+        .zip(scales.iter().zip(zero_points.iter()))
+        .map(|(x, (scale, zero_point))| (x - zero_point) * scale)
+        .collect();
+    from_f64(values, output_shape.data_type)
+}
+
+fn resize(
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    let mode = proto.get_attribute_value("mode", Some("nearest".to_string()))?;
+    if mode != "nearest" {
+        return Err(CompileError::UnimplementedVariant {
+            op: String::from("Resize"),
+            variant: format!("mode={}", mode),
+        }
+        .into());
+    }
+
+    let nearest_mode =
+        proto.get_attribute_value("nearest_mode", Some("round_prefer_floor".to_string()))?;
+    if nearest_mode != "floor" {
+        return Err(CompileError::UnimplementedVariant {
+            op: "Resize".to_string(),
+            variant: format!("nearest_mode={}", nearest_mode),
+        }
+        .into());
+    }
+
+    let input_dims = dims_of(&input.shape);
+    let output_dims = dims_of(output_shape);
+    let input_strides = strides(&input_dims);
+    let scales: Vec<f32> = proto.get_attribute_value("scales", Some(vec![]))?;
+    let scales: Vec<f64> = if scales.is_empty() {
+        input_dims
+            .iter()
+            .zip(output_dims.iter())
+            .map(|(i, o)| *o as f64 / *i as f64)
+            .collect()
+    } else {
+        scales.into_iter().map(|s| s as f64).collect()
+    };
+
+    // Like the shader (matrix/resize.wgsl), take the input element that the center of the output element falls in
+    let mut selection = Vec::with_capacity(output_shape.element_count() as usize);
+    for_each_index(&output_dims, |_, index| {
+        selection.push(
+            index
+                .iter()
+                .enumerate()
+                .map(|(d, i)| {
+                    let source = (((*i as f64) + 0.5) / scales[d]).floor() as usize;
+                    source.min(input_dims[d] - 1) * input_strides[d]
+                })
+                .sum(),
+        );
+    });
+
+    Ok(select(&input.data, &selection))
+}
+
+fn split(
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shapes: &[Shape],
+) -> Result<Vec<OutputTensor>, KernelError> {
+    let input_dims = dims_of(&input.shape);
+    let input_strides = strides(&input_dims);
+    let axis = normalized_axis(
+        proto.get_attribute_value("axis", Some(0))?,
+        input_dims.len(),
+        0,
+    )?;
+
+    // The size of each part follows from the output shapes
+    let mut offset = 0;
+    let mut outputs = Vec::with_capacity(output_shapes.len());
+    for output_shape in output_shapes {
+        let output_dims = dims_of(output_shape);
+        let mut selection = Vec::with_capacity(output_shape.element_count() as usize);
+        for_each_index(&output_dims, |_, index| {
+            selection.push(
+                index
+                    .iter()
+                    .enumerate()
+                    .map(|(d, i)| if d == axis { i + offset } else { *i } * input_strides[d])
+                    .sum(),
+            );
+        });
+        offset += output_dims[axis];
+        outputs.push(select(&input.data, &selection));
+    }
+
+    if offset > input_dims[axis] {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: input.shape.clone(),
+        }
+        .into());
+    }
+
+    Ok(outputs)
+}
+
+fn pad(
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    let mode = proto.get_attribute_value("mode", Some("constant".to_string()))?;
+    let input_dims = dims_of(&input.shape);
+    let input_strides = strides(&input_dims);
+    let rank = input_dims.len();
+
+    let pads: Vec<i64> = proto.get_attribute_value("pads", None)?;
+    if pads.len() != rank * 2 {
+        return Err(CompileError::InvalidAttributeValue {
+            attribute: "pads".into(),
+            value: format!("{:?}", pads),
+            opset_version,
+        }
+        .into());
+    }
+
+    // Before opset 11, the constant value was specified using the 'value' attribute
+    let constant_value = numeric_attribute(proto, "constant_value")
+        .or_else(|| numeric_attribute(proto, "value"))
+        .unwrap_or(0.0);
+
+    let mut selection = Vec::with_capacity(output_shape.element_count() as usize);
+    let mut invalid_mode = false;
+    for_each_index(&dims_of(output_shape), |_, index| {
+        let mut source = Some(0);
+        for d in 0..rank {
+            let size = input_dims[d] as i64;
+            let mut position = index[d] as i64 - pads[d];
+            if position < 0 || position >= size {
+                match mode.as_str() {
+                    "constant" => {
+                        source = None;
+                        break;
+                    }
+                    "edge" => position = position.clamp(0, size - 1),
+                    "reflect" => {
+                        let period = (2 * (size - 1)).max(1);
+                        position = position.rem_euclid(period);
+                        if position >= size {
+                            position = period - position;
+                        }
+                    }
+                    _ => {
+                        invalid_mode = true;
+                        source = None;
+                        break;
+                    }
+                }
+            }
+            source = source.map(|s| s + position as usize * input_strides[d]);
+        }
+        selection.push(source);
+    });
+
+    if invalid_mode {
+        return Err(CompileError::UnimplementedVariant {
+            op: String::from("Pad"),
+            variant: format!("mode={}", mode),
+        }
+        .into());
+    }
+
+    select_or(&input.data, &selection, constant_value)
+}
+
+fn expand(input: &CpuTensor, output_shape: &Shape) -> Result<OutputTensor, KernelError> {
+    let indices =
+        broadcast_indices(&dims_of(&input.shape), &dims_of(output_shape)).ok_or_else(|| {
+            CompileError::InvalidBroadcast {
+                input_shapes: vec![input.shape.clone()],
+                output_shape: output_shape.clone(),
+            }
+        })?;
+    Ok(select(&input.data, &indices))
+}
+
+fn tile(
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    let input_dims = dims_of(&input.shape);
+    let input_strides = strides(&input_dims);
+    let repeats: Vec<i64> = proto.get_attribute_value("repeats", None)?;
+    if repeats.len() != input_dims.len()
+        || repeats
+            .iter()
+            .zip(input_dims.iter())
+            .zip(output_shape.dims.iter())
+            .any(|((repeat, dim), out_dim)| {
+                *repeat < 0 || (*repeat as u64) * (*dim as u64) != *out_dim
+            })
+    {
+        return Err(CompileError::InvalidAttributeValue {
+            attribute: "repeats".to_string(),
+            value: format!("{:?}", repeats),
+            opset_version,
+        }
+        .into());
+    }
+
+    let mut selection = Vec::with_capacity(output_shape.element_count() as usize);
+    for_each_index(&dims_of(output_shape), |_, index| {
+        selection.push(
+            index
+                .iter()
+                .zip(input_dims.iter().zip(input_strides.iter()))
+                .map(|(position, (dim, stride))| (position % dim) * stride)
+                .sum(),
+        );
+    });
+    Ok(select(&input.data, &selection))
+}
+
+fn slice(
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    let ranges = slice_ranges(proto, &input.shape, output_shape, opset_version)?;
+    let input_strides = strides(&dims_of(&input.shape));
+
+    let mut selection = Vec::with_capacity(output_shape.element_count() as usize);
+    for_each_index(&dims_of(output_shape), |_, index| {
+        let source: i64 = ranges
+            .iter()
+            .zip(index.iter().zip(input_strides.iter()))
+            .map(|((start, step), (position, stride))| {
+                (start + step * (*position as i64)) * (*stride as i64)
+            })
+            .sum();
+        selection.push(source as usize);
+    });
+    Ok(select(&input.data, &selection))
+}
+
+/// TopK selects the first k elements along an axis (ordered by value, then by index) and returns both their values and
+/// indices. ArgMax and ArgMin select a single element and only return its index.
+fn top_k(
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shapes: &[Shape],
+    opset_version: i64,
+) -> Result<Vec<OutputTensor>, KernelError> {
+    let selection = top_k_selection(proto, &input.shape, opset_version)?;
+    let (k, axis_length, inner_size) = (
+        selection.k as usize,
+        selection.axis_length as usize,
+        selection.inner_size as usize,
+    );
+    let values = to_f64(&input.data);
+
+    let output_count = selection.row_count as usize * k;
+    let mut selected = vec![0; output_count];
+    let mut indices = vec![0; output_count];
+    for row in 0..(selection.row_count as usize) {
+        let (outer, inner) = (row / inner_size, row % inner_size);
+        let start = outer * axis_length * inner_size + inner;
+        let value = |index: usize| values[start + index * inner_size];
+
+        let mut order: Vec<usize> = (0..axis_length).collect();
+        order.sort_by(|a, b| {
+            let by_value = if selection.largest {
+                value(*b).total_cmp(&value(*a))
+            } else {
+                value(*a).total_cmp(&value(*b))
+            };
+            by_value.then(if selection.prefer_last_index {
+                b.cmp(a)
+            } else {
+                a.cmp(b)
+            })
+        });
+
+        for (position, index) in order.into_iter().take(k).enumerate() {
+            let output_index = (outer * k + position) * inner_size + inner;
+            selected[output_index] = start + index * inner_size;
+            indices[output_index] = index as i64;
+        }
+    }
+
+    Ok(if proto.get_op_type() == "TopK" {
+        vec![
+            select(&input.data, &selected),
+            from_i64(indices, output_shapes[1].data_type)?,
+        ]
+    } else {
+        vec![from_i64(indices, output_shapes[0].data_type)?]
+    })
+}
+
+/// NonMaxSuppression selects, for each batch and class, the boxes with the highest scores that do not overlap too much
+/// with a box selected before. The output lists the (batch, class, box) indices of the selected boxes, followed by rows
+/// of -1 that pad the output to its fixed size (see `op_has_data_dependent_output`).
+fn non_max_suppression(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    let (boxes, scores) = (&inputs[0], &inputs[1]);
+    if boxes.shape.rank() != 3 || boxes.shape.dim(2) != 4 {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 0,
+            input_shape: boxes.shape.clone(),
+        }
+        .into());
+    }
+    let (batch_count, box_count) = (boxes.shape.dim(0) as usize, boxes.shape.dim(1) as usize);
+    if scores.shape.rank() != 3
+        || scores.shape.dim(0) as usize != batch_count
+        || scores.shape.dim(2) as usize != box_count
+    {
+        return Err(CompileError::InvalidInputShape {
+            input_index: 1,
+            input_shape: scores.shape.clone(),
+        }
+        .into());
+    }
+    let class_count = scores.shape.dim(1) as usize;
+
+    // The optional scalar inputs. Without a limit on the number of boxes, no boxes are selected at all.
+    let scalar_input = |position: usize| {
+        provided_input_index(proto, position)
+            .and_then(|index| inputs.get(index))
+            .filter(|input| element_count(&input.data) > 0)
+    };
+    let max_boxes_per_class = scalar_input(2)
+        .map(|input| to_i64(&input.data)[0])
+        .unwrap_or(0);
+    let iou_threshold = scalar_input(3)
+        .map(|input| to_f64(&input.data)[0])
+        .unwrap_or(0.0);
+    let score_threshold = scalar_input(4).map(|input| to_f64(&input.data)[0]);
+    let center_point_box = proto.get_attribute_value("center_point_box", Some(0))? != 0;
+
+    // Boxes are converted to (y1, x1, y2, x2) with y1 <= y2 and x1 <= x2
+    let box_values = to_f64(&boxes.data);
+    let corners = |batch: usize, index: usize| -> [f64; 4] {
+        let b = &box_values[(batch * box_count + index) * 4..][..4];
+        if center_point_box {
+            let (half_width, half_height) = (b[2] / 2.0, b[3] / 2.0);
+            [
+                b[1] - half_height,
+                b[0] - half_width,
+                b[1] + half_height,
+                b[0] + half_width,
+            ]
+        } else {
+            [
+                b[0].min(b[2]),
+                b[1].min(b[3]),
+                b[0].max(b[2]),
+                b[1].max(b[3]),
+            ]
+        }
+    };
+    let intersection_over_union = |a: &[f64; 4], b: &[f64; 4]| {
+        let area = |c: &[f64; 4]| (c[2] - c[0]) * (c[3] - c[1]);
+        let (a_area, b_area) = (area(a), area(b));
+        if a_area <= 0.0 || b_area <= 0.0 {
+            return 0.0;
+        }
+        let intersection =
+            (a[2].min(b[2]) - a[0].max(b[0])).max(0.0) * (a[3].min(b[3]) - a[1].max(b[1])).max(0.0);
+        if intersection <= 0.0 {
+            return 0.0;
+        }
+        intersection / (a_area + b_area - intersection)
+    };
+
+    let score_values = to_f64(&scores.data);
+    let mut selected: Vec<i64> = vec![];
+    for batch in 0..batch_count {
+        for class in 0..class_count {
+            let class_scores =
+                &score_values[(batch * class_count + class) * box_count..][..box_count];
+            let mut candidates: Vec<usize> = (0..box_count)
+                .filter(|index| match score_threshold {
+                    Some(threshold) => class_scores[*index] > threshold,
+                    None => true,
+                })
+                .collect();
+
+            // Candidates are considered by descending score (boxes with equal scores in order of their index)
+            candidates.sort_by(|a, b| class_scores[*b].total_cmp(&class_scores[*a]));
+
+            let mut kept: Vec<[f64; 4]> = vec![];
+            for index in candidates {
+                if kept.len() as i64 >= max_boxes_per_class {
+                    break;
+                }
+                let candidate = corners(batch, index);
+                if kept
+                    .iter()
+                    .all(|other| intersection_over_union(other, &candidate) <= iou_threshold)
+                {
+                    kept.push(candidate);
+                    selected.extend([batch as i64, class as i64, index as i64]);
+                }
+            }
+        }
+    }
+
+    let capacity = output_shape.element_count() as usize;
+    if selected.len() > capacity {
+        return Err(KernelError::OutOfBounds);
+    }
+    selected.resize(capacity, -1);
+    from_i64(selected, output_shape.data_type)
+}
+
+/// RoiAlign pools each region of interest (RoI) of the input into a fixed number of bins. The value of each bin is the
+/// average (or maximum) of values sampled at regularly spaced points in the bin, which are bilinearly interpolated.
+fn roi_align(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    let input_shapes: Vec<&Shape> = inputs.iter().map(|input| &input.shape).collect();
+    let alignment = roi_alignment(proto, &input_shapes, output_shape, opset_version)?;
+    let (height, width) = (alignment.height as usize, alignment.width as usize);
+    let (output_height, output_width) = (
+        alignment.output_height as usize,
+        alignment.output_width as usize,
+    );
+    let spatial_scale = alignment.spatial_scale as f64;
+    let offset = if alignment.half_pixel { 0.5 } else { 0.0 };
+
+    let values = to_f64(&inputs[0].data);
+    let rois = to_f64(&inputs[1].data);
+    let batch_indices = to_i64(&inputs[2].data);
+    let batch_count = inputs[0].shape.dim(0) as i64;
+
+    // Bilinear interpolation of the value at (y, x), where the four surrounding pixels are weighted by their distance. In
+    // 'max' mode, the largest of the weighted values is taken instead of their sum.
+    let sample = |channel: &[f64], y: f64, x: f64| -> f64 {
+        if y < -1.0 || y > height as f64 || x < -1.0 || x > width as f64 {
+            return 0.0;
+        }
+        let (mut y, mut x) = (y.max(0.0), x.max(0.0));
+        let (mut y_low, mut x_low) = (y as usize, x as usize);
+        let (mut y_high, mut x_high) = (y_low + 1, x_low + 1);
+        if y_low >= height - 1 {
+            y_low = height - 1;
+            y_high = y_low;
+            y = y_low as f64;
+        }
+        if x_low >= width - 1 {
+            x_low = width - 1;
+            x_high = x_low;
+            x = x_low as f64;
+        }
+        let (ly, lx) = (y - y_low as f64, x - x_low as f64);
+        let (hy, hx) = (1.0 - ly, 1.0 - lx);
+        let weighted = [
+            hy * hx * channel[y_low * width + x_low],
+            hy * lx * channel[y_low * width + x_high],
+            ly * hx * channel[y_high * width + x_low],
+            ly * lx * channel[y_high * width + x_high],
+        ];
+        if alignment.max {
+            weighted.iter().copied().fold(f64::MIN, f64::max)
+        } else {
+            weighted.iter().sum()
+        }
+    };
+
+    let mut output = Vec::with_capacity(output_shape.element_count() as usize);
+    for (roi, batch) in batch_indices.iter().enumerate() {
+        if *batch < 0 || *batch >= batch_count {
+            return Err(KernelError::OutOfBounds);
+        }
+        let coordinates: Vec<f64> = rois[roi * 4..][..4]
+            .iter()
+            .map(|c| c * spatial_scale - offset)
+            .collect();
+        let (start_x, start_y) = (coordinates[0], coordinates[1]);
+        let (mut roi_width, mut roi_height) = (coordinates[2] - start_x, coordinates[3] - start_y);
+        if !alignment.half_pixel {
+            // Malformed RoIs are forced to be at least 1x1
+            roi_width = roi_width.max(1.0);
+            roi_height = roi_height.max(1.0);
+        }
+        let bin_height = roi_height / output_height as f64;
+        let bin_width = roi_width / output_width as f64;
+        let (grid_y, grid_x) = if alignment.sampling_ratio > 0 {
+            (
+                alignment.sampling_ratio as usize,
+                alignment.sampling_ratio as usize,
+            )
+        } else {
+            (
+                bin_height.ceil().max(0.0) as usize,
+                bin_width.ceil().max(0.0) as usize,
+            )
+        };
+
+        for channel_index in 0..(alignment.channels as usize) {
+            let channel_start =
+                (*batch as usize * alignment.channels as usize + channel_index) * height * width;
+            let channel = &values[channel_start..][..(height * width)];
+            for bin_y in 0..output_height {
+                for bin_x in 0..output_width {
+                    let mut result = 0.0;
+                    for iy in 0..grid_y {
+                        let y = start_y
+                            + bin_y as f64 * bin_height
+                            + (iy as f64 + 0.5) * bin_height / grid_y as f64;
+                        for ix in 0..grid_x {
+                            let x = start_x
+                                + bin_x as f64 * bin_width
+                                + (ix as f64 + 0.5) * bin_width / grid_x as f64;
+                            let value = sample(channel, y, x);
+                            result = if !alignment.max {
+                                result + value
+                            } else if iy == 0 && ix == 0 {
+                                value
+                            } else {
+                                f64::max(result, value)
+                            };
+                        }
+                    }
+                    if !alignment.max {
+                        result /= (grid_y * grid_x).max(1) as f64;
+                    }
+                    output.push(result);
+                }
+            }
+        }
+    }
+    from_f64(output, output_shape.data_type)
+}
+
+/// Each output element of Einsum is the sum of the products of the inputs over all values of the summed labels
+fn einsum(
+    proto: &NodeProto,
+    inputs: &[CpuTensor],
+    output_shape: &Shape,
+) -> Result<OutputTensor, KernelError> {
+    fn apply<T>(
+        values: &[Vec<T>],
+        base_strides: &[Vec<u64>],
+        summed_strides: &[Vec<u64>],
+        output_dims: &[usize],
+        summed_dims: &[usize],
+        zero: T,
+        one: T,
+    ) -> Vec<T>
+    where
+        T: Copy + std::ops::Add<Output = T> + std::ops::Mul<Output = T>,
+    {
+        let offset = |index: &[usize], strides: &[u64]| -> usize {
+            index
+                .iter()
+                .zip(strides.iter())
+                .map(|(i, stride)| i * (*stride as usize))
+                .sum()
+        };
+
+        let mut results = Vec::with_capacity(output_dims.iter().product());
+        for_each_index(output_dims, |_, output_index| {
+            let bases: Vec<usize> = base_strides
+                .iter()
+                .map(|strides| offset(output_index, strides))
+                .collect();
+            let mut sum = zero;
+            for_each_index(summed_dims, |_, summed_index| {
+                sum = sum
+                    + values
+                        .iter()
+                        .zip(bases.iter().zip(summed_strides.iter()))
+                        .fold(one, |product, (input, (base, strides))| {
+                            product * input[base + offset(summed_index, strides)]
+                        });
+            });
+            results.push(sum);
+        });
+        results
+    }
+
+    let input_shapes: Vec<&Shape> = inputs.iter().map(|input| &input.shape).collect();
+    let equation = einsum_equation(proto, &input_shapes, &[output_shape])?;
+    let input_dims: Vec<&[u64]> = input_shapes
+        .iter()
+        .map(|shape| shape.dims.as_slice())
+        .collect();
+    let sizes = equation
+        .label_sizes(&input_dims)
+        .map_err(|error| invalid_einsum_equation(proto, error))?;
+    let summed_labels = equation.summed_labels();
+    let summed_dims: Vec<usize> = summed_labels
+        .iter()
+        .map(|label| sizes[label] as usize)
+        .collect();
+    let base_strides = equation.label_strides(&input_dims, &equation.output);
+    let summed_strides = equation.label_strides(&input_dims, &summed_labels);
+    let output_dims = dims_of(output_shape);
+
+    if output_shape.data_type.is_float() {
+        let values: Vec<Vec<f64>> = inputs.iter().map(|input| to_f64(&input.data)).collect();
+        let results = apply(
+            &values,
+            &base_strides,
+            &summed_strides,
+            &output_dims,
+            &summed_dims,
+            0.0,
+            1.0,
+        );
+        from_f64(results, output_shape.data_type)
+    } else {
+        let values: Vec<Vec<i64>> = inputs.iter().map(|input| to_i64(&input.data)).collect();
+        let results = apply(
+            &values,
+            &base_strides,
+            &summed_strides,
+            &output_dims,
+            &summed_dims,
+            0,
+            1,
+        );
+        from_i64(results, output_shape.data_type)
+    }
+}
+
+fn transpose(
+    proto: &NodeProto,
+    input: &CpuTensor,
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Result<OutputTensor, KernelError> {
+    let input_dims = dims_of(&input.shape);
+    let input_strides = strides(&input_dims);
+    let default = (0..(input_dims.len() as i64)).rev().collect::<Vec<i64>>();
+    let perms: Vec<i64> = proto.get_attribute_value("perm", Some(default))?;
+
+    // The number of elements in the permutations list must be equal to the output shape rank
+    if perms.len() != output_shape.rank()
+        || perms
+            .iter()
+            .any(|p| *p < 0 || *p as usize >= input_dims.len())
+    {
+        return Err(CompileError::InvalidAttributeValue {
+            attribute: "perm".to_string(),
+            value: format!("{:?}", perms),
+            opset_version,
+        }
+        .into());
+    }
+
+    let mut selection = Vec::with_capacity(output_shape.element_count() as usize);
+    for_each_index(&dims_of(output_shape), |_, index| {
+        selection.push(
+            index
+                .iter()
+                .zip(perms.iter())
+                .map(|(i, p)| i * input_strides[*p as usize])
+                .sum(),
+        );
+    });
+
+    Ok(select(&input.data, &selection))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{broadcast_indices, round_half_to_even};
+
+    #[test]
+    fn test_broadcast_indices() {
+        assert_eq!(broadcast_indices(&[2], &[2, 2]), Some(vec![0, 1, 0, 1]));
+        assert_eq!(broadcast_indices(&[2, 1], &[2, 2]), Some(vec![0, 0, 1, 1]));
+        assert_eq!(broadcast_indices(&[], &[3]), Some(vec![0, 0, 0]));
+        assert_eq!(broadcast_indices(&[3], &[2]), None);
+        assert_eq!(broadcast_indices(&[1, 2, 3], &[2, 3]), None);
+    }
+
+    #[test]
+    fn test_round_half_to_even() {
+        assert_eq!(round_half_to_even(0.5), 0.0);
+        assert_eq!(round_half_to_even(1.5), 2.0);
+        assert_eq!(round_half_to_even(2.5), 2.0);
+        assert_eq!(round_half_to_even(-2.5), -2.0);
+        assert_eq!(round_half_to_even(2.4), 2.0);
+        assert_eq!(round_half_to_even(2.6), 3.0);
+    }
+}
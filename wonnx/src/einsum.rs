@@ -0,0 +1,330 @@
+//! Parsing of the equations of the Einsum operator (https://github.com/onnx/onnx/blob/main/docs/Operators.md#Einsum).
+//! An equation such as `bij,bjk->bik` assigns a label to each axis of each input and of the output. Labels that do not
+//! occur in the output are summed over. An ellipsis (`...`) stands for any number of leading axes that are broadcast.
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+/// A label identifies an axis in an Einsum equation. Letters are numbered in ASCII order (`A`-`Z` are 0-25, `a`-`z` are
+/// 26-51), so that sorting labels sorts the letters alphabetically. Each axis covered by an ellipsis gets a label starting
+/// at [`ELLIPSIS_LABEL`] (aligned to the right, like broadcasting).
+pub type EinsumLabel = usize;
+
+/// The label of the first axis covered by an ellipsis
+pub const ELLIPSIS_LABEL: EinsumLabel = 52;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EinsumError {
+    #[error("invalid character '{0}' in equation")]
+    InvalidCharacter(char),
+
+    #[error("term '{0}' contains more than one ellipsis")]
+    MultipleEllipses(String),
+
+    #[error("equation has {terms} input terms, but there are {inputs} inputs")]
+    InputCountMismatch { terms: usize, inputs: usize },
+
+    #[error("term '{term}' does not match the rank ({rank}) of input {input_index}")]
+    RankMismatch {
+        term: String,
+        rank: usize,
+        input_index: usize,
+    },
+
+    #[error("label '{0}' occurs more than once in the output")]
+    DuplicateOutputLabel(String),
+
+    #[error("output label '{0}' does not occur in any of the inputs")]
+    UnknownOutputLabel(String),
+
+    #[error("label '{label}' has sizes {size} and {other_size}, which cannot be broadcast")]
+    SizeMismatch {
+        label: String,
+        size: u64,
+        other_size: u64,
+    },
+}
+
+/// A parsed Einsum equation with the labels of each axis of the inputs and the output (ellipses expanded)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EinsumEquation {
+    pub inputs: Vec<Vec<EinsumLabel>>,
+    pub output: Vec<EinsumLabel>,
+}
+
+/// Returns the letter for a label, or `...` when the label belongs to an ellipsis
+pub fn label_name(label: EinsumLabel) -> String {
+    match label {
+        0..=25 => ((b'A' + label as u8) as char).to_string(),
+        26..=51 => ((b'a' + (label - 26) as u8) as char).to_string(),
+        _ => "...".to_string(),
+    }
+}
+
+/// Splits a term into its letter labels, and the position of the ellipsis (if any)
+fn parse_term(term: &str) -> Result<(Vec<EinsumLabel>, Option<usize>), EinsumError> {
+    let mut labels = vec![];
+    let mut ellipsis = None;
+    let mut rest = term;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("...") {
+            if ellipsis.is_some() {
+                return Err(EinsumError::MultipleEllipses(term.to_string()));
+            }
+            ellipsis = Some(labels.len());
+            rest = after;
+            continue;
+        }
+
+        labels.push(match c {
+            'A'..='Z' => (c as u8 - b'A') as EinsumLabel,
+            'a'..='z' => (c as u8 - b'a') as EinsumLabel + 26,
+            _ => return Err(EinsumError::InvalidCharacter(c)),
+        });
+        rest = &rest[c.len_utf8()..];
+    }
+    Ok((labels, ellipsis))
+}
+
+/// Inserts the labels for the last `count` of the `ellipsis_rank` axes covered by an ellipsis at the indicated position
+fn expand_ellipsis(
+    mut labels: Vec<EinsumLabel>,
+    position: Option<usize>,
+    count: usize,
+    ellipsis_rank: usize,
+) -> Vec<EinsumLabel> {
+    if let Some(position) = position {
+        let first = ELLIPSIS_LABEL + ellipsis_rank - count;
+        labels.splice(position..position, first..(first + count));
+    }
+    labels
+}
+
+impl EinsumEquation {
+    /// Parse an equation for inputs of the given ranks. When the equation has no explicit output (`->`), the output
+    /// consists of the ellipsis axes followed by the labels that occur exactly once, in alphabetical order.
+    pub fn parse(equation: &str, input_ranks: &[usize]) -> Result<EinsumEquation, EinsumError> {
+        let equation: String = equation.chars().filter(|c| !c.is_whitespace()).collect();
+        let (inputs_part, output_part) = match equation.split_once("->") {
+            Some((inputs, output)) => (inputs, Some(output)),
+            None => (equation.as_str(), None),
+        };
+
+        let terms: Vec<&str> = inputs_part.split(',').collect();
+        if terms.len() != input_ranks.len() {
+            return Err(EinsumError::InputCountMismatch {
+                terms: terms.len(),
+                inputs: input_ranks.len(),
+            });
+        }
+
+        // Determine how many axes each ellipsis covers
+        let mut parsed_terms = Vec::with_capacity(terms.len());
+        for (input_index, (term, rank)) in terms.iter().zip(input_ranks.iter()).enumerate() {
+            let (labels, ellipsis) = parse_term(term)?;
+            let ellipsis_count = match ellipsis {
+                Some(_) if labels.len() <= *rank => *rank - labels.len(),
+                None if labels.len() == *rank => 0,
+                _ => {
+                    return Err(EinsumError::RankMismatch {
+                        term: term.to_string(),
+                        rank: *rank,
+                        input_index,
+                    })
+                }
+            };
+            parsed_terms.push((labels, ellipsis, ellipsis_count));
+        }
+        let ellipsis_rank = parsed_terms
+            .iter()
+            .map(|(_, _, count)| *count)
+            .max()
+            .unwrap_or(0);
+
+        let inputs: Vec<Vec<EinsumLabel>> = parsed_terms
+            .into_iter()
+            .map(|(labels, ellipsis, count)| {
+                expand_ellipsis(labels, ellipsis, count, ellipsis_rank)
+            })
+            .collect();
+
+        let output = match output_part {
+            Some(output_term) => {
+                let (labels, ellipsis) = parse_term(output_term)?;
+                for (index, label) in labels.iter().enumerate() {
+                    if labels[0..index].contains(label) {
+                        return Err(EinsumError::DuplicateOutputLabel(label_name(*label)));
+                    }
+                    if !inputs.iter().any(|input| input.contains(label)) {
+                        return Err(EinsumError::UnknownOutputLabel(label_name(*label)));
+                    }
+                }
+                expand_ellipsis(labels, ellipsis, ellipsis_rank, ellipsis_rank)
+            }
+            None => {
+                let once = (0..ELLIPSIS_LABEL).filter(|label| {
+                    inputs
+                        .iter()
+                        .flatten()
+                        .filter(|input_label| *input_label == label)
+                        .count()
+                        == 1
+                });
+                (ELLIPSIS_LABEL..(ELLIPSIS_LABEL + ellipsis_rank))
+                    .chain(once)
+                    .collect()
+            }
+        };
+
+        Ok(EinsumEquation { inputs, output })
+    }
+
+    /// The labels that do not occur in the output (and are summed over), in order of first occurrence
+    pub fn summed_labels(&self) -> Vec<EinsumLabel> {
+        let mut summed = vec![];
+        for label in self.inputs.iter().flatten() {
+            if !self.output.contains(label) && !summed.contains(label) {
+                summed.push(*label);
+            }
+        }
+        summed
+    }
+
+    /// Determines the size of each label from the dimensions of the inputs. A dimension of size 1 is broadcast to the
+    /// size of the same label elsewhere.
+    pub fn label_sizes(
+        &self,
+        input_dims: &[&[u64]],
+    ) -> Result<HashMap<EinsumLabel, u64>, EinsumError> {
+        let mut sizes: HashMap<EinsumLabel, u64> = HashMap::new();
+        for (labels, dims) in self.inputs.iter().zip(input_dims.iter()) {
+            for (label, dim) in labels.iter().zip(dims.iter()) {
+                let size = sizes.entry(*label).or_insert(*dim);
+                if *size == 1 {
+                    *size = *dim;
+                } else if *dim != 1 && *dim != *size {
+                    return Err(EinsumError::SizeMismatch {
+                        label: label_name(*label),
+                        size: *size,
+                        other_size: *dim,
+                    });
+                }
+            }
+        }
+        Ok(sizes)
+    }
+
+    /// The dimensions of the output for inputs of the given dimensions
+    pub fn output_dims(&self, input_dims: &[&[u64]]) -> Result<Vec<u64>, EinsumError> {
+        let sizes = self.label_sizes(input_dims)?;
+        Ok(self.output.iter().map(|label| sizes[label]).collect())
+    }
+
+    /// For each input, the number of elements to advance in the input when the value of each of the given labels
+    /// increases by one. The stride is zero for labels that do not occur in the input or that are broadcast. When a
+    /// label occurs more than once in an input, the strides of its axes are added (i.e. the diagonal is taken).
+    pub fn label_strides(&self, input_dims: &[&[u64]], labels: &[EinsumLabel]) -> Vec<Vec<u64>> {
+        self.inputs
+            .iter()
+            .zip(input_dims.iter())
+            .map(|(input_labels, dims)| {
+                labels
+                    .iter()
+                    .map(|label| {
+                        input_labels
+                            .iter()
+                            .enumerate()
+                            .filter(|(axis, input_label)| *input_label == label && dims[*axis] != 1)
+                            .map(|(axis, _)| dims[(axis + 1)..].iter().product::<u64>())
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EinsumEquation, EinsumError, ELLIPSIS_LABEL};
+
+    const A: usize = 26;
+    const I: usize = 26 + 8;
+    const J: usize = 26 + 9;
+    const K: usize = 26 + 10;
+
+    #[test]
+    fn test_parse_explicit() {
+        let equation = EinsumEquation::parse("ij, jk -> ik", &[2, 2]).unwrap();
+        assert_eq!(equation.inputs, vec![vec![I, J], vec![J, K]]);
+        assert_eq!(equation.output, vec![I, K]);
+        assert_eq!(equation.summed_labels(), vec![J]);
+        assert_eq!(
+            equation.output_dims(&[&[2, 3], &[3, 4]]).unwrap(),
+            vec![2, 4]
+        );
+    }
+
+    #[test]
+    fn test_parse_implicit() {
+        // Labels that occur once are sorted (uppercase first), repeated labels are summed
+        let equation = EinsumEquation::parse("kj,ji,Ai", &[2, 2, 2]).unwrap();
+        assert_eq!(equation.output, vec![0, K]);
+
+        let trace = EinsumEquation::parse("ii", &[2]).unwrap();
+        assert!(trace.output.is_empty());
+        assert_eq!(trace.label_strides(&[&[3, 3]], &[I]), vec![vec![4]]);
+    }
+
+    #[test]
+    fn test_parse_ellipsis() {
+        let equation = EinsumEquation::parse("...ij,j...->...i", &[4, 2]).unwrap();
+        let e = ELLIPSIS_LABEL;
+        assert_eq!(equation.inputs, vec![vec![e, e + 1, I, J], vec![J, e + 1]]);
+        assert_eq!(equation.output, vec![e, e + 1, I]);
+        assert_eq!(
+            equation.output_dims(&[&[2, 1, 3, 4], &[4, 5]]).unwrap(),
+            vec![2, 5, 3]
+        );
+        assert_eq!(
+            equation.label_strides(&[&[2, 1, 3, 4], &[4, 5]], &[e + 1, J]),
+            vec![vec![0, 1], vec![1, 5]]
+        );
+
+        let implicit = EinsumEquation::parse("a...", &[3]).unwrap();
+        assert_eq!(implicit.output, vec![e, e + 1, A]);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(
+            EinsumEquation::parse("ij,jk", &[2]),
+            Err(EinsumError::InputCountMismatch {
+                terms: 2,
+                inputs: 1
+            })
+        );
+        assert_eq!(
+            EinsumEquation::parse("ij->i.", &[2]),
+            Err(EinsumError::InvalidCharacter('.'))
+        );
+        assert_eq!(
+            EinsumEquation::parse("ij->ii", &[2]),
+            Err(EinsumError::DuplicateOutputLabel("i".to_string()))
+        );
+        assert_eq!(
+            EinsumEquation::parse("ij->k", &[2]),
+            Err(EinsumError::UnknownOutputLabel("k".to_string()))
+        );
+        assert!(matches!(
+            EinsumEquation::parse("ijk", &[2]),
+            Err(EinsumError::RankMismatch { .. })
+        ));
+        assert!(matches!(
+            EinsumEquation::parse("ij,ij->ij", &[2, 2])
+                .unwrap()
+                .output_dims(&[&[2, 3], &[2, 4]]),
+            Err(EinsumError::SizeMismatch { .. })
+        ));
+    }
+}
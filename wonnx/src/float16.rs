@@ -0,0 +1,175 @@
+//! Support for float16 models on the GPU, which cannot perform calculations in half precision yet (shaders using `f16` are
+//! not supported by the version of naga used by wgpu). All float16 tensors in the model are widened to float32 when the
+//! model is loaded. Inputs are widened and outputs narrowed
+//! during inference, so that the conversion is transparent to the user.
+use std::{borrow::Cow, collections::HashMap};
+
+use half::f16;
+use protobuf::ProtobufEnum;
+
+use crate::{
+    onnx::{ModelProto, TensorProto, TensorProto_DataType, ValueInfoProto},
+    utils::{float16_data, InputTensor, OutputTensor, ScalarType, TensorInfo, TypedOutputTensor},
+};
+
+/// Convert all float16 values, initializers and constants in the model to float32. Returns whether the model contained
+/// any float16 values.
+pub(crate) fn widen_model(model: &mut ModelProto) -> bool {
+    let mut widened = false;
+    let graph = model.mut_graph();
+
+    for value_info in graph
+        .input
+        .iter_mut()
+        .chain(graph.output.iter_mut())
+        .chain(graph.value_info.iter_mut())
+    {
+        widened |= widen_value_info(value_info);
+    }
+
+    for initializer in graph.mut_initializer().iter_mut() {
+        widened |= widen_tensor(initializer);
+    }
+
+    for node in graph.mut_node().iter_mut() {
+        let is_cast = node.get_op_type() == "Cast";
+        for attribute in node.mut_attribute().iter_mut() {
+            if attribute.has_t() {
+                widened |= widen_tensor(attribute.mut_t());
+            } else if is_cast
+                && attribute.get_name() == "to"
+                && attribute.get_i() == TensorProto_DataType::FLOAT16.value() as i64
+            {
+                attribute.set_i(TensorProto_DataType::FLOAT.value() as i64);
+                widened = true;
+            }
+        }
+    }
+
+    widened
+}
+
+fn widen_value_info(value_info: &mut ValueInfoProto) -> bool {
+    if !value_info.get_field_type().has_tensor_type() {
+        return false;
+    }
+    let tensor_type = value_info.mut_field_type().mut_tensor_type();
+    if tensor_type.get_elem_type() != TensorProto_DataType::FLOAT16.value() {
+        return false;
+    }
+    tensor_type.set_elem_type(TensorProto_DataType::FLOAT.value());
+    true
+}
+
+fn widen_tensor(tensor: &mut TensorProto) -> bool {
+    if tensor.get_data_type() != TensorProto_DataType::FLOAT16.value() {
+        return false;
+    }
+    let floats: Vec<f32> = float16_data(tensor).into_iter().map(f32::from).collect();
+    tensor.clear_int32_data();
+    tensor.clear_raw_data();
+    tensor.set_float_data(floats);
+    tensor.set_data_type(TensorProto_DataType::FLOAT.value());
+    true
+}
+
+/// Returns a copy of the inputs in which float16 tensors are widened to float32
+pub(crate) fn widen_inputs<'a>(
+    inputs: &HashMap<String, InputTensor<'a>>,
+) -> HashMap<String, InputTensor<'a>> {
+    inputs
+        .iter()
+        .map(|(name, tensor)| (name.clone(), widen_input(tensor)))
+        .collect()
+}
+
+/// Widens a float16 tensor to float32. Tensors of other types are returned unchanged.
+pub(crate) fn widen_input<'a>(tensor: &InputTensor<'a>) -> InputTensor<'a> {
+    match tensor {
+        InputTensor::F16(halves) => {
+            InputTensor::F32(Cow::Owned(halves.iter().map(|x| f32::from(*x)).collect()))
+        }
+        other => other.clone(),
+    }
+}
+
+/// Narrows outputs that are float16 in the original model (but were calculated in float32) back to float16
+pub(crate) fn narrow_outputs(
+    outputs: &mut HashMap<String, TypedOutputTensor>,
+    output_infos: &[TensorInfo],
+) {
+    for info in output_infos {
+        if info.data_type != ScalarType::F16 {
+            continue;
+        }
+        if let Some(output) = outputs.get_mut(&info.name) {
+            if let OutputTensor::F32(floats) = &output.data {
+                output.data = OutputTensor::F16(floats.iter().map(|x| f16::from_f32(*x)).collect());
+                output.shape.data_type = ScalarType::F16;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use half::f16;
+    use protobuf::ProtobufEnum;
+
+    use super::widen_model;
+    use crate::{
+        onnx::TensorProto_DataType,
+        utils::{attribute, graph, model, node, tensor_of_type, ScalarType},
+    };
+
+    #[test]
+    fn test_widen_model() {
+        let mut initializer = crate::onnx::TensorProto::new();
+        initializer.set_name("W".to_string());
+        initializer.set_data_type(TensorProto_DataType::FLOAT16.value());
+        initializer.set_dims(vec![2]);
+        initializer.set_int32_data(vec![
+            f16::from_f32(1.5).to_bits() as i32,
+            f16::from_f32(-2.0).to_bits() as i32,
+        ]);
+
+        let mut m = model(graph(
+            vec![tensor_of_type("X", &[2], TensorProto_DataType::FLOAT16)],
+            vec![tensor_of_type("Y", &[2], TensorProto_DataType::FLOAT16)],
+            vec![],
+            vec![initializer],
+            vec![
+                node(vec!["X", "W"], vec!["Z"], "add", "Add", vec![]),
+                node(
+                    vec!["Z"],
+                    vec!["Y"],
+                    "cast",
+                    "Cast",
+                    vec![attribute(
+                        "to",
+                        TensorProto_DataType::FLOAT16.value() as i64,
+                    )],
+                ),
+            ],
+        ));
+
+        assert!(widen_model(&mut m));
+        let graph = m.get_graph();
+        assert_eq!(
+            graph.get_input()[0].get_shape().unwrap().data_type,
+            ScalarType::F32
+        );
+        assert_eq!(
+            graph.get_output()[0].get_shape().unwrap().data_type,
+            ScalarType::F32
+        );
+        assert_eq!(graph.get_initializer()[0].get_float_data(), &[1.5, -2.0]);
+        assert_eq!(
+            graph.get_node()[1].get_attribute()[0].get_i(),
+            TensorProto_DataType::FLOAT.value() as i64
+        );
+
+        // A model without float16 values is left alone
+        assert!(!widen_model(&mut m));
+    }
+}
@@ -7,33 +7,41 @@ use std::{
     ops::Sub,
     rc::Rc,
     sync::Arc,
+    time::Duration,
 };
 
 use bytemuck::NoUninit;
+use half::f16;
 use num::FromPrimitive;
 use thiserror::Error;
 use wgpu::{Buffer, BufferAsyncError, BufferUsages, CommandEncoder, Device};
 
 use crate::{
-    compiler::{compile, CompileError, CompiledNode},
+    compiler::{compile, recurrence, CompileError, CompiledNode, MAX_BINDINGS_PER_GROUP},
+    cpu::{execute_on_host, CpuError},
     ir::{Node, NodeDefinition, NodeIdentifier, OperatorDefinition},
-    onnx::TensorProto,
+    onnx::{NodeProto, TensorProto},
+    padding::{padded_values, produced_rows, without_padding, PaddingError},
+    profile::{NodeProfile, ProfileReport, TimingMethod},
+    registry::OperatorRegistry,
     resource::{self, resize},
     utils::{
-        ceil, DataTypeError, InputTensor, OutputTensor, ScalarType, Shape,
-        MINIMUM_BUFFER_SIZE_BYTES,
+        bool_data, ceil, float16_data, int8_data, uint8_data, DataTypeError, InputTensor,
+        OutputTensor, ScalarType, Shape, TypedOutputTensor, MINIMUM_BUFFER_SIZE_BYTES,
     },
 };
 
-/// The maximum number of bindings in a binding group (defined by wgpu)
-const MAX_BINDINGS_PER_GROUP: usize = 4;
-
 pub struct GpuModel {
-    device: wgpu::Device,
-    queue: wgpu::Queue,
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
     onnx_opset_version: i64,
+    operator_registry: OperatorRegistry,
+
+    /// Whether int64 tensors are stored and calculated with (emulated) 64-bit integers instead of being truncated to int32
+    strict_int64: bool,
     steps: Vec<GpuStep>,
     inference_outputs: HashMap<String, InferenceOutput>,
+    input_tensors: HashMap<String, GpuTensor>,
 }
 
 /// An operation that is performed on the GPU as part of inference
@@ -46,12 +54,26 @@ enum GpuStep {
 
     /// A GPU program (shader) that reads from buffers created by other steps and writes to output buffers
     Operator {
+        node_name: String,
+        op_type: String,
         pipeline: wgpu::ComputePipeline,
         bind_groups: Vec<wgpu::BindGroup>,
+        /// For ops that are dispatched repeatedly (e.g. once for each step of a recurrent op), the bind group that provides
+        /// the parameters of each dispatch. When empty, the shader is dispatched once.
+        step_bind_groups: Vec<wgpu::BindGroup>,
         threads: (u32, u32, u32),
         output_tensors: Vec<GpuTensor>,
     },
 
+    /// An op that is performed on the host, because the number of results depends on the values of the inputs (see
+    /// `op_has_data_dependent_output`). The inputs are read back from the GPU and the results are written to the output
+    /// buffers, after which the steps that follow can be executed.
+    Host {
+        proto: Box<NodeProto>,
+        input_tensors: Vec<GpuTensor>,
+        output_tensors: Vec<GpuTensor>,
+    },
+
     /// Operation that takes the output from a previous operation and assigns it to a second logical output
     Forward(GpuTensor),
 
@@ -61,9 +83,9 @@ enum GpuStep {
 
 /// A tensor that resides in GPU memory
 #[derive(Clone)]
-struct GpuTensor {
-    buffer: Arc<Buffer>,
-    shape: Shape,
+pub(crate) struct GpuTensor {
+    pub(crate) buffer: Arc<Buffer>,
+    pub(crate) shape: Shape,
 }
 
 #[derive(Error, Debug)]
@@ -85,11 +107,34 @@ pub enum GpuError {
 
     #[error("async buffer error: {0}")]
     BufferAsyncError(#[from] BufferAsyncError),
+
+    #[error("performing step on the host failed: {0}")]
+    HostStepError(#[from] CpuError),
+
+    #[error("padding error: {0}")]
+    PaddingError(#[from] PaddingError),
+
+    #[error(
+        "the device does not support timestamp queries, and steps cannot be timed on this platform"
+    )]
+    TimingUnavailable,
 }
 
 enum InferenceOutput {
-    InferenceInput(String),
+    InferenceInput(String, Shape),
     Tensor(GpuTensor),
+    /// A tensor that contains rows of padding (see `padding`), which are removed when it is read
+    PaddedTensor(GpuTensor, PaddingSource),
+}
+
+/// Determines which part of a tensor consists of padding (see `padding`)
+#[derive(Clone)]
+pub(crate) struct PaddingSource {
+    /// The output of the op with a data-dependent number of results, from which the number of produced rows follows
+    pub(crate) rows: GpuTensor,
+
+    /// The axis of the padded tensor that corresponds to the rows of `rows`
+    pub(crate) axis: usize,
 }
 
 /// The buffer manager tracks the use of buffers and manages recycling (sharing) of buffers for intermediate values that
@@ -299,16 +344,22 @@ impl GpuModel {
     /// Create a version of the specified model for which inference can be performed using the powers of the GPU
     pub fn from(
         root: Arc<Node>,
-        device: wgpu::Device,
-        queue: wgpu::Queue,
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
         onnx_opset_version: i64,
+        capture_intermediates: bool,
+        operator_registry: &OperatorRegistry,
+        strict_int64: bool,
     ) -> Result<GpuModel, GpuError> {
         let mut gpu_model = GpuModel {
             device,
             queue,
             onnx_opset_version,
+            operator_registry: operator_registry.clone(),
+            strict_int64,
             steps: vec![],
             inference_outputs: HashMap::new(),
+            input_tensors: HashMap::new(),
         };
 
         // Walk the IR DAG and encode into GPU execution steps
@@ -319,9 +370,16 @@ impl GpuModel {
 
         let mut nodes = vec![];
         let mut nodes_seen = HashSet::new();
-        GpuModel::topological_sort(root.clone(), &mut nodes_seen, &mut nodes);
+        Node::topological_sort(root.clone(), &mut nodes_seen, &mut nodes);
         drop(nodes_seen);
-        GpuModel::pre_sequence(&nodes, &mut readable_nodes, &mut buffer_manager)?;
+        #[allow(clippy::mutable_key_type)]
+        let padded = padded_values(&nodes, onnx_opset_version)?;
+        GpuModel::pre_sequence(
+            &nodes,
+            &mut readable_nodes,
+            &mut buffer_manager,
+            capture_intermediates,
+        )?;
 
         #[cfg(debug_assertions)]
         {
@@ -354,11 +412,21 @@ impl GpuModel {
                             let source_identifier = input.source_node.identifier();
                             let outputs = &node_outputs[&source_identifier];
                             let tensor = outputs[input.output_index].clone();
-                            InferenceOutput::Tensor(tensor)
-                        }
-                        NodeDefinition::Input(proto) => {
-                            InferenceOutput::InferenceInput(proto.get_name().to_string())
+                            match padded.get(&(source_identifier, input.output_index)) {
+                                Some(rows) => InferenceOutput::PaddedTensor(
+                                    tensor,
+                                    PaddingSource {
+                                        rows: node_outputs[&rows.source.0][rows.source.1].clone(),
+                                        axis: rows.axis,
+                                    },
+                                ),
+                                None => InferenceOutput::Tensor(tensor),
+                            }
                         }
+                        NodeDefinition::Input(proto) => InferenceOutput::InferenceInput(
+                            proto.get_name().to_string(),
+                            proto.get_shape()?,
+                        ),
                         NodeDefinition::Outputs { .. } => {
                             unimplemented!("output after output node")
                         }
@@ -372,6 +440,37 @@ impl GpuModel {
             unimplemented!("reading from non-outputs IR node")
         }
 
+        // When capturing intermediate values, also return the outputs of all other nodes (by ONNX value name)
+        if capture_intermediates {
+            for node in &nodes {
+                if let NodeDefinition::Operator(op_def) = &node.definition {
+                    let outputs = &node_outputs[&node.identifier()];
+                    for (output_index, (output_name, tensor)) in
+                        op_def.proto.get_output().iter().zip(outputs).enumerate()
+                    {
+                        if !output_name.is_empty() {
+                            gpu_model
+                                .inference_outputs
+                                .entry(output_name.to_string())
+                                .or_insert_with(|| {
+                                    match padded.get(&(node.identifier(), output_index)) {
+                                        Some(rows) => InferenceOutput::PaddedTensor(
+                                            tensor.clone(),
+                                            PaddingSource {
+                                                rows: node_outputs[&rows.source.0][rows.source.1]
+                                                    .clone(),
+                                                axis: rows.axis,
+                                            },
+                                        ),
+                                        None => InferenceOutput::Tensor(tensor.clone()),
+                                    }
+                                });
+                        }
+                    }
+                }
+            }
+        }
+
         // Upload the data (for initializers etc.) by submitting an empty command queue
         log::debug!("submit initializer buffers");
         let encoder = gpu_model
@@ -382,42 +481,35 @@ impl GpuModel {
         Ok(gpu_model)
     }
 
-    /// Traverse the graph and sort nodes in the order of execution (topological sort)
-    fn topological_sort<'model>(
-        node: Arc<Node<'model>>,
-        nodes_seen: &mut HashSet<NodeIdentifier<'model>>,
-        sorted_nodes: &mut Vec<Arc<Node<'model>>>,
-    ) {
-        let identifier = node.identifier();
-        if !nodes_seen.contains(&identifier) {
-            nodes_seen.insert(identifier);
-            for node_input in &node.inputs {
-                GpuModel::topological_sort(
-                    node_input.source_node.clone(),
-                    nodes_seen,
-                    sorted_nodes,
-                );
-            }
-            sorted_nodes.push(node);
-        }
-    }
-
     /// Run a first pass over the IR graph to determine the outputs of which nodes are supposed to be readable as outputs
     /// of the graph after inference. This needs to be done in a separate pass because otherwise we may run into an issue
     /// where nodes are not marked as 'outputs readable' when their outputs are used by some node while also being used as
     /// output (and the sequenceer might simply follow one 'path' before the other). This pass is also used to assign buffers
     /// for intermediate values (which may be re-used). For this reason, a 'breadth first'-search is performed (whereas
-    /// `GpuModel::sequence` will perform a depth-first search).
+    /// `GpuModel::sequence` will perform a depth-first search). When `capture_intermediates` is set, the outputs of all
+    /// operator nodes are marked readable (readable outputs do not use shared buffers).
     fn pre_sequence<'model>(
         nodes: &[Arc<Node<'model>>],
         nodes_readable: &mut HashSet<NodeIdentifier<'model>>,
         buffer_manager: &mut BufferManager<'model>,
+        capture_intermediates: bool,
     ) -> Result<(), GpuError> {
         for node in nodes.iter().rev() {
             let node_identifier = node.identifier();
+            if capture_intermediates && matches!(node.definition, NodeDefinition::Operator(_)) {
+                nodes_readable.insert(node_identifier.clone());
+            }
+
+            // The outputs of ops with a data-dependent number of results are read again after inference, to determine
+            // how many rows of the values that follow from them were produced (see `padding`)
+            if let NodeDefinition::Operator(op_def) = &node.definition {
+                if op_has_data_dependent_output(op_def.proto.get_op_type()) {
+                    nodes_readable.insert(node_identifier.clone());
+                }
+            }
             let mut outputs_readable = nodes_readable.contains(&node_identifier);
 
-            for node_input in &node.inputs {
+            for (input_index, node_input) in node.inputs.iter().enumerate() {
                 // Tell the buffer manager that we are consuming an intermediate result produced by some earlier op
                 if let NodeDefinition::Operator(ref source_node_def) =
                     node_input.source_node.definition
@@ -427,9 +519,12 @@ impl GpuModel {
                     while let NodeDefinition::Operator(ultimate_input_op_def) =
                         ultimate_input.source_node.definition()
                     {
-                        if op_forwards_input(ultimate_input_op_def.proto.get_op_type()) {
+                        let op_type = ultimate_input_op_def.proto.get_op_type();
+                        if op_forwards_input(op_type) {
                             assert_eq!(ultimate_input.source_node.inputs.len(), 1);
                             ultimate_input = ultimate_input.source_node.inputs[0].clone();
+                        } else if op_updates_input_in_place(op_type) {
+                            ultimate_input = ultimate_input.source_node.inputs[0].clone();
                         } else {
                             break;
                         }
@@ -452,17 +547,29 @@ impl GpuModel {
 
                 if outputs_readable {
                     if let NodeDefinition::Operator(op_def) = &node.definition {
-                        // For these ops we just forward the buffer (so we should also forward readability)
-                        if op_forwards_input(op_def.proto.get_op_type()) {
+                        // For these ops we just forward the buffer (so we should also forward readability). The same
+                        // goes for the first input of ops that write their output to the buffer of that input.
+                        let op_type = op_def.proto.get_op_type();
+                        if op_forwards_input(op_type)
+                            || (op_updates_input_in_place(op_type) && input_index == 0)
+                        {
                             nodes_readable.insert(source_node_identifier.clone());
                         }
                     }
                 }
+
+                // Ops that are performed on the host read back all their inputs
+                if let NodeDefinition::Operator(op_def) = &node.definition {
+                    if op_has_data_dependent_output(op_def.proto.get_op_type()) {
+                        nodes_readable.insert(source_node_identifier.clone());
+                    }
+                }
             }
 
             // Tell the buffer manager we are producing an intermediate value; nodes that run 'before' us may reuse this buffer
             if let NodeDefinition::Operator(op_def) = &node.definition {
-                if !op_forwards_input(op_def.proto.get_op_type()) {
+                let op_type = op_def.proto.get_op_type();
+                if !op_forwards_input(op_type) && !op_updates_input_in_place(op_type) {
                     for (output_index, output_shape) in op_def.output_shapes.iter().enumerate() {
                         buffer_manager.release(
                             node_identifier.clone(),
@@ -493,6 +600,11 @@ impl GpuModel {
         // Sequence inputs of this node first (recursively)
         let mut input_tensors: Vec<GpuTensor> = vec![];
         for node_input in &node.inputs {
+            // Optional inputs that are not provided are not passed to the op
+            if let NodeDefinition::Missing = node_input.source_node.definition {
+                continue;
+            }
+
             let identifier = node_input.source_node.identifier();
 
             // If we haven't seen this input node yet, sequence it now
@@ -548,18 +660,17 @@ impl GpuModel {
                             })
                             .collect();
 
-                    let gpu_op = op_def.gpu_op(
-                        &self.device,
-                        outputs_readable,
-                        self.onnx_opset_version,
-                        &input_tensors,
-                        &shared_buffers,
-                    )?;
+                    let gpu_op =
+                        op_def.gpu_op(self, outputs_readable, &input_tensors, &shared_buffers)?;
 
                     match &gpu_op {
                         GpuStep::Operator {
                             output_tensors: op_output_tensors,
                             ..
+                        }
+                        | GpuStep::Host {
+                            output_tensors: op_output_tensors,
+                            ..
                         } => {
                             output_tensors.extend(op_output_tensors.iter().cloned());
                         }
@@ -573,8 +684,11 @@ impl GpuModel {
                 }
                 // For tensor (initializer) nodes, we just create a buffer and fill it with the initializer data
                 NodeDefinition::Tensor(tensor_def) => {
-                    let tensor_buffer =
-                        Arc::new(tensor_def.buffer(&self.device, outputs_readable)?);
+                    let tensor_buffer = Arc::new(tensor_def.buffer(
+                        &self.device,
+                        outputs_readable,
+                        self.strict_int64,
+                    )?);
                     output_tensors.push(GpuTensor {
                         shape: Shape::from(
                             ScalarType::from_i32(tensor_def.get_data_type())?,
@@ -605,15 +719,19 @@ impl GpuModel {
                         &self.device,
                         input_shape.buffer_bytes_aligned(),
                         input_def.get_name(),
-                        // Usage is not COPY_SRC/MAP_READ even when outputs_readable is true; we'll deal with the special
-                        // case of reading back inputs as outputs separately.
-                        BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                        // Usage is not MAP_READ even when outputs_readable is true; we'll deal with the special case of
+                        // reading back inputs as outputs separately. COPY_SRC allows the buffer to be copied to a bound
+                        // output buffer (see `IoBinding`).
+                        BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
                     ));
 
-                    output_tensors.push(GpuTensor {
+                    let input_tensor = GpuTensor {
                         shape: input_shape,
                         buffer: input_buffer.clone(),
-                    });
+                    };
+                    self.input_tensors
+                        .insert(input_def.get_name().to_string(), input_tensor.clone());
+                    output_tensors.push(input_tensor);
 
                     GpuStep::Input(input_def.get_name().to_string(), input_buffer)
                 }
@@ -637,36 +755,348 @@ impl GpuModel {
     pub async fn infer<'a>(
         &self,
         inference_inputs: &HashMap<String, InputTensor<'a>>,
-    ) -> Result<HashMap<String, OutputTensor>, GpuError> {
+    ) -> Result<HashMap<String, TypedOutputTensor>, GpuError> {
         log::info!("encode inference steps");
-        let mut encoder = self
+        let encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         for step in &self.steps {
-            step.encode(&self.queue, &mut encoder, inference_inputs)?;
+            step.write_input(&self.queue, inference_inputs, self.strict_int64)?;
         }
+        let encoder = self.encode_steps(encoder).await?;
         log::debug!("submit inference steps");
         self.queue.submit(Some(encoder.finish()));
         log::info!("inference completed");
         self.read_outputs(inference_inputs).await
     }
 
+    /// Perform inference using the specified inference inputs while measuring the time taken by each operator step. When
+    /// the device supports timestamp queries, these are used to time each compute pass on the GPU. Otherwise each step is
+    /// submitted separately and timed on the host (this is not possible when running on WebAssembly).
+    pub async fn profile<'a>(
+        &self,
+        inference_inputs: &HashMap<String, InputTensor<'a>>,
+    ) -> Result<ProfileReport, GpuError> {
+        for step in &self.steps {
+            step.write_input(&self.queue, inference_inputs, self.strict_int64)?;
+        }
+
+        let method = if self
+            .device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+        {
+            TimingMethod::TimestampQueries
+        } else {
+            TimingMethod::StepSubmission
+        };
+
+        // Steps that are performed on the host need the results of the operators before them
+        let mut nodes = vec![];
+        let mut operators: Vec<&GpuStep> = vec![];
+        for step in &self.steps {
+            match step {
+                GpuStep::Operator { .. } => operators.push(step),
+                GpuStep::Host {
+                    proto,
+                    input_tensors,
+                    output_tensors,
+                } => {
+                    nodes.extend(self.profile_operators(&operators, method).await?);
+                    operators.clear();
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let start = std::time::Instant::now();
+                    self.perform_on_host(proto, input_tensors, output_tensors)
+                        .await?;
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let duration = start.elapsed();
+                    #[cfg(target_arch = "wasm32")]
+                    let duration = Duration::ZERO;
+
+                    nodes.push(NodeProfile {
+                        node_name: proto.get_name().to_string(),
+                        op_type: proto.get_op_type().to_string(),
+                        output_shapes: output_tensors.iter().map(|t| t.shape.clone()).collect(),
+                        dispatch: (0, 0, 0),
+                        duration,
+                        on_host: true,
+                    });
+                }
+                _ => {}
+            }
+        }
+        nodes.extend(self.profile_operators(&operators, method).await?);
+
+        Ok(ProfileReport { method, nodes })
+    }
+
+    /// Execute the specified operator steps, measuring the time taken by each of them using the specified method
+    async fn profile_operators(
+        &self,
+        operators: &[&GpuStep],
+        method: TimingMethod,
+    ) -> Result<Vec<NodeProfile>, GpuError> {
+        let durations = match method {
+            TimingMethod::TimestampQueries => {
+                let mut durations = Vec::with_capacity(operators.len());
+                // Each step requires two queries (start and end of the compute pass)
+                for chunk in operators.chunks((wgpu::QUERY_SET_MAX_QUERIES / 2) as usize) {
+                    durations.extend(self.profile_with_timestamps(chunk).await?);
+                }
+                durations
+            }
+            TimingMethod::StepSubmission => self.profile_with_submissions(operators)?,
+        };
+
+        Ok(operators
+            .iter()
+            .zip(durations)
+            .map(|(step, duration)| match step {
+                GpuStep::Operator {
+                    node_name,
+                    op_type,
+                    threads,
+                    output_tensors,
+                    ..
+                } => NodeProfile {
+                    node_name: node_name.clone(),
+                    op_type: op_type.clone(),
+                    output_shapes: output_tensors.iter().map(|t| t.shape.clone()).collect(),
+                    dispatch: *threads,
+                    duration,
+                    on_host: false,
+                },
+                _ => unreachable!("only operator steps are profiled"),
+            })
+            .collect())
+    }
+
+    /// Execute the specified steps in a single submission, recording timestamps at the start and end of each step
+    async fn profile_with_timestamps(&self, steps: &[&GpuStep]) -> Result<Vec<Duration>, GpuError> {
+        let query_count = (steps.len() * 2) as u32;
+        if query_count == 0 {
+            return Ok(vec![]);
+        }
+        let query_set = self.device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("profile"),
+            ty: wgpu::QueryType::Timestamp,
+            count: query_count,
+        });
+        let size = (query_count as usize) * std::mem::size_of::<u64>();
+        let resolve_buffer = resource::buffer(
+            &self.device,
+            size,
+            "profile_resolve",
+            BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+        );
+        let read_buffer = resource::buffer(
+            &self.device,
+            size,
+            "profile_read",
+            BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        for (index, step) in steps.iter().enumerate() {
+            step.encode(
+                &mut encoder,
+                Some(wgpu::ComputePassTimestampWrites {
+                    query_set: &query_set,
+                    beginning_of_pass_write_index: Some((index * 2) as u32),
+                    end_of_pass_write_index: Some((index * 2 + 1) as u32),
+                }),
+            );
+        }
+        encoder.resolve_query_set(&query_set, 0..query_count, &resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&resolve_buffer, 0, &read_buffer, 0, size as u64);
+        self.queue.submit(Some(encoder.finish()));
+
+        map_read(&self.device, &read_buffer).await?;
+        let period = self.queue.get_timestamp_period() as f64;
+        let durations = {
+            let bytes = read_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = &bytemuck::cast_slice(&bytes)[..query_count as usize];
+            timestamps
+                .chunks(2)
+                .map(|ts| {
+                    Duration::from_nanos((ts[1].saturating_sub(ts[0]) as f64 * period) as u64)
+                })
+                .collect()
+        };
+        read_buffer.unmap();
+        Ok(durations)
+    }
+
+    /// Execute the specified steps one by one, measuring the time until each submission has completed on the host
+    fn profile_with_submissions(&self, steps: &[&GpuStep]) -> Result<Vec<Duration>, GpuError> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = steps;
+            Err(GpuError::TimingUnavailable)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Make sure input data has been written before timing the first step
+            self.queue.submit(None);
+            self.device.poll(wgpu::Maintain::Wait);
+
+            Ok(steps
+                .iter()
+                .map(|step| {
+                    let start = std::time::Instant::now();
+                    let mut encoder = self
+                        .device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                    step.encode(&mut encoder, None);
+                    self.queue.submit(Some(encoder.finish()));
+                    self.device.poll(wgpu::Maintain::Wait);
+                    start.elapsed()
+                })
+                .collect())
+        }
+    }
+
+    /// Encode the shader invocations for all steps of the model into the specified command encoder. Input data must have
+    /// been written to the input buffers before the commands are submitted. Steps that are performed on the host need the
+    /// results of the steps before them, so the commands encoded up to such a step are submitted before it is performed.
+    /// Returns the encoder holding the commands for the steps after the last host step, which have not been submitted.
+    pub(crate) async fn encode_steps(
+        &self,
+        mut encoder: CommandEncoder,
+    ) -> Result<CommandEncoder, GpuError> {
+        for step in &self.steps {
+            if let GpuStep::Host {
+                proto,
+                input_tensors,
+                output_tensors,
+            } = step
+            {
+                self.queue.submit(Some(encoder.finish()));
+                self.perform_on_host(proto, input_tensors, output_tensors)
+                    .await?;
+                encoder = self
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            } else {
+                step.encode(&mut encoder, None);
+            }
+        }
+        Ok(encoder)
+    }
+
+    /// Reads back the inputs of an op, performs the op on the host and writes the results to its output buffers
+    async fn perform_on_host(
+        &self,
+        proto: &NodeProto,
+        input_tensors: &[GpuTensor],
+        output_tensors: &[GpuTensor],
+    ) -> Result<(), GpuError> {
+        log::debug!(
+            "perform {} ({}) on host",
+            proto.get_name(),
+            proto.get_op_type()
+        );
+        let mut inputs = Vec::with_capacity(input_tensors.len());
+        for tensor in input_tensors {
+            inputs.push(TypedOutputTensor {
+                data: tensor
+                    .read_to_vec(&self.device, &self.queue, self.strict_int64)
+                    .await?,
+                shape: tensor.shape.clone(),
+            });
+        }
+
+        let output_shapes: Vec<Shape> = output_tensors.iter().map(|t| t.shape.clone()).collect();
+        let outputs = execute_on_host(proto, inputs, &output_shapes, self.onnx_opset_version)?;
+        for (tensor, data) in output_tensors.iter().zip(outputs.iter()) {
+            write_input_tensor(
+                &self.queue,
+                &tensor.buffer,
+                proto.get_name(),
+                &InputTensor::from(data),
+                self.strict_int64,
+            )?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn device(&self) -> &Arc<wgpu::Device> {
+        &self.device
+    }
+
+    pub(crate) fn queue(&self) -> &Arc<wgpu::Queue> {
+        &self.queue
+    }
+
+    pub(crate) fn strict_int64(&self) -> bool {
+        self.strict_int64
+    }
+
+    /// The buffers that receive the inference inputs of this model, by input name
+    pub(crate) fn input_tensors(&self) -> &HashMap<String, GpuTensor> {
+        &self.input_tensors
+    }
+
+    /// The tensors that hold the inference outputs of this model after inference, by output name, together with the
+    /// padding they contain (see `padding`)
+    pub(crate) fn output_tensors(&self) -> HashMap<String, (GpuTensor, Option<PaddingSource>)> {
+        self.inference_outputs
+            .iter()
+            .map(|(output_name, output_source)| {
+                let tensor = match output_source {
+                    InferenceOutput::InferenceInput(input_name, _) => {
+                        (self.input_tensors[input_name].clone(), None)
+                    }
+                    InferenceOutput::Tensor(tensor) => (tensor.clone(), None),
+                    InferenceOutput::PaddedTensor(tensor, padding) => {
+                        (tensor.clone(), Some(padding.clone()))
+                    }
+                };
+                (output_name.clone(), tensor)
+            })
+            .collect()
+    }
+
     /// Reads the relevant buffers for the requested inference outputs
     async fn read_outputs<'a>(
         &self,
         inference_inputs: &HashMap<String, InputTensor<'a>>,
-    ) -> Result<HashMap<String, OutputTensor>, GpuError> {
-        let mut output_data: HashMap<String, OutputTensor> = HashMap::new();
+    ) -> Result<HashMap<String, TypedOutputTensor>, GpuError> {
+        let mut output_data: HashMap<String, TypedOutputTensor> = HashMap::new();
 
         for (output_name, output_source) in &self.inference_outputs {
             output_data.insert(
                 output_name.to_string(),
                 match output_source {
-                    InferenceOutput::InferenceInput(input_name) => {
-                        (&inference_inputs[input_name]).into()
-                    }
-                    InferenceOutput::Tensor(tensor) => {
-                        tensor.read_to_vec(&self.device, &self.queue).await?
+                    InferenceOutput::InferenceInput(input_name, shape) => TypedOutputTensor {
+                        shape: shape.clone(),
+                        data: (&inference_inputs[input_name]).into(),
+                    },
+                    InferenceOutput::Tensor(tensor) => TypedOutputTensor {
+                        shape: tensor.shape.clone(),
+                        data: tensor
+                            .read_to_vec(&self.device, &self.queue, self.strict_int64)
+                            .await?,
+                    },
+                    InferenceOutput::PaddedTensor(tensor, padding) => {
+                        let rows = padding
+                            .rows
+                            .read_to_vec(&self.device, &self.queue, self.strict_int64)
+                            .await?;
+                        without_padding(
+                            TypedOutputTensor {
+                                shape: tensor.shape.clone(),
+                                data: tensor
+                                    .read_to_vec(&self.device, &self.queue, self.strict_int64)
+                                    .await?,
+                            },
+                            padding.axis,
+                            produced_rows(&rows, &padding.rows.shape),
+                        )
                     }
                 },
             );
@@ -677,12 +1107,22 @@ impl GpuModel {
 }
 
 trait TensorProtoExtra {
-    fn buffer(&self, device: &wgpu::Device, readable: bool) -> Result<Buffer, GpuError>;
+    fn buffer(
+        &self,
+        device: &wgpu::Device,
+        readable: bool,
+        strict_int64: bool,
+    ) -> Result<Buffer, GpuError>;
 }
 
 impl TensorProtoExtra for TensorProto {
     /// Create a GPU buffer containing the data of this initializer
-    fn buffer(&self, device: &wgpu::Device, readable: bool) -> Result<Buffer, GpuError> {
+    fn buffer(
+        &self,
+        device: &wgpu::Device,
+        readable: bool,
+        strict_int64: bool,
+    ) -> Result<Buffer, GpuError> {
         let scalar_type = ScalarType::from_i32(self.get_data_type())?;
         let input_shape = Shape::from(scalar_type, self.get_dims());
         log::debug!(
@@ -705,16 +1145,36 @@ impl TensorProtoExtra for TensorProto {
                     },
                 )
             }
+            ScalarType::F16 => {
+                let data = float16_data(self);
+                buffer_with_bytes(
+                    device,
+                    readable,
+                    self.get_name(),
+                    bytemuck::cast_slice(&data),
+                )
+            }
             ScalarType::U8 => {
-                // WGSL doesn't support 8 bit unsigned integers, so we load them as 32 bit ints
-                log::warn!("initializers with uint8 data type are not supported, converting into int32 initializer");
-                let ints: Vec<i32> = self
-                    .get_raw_data()
-                    .iter()
-                    .map(|x| (*x as i32))
-                    .collect::<Vec<i32>>();
-                let raw_data = bytemuck::cast_slice(&ints);
-                buffer_with_bytes(device, readable, self.get_name(), raw_data)
+                // WGSL doesn't support 8 bit integers, so these are packed four to a u32 (in little-endian order)
+                let data = packed_bytes(&uint8_data(self));
+                buffer_with_bytes(device, readable, self.get_name(), &data)
+            }
+            ScalarType::I8 => {
+                let data = packed_bytes(bytemuck::cast_slice(&int8_data(self)));
+                buffer_with_bytes(device, readable, self.get_name(), &data)
+            }
+            ScalarType::I64 if strict_int64 => {
+                let data = self.get_int64_data();
+                buffer_with_bytes(
+                    device,
+                    readable,
+                    self.get_name(),
+                    if !data.is_empty() {
+                        bytemuck::cast_slice(data)
+                    } else {
+                        self.get_raw_data()
+                    },
+                )
             }
             ScalarType::I64 => {
                 // WGSL doesn't support 64 bit integers, so we load 64 bit tensors as 32 bit ints
@@ -728,6 +1188,16 @@ impl TensorProtoExtra for TensorProto {
                 let raw_data = bytemuck::cast_slice(&ints);
                 buffer_with_bytes(device, readable, self.get_name(), raw_data)
             }
+            ScalarType::Bool => {
+                // WGSL doesn't support booleans in buffers, so we load them as 32 bit unsigned ints
+                let ints: Vec<u32> = bool_data(self).into_iter().map(u32::from).collect();
+                buffer_with_bytes(
+                    device,
+                    readable,
+                    self.get_name(),
+                    bytemuck::cast_slice(&ints),
+                )
+            }
             ScalarType::I32 => {
                 let data = self.get_int32_data();
                 buffer_with_bytes(
@@ -766,25 +1236,47 @@ fn buffer_with_bytes(
     })
 }
 
+/// Returns the bytes of an 8-bit tensor padded to a multiple of four, so they can be accessed as an array of u32 on the GPU
+fn packed_bytes(data: &[u8]) -> Vec<u8> {
+    let mut packed = data.to_vec();
+    packed.resize(ceil(data.len() as u64, 4) as usize * 4, 0);
+    packed
+}
+
 /// Returns whether the op of the specified type will forward inputs unchanged. If this is the case, the inputs of such
 /// an op should be marked as 'outputs readable' if the output of the op itself is to be readable.
-fn op_forwards_input(op_type: &str) -> bool {
+pub(crate) fn op_forwards_input(op_type: &str) -> bool {
     matches!(
         op_type,
         "Reshape" | "Identity" | "Flatten" | "Squeeze" | "Unsqueeze" | "Dropout"
     )
 }
 
+/// Returns whether the number of results of the op depends on the values of its inputs. The shapes of all tensors are
+/// fixed when the model is built, so the output of such an op has the maximum number of rows, and the rows that are not
+/// needed are filled with -1. Nodes that use the output compute results for these rows as well, which are removed when
+/// values are returned as inference output (see `padding`). These ops cannot be performed by a shader, and are instead
+/// performed on the host (see `GpuStep::Host`).
+pub(crate) fn op_has_data_dependent_output(op_type: &str) -> bool {
+    op_type == "NonMaxSuppression"
+}
+
+/// Returns whether the op writes its (only) output to the buffer of its first input. The optimizer only produces these
+/// ops when the first input is not used by any other node.
+pub(crate) fn op_updates_input_in_place(op_type: &str) -> bool {
+    op_type == "ScatterNDInPlace"
+}
+
 impl<'model> OperatorDefinition<'model> {
     fn gpu_op(
         &self,
-        device: &wgpu::Device,
+        model: &GpuModel,
         outputs_readable: bool,
-        opset_version: i64,
         input_tensors: &[GpuTensor],
         shared_buffers: &[Option<Rc<RefCell<LeaseableBuffer>>>],
     ) -> Result<GpuStep, GpuError> {
         let proto = &self.proto;
+        let device = model.device.as_ref();
 
         // Some nodes have specific GPU implementations, match these here
         if op_forwards_input(proto.get_op_type()) {
@@ -798,17 +1290,26 @@ impl<'model> OperatorDefinition<'model> {
         }
 
         let label = Some(proto.get_name());
+        let in_place = op_updates_input_in_place(proto.get_op_type());
+        let on_host = op_has_data_dependent_output(proto.get_op_type());
 
-        // Create output buffers for this op node
+        // Create output buffers for this op node (or use the buffer of the first input, when updating it in place)
         let output_tensors: Vec<GpuTensor> = proto
             .get_output()
             .iter()
             .enumerate()
             .map(|(output_index, output_name)| {
+                if in_place {
+                    return GpuTensor {
+                        buffer: input_tensors[0].buffer.clone(),
+                        shape: self.output_shapes[output_index].clone(),
+                    };
+                }
+
                 let value_shape = &self.output_shapes[output_index];
 
                 let buffer = match shared_buffers.get(output_index) {
-                    Some(Some(shared_buffer)) if !outputs_readable => {
+                    Some(Some(shared_buffer)) if !outputs_readable && !on_host => {
                         let mut shared_buffer = shared_buffer.borrow_mut();
                         shared_buffer.allocated_on(device)
                     }
@@ -821,12 +1322,17 @@ impl<'model> OperatorDefinition<'model> {
                             value_shape
                         );
 
-                        let buffer_usage = if outputs_readable {
+                        let mut buffer_usage = if outputs_readable {
                             BufferUsages::STORAGE | BufferUsages::COPY_SRC
                         } else {
                             BufferUsages::STORAGE
                         };
 
+                        // Results of ops that are performed on the host are written to the buffer
+                        if on_host {
+                            buffer_usage |= BufferUsages::COPY_DST;
+                        }
+
                         Arc::new(resource::buffer(
                             device,
                             value_shape.buffer_bytes_aligned(),
@@ -843,29 +1349,42 @@ impl<'model> OperatorDefinition<'model> {
             })
             .collect();
 
+        if on_host {
+            return Ok(GpuStep::Host {
+                proto: Box::new(proto.clone().into_owned()),
+                input_tensors: input_tensors.to_vec(),
+                output_tensors,
+            });
+        }
+
         let input_shapes: Vec<&Shape> = input_tensors.iter().map(|input| &input.shape).collect();
         let output_shapes: Vec<&Shape> = self.output_shapes.iter().collect();
 
         // Compile shader for node
-        let CompiledNode { shader, threads } =
-            compile(proto, &input_shapes, &output_shapes, opset_version).map_err(|ce| {
-                GpuError::CompileError {
-                    node: if proto.has_name() {
-                        proto.get_name().to_string()
-                    } else {
-                        proto.get_op_type().to_string()
-                    },
-                    error: ce,
-                }
-            })?;
+        let CompiledNode { shader, threads } = compile(
+            proto,
+            &input_shapes,
+            &output_shapes,
+            model.onnx_opset_version,
+            &model.operator_registry,
+            model.strict_int64,
+        )
+        .map_err(|ce| GpuError::CompileError {
+            node: if proto.has_name() {
+                proto.get_name().to_string()
+            } else {
+                proto.get_op_type().to_string()
+            },
+            error: ce,
+        })?;
         log::trace!("shader: {}", shader);
 
         // Bind input and output buffers to the shader
         let mut binding_counter: usize = 0;
         let mut entries = vec![];
 
-        // Bind input buffers
-        for input in input_tensors {
+        // Bind input buffers (the buffer of an input that is updated in place is only bound as output)
+        for input in input_tensors.iter().skip(in_place as usize) {
             // Bindings are numbered 0...3 (MAX_BINDINGS_PER_GROUP-1) in binding groups (starting at group 0)
             let binding_index = (binding_counter % MAX_BINDINGS_PER_GROUP) as u32;
 
@@ -888,6 +1407,40 @@ impl<'model> OperatorDefinition<'model> {
             binding_counter += 1;
         }
 
+        // Recurrent ops are dispatched once for each step (and phase) of the sequence, and keep their hidden state in a
+        // separate buffer that is bound after the outputs: two copies of the hidden state (for the previous and current
+        // step), followed by the cell state (LSTM) or reset hidden state (GRU).
+        let recurrence = match proto.get_op_type() {
+            "LSTM" | "GRU" | "RNN" => Some(
+                recurrence(proto, &input_shapes, model.onnx_opset_version).map_err(|ce| {
+                    GpuError::CompileError {
+                        node: proto.get_name().to_string(),
+                        error: ce,
+                    }
+                })?,
+            ),
+            _ => None,
+        };
+        let state_buffer = recurrence.as_ref().map(|recurrence| {
+            resource::buffer(
+                device,
+                Shape::from(
+                    input_shapes[0].data_type,
+                    &[3 * recurrence.state_length() as i64],
+                )
+                .buffer_bytes_aligned(),
+                &format!("{}_state", proto.get_name()),
+                BufferUsages::STORAGE,
+            )
+        });
+        if let Some(state_buffer) = &state_buffer {
+            entries.push(wgpu::BindGroupEntry {
+                binding: (binding_counter % MAX_BINDINGS_PER_GROUP) as u32,
+                resource: state_buffer.as_entire_binding(),
+            });
+            binding_counter += 1;
+        }
+
         // Set up a pipeline (basically the shader source code with some metadata that determines how it will be executed)
         let mut bind_groups = vec![];
         let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
@@ -915,106 +1468,217 @@ impl<'model> OperatorDefinition<'model> {
             }));
         }
 
+        // The index and phase of each step are passed in a uniform buffer (bound in the group following the buffers), at
+        // offsets that satisfy the alignment required for uniform bindings.
+        let mut step_bind_groups = vec![];
+        if let Some(recurrence) = recurrence {
+            let alignment = device.limits().min_uniform_buffer_offset_alignment as usize;
+            let steps: Vec<(u32, u32)> = (0..recurrence.sequence_length as u32)
+                .flat_map(|step| (0..recurrence.phases).map(move |phase| (step, phase)))
+                .collect();
+            let mut step_data = vec![0u32; steps.len() * alignment / 4];
+            for (index, (step, phase)) in steps.iter().enumerate() {
+                step_data[index * alignment / 4] = *step;
+                step_data[index * alignment / 4 + 1] = *phase;
+            }
+            let step_buffer = resource::create_buffer_init(
+                device,
+                &step_data,
+                &format!("{}_steps", proto.get_name()),
+                BufferUsages::UNIFORM,
+            );
+            let step_layout = pipeline.get_bind_group_layout(number_of_groups as u32);
+            for index in 0..steps.len() {
+                step_bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label,
+                    layout: &step_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &step_buffer,
+                            offset: (index * alignment) as u64,
+                            size: wgpu::BufferSize::new(8),
+                        }),
+                    }],
+                }));
+            }
+        }
+
         Ok(GpuStep::Operator {
+            node_name: proto.get_name().to_string(),
+            op_type: proto.get_op_type().to_string(),
             output_tensors,
             pipeline,
             bind_groups,
+            step_bind_groups,
             threads,
         })
     }
 }
 
 impl GpuStep {
-    /// Writes the necessary commands for the GPU to execute this step into the command queue. Among other things this means
-    /// writing the inference input data to the appropriate (already created) buffers.
-    fn encode(
+    /// Writes the inference input data to the appropriate (already created) buffer, if this step is an input step.
+    fn write_input(
         &self,
         queue: &wgpu::Queue,
-        encoder: &mut CommandEncoder,
         inputs: &HashMap<String, InputTensor>,
+        strict_int64: bool,
     ) -> Result<(), GpuError> {
+        if let GpuStep::Input(input_name, input_buffer) = self {
+            let input_data = inputs
+                .get(input_name)
+                .ok_or_else(|| GpuError::InferenceInputMissing(input_name.to_string()))?;
+            write_input_tensor(queue, input_buffer, input_name, input_data, strict_int64)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the necessary commands for the GPU to execute this step into the command queue. When timestamp writes are
+    /// specified, the GPU records timestamps at the beginning and end of the compute pass for this step (if any).
+    fn encode(
+        &self,
+        encoder: &mut CommandEncoder,
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
         match self {
-            GpuStep::None | GpuStep::Forward(_) | GpuStep::Initializer() => {
+            GpuStep::None | GpuStep::Forward(_) | GpuStep::Initializer() | GpuStep::Input(..) => {
                 // Buffer already filled, no need to encode anything at this point.
-                Ok(())
             }
-            GpuStep::Input(input_name, input_buffer) => {
-                // Encode a command to write the input data to the corresponding input buffer (which was created empty
-                // by `GpuModel::from`
-                let input_data = inputs
-                    .get(input_name)
-                    .ok_or_else(|| GpuError::InferenceInputMissing(input_name.to_string()))?;
-                log::debug!("write input data for {}", input_name);
-
-                match input_data {
-                    InputTensor::F32(float_input) => {
-                        queue.write_buffer(
-                            input_buffer,
-                            0,
-                            bytemuck::cast_slice(&resize(float_input.to_vec())),
-                        );
-                    }
-                    InputTensor::I32(int_input) => {
-                        queue.write_buffer(
-                            input_buffer,
-                            0,
-                            bytemuck::cast_slice(&resize(int_input.to_vec())),
-                        );
-                    }
-                    InputTensor::I64(int_input) => {
-                        log::warn!("reading int64 input '{input_name}' as int32 (int64 is not supported for calculation but can be used as input as long as values fit in int32)");
-                        let int32_input = int_input
-                            .iter()
-                            .map(|i| i32::from_i64(*i).ok_or(GpuError::OutOfBoundsError))
-                            .collect::<Result<_, _>>()?;
-                        queue.write_buffer(
-                            input_buffer,
-                            0,
-                            bytemuck::cast_slice(&resize(int32_input)),
-                        );
-                    }
-                    InputTensor::U8(int_input) => {
-                        log::warn!("reading uint8 input as int32 (uint8 is not supported for calculation but can be used as input)");
-                        let int32_input = int_input
-                            .iter()
-                            .map(|i| i32::from_u8(*i).ok_or(GpuError::OutOfBoundsError))
-                            .collect::<Result<_, _>>()?;
-                        queue.write_buffer(
-                            input_buffer,
-                            0,
-                            bytemuck::cast_slice(&resize(int32_input)),
-                        );
-                    }
-                }
-
-                Ok(())
+            GpuStep::Host { .. } => {
+                // Performed on the host by `GpuModel::encode_steps`
             }
             GpuStep::Operator {
                 pipeline,
                 bind_groups,
+                step_bind_groups,
                 threads,
                 ..
             } => {
                 // Encode a command for invocation of a shader.
-                let mut compute_pass = encoder.begin_compute_pass(&Default::default());
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: None,
+                    timestamp_writes,
+                });
                 compute_pass.set_pipeline(pipeline);
                 for (index, bind_group) in bind_groups.iter().enumerate() {
                     compute_pass.set_bind_group(index as u32, bind_group, &[]);
                 }
                 let (x, y, z) = *threads;
-                compute_pass.dispatch_workgroups(x, y, z);
-                Ok(())
+                if step_bind_groups.is_empty() {
+                    compute_pass.dispatch_workgroups(x, y, z);
+                } else {
+                    for step_bind_group in step_bind_groups {
+                        compute_pass.set_bind_group(bind_groups.len() as u32, step_bind_group, &[]);
+                        compute_pass.dispatch_workgroups(x, y, z);
+                    }
+                }
             }
         }
     }
 }
 
+/// Encode a command to write the input data to an input buffer (which was created empty by `GpuModel::from`)
+pub(crate) fn write_input_tensor(
+    queue: &wgpu::Queue,
+    input_buffer: &Buffer,
+    input_name: &str,
+    input_data: &InputTensor,
+    strict_int64: bool,
+) -> Result<(), GpuError> {
+    log::debug!("write input data for {}", input_name);
+
+    match input_data {
+        InputTensor::F32(float_input) => {
+            queue.write_buffer(
+                input_buffer,
+                0,
+                bytemuck::cast_slice(&resize(float_input.to_vec())),
+            );
+        }
+        InputTensor::F16(half_input) => {
+            // Buffer writes must be a multiple of four bytes in size
+            let mut half_input = half_input.to_vec();
+            if half_input.len() % 2 != 0 {
+                half_input.push(f16::ZERO);
+            }
+            queue.write_buffer(input_buffer, 0, bytemuck::cast_slice(&resize(half_input)));
+        }
+        InputTensor::I32(int_input) => {
+            queue.write_buffer(
+                input_buffer,
+                0,
+                bytemuck::cast_slice(&resize(int_input.to_vec())),
+            );
+        }
+        InputTensor::I64(int_input) if strict_int64 => {
+            queue.write_buffer(
+                input_buffer,
+                0,
+                bytemuck::cast_slice(&resize(int_input.to_vec())),
+            );
+        }
+        InputTensor::I64(int_input) => {
+            log::warn!("reading int64 input '{input_name}' as int32 (int64 is not supported for calculation but can be used as input as long as values fit in int32)");
+            let int32_input = int_input
+                .iter()
+                .map(|i| i32::from_i64(*i).ok_or(GpuError::OutOfBoundsError))
+                .collect::<Result<_, _>>()?;
+            queue.write_buffer(input_buffer, 0, bytemuck::cast_slice(&resize(int32_input)));
+        }
+        InputTensor::Bool(bool_input) => {
+            let int_input: Vec<u32> = bool_input.iter().map(|b| u32::from(*b)).collect();
+            queue.write_buffer(input_buffer, 0, bytemuck::cast_slice(&resize(int_input)));
+        }
+        InputTensor::U8(int_input) => {
+            queue.write_buffer(input_buffer, 0, &packed_bytes(int_input));
+        }
+        InputTensor::I8(int_input) => {
+            queue.write_buffer(
+                input_buffer,
+                0,
+                &packed_bytes(bytemuck::cast_slice(int_input)),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Map a staging buffer for reading and wait until it is available
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+pub(crate) async fn map_read(device: &wgpu::Device, buffer: &Buffer) -> Result<(), GpuError> {
+    let buffer_slice = buffer.slice(..);
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).unwrap();
+        });
+        receiver.await.unwrap()?;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            tx.send(result).unwrap();
+        });
+        device.poll(wgpu::Maintain::Wait);
+        // The callback will have been called by now due to poll(Wait)
+        rx.recv().unwrap()?;
+    }
+
+    Ok(())
+}
+
 impl GpuTensor {
     /// Read the tensor from GPU memory to main memory (as Vec<f32>)
     async fn read_to_vec(
         &self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        strict_int64: bool,
     ) -> Result<OutputTensor, GpuError> {
         let shape = self.shape.clone();
 
@@ -1032,7 +1696,7 @@ impl GpuTensor {
                 );
                 sender
                     .send(match buffer {
-                        Ok(bytes) => Ok(Self::read_bytes_to_vec(&bytes, shape)),
+                        Ok(bytes) => Ok(Self::read_bytes_to_vec(&bytes, shape, strict_int64)),
                         Err(error) => Err(GpuError::BufferAsyncError(error)),
                     })
                     .unwrap();
@@ -1049,7 +1713,7 @@ impl GpuTensor {
             wgpu::util::DownloadBuffer::read_buffer(device, queue, &buffer_slice, move |buffer| {
                 // Called on download completed
                 tx.send(match buffer {
-                    Ok(bytes) => Ok(Self::read_bytes_to_vec(&bytes, shape)),
+                    Ok(bytes) => Ok(Self::read_bytes_to_vec(&bytes, shape, strict_int64)),
                     Err(error) => Err(GpuError::BufferAsyncError(error)),
                 })
                 .unwrap();
@@ -1060,7 +1724,7 @@ impl GpuTensor {
         }
     }
 
-    fn read_bytes_to_vec<A>(output_data: &[A], shape: Shape) -> OutputTensor
+    fn read_bytes_to_vec<A>(output_data: &[A], shape: Shape, strict_int64: bool) -> OutputTensor
     where
         A: NoUninit,
     {
@@ -1071,12 +1735,25 @@ impl GpuTensor {
             ScalarType::F32 => {
                 OutputTensor::F32(bytemuck::cast_slice(output_data)[..output_buffer_size].to_vec())
             }
+            ScalarType::F16 => {
+                OutputTensor::F16(bytemuck::cast_slice(output_data)[..output_buffer_size].to_vec())
+            }
             ScalarType::I32 => {
                 OutputTensor::I32(bytemuck::cast_slice(output_data)[..output_buffer_size].to_vec())
             }
             ScalarType::U8 => {
                 OutputTensor::U8(bytemuck::cast_slice(output_data)[..output_buffer_size].to_vec())
             }
+            ScalarType::I8 => {
+                OutputTensor::I8(bytemuck::cast_slice(output_data)[..output_buffer_size].to_vec())
+            }
+            ScalarType::Bool => {
+                let result_ints: &[u32] = &bytemuck::cast_slice(output_data)[..output_buffer_size];
+                OutputTensor::Bool(result_ints.iter().map(|i| *i != 0).collect())
+            }
+            ScalarType::I64 if strict_int64 => {
+                OutputTensor::I64(bytemuck::cast_slice(output_data)[..output_buffer_size].to_vec())
+            }
             ScalarType::I64 => {
                 log::warn!("reading int64 output as int32 because internally int64 scalars are not supported");
                 let result_ints: Vec<i32> =
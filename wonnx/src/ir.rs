@@ -5,7 +5,10 @@ use std::borrow::Cow;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::ptr;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 use thiserror::Error;
 
 #[derive(Clone)]
@@ -343,4 +346,20 @@ impl<'model> Node<'model> {
     pub fn identifier(self: &Arc<Self>) -> NodeIdentifier<'model> {
         NodeIdentifier(self.clone())
     }
+
+    /// Traverse the graph and sort nodes in the order of execution (topological sort)
+    pub fn topological_sort(
+        node: Arc<Node<'model>>,
+        nodes_seen: &mut HashSet<NodeIdentifier<'model>>,
+        sorted_nodes: &mut Vec<Arc<Node<'model>>>,
+    ) {
+        let identifier = node.identifier();
+        if !nodes_seen.contains(&identifier) {
+            nodes_seen.insert(identifier);
+            for node_input in &node.inputs {
+                Node::topological_sort(node_input.source_node.clone(), nodes_seen, sorted_nodes);
+            }
+            sorted_nodes.push(node);
+        }
+    }
 }
@@ -1,25 +1,48 @@
+mod binding;
 mod compiler;
+mod cpu;
+mod einsum;
+mod float16;
 mod gpu;
 mod ir;
 pub mod onnx;
 mod optimizer;
+mod padding;
+mod profile;
+mod registry;
 mod resource;
 pub mod utils;
 
+pub use binding::IoBinding;
 pub use compiler::CompileError;
+pub use cpu::CpuError;
+pub use einsum::{EinsumEquation, EinsumError, EinsumLabel};
 pub use gpu::GpuError;
 use ir::IrError;
 pub use optimizer::constant_of_shape_output;
 use optimizer::{Optimizer, OptimizerError};
+pub use padding::PaddingError;
+pub use profile::{NodeProfile, ProfileReport, TimingMethod};
 use protobuf::{self, Message, ProtobufError};
+pub use registry::{CustomOperator, CustomShader, OperatorRegistry, ShaderSource};
+pub use resource::{request_device_queue, AdapterOptions, DeviceError};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::path::Path;
 use std::result::Result;
-use utils::{get_opset_version, DataTypeError, InputTensor, OpsetError, OutputTensor};
+use std::sync::Arc;
+use utils::{
+    get_opset_version, DataTypeError, InputTensor, ModelMetadata, OpsetError, OutputTensor,
+    ScalarType, Shape, TensorInfo, TypedOutputTensor,
+};
 
-use crate::gpu::GpuModel;
+use crate::{cpu::CpuModel, gpu::GpuModel};
 use thiserror::Error;
 
+// Re-exported so that callers can supply a device and queue created with the same version of wgpu
+pub use half;
+pub use wgpu;
+
 #[derive(Error, Debug)]
 pub enum WonnxError {
     #[error("error compiling model: {0}")]
@@ -35,7 +58,8 @@ pub enum WonnxError {
     TypeError(#[from] DataTypeError),
 }
 
-/// An inference [session](Session) represents a model that is loaded and ready to perform inference on the GPU.
+/// An inference [session](Session) represents a model that is loaded and ready to perform inference on the GPU (or, when
+/// configured to do so, on the CPU).
 ///
 /// # Examples
 ///
@@ -45,7 +69,38 @@ pub enum WonnxError {
 /// let mut session = Session::from_path("path/to/model.onnx").await.unwrap();
 /// ```
 pub struct Session {
-    gpu_model: GpuModel,
+    model: Model,
+    inputs: Vec<TensorInfo>,
+    outputs: Vec<TensorInfo>,
+    metadata: ModelMetadata,
+
+    /// When checking for non-finite values: the names of node outputs (and the node producing them) in order of execution
+    non_finite_check_order: Option<Vec<(String, String)>>,
+
+    /// Whether the values of intermediate node outputs are returned from inference (they may be captured only to check
+    /// them for non-finite values)
+    return_intermediates: bool,
+
+    /// Whether float16 values in the model were widened to float32 because the GPU cannot calculate in half precision
+    float16_widened: bool,
+}
+
+/// The compiled model for the backend a [Session] was created for
+enum Model {
+    Gpu(GpuModel),
+    Cpu(CpuModel),
+}
+
+/// The backend that performs inference for a [Session].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Run the model using WGSL compute shaders on the GPU.
+    #[default]
+    Gpu,
+
+    /// Run the model using reference implementations of the ops on the CPU. This is much slower than the GPU backend but
+    /// does not require a GPU, and is useful to check the results of the GPU backend against.
+    Cpu,
 }
 
 #[derive(Error, Debug)]
@@ -66,6 +121,45 @@ pub enum SessionError {
     )]
     InvalidOutput(String),
 
+    #[error("input '{name}' has {actual} elements, but the model expects shape {expected} ({expected_elements} elements)", expected_elements = .expected.element_count())]
+    InputSizeMismatch {
+        name: String,
+        expected: Shape,
+        actual: usize,
+    },
+
+    #[error("input '{name}' has data type {actual:?}, but the model expects {expected:?}")]
+    InputTypeMismatch {
+        name: String,
+        expected: ScalarType,
+        actual: ScalarType,
+    },
+
+    #[error("output '{output}' of node '{node}' contains non-finite values (NaN or infinity)")]
+    NonFiniteValue { node: String, output: String },
+
+    #[error("output '{output}' has a data-dependent number of rows and cannot be bound to input '{input}' of another session")]
+    DataDependentOutputChained { output: String, input: String },
+
+    #[error("profiling is only supported by the GPU backend")]
+    ProfilingUnsupported,
+
+    #[error("IO bindings are only supported by the GPU backend")]
+    IoBindingUnsupported,
+
+    #[error("the buffer bound to output '{0}' does not have COPY_DST usage")]
+    OutputBufferUsage(String),
+
+    #[error("the buffer bound to output '{name}' is {actual} bytes in size, but {expected} bytes are required")]
+    OutputBufferTooSmall {
+        name: String,
+        expected: u64,
+        actual: u64,
+    },
+
+    #[error("sessions can only be chained when they use the same GPU device")]
+    DeviceMismatch,
+
     #[error("the model did not reference a specific version of the ONNX opset")]
     UnknownOnnxOpsetVersion,
 
@@ -75,11 +169,20 @@ pub enum SessionError {
     #[error("GPU model error: {0}")]
     GpuError(#[from] GpuError),
 
+    #[error("CPU model error: {0}")]
+    CpuError(#[from] CpuError),
+
+    #[error("could not obtain GPU device: {0}")]
+    DeviceError(#[from] DeviceError),
+
     #[error("optimizer error: {0}")]
     OptimizerError(#[from] OptimizerError),
 
     #[error("opset error: {0}")]
     OpsetError(#[from] OpsetError),
+
+    #[error("data type error: {0}")]
+    DataTypeError(#[from] DataTypeError),
 }
 
 /// Provides optional configuration when creating an inference [Session].
@@ -87,12 +190,52 @@ pub enum SessionError {
 pub struct SessionConfig {
     /// When set, only the specified outputs will be calculated, and nodes that are not inputs to these outputs may not be processed
     pub outputs: Option<Vec<String>>,
+
+    /// The backend that will perform inference (the GPU by default)
+    pub backend: Backend,
+
+    /// When set, inference on the GPU is performed using this device and queue instead of a newly created device
+    pub device_queue: Option<(Arc<wgpu::Device>, Arc<wgpu::Queue>)>,
+
+    /// Options for selecting the GPU adapter when a new device is created (not used when a device is provided)
+    pub adapter_options: AdapterOptions,
+
+    /// When set, inference returns the values of all intermediate node outputs (keyed by their ONNX value name) in
+    /// addition to the model outputs. On the GPU this prevents buffers from being shared between intermediate values, so
+    /// inference will use more memory. Values of nodes that are removed by the optimizer (e.g. because they are fused with
+    /// another node) are not available.
+    pub capture_intermediates: bool,
+
+    /// When set, inference fails with [SessionError::NonFiniteValue] when any node output contains NaN or infinity,
+    /// naming the first node (in order of execution) that produced such a value. To be able to check them, the values of
+    /// all intermediate node outputs are kept (as with [SessionConfig::capture_intermediates]), but they are only returned
+    /// when [SessionConfig::capture_intermediates] is also set.
+    pub check_non_finite: bool,
+
+    /// Custom operators that may be used by nodes in the model, in addition to the built-in operators. Custom operators
+    /// are only supported by the GPU backend.
+    pub operator_registry: OperatorRegistry,
+
+    /// When set, int64 tensors are stored with their full 64 bits on the GPU and calculations on them are performed using
+    /// emulated 64-bit integers. By default, int64 values are truncated to int32, which silently produces wrong results for
+    /// values that do not fit in 32 bits. In strict mode only a subset of the operators supports int64 tensors (Add, Sub,
+    /// Mul, Div, Mod, comparisons, Gather, Cast and Concat); compiling other operators on int64 tensors fails.
+    pub strict_int64: bool,
 }
 
 impl SessionConfig {
     /// Creates a new [SessionConfig] struct with the default options set.
     pub fn new() -> Self {
-        Self { outputs: None }
+        Self {
+            outputs: None,
+            backend: Backend::default(),
+            device_queue: None,
+            adapter_options: AdapterOptions::default(),
+            capture_intermediates: false,
+            check_non_finite: false,
+            operator_registry: OperatorRegistry::default(),
+            strict_int64: false,
+        }
     }
 
     /// Sets [`SessionConfig::outputs`] to the specified value and returns [Self].
@@ -100,6 +243,48 @@ impl SessionConfig {
         self.outputs = outputs;
         self
     }
+
+    /// Sets [`SessionConfig::backend`] to the specified value and returns [Self].
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets [`SessionConfig::device_queue`] to the specified device and queue and returns [Self].
+    pub fn with_device_queue(mut self, device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>) -> Self {
+        self.device_queue = Some((device, queue));
+        self
+    }
+
+    /// Sets [`SessionConfig::adapter_options`] to the specified value and returns [Self].
+    pub fn with_adapter_options(mut self, adapter_options: AdapterOptions) -> Self {
+        self.adapter_options = adapter_options;
+        self
+    }
+
+    /// Sets [`SessionConfig::capture_intermediates`] to the specified value and returns [Self].
+    pub fn with_capture_intermediates(mut self, capture_intermediates: bool) -> Self {
+        self.capture_intermediates = capture_intermediates;
+        self
+    }
+
+    /// Sets [`SessionConfig::check_non_finite`] to the specified value and returns [Self].
+    pub fn with_check_non_finite(mut self, check_non_finite: bool) -> Self {
+        self.check_non_finite = check_non_finite;
+        self
+    }
+
+    /// Sets [`SessionConfig::operator_registry`] to the specified value and returns [Self].
+    pub fn with_operator_registry(mut self, operator_registry: OperatorRegistry) -> Self {
+        self.operator_registry = operator_registry;
+        self
+    }
+
+    /// Sets [`SessionConfig::strict_int64`] to the specified value and returns [Self].
+    pub fn with_strict_int64(mut self, strict_int64: bool) -> Self {
+        self.strict_int64 = strict_int64;
+        self
+    }
 }
 
 impl Default for SessionConfig {
@@ -141,23 +326,135 @@ impl Session {
 
     /// Create a session using the provided [`onnx::ModelProto`] and [session config](SessionConfig).
     pub async fn from_model_with_config(
-        model: onnx::ModelProto,
+        mut model: onnx::ModelProto,
         config: &SessionConfig,
     ) -> Result<Session, SessionError> {
-        let (device, queue) = resource::request_device_queue().await;
-
-        // Optimize and compile the model graph to a set of buffers and 'builders' which can basically run GPU shader code referencing these buffers
         let onnx_opset_version = get_opset_version(&model)
             .map_err(SessionError::OpsetError)?
             .ok_or(SessionError::UnknownOnnxOpsetVersion)?;
+        let (inputs, outputs) = Self::describe_inputs_outputs(&model, config.outputs.as_deref())?;
+        let metadata = ModelMetadata::from(&model, onnx_opset_version);
+
+        let mut optimizer = Optimizer::new(onnx_opset_version)
+            .with_backend(config.backend)
+            .with_operator_registry(config.operator_registry.clone())
+            .with_strict_int64(config.strict_int64)
+            .with_in_place_updates(!config.capture_intermediates && !config.check_non_finite);
+        let device_queue = match config.backend {
+            Backend::Gpu => {
+                let (device, queue) = match &config.device_queue {
+                    Some((device, queue)) => (device.clone(), queue.clone()),
+                    None => resource::request_device_queue(&config.adapter_options).await?,
+                };
+                optimizer = optimizer.with_device_queue(device.clone(), queue.clone());
+                Some((device, queue))
+            }
+            Backend::Cpu => None,
+        };
+
+        // The version of naga used by wgpu cannot parse f16 shaders yet (even when the device supports SHADER_F16), so on
+        // the GPU float16 values are always widened to float32
+        let float16_widened = match &device_queue {
+            Some(_) => float16::widen_model(&mut model),
+            None => false,
+        };
 
-        let mut optimizer = Optimizer::new(onnx_opset_version);
         let ir = optimizer
             .optimize(ir::Node::from_model(&model, config.outputs.as_deref())?)
             .await?;
-        let gpu_model = GpuModel::from(ir, device, queue, onnx_opset_version)?;
 
-        Ok(Session { gpu_model })
+        let non_finite_check_order = if config.check_non_finite {
+            Some(Self::node_output_order(ir.clone()))
+        } else {
+            None
+        };
+
+        let capture = config.capture_intermediates || config.check_non_finite;
+        let model = match device_queue {
+            // Compile the model graph to a set of buffers and 'builders' which can basically run GPU shader code referencing these buffers
+            Some((device, queue)) => Model::Gpu(GpuModel::from(
+                ir,
+                device,
+                queue,
+                onnx_opset_version,
+                capture,
+                &config.operator_registry,
+                config.strict_int64,
+            )?),
+            None => Model::Cpu(CpuModel::from(
+                ir,
+                onnx_opset_version,
+                capture,
+                &config.operator_registry,
+            )?),
+        };
+
+        Ok(Session {
+            model,
+            inputs,
+            outputs,
+            metadata,
+            non_finite_check_order,
+            return_intermediates: config.capture_intermediates,
+            float16_widened,
+        })
+    }
+
+    /// Collect information on the inputs the model expects and the outputs it will produce. Graph inputs that have an
+    /// initializer are constants and are therefore not reported as inputs.
+    fn describe_inputs_outputs(
+        model: &onnx::ModelProto,
+        outputs: Option<&[String]>,
+    ) -> Result<(Vec<TensorInfo>, Vec<TensorInfo>), SessionError> {
+        let graph = model.get_graph();
+        let inputs = graph
+            .get_input()
+            .iter()
+            .filter(|input| {
+                !graph
+                    .get_initializer()
+                    .iter()
+                    .any(|init| init.get_name() == input.get_name())
+            })
+            .map(TensorInfo::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let outputs = match outputs {
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    let value_info = graph
+                        .get_output()
+                        .iter()
+                        .chain(graph.get_value_info().iter())
+                        .find(|info| info.get_name() == name)
+                        .ok_or_else(|| SessionError::InvalidOutput(name.clone()))?;
+                    Ok(TensorInfo::try_from(value_info)?)
+                })
+                .collect::<Result<Vec<_>, SessionError>>()?,
+            None => graph
+                .get_output()
+                .iter()
+                .map(TensorInfo::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        Ok((inputs, outputs))
+    }
+
+    /// The inputs this session expects to be provided when performing inference
+    pub fn inputs(&self) -> &[TensorInfo] {
+        &self.inputs
+    }
+
+    /// The outputs this session produces when performing inference
+    pub fn outputs(&self) -> &[TensorInfo] {
+        &self.outputs
+    }
+
+    /// Model-level information such as the producer, opset version and `metadata_props` of the model
+    pub fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
     }
 
     /// Create a Session given an ONNX model, using default configuration.
@@ -170,6 +467,176 @@ impl Session {
         &self,
         inputs: &HashMap<String, InputTensor<'a>>,
     ) -> Result<HashMap<String, OutputTensor>, SessionError> {
-        Ok(self.gpu_model.infer(inputs).await?)
+        Ok(self
+            .run_typed(inputs)
+            .await?
+            .into_iter()
+            .map(|(name, tensor)| (name, tensor.data))
+            .collect())
+    }
+
+    /// Perform inference given the inputs provided and return all the outputs the model was compiled to return, together
+    /// with their shapes.
+    pub async fn run_typed<'a>(
+        &self,
+        inputs: &HashMap<String, InputTensor<'a>>,
+    ) -> Result<HashMap<String, TypedOutputTensor>, SessionError> {
+        // Inputs are validated here rather than by the backend, as they need to be checked against the types declared by
+        // the model before float16 inputs are widened (the backend only sees the widened inputs)
+        self.validate_inputs(inputs)?;
+        let widened_inputs;
+        let inputs = if self.float16_widened {
+            widened_inputs = float16::widen_inputs(inputs);
+            &widened_inputs
+        } else {
+            inputs
+        };
+
+        let mut outputs = match &self.model {
+            Model::Gpu(gpu_model) => gpu_model.infer(inputs).await?,
+            Model::Cpu(cpu_model) => cpu_model.infer(inputs)?,
+        };
+        if self.float16_widened {
+            float16::narrow_outputs(&mut outputs, &self.outputs);
+        }
+
+        if let Some(order) = &self.non_finite_check_order {
+            for (node_name, output_name) in order {
+                let has_non_finite = match outputs.get(output_name).map(|output| &output.data) {
+                    Some(OutputTensor::F32(values)) => values.iter().any(|v| !v.is_finite()),
+                    Some(OutputTensor::F16(values)) => values.iter().any(|v| !v.is_finite()),
+                    _ => false,
+                };
+                if has_non_finite {
+                    return Err(SessionError::NonFiniteValue {
+                        node: node_name.clone(),
+                        output: output_name.clone(),
+                    });
+                }
+            }
+
+            if !self.return_intermediates {
+                outputs.retain(|name, _| self.outputs.iter().any(|info| &info.name == name));
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// Returns the names of the outputs of all operator nodes in the graph, together with the name of the node that
+    /// produces them (or its op type when the node is unnamed), in order of execution
+    #[allow(clippy::mutable_key_type)]
+    fn node_output_order(root: Arc<ir::Node>) -> Vec<(String, String)> {
+        let mut nodes = vec![];
+        let mut nodes_seen = std::collections::HashSet::new();
+        ir::Node::topological_sort(root, &mut nodes_seen, &mut nodes);
+
+        let mut order = vec![];
+        for node in &nodes {
+            if let ir::NodeDefinition::Operator(op_def) = &node.definition {
+                let proto = &op_def.proto;
+                let node_name = if proto.has_name() {
+                    proto.get_name()
+                } else {
+                    proto.get_op_type()
+                };
+                for output_name in proto.get_output() {
+                    order.push((node_name.to_string(), output_name.to_string()));
+                }
+            }
+        }
+        order
+    }
+
+    /// Perform inference given the inputs provided while measuring the time taken by each node in the model. See
+    /// [ProfileReport] for the information that is returned. This is only supported for sessions using the GPU backend.
+    pub async fn profile<'a>(
+        &self,
+        inputs: &HashMap<String, InputTensor<'a>>,
+    ) -> Result<ProfileReport, SessionError> {
+        self.validate_inputs(inputs)?;
+        let widened_inputs;
+        let inputs = if self.float16_widened {
+            widened_inputs = float16::widen_inputs(inputs);
+            &widened_inputs
+        } else {
+            inputs
+        };
+
+        match &self.model {
+            Model::Gpu(gpu_model) => Ok(gpu_model.profile(inputs).await?),
+            Model::Cpu(_) => Err(SessionError::ProfilingUnsupported),
+        }
+    }
+
+    /// Check that each of the provided inputs is an input of the model, and that its data type and number of elements
+    /// match what the model expects. Writing a tensor of the wrong size or type to an input buffer would otherwise
+    /// lead to a panic or silently incorrect results.
+    fn validate_inputs(&self, inputs: &HashMap<String, InputTensor>) -> Result<(), SessionError> {
+        for (name, tensor) in inputs {
+            let info = self
+                .inputs
+                .iter()
+                .find(|info| &info.name == name)
+                .ok_or_else(|| SessionError::InvalidInput(name.clone()))?;
+            validate_input(name, tensor, info.data_type, info.shape())?;
+        }
+        Ok(())
+    }
+
+    /// The GPU device and queue this session performs inference on (None when the session uses the CPU backend). These can
+    /// be passed to [SessionConfig::with_device_queue] to create another session on the same device, e.g. to chain sessions
+    /// using [IoBinding::bind_output_to_input].
+    pub fn device_queue(&self) -> Option<(Arc<wgpu::Device>, Arc<wgpu::Queue>)> {
+        match &self.model {
+            Model::Gpu(gpu_model) => Some((gpu_model.device().clone(), gpu_model.queue().clone())),
+            Model::Cpu(_) => None,
+        }
+    }
+
+    /// Create an [IO binding](IoBinding) that holds pre-allocated buffers for the inputs and outputs of this session.
+    /// This is only supported for sessions using the GPU backend.
+    pub fn create_io_binding(&self) -> Result<IoBinding, SessionError> {
+        match &self.model {
+            Model::Gpu(gpu_model) => Ok(IoBinding::new(gpu_model)),
+            Model::Cpu(_) => Err(SessionError::IoBindingUnsupported),
+        }
+    }
+
+    /// Perform inference using the inputs bound to the specified [IO binding](IoBinding), and copy the results to the
+    /// bound outputs. The binding must have been created by this session.
+    pub async fn run_with_binding(&self, binding: &mut IoBinding) -> Result<(), SessionError> {
+        match &self.model {
+            Model::Gpu(gpu_model) => binding.run(gpu_model).await,
+            Model::Cpu(_) => Err(SessionError::IoBindingUnsupported),
+        }
+    }
+}
+
+/// Check that the data type and number of elements of an input tensor match what the model expects. The size is not
+/// checked when the shape is not known (e.g. because it has symbolic dimensions).
+pub(crate) fn validate_input(
+    name: &str,
+    tensor: &InputTensor,
+    data_type: ScalarType,
+    shape: Option<Shape>,
+) -> Result<(), SessionError> {
+    if tensor.data_type() != data_type {
+        return Err(SessionError::InputTypeMismatch {
+            name: name.to_string(),
+            expected: data_type,
+            actual: tensor.data_type(),
+        });
+    }
+
+    if let Some(shape) = shape {
+        if shape.element_count() as usize != tensor.len() {
+            return Err(SessionError::InputSizeMismatch {
+                name: name.to_string(),
+                expected: shape,
+                actual: tensor.len(),
+            });
+        }
     }
+    Ok(())
 }
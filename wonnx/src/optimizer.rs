@@ -1,21 +1,25 @@
 //! Optimizer that walks the DAG and transforms or coalesces ops for quicker execution
 use crate::{
-    gpu::GpuModel,
+    cpu::{CpuError, CpuModel},
+    einsum::{EinsumEquation, EinsumLabel},
+    gpu::{op_forwards_input, op_has_data_dependent_output, GpuModel},
     ir::{Input, Node, NodeDefinition, NodeIdentifier, OperatorDefinition},
-    onnx::{NodeProto, TensorProto},
-    resource::{padding, request_device_queue},
+    onnx::{AttributeProto, NodeProto, TensorProto},
+    registry::OperatorRegistry,
+    resource::{padding, request_device_queue, AdapterOptions, DeviceError},
     utils::{
-        attribute, AttributeNotFoundError, DataTypeError, NodeAttributes, OutputTensor, ScalarType,
-        Shape,
+        attribute, bool_data, float16_data, initializer, int8_data, node, AttributeNotFoundError,
+        DataTypeError, NodeAttributes, OutputTensor, ScalarType, Shape,
     },
-    GpuError,
+    Backend, GpuError,
 };
 use async_recursion::async_recursion;
 use bytemuck::pod_collect_to_vec;
+use num::integer::gcd;
 use protobuf::RepeatedField;
 use std::{
     borrow::Cow,
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     sync::Arc,
 };
 use thiserror::Error;
@@ -46,12 +50,24 @@ pub enum OptimizerError {
 
     #[error("error during constant folding: {0}")]
     ConstantFoldingError(#[from] GpuError),
+
+    #[error("error during constant folding on the CPU: {0}")]
+    CpuConstantFoldingError(#[from] CpuError),
+
+    #[error("could not obtain a GPU device for constant folding: {0}")]
+    DeviceError(#[from] DeviceError),
 }
 
 pub struct Optimizer<'model> {
     padded_tensors: HashMap<String, Arc<Node<'model>>>,
     optimized: HashMap<NodeIdentifier<'model>, Arc<Node<'model>>>,
     onnx_opset_version: i64,
+    backend: Backend,
+    device_queue: Option<(Arc<wgpu::Device>, Arc<wgpu::Queue>)>,
+    operator_registry: OperatorRegistry,
+    strict_int64: bool,
+    in_place_updates: bool,
+    consumers: HashMap<(NodeIdentifier<'model>, usize), usize>,
 }
 
 impl<'model> Optimizer<'model> {
@@ -60,9 +76,50 @@ impl<'model> Optimizer<'model> {
             padded_tensors: HashMap::new(),
             optimized: HashMap::new(),
             onnx_opset_version,
+            backend: Backend::Gpu,
+            device_queue: None,
+            operator_registry: OperatorRegistry::default(),
+            strict_int64: false,
+            in_place_updates: true,
+            consumers: HashMap::new(),
         }
     }
 
+    /// Sets the backend the optimized graph will be executed on. This determines how constant nodes are folded and
+    /// which backend-specific transformations are applied.
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets the device and queue used to fold constant nodes on the GPU. When not set, a device is created each time a
+    /// node is folded.
+    pub fn with_device_queue(mut self, device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>) -> Self {
+        self.device_queue = Some((device, queue));
+        self
+    }
+
+    /// Sets the registry of custom operators that may appear in the graph. Nodes implemented by a custom operator are
+    /// never folded into constants.
+    pub fn with_operator_registry(mut self, operator_registry: OperatorRegistry) -> Self {
+        self.operator_registry = operator_registry;
+        self
+    }
+
+    /// Sets whether constant nodes with int64 values are folded on the GPU using (emulated) 64-bit integers instead of
+    /// truncating the values to int32.
+    pub fn with_strict_int64(mut self, strict_int64: bool) -> Self {
+        self.strict_int64 = strict_int64;
+        self
+    }
+
+    /// Sets whether ops may write their output to the buffer of an input that no other node uses (on the GPU). This must
+    /// be disabled when intermediate values are captured, as the value of such an input would be overwritten.
+    pub fn with_in_place_updates(mut self, in_place_updates: bool) -> Self {
+        self.in_place_updates = in_place_updates;
+        self
+    }
+
     // Calculates the output of a constant node, then returns a node that contains the result as initializer
     async fn fold_constant_node(
         &self,
@@ -262,21 +319,39 @@ impl<'model> Optimizer<'model> {
             });
 
             // Perform inference
-            let (device, queue) = request_device_queue().await;
-            let gm = GpuModel::from(out_node, device, queue, self.onnx_opset_version)
-                .map_err(OptimizerError::ConstantFoldingError)?;
-            let mut outputs = gm.infer(&HashMap::new()).await?;
+            let mut outputs = match self.backend {
+                Backend::Gpu => {
+                    let (device, queue) = match &self.device_queue {
+                        Some((device, queue)) => (device.clone(), queue.clone()),
+                        None => request_device_queue(&AdapterOptions::default()).await?,
+                    };
+                    let gm = GpuModel::from(
+                        out_node,
+                        device,
+                        queue,
+                        self.onnx_opset_version,
+                        false,
+                        &self.operator_registry,
+                        self.strict_int64,
+                    )
+                    .map_err(OptimizerError::ConstantFoldingError)?;
+                    gm.infer(&HashMap::new()).await?
+                }
+                Backend::Cpu => CpuModel::from(
+                    out_node,
+                    self.onnx_opset_version,
+                    false,
+                    &self.operator_registry,
+                )?
+                .infer(&HashMap::new())?,
+            };
 
             // Take the output tensor and make it into an initializer node
             let (_, output_tensor) = outputs.drain().take(1).next().unwrap();
             log::info!("folded {output_name} to {output_tensor:?}");
             let mut output_tensor_proto = TensorProto::from(
-                output_tensor,
-                op_def.output_shapes[0]
-                    .dims
-                    .iter()
-                    .map(|x| *x as i64)
-                    .collect(),
+                output_tensor.data,
+                output_tensor.shape.dims.iter().map(|x| *x as i64).collect(),
             );
             output_tensor_proto.set_name(output_name);
 
@@ -298,6 +373,11 @@ impl<'model> Optimizer<'model> {
         &mut self,
         node: Arc<Node<'model>>,
     ) -> Result<Arc<Node<'model>>, OptimizerError> {
+        // Before optimizing the graph, count how many times each node output is used in the original graph
+        if let NodeDefinition::Outputs { .. } = node.definition {
+            self.count_consumers(node.clone());
+        }
+
         let identifier = node.identifier();
         match self.optimized.get(&identifier) {
             Some(opt_node) => Ok(opt_node.clone()),
@@ -309,6 +389,49 @@ impl<'model> Optimizer<'model> {
         }
     }
 
+    /// Count the number of node inputs that refer to each node output in the graph that ends in the specified node
+    #[allow(clippy::mutable_key_type)]
+    fn count_consumers(&mut self, root: Arc<Node<'model>>) {
+        let mut nodes = vec![];
+        let mut nodes_seen = HashSet::new();
+        Node::topological_sort(root, &mut nodes_seen, &mut nodes);
+        for node in nodes {
+            for input in &node.inputs {
+                *self
+                    .consumers
+                    .entry((input.source_node.identifier(), input.output_index))
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Returns whether the specified input of a node is an intermediate value (i.e. produced by an operator in its own
+    /// buffer) that no other node in the original graph uses, so that the node may overwrite it. The node is the original
+    /// node, whereas the new inputs are its optimized inputs.
+    fn is_exclusive_input(
+        &self,
+        node: &Arc<Node<'model>>,
+        new_inputs: &[Input<'model>],
+        input_index: usize,
+    ) -> bool {
+        let is_intermediate = |input: &Input| match &input.source_node.definition {
+            NodeDefinition::Operator(op_def) => !op_forwards_input(op_def.proto.get_op_type()),
+            _ => false,
+        };
+
+        match (node.inputs.get(input_index), new_inputs.get(input_index)) {
+            (Some(input), Some(new_input)) => {
+                is_intermediate(input)
+                    && is_intermediate(new_input)
+                    && self
+                        .consumers
+                        .get(&(input.source_node.identifier(), input.output_index))
+                        == Some(&1)
+            }
+            _ => false,
+        }
+    }
+
     /// Optimize a branch of a graph. Takes a node an attempts to form a chain of nodes with single (dynamic) inputs by
     /// traversing towards the inputs.
     #[cfg_attr(target_arch = "wasm32", async_recursion(?Send))]
@@ -317,6 +440,8 @@ impl<'model> Optimizer<'model> {
         &mut self,
         node: Arc<Node<'model>>,
     ) -> Result<Arc<Node<'model>>, OptimizerError> {
+        let node = Self::with_fused_subgraph(node)?;
+
         // Try to form a chain of nodes that have one dynamic input
         let prior;
         let mut chain = VecDeque::new();
@@ -334,7 +459,9 @@ impl<'model> Optimizer<'model> {
                 prior = chain.pop_front().unwrap();
                 break;
             }
-            chain.push_front(dynamic_inputs[0].source_node.clone());
+            chain.push_front(Self::with_fused_subgraph(
+                dynamic_inputs[0].source_node.clone(),
+            )?);
         }
 
         log::debug!(
@@ -390,6 +517,14 @@ impl<'model> Optimizer<'model> {
                 } else {
                     final_chain[node_index - 1].clone()
                 };
+
+                // Each node is guaranteed to have only one 'dynamic' input. This is the one we will replace, along with
+                // any other inputs that take a different output from the same source node.
+                let dynamic_source = consumer
+                    .inputs
+                    .iter()
+                    .find(|input| input.source_node.is_dynamic() && input.output_index == 0)
+                    .map(|input| input.source_node.identifier());
                 final_chain[node_index] = self
                     .locally_optimized_node_with(
                         consumer.clone(),
@@ -397,13 +532,10 @@ impl<'model> Optimizer<'model> {
                             .inputs
                             .iter()
                             .map(|old_input| {
-                                // Each node is guaranteed to have only one 'dynamic' input. This is the one we will replace
-                                let is_dynamic_source = old_input.source_node.is_dynamic()
-                                    && old_input.output_index == 0;
-                                if is_dynamic_source {
+                                if Some(old_input.source_node.identifier()) == dynamic_source {
                                     Input {
                                         source_node: producer.clone(),
-                                        output_index: 0,
+                                        output_index: old_input.output_index,
                                     }
                                 } else {
                                     old_input.clone()
@@ -465,7 +597,23 @@ impl<'model> Optimizer<'model> {
         }
 
         // Fold constant nodes
-        if node.is_constant() && !matches!(node.definition, NodeDefinition::Missing) {
+        let is_custom = match &node.definition {
+            NodeDefinition::Operator(op_def) => self.operator_registry.get(&op_def.proto).is_some(),
+            _ => false,
+        };
+        // The output of an op with a data-dependent number of results is padded, and the padding is only removed when it
+        // is read back as inference output, so such ops are not folded into an initializer
+        let is_data_dependent = match &node.definition {
+            NodeDefinition::Operator(op_def) => {
+                op_has_data_dependent_output(op_def.proto.get_op_type())
+            }
+            _ => false,
+        };
+        if node.is_constant()
+            && !is_custom
+            && !is_data_dependent
+            && !matches!(node.definition, NodeDefinition::Missing)
+        {
             log::debug!(
                 "node is constant: {:?} {:?}",
                 node.identifier(),
@@ -482,7 +630,8 @@ impl<'model> Optimizer<'model> {
                     "Conv" | "ConvRelu" | "ConvLeakyRelu" => {
                         // This optimization inserts some padding to convolution between kernels with kernel 3x3, because of
                         // the stride of matrix3x3 is 16 in wgsl. It makes the computation matrixable and increases the performance.
-                        if new_inputs.len() > 2
+                        if self.backend == Backend::Gpu
+                            && new_inputs.len() > 2
                             && op_def
                                 .proto
                                 .get_attribute_value::<Vec<i64>>("kernel_shape", None)?
@@ -555,13 +704,13 @@ impl<'model> Optimizer<'model> {
                         Ok(Arc::new(new_node))
                     }
 
-                    // The Clip, Split, Resize, Reshape and Reduce* operators each take optional inputs that influence the operation.
+                    // The Clip, Pad, Split, Resize, Reshape, Slice, Expand, Tile, TopK and Reduce* operators each take optional inputs that influence the operation.
                     // These are typically statically initialized tensors containing shapes. For more efficient execution we
                     // move these static values to attributes.
-                    op @ ("Clip" | "Pad" | "Split" | "Resize" | "Reshape" | "ReduceMean"
-                    | "ReduceSum" | "ReduceMin" | "ReduceMax" | "ReduceSumSquare"
-                    | "ReduceLogSumExp" | "ReduceLogSum" | "ReduceL2" | "ReduceL1"
-                    | "ReduceProd") => {
+                    op @ ("Clip" | "Pad" | "Split" | "Resize" | "Reshape" | "Slice" | "Expand"
+                    | "Tile" | "TopK" | "ReduceMean" | "ReduceSum" | "ReduceMin"
+                    | "ReduceMax" | "ReduceSumSquare" | "ReduceLogSumExp"
+                    | "ReduceLogSum" | "ReduceL2" | "ReduceL1" | "ReduceProd") => {
                         if new_inputs.is_empty() {
                             return Err(OptimizerError::NoInputs);
                         }
@@ -573,6 +722,10 @@ impl<'model> Optimizer<'model> {
                             "Reshape" => RESHAPE_INPUT_NAMES,
                             "Clip" => CLIP_INPUT_NAMES,
                             "Pad" => PAD_INPUT_NAMES,
+                            "Slice" => SLICE_INPUT_NAMES,
+                            "Expand" => EXPAND_INPUT_NAMES,
+                            "Tile" => TILE_INPUT_NAMES,
+                            "TopK" => TOPK_INPUT_NAMES,
                             "ReduceSum" => REDUCE_OPS_INPUT_NAMES,
                             "ReduceL1" => REDUCE_OPS_INPUT_NAMES,
                             "ReduceL2" => REDUCE_OPS_INPUT_NAMES,
@@ -605,6 +758,9 @@ impl<'model> Optimizer<'model> {
                                         | ("Resize", "roi")
                                         | ("Resize", "sizes")
                                         | ("Reshape", "shape")
+                                        | ("Expand", "shape")
+                                        | ("Tile", "repeats")
+                                        | ("TopK", "K")
                                         | (
                                             "ReduceMean" | "ReduceSum" | "ReduceMin" | "ReduceMax"
                                             | "ReduceSumSquare" | "ReduceLogSumExp"
@@ -614,6 +770,7 @@ impl<'model> Optimizer<'model> {
                                         )
                                         | ("Pad", "pads")
                                         | ("Resize", "scales")
+                                        | ("Slice", "starts" | "ends" | "axes" | "steps")
                                         | ("Clip", "min" | "max") => match data_type {
                                             ScalarType::F32 => {
                                                 let value: Vec<f32> = if tensor_proto
@@ -657,6 +814,34 @@ impl<'model> Optimizer<'model> {
                                                     value,
                                                 ));
                                             }
+                                            ScalarType::I32 => {
+                                                let value: Vec<i64> =
+                                                    if tensor_proto.get_int32_data().is_empty() {
+                                                        pod_collect_to_vec::<u8, i32>(
+                                                            tensor_proto.get_raw_data(),
+                                                        )
+                                                        .into_iter()
+                                                        .map(|x| x as i64)
+                                                        .collect()
+                                                    } else {
+                                                        tensor_proto
+                                                            .get_int32_data()
+                                                            .iter()
+                                                            .map(|x| *x as i64)
+                                                            .collect()
+                                                    };
+                                                log::info!(
+                                                    "transferring input {} for op {} to i64 attribute (initializer data type: {:?}): {:?}",
+                                                    attr_name,
+                                                    op,
+                                                    data_type,
+                                                    value,
+                                                );
+                                                attributes.push(attribute(
+                                                    attr_names[input_index],
+                                                    value,
+                                                ));
+                                            }
                                             _ => {
                                                 return Err(OptimizerError::InvalidInputDataType {
                                                     data_type,
@@ -703,6 +888,41 @@ impl<'model> Optimizer<'model> {
                         Ok(Arc::new(new_node))
                     }
 
+                    // When the data is not used elsewhere, ScatterND can write the updates to its buffer instead of
+                    // copying all data to the output first
+                    "ScatterND"
+                        if self.backend == Backend::Gpu
+                            && self.in_place_updates
+                            && self.is_exclusive_input(&node, &new_inputs, 0) =>
+                    {
+                        log::debug!(
+                            "ScatterND node {} can update its data in place",
+                            op_def.proto.get_name()
+                        );
+                        let mut new_proto = op_def.proto.clone().into_owned();
+                        new_proto.set_op_type("ScatterNDInPlace".to_string());
+                        Ok(Arc::new(Node {
+                            inputs: new_inputs,
+                            definition: NodeDefinition::Operator(Box::new(OperatorDefinition {
+                                proto: Cow::Owned(new_proto),
+                                output_shapes: op_def.output_shapes.clone(),
+                            })),
+                        }))
+                    }
+
+                    // Einsum equations that amount to taking diagonals, sums, transpositions and (batched) matrix
+                    // multiplications are lowered to the corresponding ops. Other equations are executed by the generic
+                    // Einsum shader.
+                    "Einsum" => {
+                        match EinsumLowering::new(op_def, self.backend).lower(&new_inputs)? {
+                            Some(lowered) => Ok(lowered),
+                            None => Ok(Arc::new(Node {
+                                inputs: new_inputs,
+                                definition: NodeDefinition::Operator(op_def.clone()),
+                            })),
+                        }
+                    }
+
                     _ => Ok(Arc::new(Node {
                         inputs: new_inputs,
                         definition: NodeDefinition::Operator(op_def.clone()),
@@ -725,6 +945,346 @@ impl<'model> Optimizer<'model> {
         }
     }
 
+    /// Replace the node with a single fused node if it is the final node of a subgraph that can be fused
+    fn with_fused_subgraph(node: Arc<Node<'model>>) -> Result<Arc<Node<'model>>, OptimizerError> {
+        let node = Self::with_fused_quantized_conv(node)?;
+        Self::with_fused_layer_normalization(node)
+    }
+
+    /// Fuse a QuantizeLinear node that quantizes the output of a Conv node with dequantized (DequantizeLinear) inputs into a
+    /// single QLinearConv node, or return the node unchanged if this is not possible. This is done before the inputs of
+    /// the node are optimized, because constant folding would otherwise replace the dequantized weights with floats.
+    fn with_fused_quantized_conv(
+        node: Arc<Node<'model>>,
+    ) -> Result<Arc<Node<'model>>, OptimizerError> {
+        let quantize = match &node.definition {
+            NodeDefinition::Operator(op_def) if op_def.proto.get_op_type() == "QuantizeLinear" => {
+                op_def
+            }
+            _ => return Ok(node),
+        };
+        let (conv_node, dequantize_x, dequantize_w) = match operator_at(&node, 0, "Conv") {
+            Some(conv_node) => match (
+                operator_at(conv_node, 0, "DequantizeLinear"),
+                operator_at(conv_node, 1, "DequantizeLinear"),
+            ) {
+                (Some(dequantize_x), Some(dequantize_w)) => (conv_node, dequantize_x, dequantize_w),
+                _ => return Ok(node),
+            },
+            None => return Ok(node),
+        };
+
+        // All scales and zero points (and the weights) need to be known, and only the scale and zero point of the
+        // weights may be specified per output channel (axis 0)
+        let (x_scale, y_scale, w_scale) = match (
+            initializer_at(dequantize_x, 1),
+            initializer_at(&node, 1),
+            initializer_at(dequantize_w, 1),
+        ) {
+            (Some(x_scale), Some(y_scale), Some(w_scale)) => (
+                float_values(x_scale),
+                float_values(y_scale),
+                float_values(w_scale),
+            ),
+            _ => return Ok(node),
+        };
+        if initializer_at(dequantize_w, 0).is_none()
+            || [(dequantize_x, 2), (dequantize_w, 2), (&node, 2)]
+                .iter()
+                .any(|(node, index)| initializer_at(node, *index).is_none())
+            || x_scale.len() != 1
+            || y_scale.len() != 1
+        {
+            return Ok(node);
+        }
+        if let NodeDefinition::Operator(dequantize_w_def) = &dequantize_w.definition {
+            if w_scale.len() > 1
+                && dequantize_w_def
+                    .proto
+                    .get_attribute_value("axis", Some(1))?
+                    != 0
+            {
+                return Ok(node);
+            }
+        }
+
+        // The bias (if any) must be dequantized from int32 with a zero point of zero and the product of the input and
+        // weight scales as scale, in which case QLinearConv can use the int32 values directly
+        let bias = match conv_node.inputs.get(2) {
+            None => None,
+            Some(_) => {
+                let dequantize_b = match operator_at(conv_node, 2, "DequantizeLinear") {
+                    Some(dequantize_b) => dequantize_b,
+                    None => return Ok(node),
+                };
+                let (bias, bias_scale) = match (
+                    initializer_at(dequantize_b, 0),
+                    initializer_at(dequantize_b, 1),
+                ) {
+                    (Some(bias), Some(bias_scale)) => (bias, float_values(bias_scale)),
+                    _ => return Ok(node),
+                };
+                let zero_point_is_zero = match dequantize_b.inputs.get(2) {
+                    None => true,
+                    Some(_) => match initializer_at(dequantize_b, 2) {
+                        Some(zero_point) => {
+                            zero_point.get_int32_data().iter().all(|x| *x == 0)
+                                && zero_point.get_raw_data().iter().all(|x| *x == 0)
+                        }
+                        None => false,
+                    },
+                };
+                let scales_match = (bias_scale.len() == 1 || bias_scale.len() == w_scale.len())
+                    && (0..bias_scale.len().max(w_scale.len())).all(|channel| {
+                        let expected = x_scale[0] * w_scale[channel.min(w_scale.len() - 1)];
+                        let actual = bias_scale[channel.min(bias_scale.len() - 1)];
+                        (actual - expected).abs() <= expected.abs() * 1e-6
+                    });
+                if ScalarType::from_i32(bias.get_data_type())? != ScalarType::I32
+                    || !zero_point_is_zero
+                    || !scales_match
+                {
+                    return Ok(node);
+                }
+                Some(dequantize_b)
+            }
+        };
+
+        if let (
+            NodeDefinition::Operator(conv),
+            NodeDefinition::Operator(dequantize_x_def),
+            NodeDefinition::Operator(dequantize_w_def),
+        ) = (
+            &conv_node.definition,
+            &dequantize_x.definition,
+            &dequantize_w.definition,
+        ) {
+            // Inputs of QLinearConv: x, x_scale, x_zero_point, w, w_scale, w_zero_point, y_scale, y_zero_point, B
+            let mut inputs: Vec<Input> = dequantize_x.inputs[0..3].to_vec();
+            inputs.extend_from_slice(&dequantize_w.inputs[0..3]);
+            inputs.extend_from_slice(&node.inputs[1..3]);
+            let mut input_names: Vec<String> = dequantize_x_def.proto.get_input()[0..3].to_vec();
+            input_names.extend_from_slice(&dequantize_w_def.proto.get_input()[0..3]);
+            input_names.extend_from_slice(&quantize.proto.get_input()[1..3]);
+            if let Some(dequantize_b) = bias {
+                inputs.push(dequantize_b.inputs[0].clone());
+                if let NodeDefinition::Operator(dequantize_b_def) = &dequantize_b.definition {
+                    input_names.push(dequantize_b_def.proto.get_input()[0].clone());
+                }
+            }
+
+            // Use the Conv node as template for the QLinearConv node, which produces the output of the QuantizeLinear node
+            let mut fused_proto = conv.proto.clone().into_owned();
+            fused_proto.set_op_type("QLinearConv".to_string());
+            fused_proto.set_name(format!(
+                "{}+{}",
+                conv.proto.get_name(),
+                quantize.proto.get_name()
+            ));
+            fused_proto.set_input(RepeatedField::from(input_names));
+            fused_proto.set_output(RepeatedField::from(quantize.proto.get_output().to_vec()));
+
+            log::debug!(
+                "fusing DequantizeLinear/Conv/QuantizeLinear into QLinearConv {}",
+                fused_proto.get_name()
+            );
+
+            Ok(Arc::new(Node {
+                inputs,
+                definition: NodeDefinition::Operator(Box::new(OperatorDefinition {
+                    proto: Cow::Owned(fused_proto),
+                    output_shapes: quantize.output_shapes.clone(),
+                })),
+            }))
+        } else {
+            unreachable!()
+        }
+    }
+
+    /// Fuse the subgraph that older exporters emit for layer normalization into a single LayerNormalization node, or return
+    /// the node unchanged if it is not the final node of such a subgraph. The subgraph computes the following (the Mul and
+    /// Add applying scale and bias are optional):
+    ///
+    /// D = X - ReduceMean(X), Y = D / Sqrt(ReduceMean(Pow(D, 2)) + epsilon) * scale + bias
+    fn with_fused_layer_normalization(
+        node: Arc<Node<'model>>,
+    ) -> Result<Arc<Node<'model>>, OptimizerError> {
+        let root = match &node.definition {
+            NodeDefinition::Operator(op_def) => op_def,
+            _ => return Ok(node),
+        };
+
+        // Find the Div node and the constant scale and bias applied to its output (if any)
+        let mut div_node = &node;
+        let mut bias_index = None;
+        let mut scale_index = None;
+        if root.proto.get_op_type() == "Add" {
+            if let Some((mul_node, index)) = operator_and_initializer(&node, "Mul") {
+                div_node = mul_node;
+                bias_index = Some(index);
+            } else if let Some((div, index)) = operator_and_initializer(&node, "Div") {
+                div_node = div;
+                bias_index = Some(index);
+            } else {
+                return Ok(node);
+            }
+        }
+        if let NodeDefinition::Operator(op_def) = &div_node.definition {
+            if op_def.proto.get_op_type() == "Mul" {
+                match operator_and_initializer(div_node, "Div") {
+                    Some((div, index)) => {
+                        scale_index = Some((div_node, index));
+                        div_node = div;
+                    }
+                    None => return Ok(node),
+                }
+            }
+        }
+        let div = match &div_node.definition {
+            NodeDefinition::Operator(op_def) if op_def.proto.get_op_type() == "Div" => op_def,
+            _ => return Ok(node),
+        };
+
+        // Match the numerator (X - ReduceMean(X)) and denominator (Sqrt(ReduceMean(Pow(D, 2)) + epsilon))
+        let (sub_node, sqrt_node) = match (
+            operator_at(div_node, 0, "Sub"),
+            operator_at(div_node, 1, "Sqrt"),
+        ) {
+            (Some(sub_node), Some(sqrt_node)) => (sub_node, sqrt_node),
+            _ => return Ok(node),
+        };
+        let x = &sub_node.inputs[0];
+        let mean_node = match operator_at(sub_node, 1, "ReduceMean") {
+            Some(mean_node)
+                if Arc::ptr_eq(&mean_node.inputs[0].source_node, &x.source_node)
+                    && mean_node.inputs[0].output_index == x.output_index =>
+            {
+                mean_node
+            }
+            _ => return Ok(node),
+        };
+        let (variance_node, epsilon) = match operator_at(sqrt_node, 0, "Add").and_then(|add_node| {
+            Some((add_node, operator_and_initializer(add_node, "ReduceMean")?))
+        }) {
+            Some((add_node, (variance_node, index))) => {
+                match &float_values(initializer_at(add_node, index).unwrap())[..] {
+                    [epsilon] => (variance_node, *epsilon),
+                    _ => return Ok(node),
+                }
+            }
+            None => return Ok(node),
+        };
+        match operator_at(variance_node, 0, "Pow") {
+            Some(pow_node)
+                if Arc::ptr_eq(&pow_node.inputs[0].source_node, sub_node)
+                    && pow_node.inputs[0].output_index == 0
+                    && initializer_at(pow_node, 1).map(float_values) == Some(vec![2.0]) => {}
+            _ => return Ok(node),
+        }
+
+        // Both means must be taken over the same trailing dimensions, which are the dimensions that are normalized
+        let shape = &div.output_shapes[0];
+        let rank = shape.rank() as i64;
+        let reduced_axes = |reduce_node: &Node| -> Result<Option<Vec<i64>>, OptimizerError> {
+            let reduce = match &reduce_node.definition {
+                NodeDefinition::Operator(op_def) => op_def,
+                _ => unreachable!(),
+            };
+            if reduce.proto.get_attribute_value("keepdims", Some(1))? != 1 {
+                return Ok(None);
+            }
+            let mut axes = match reduce_node.inputs.get(1) {
+                Some(_) => match initializer_at(reduce_node, 1) {
+                    Some(axes) => int64_values(axes),
+                    None => return Ok(None),
+                },
+                None => reduce.proto.get_attribute_value("axes", Some(vec![]))?,
+            };
+            if axes.is_empty() {
+                axes = (0..rank).collect();
+            }
+            let mut axes: Vec<i64> = axes
+                .iter()
+                .map(|axis| if *axis < 0 { axis + rank } else { *axis })
+                .collect();
+            axes.sort_unstable();
+            Ok(Some(axes))
+        };
+        let axes = match (reduced_axes(mean_node)?, reduced_axes(variance_node)?) {
+            (Some(mean_axes), Some(variance_axes)) if mean_axes == variance_axes => mean_axes,
+            _ => return Ok(node),
+        };
+        let axis = rank - axes.len() as i64;
+        if axes.is_empty()
+            || axes != (axis..rank).collect::<Vec<i64>>()
+            || !shape.data_type.is_float()
+        {
+            return Ok(node);
+        }
+
+        // The scale and bias must be broadcastable to the normalized dimensions
+        let normalized_dims = &shape.dims[(axis as usize)..];
+        let affine_inputs = [scale_index, bias_index.map(|index| (&node, index))];
+        for (affine_node, index) in affine_inputs.iter().flatten() {
+            let dims = initializer_at(affine_node, *index).unwrap().get_dims();
+            let first_non_one = dims.iter().position(|d| *d != 1).unwrap_or(dims.len());
+            let trailing_dims: Vec<u64> = dims[first_non_one..].iter().map(|d| *d as u64).collect();
+            if !normalized_dims.ends_with(&trailing_dims) {
+                return Ok(node);
+            }
+        }
+
+        // LayerNormalization requires a scale, so use ones when the subgraph does not scale
+        let mut input_names = vec![input_name(sub_node, 0)];
+        let mut inputs = vec![x.clone()];
+        if scale_index.is_none() {
+            let name = format!("{}_scale", div.proto.get_name());
+            let dims: Vec<i64> = normalized_dims.iter().map(|d| *d as i64).collect();
+            let ones = vec![1.0; normalized_dims.iter().product::<u64>() as usize];
+            input_names.push(name.clone());
+            inputs.push(Input {
+                source_node: Arc::new(Node {
+                    definition: NodeDefinition::Tensor(Box::new(Cow::Owned(initializer(
+                        &name, ones, dims,
+                    )))),
+                    inputs: vec![],
+                }),
+                output_index: 0,
+            });
+        }
+        for (affine_node, index) in affine_inputs.iter().flatten() {
+            input_names.push(input_name(affine_node, *index));
+            inputs.push(affine_node.inputs[*index].clone());
+        }
+
+        let mut fused_proto = NodeProto::new();
+        fused_proto.set_op_type("LayerNormalization".to_string());
+        fused_proto.set_name(format!(
+            "{}+{}",
+            div.proto.get_name(),
+            root.proto.get_name()
+        ));
+        fused_proto.set_input(RepeatedField::from(input_names));
+        fused_proto.set_output(RepeatedField::from(root.proto.get_output().to_vec()));
+        fused_proto.set_attribute(RepeatedField::from(vec![
+            attribute("axis", axis),
+            attribute("epsilon", epsilon),
+        ]));
+
+        log::debug!(
+            "fusing ReduceMean/Sub/Pow/ReduceMean/Add/Sqrt/Div into LayerNormalization {}",
+            fused_proto.get_name()
+        );
+
+        Ok(Arc::new(Node {
+            inputs,
+            definition: NodeDefinition::Operator(Box::new(OperatorDefinition {
+                proto: Cow::Owned(fused_proto),
+                output_shapes: root.output_shapes.clone(),
+            })),
+        }))
+    }
+
     /// Attempt to fuse several operators in a chain of operators with no other dynamic inputs. The function receives a list
     /// of nodes that are guaranteed to be operators that each have one input (exactly). It is free to remove or add nodes
     /// to this list. The caller will fix up the input/output relationships between the nodes.
@@ -756,8 +1316,8 @@ impl<'model> Optimizer<'model> {
                 Ok(true)
             }
 
-            // Conv+Relu or Conv+LeakyRelu: combine into ConvRelu/ConvLeakyRelu
-            ["Conv", "Relu", ..] | ["Conv", "LeakyRelu", ..] => {
+            // Conv+Relu or Conv+LeakyRelu: combine into ConvRelu/ConvLeakyRelu (and likewise for ConvTranspose)
+            ["Conv" | "ConvTranspose", "Relu" | "LeakyRelu", ..] => {
                 let conv = chain[0].clone();
                 let relu = chain[1].clone();
 
@@ -767,12 +1327,12 @@ impl<'model> Optimizer<'model> {
                     // Use the Conv node as template for the new fused Conv[Leaky]Relu node
                     let mut convrelu_def = *conv_def.clone();
                     let mut convrelu_proto = conv_def.proto.clone().into_owned();
-                    let new_op_type = match relu_def.proto.get_op_type() {
-                        "LeakyRelu" => "ConvLeakyRelu",
-                        "Relu" => "ConvRelu",
-                        _ => unreachable!(),
-                    };
-                    convrelu_proto.set_op_type(new_op_type.to_string());
+                    let new_op_type = format!(
+                        "{}{}",
+                        conv_def.proto.get_op_type(),
+                        relu_def.proto.get_op_type()
+                    );
+                    convrelu_proto.set_op_type(new_op_type);
 
                     // Copy all Relu attributes over to the copy of the Conv node
                     let mut attributes = conv_def.proto.get_attribute().to_vec();
@@ -812,6 +1372,512 @@ impl<'model> Optimizer<'model> {
     }
 }
 
+/// Returns the operator node at the specified input, if it is of the specified type
+fn operator_at<'a, 'model>(
+    node: &'a Node<'model>,
+    input_index: usize,
+    op_type: &str,
+) -> Option<&'a Arc<Node<'model>>> {
+    let input = node.inputs.get(input_index)?;
+    match &input.source_node.definition {
+        NodeDefinition::Operator(op_def)
+            if op_def.proto.get_op_type() == op_type && input.output_index == 0 =>
+        {
+            Some(&input.source_node)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the initializer at the specified input
+fn initializer_at<'a>(node: &'a Node, input_index: usize) -> Option<&'a TensorProto> {
+    match &node.inputs.get(input_index)?.source_node.definition {
+        NodeDefinition::Tensor(tensor) => Some(tensor.as_ref().as_ref()),
+        _ => None,
+    }
+}
+
+/// For a node with two inputs of which one is the output of an operator of the specified type and the other is an
+/// initializer (in either order), returns the operator node and the index of the initializer input
+fn operator_and_initializer<'a, 'model>(
+    node: &'a Node<'model>,
+    op_type: &str,
+) -> Option<(&'a Arc<Node<'model>>, usize)> {
+    if node.inputs.len() != 2 {
+        return None;
+    }
+    match (operator_at(node, 0, op_type), initializer_at(node, 1)) {
+        (Some(operator), Some(_)) => Some((operator, 1)),
+        _ => match (operator_at(node, 1, op_type), initializer_at(node, 0)) {
+            (Some(operator), Some(_)) => Some((operator, 0)),
+            _ => None,
+        },
+    }
+}
+
+/// Returns the name of the specified input of an operator node
+fn input_name(node: &Node, input_index: usize) -> String {
+    match &node.definition {
+        NodeDefinition::Operator(op_def) => op_def.proto.get_input()[input_index].clone(),
+        _ => unreachable!(),
+    }
+}
+
+fn float_values(tensor: &TensorProto) -> Vec<f32> {
+    if tensor.get_float_data().is_empty() {
+        pod_collect_to_vec(tensor.get_raw_data())
+    } else {
+        tensor.get_float_data().to_vec()
+    }
+}
+
+fn int64_values(tensor: &TensorProto) -> Vec<i64> {
+    if tensor.get_int64_data().is_empty() {
+        pod_collect_to_vec(tensor.get_raw_data())
+    } else {
+        tensor.get_int64_data().to_vec()
+    }
+}
+
+/// Returns the shape of the value an input refers to, if known
+pub(crate) fn input_shape(input: &Input) -> Option<Shape> {
+    match &input.source_node.definition {
+        NodeDefinition::Input(value_info) => value_info.get_shape().ok(),
+        NodeDefinition::Operator(op_def) => op_def.output_shapes.get(input.output_index).cloned(),
+        NodeDefinition::Tensor(tensor) => Some(Shape::from(
+            ScalarType::from_i32(tensor.get_data_type()).ok()?,
+            tensor.get_dims(),
+        )),
+        NodeDefinition::Outputs { .. } | NodeDefinition::Missing => None,
+    }
+}
+
+/// A value that takes part in a lowered Einsum, along with the label of each of its axes
+struct EinsumOperand<'model> {
+    input: Input<'model>,
+    name: String,
+    labels: Vec<EinsumLabel>,
+    dims: Vec<u64>,
+}
+
+/// Lowers an Einsum node to a subgraph of Transpose, Reshape, Slice, ReduceSum, Mul and MatMul nodes. Each input is first
+/// reduced to a value in which each label occurs once and that only has labels that are needed elsewhere (by taking
+/// diagonals and sums). The inputs are then contracted pairwise, and the result is transposed to the order of the output.
+struct EinsumLowering<'a, 'model> {
+    op_def: &'a OperatorDefinition<'model>,
+    backend: Backend,
+    data_type: ScalarType,
+    node_count: usize,
+}
+
+impl<'a, 'model> EinsumLowering<'a, 'model> {
+    fn new(op_def: &'a OperatorDefinition<'model>, backend: Backend) -> Self {
+        EinsumLowering {
+            op_def,
+            backend,
+            data_type: op_def.output_shapes[0].data_type,
+            node_count: 0,
+        }
+    }
+
+    /// Returns the lowered subgraph, or None when the equation cannot be lowered
+    fn lower(
+        mut self,
+        inputs: &[Input<'model>],
+    ) -> Result<Option<Arc<Node<'model>>>, OptimizerError> {
+        let shapes = match inputs
+            .iter()
+            .map(input_shape)
+            .collect::<Option<Vec<Shape>>>()
+        {
+            Some(shapes) => shapes,
+            None => return Ok(None),
+        };
+        let ranks: Vec<usize> = shapes.iter().map(|shape| shape.rank()).collect();
+        let equation_text: String = self.op_def.proto.get_attribute_value("equation", None)?;
+        let equation = EinsumEquation::parse(&equation_text, &ranks)
+            .map_err(|e| OptimizerError::InvalidNode(format!("Einsum equation: {}", e)))?;
+
+        // Broadcasting a label of size one would require an Expand step
+        let input_dims: Vec<&[u64]> = shapes.iter().map(|shape| shape.dims.as_slice()).collect();
+        let sizes = equation
+            .label_sizes(&input_dims)
+            .map_err(|e| OptimizerError::InvalidNode(format!("Einsum equation: {}", e)))?;
+        let broadcasts = equation
+            .inputs
+            .iter()
+            .zip(input_dims.iter())
+            .any(|(labels, dims)| {
+                labels
+                    .iter()
+                    .zip(dims.iter())
+                    .any(|(label, dim)| sizes[label] != *dim)
+            });
+        if broadcasts {
+            return Ok(None);
+        }
+
+        let mut operands: Vec<EinsumOperand<'model>> = inputs
+            .iter()
+            .zip(self.op_def.proto.get_input().iter())
+            .zip(equation.inputs.iter().zip(shapes.iter()))
+            .map(|((input, name), (labels, shape))| EinsumOperand {
+                input: input.clone(),
+                name: name.clone(),
+                labels: labels.clone(),
+                dims: shape.dims.clone(),
+            })
+            .collect();
+
+        // Take diagonals for repeated labels, and sum over labels that are not needed elsewhere
+        for index in 0..operands.len() {
+            let mut operand = operands.remove(index);
+            while let Some(label) = operand
+                .labels
+                .iter()
+                .enumerate()
+                .find(|(axis, label)| operand.labels[0..*axis].contains(label))
+                .map(|(_, label)| *label)
+            {
+                operand = self.diagonal(operand, label);
+            }
+
+            let summed: Vec<EinsumLabel> = operand
+                .labels
+                .iter()
+                .filter(|label| {
+                    !equation.output.contains(label)
+                        && !operands.iter().any(|other| other.labels.contains(label))
+                })
+                .copied()
+                .collect();
+            if !summed.is_empty() {
+                operand = self.sum(operand, &summed);
+            }
+            operands.insert(index, operand);
+        }
+
+        // Contract the operands pairwise
+        let mut operands = operands.into_iter();
+        let mut result = operands.next().ok_or(OptimizerError::NoInputs)?;
+        let remaining: Vec<EinsumOperand<'model>> = operands.collect();
+        for (index, operand) in remaining.iter().enumerate() {
+            let needed: Vec<EinsumLabel> = equation
+                .output
+                .iter()
+                .chain(
+                    remaining[(index + 1)..]
+                        .iter()
+                        .flat_map(|o| o.labels.iter()),
+                )
+                .copied()
+                .collect();
+            result = match self.contract(result, operand, &needed) {
+                Some(product) => product,
+                None => return Ok(None),
+            };
+        }
+
+        let mut result = self.transpose(result, &equation.output);
+        if self.node_count == 0 {
+            result = self.add_node(
+                "Identity",
+                vec![],
+                &[&result],
+                result.labels.clone(),
+                result.dims.clone(),
+            );
+        }
+
+        // The last node produces the output of the Einsum node
+        let last = result.input.source_node;
+        let last_def = match &last.definition {
+            NodeDefinition::Operator(op_def) => op_def,
+            _ => unreachable!("lowered Einsum must end in an operator"),
+        };
+        let mut proto = last_def.proto.clone().into_owned();
+        proto.set_name(self.op_def.proto.get_name().to_string());
+        proto.set_output(RepeatedField::from(self.op_def.proto.get_output().to_vec()));
+        log::debug!(
+            "lowered Einsum {} with equation {} to {} nodes",
+            self.op_def.proto.get_name(),
+            equation_text,
+            self.node_count
+        );
+        Ok(Some(Arc::new(Node {
+            inputs: last.inputs.clone(),
+            definition: NodeDefinition::Operator(Box::new(OperatorDefinition {
+                proto: Cow::Owned(proto),
+                output_shapes: self.op_def.output_shapes.clone(),
+            })),
+        })))
+    }
+
+    /// Adds a node that performs the indicated op on the operands, and returns its output
+    fn add_node(
+        &mut self,
+        op_type: &str,
+        attributes: Vec<AttributeProto>,
+        inputs: &[&EinsumOperand<'model>],
+        labels: Vec<EinsumLabel>,
+        dims: Vec<u64>,
+    ) -> EinsumOperand<'model> {
+        self.node_count += 1;
+        let name = format!(
+            "{}_einsum_{}",
+            self.op_def.proto.get_output()[0],
+            self.node_count
+        );
+        let proto = node(
+            inputs.iter().map(|input| input.name.as_str()).collect(),
+            vec![&name],
+            &format!("{}_{}", self.op_def.proto.get_name(), self.node_count),
+            op_type,
+            attributes,
+        );
+        let shape = Shape::from(
+            self.data_type,
+            &dims.iter().map(|dim| *dim as i64).collect::<Vec<i64>>(),
+        );
+        EinsumOperand {
+            input: Input {
+                source_node: Arc::new(Node {
+                    inputs: inputs.iter().map(|input| input.input.clone()).collect(),
+                    definition: NodeDefinition::Operator(Box::new(OperatorDefinition {
+                        proto: Cow::Owned(proto),
+                        output_shapes: vec![shape],
+                    })),
+                }),
+                output_index: 0,
+            },
+            name,
+            labels,
+            dims,
+        }
+    }
+
+    /// Transposes the operand so that its axes have the specified labels (in order)
+    fn transpose(
+        &mut self,
+        operand: EinsumOperand<'model>,
+        labels: &[EinsumLabel],
+    ) -> EinsumOperand<'model> {
+        if operand.labels == labels {
+            return operand;
+        }
+        let perm: Vec<usize> = labels
+            .iter()
+            .map(|label| operand.labels.iter().position(|l| l == label).unwrap())
+            .collect();
+        let dims = perm.iter().map(|axis| operand.dims[*axis]).collect();
+        self.add_node(
+            "Transpose",
+            vec![attribute(
+                "perm",
+                perm.iter().map(|axis| *axis as i64).collect::<Vec<i64>>(),
+            )],
+            &[&operand],
+            labels.to_vec(),
+            dims,
+        )
+    }
+
+    /// Reshapes the operand to the specified dimensions, after which its axes have the specified labels
+    fn reshape(
+        &mut self,
+        operand: EinsumOperand<'model>,
+        labels: Vec<EinsumLabel>,
+        dims: Vec<u64>,
+    ) -> EinsumOperand<'model> {
+        if operand.dims == dims {
+            return EinsumOperand { labels, ..operand };
+        }
+        self.add_node("Reshape", vec![], &[&operand], labels, dims)
+    }
+
+    /// Takes the diagonal of the first two axes with the specified label, by moving these axes to the end, flattening
+    /// them and selecting every (n+1)th element
+    fn diagonal(
+        &mut self,
+        operand: EinsumOperand<'model>,
+        label: EinsumLabel,
+    ) -> EinsumOperand<'model> {
+        let first = operand.labels.iter().position(|l| *l == label).unwrap();
+        let mut labels = operand.labels.clone();
+        labels.remove(first);
+        let second = labels.iter().position(|l| *l == label).unwrap();
+        labels.remove(second);
+        let outer_labels = labels.clone();
+        labels.extend([label, label]);
+
+        let size = operand.dims[first];
+        let operand = self.transpose_axes(operand, &labels, first, second + 1);
+        let mut flat_dims = operand.dims[0..(operand.dims.len() - 2)].to_vec();
+        flat_dims.push(size * size);
+        let mut diagonal_labels = outer_labels;
+        diagonal_labels.push(label);
+        let flat = self.reshape(operand, diagonal_labels.clone(), flat_dims.clone());
+
+        let mut diagonal_dims = flat_dims;
+        *diagonal_dims.last_mut().unwrap() = size;
+        self.add_node(
+            "Slice",
+            vec![
+                attribute("starts", vec![0]),
+                attribute("ends", vec![(size * size) as i64]),
+                attribute("axes", vec![diagonal_dims.len() as i64 - 1]),
+                attribute("steps", vec![size as i64 + 1]),
+            ],
+            &[&flat],
+            diagonal_labels,
+            diagonal_dims,
+        )
+    }
+
+    /// Moves the axes at the indicated positions to the end (the operand may have repeated labels, so the permutation
+    /// cannot be derived from the labels)
+    fn transpose_axes(
+        &mut self,
+        operand: EinsumOperand<'model>,
+        labels: &[EinsumLabel],
+        first: usize,
+        second: usize,
+    ) -> EinsumOperand<'model> {
+        let rank = operand.dims.len();
+        if first == rank - 2 && second == rank - 1 {
+            return operand;
+        }
+        let mut perm: Vec<usize> = (0..rank).filter(|a| *a != first && *a != second).collect();
+        perm.extend([first, second]);
+        let dims = perm.iter().map(|axis| operand.dims[*axis]).collect();
+        self.add_node(
+            "Transpose",
+            vec![attribute(
+                "perm",
+                perm.iter().map(|axis| *axis as i64).collect::<Vec<i64>>(),
+            )],
+            &[&operand],
+            labels.to_vec(),
+            dims,
+        )
+    }
+
+    /// Sums the operand over the axes with the specified labels
+    fn sum(
+        &mut self,
+        operand: EinsumOperand<'model>,
+        summed: &[EinsumLabel],
+    ) -> EinsumOperand<'model> {
+        let mut axes = vec![];
+        let mut labels = vec![];
+        let mut dims = vec![];
+        for (axis, (label, dim)) in operand.labels.iter().zip(operand.dims.iter()).enumerate() {
+            if summed.contains(label) {
+                axes.push(axis as i64);
+            } else {
+                labels.push(*label);
+                dims.push(*dim);
+            }
+        }
+        self.add_node(
+            "ReduceSum",
+            vec![attribute("axes", axes), attribute("keepdims", 0)],
+            &[&operand],
+            labels,
+            dims,
+        )
+    }
+
+    /// Multiplies two operands and sums over the labels they share that are not needed afterwards. When there is nothing
+    /// to sum over, the operands are multiplied element-wise (with broadcasting). Otherwise they are transposed and
+    /// reshaped to (stacks of) matrices so that a single MatMul performs the contraction. Returns None when the available
+    /// MatMul implementation cannot multiply the matrices.
+    fn contract(
+        &mut self,
+        left: EinsumOperand<'model>,
+        right: &EinsumOperand<'model>,
+        needed: &[EinsumLabel],
+    ) -> Option<EinsumOperand<'model>> {
+        let select = |operand: &EinsumOperand, filter: &dyn Fn(&EinsumLabel) -> bool| {
+            operand
+                .labels
+                .iter()
+                .filter(|label| filter(label))
+                .copied()
+                .collect::<Vec<EinsumLabel>>()
+        };
+        let shared = |label: &EinsumLabel| right.labels.contains(label);
+        let batch = select(&left, &|label| shared(label) && needed.contains(label));
+        let contracted = select(&left, &|label| shared(label) && !needed.contains(label));
+        let left_only = select(&left, &|label| !shared(label));
+        let right_only = select(right, &|label| !left.labels.contains(label));
+
+        let size = |operand: &EinsumOperand, label: &EinsumLabel| {
+            operand.dims[operand.labels.iter().position(|l| l == label).unwrap()]
+        };
+        let batch_dims: Vec<u64> = batch.iter().map(|l| size(&left, l)).collect();
+        let left_only_dims: Vec<u64> = left_only.iter().map(|l| size(&left, l)).collect();
+        let right_only_dims: Vec<u64> = right_only.iter().map(|l| size(right, l)).collect();
+        let right = EinsumOperand {
+            input: right.input.clone(),
+            name: right.name.clone(),
+            labels: right.labels.clone(),
+            dims: right.dims.clone(),
+        };
+        let labels = [&batch[..], &left_only, &right_only].concat();
+        let dims = [&batch_dims[..], &left_only_dims, &right_only_dims].concat();
+
+        if contracted.is_empty() {
+            // Insert axes of size one so that the operands broadcast to the dimensions of the product
+            let left_operand = self.transpose(left, &[&batch[..], &left_only].concat());
+            let left_dims = [&batch_dims[..], &left_only_dims, &vec![1; right_only.len()]].concat();
+            let left_operand = self.reshape(left_operand, vec![], left_dims);
+            let right_operand = self.transpose(right, &[&batch[..], &right_only].concat());
+            let right_dims =
+                [&batch_dims[..], &vec![1; left_only.len()], &right_only_dims].concat();
+            let right_operand = self.reshape(right_operand, vec![], right_dims);
+            return Some(self.add_node(
+                "Mul",
+                vec![],
+                &[&left_operand, &right_operand],
+                labels,
+                dims,
+            ));
+        }
+
+        let m: u64 = left_only_dims.iter().product();
+        let k: u64 = contracted.iter().map(|l| size(&left, l)).product();
+        let n: u64 = right_only_dims.iter().product();
+        if !self.data_type.is_float() || !self.supports_matmul(m, k, n) {
+            return None;
+        }
+        let matrix_dims = |rows: u64, columns: u64| [&batch_dims[..], &[rows, columns]].concat();
+
+        // After reshaping, the rows and columns of the matrices each represent several labels
+        let left_matrix = self.transpose(left, &[&batch[..], &left_only, &contracted].concat());
+        let left_matrix = self.reshape(left_matrix, vec![], matrix_dims(m, k));
+        let right_matrix = self.transpose(right, &[&batch[..], &contracted, &right_only].concat());
+        let right_matrix = self.reshape(right_matrix, vec![], matrix_dims(k, n));
+        let product = self.add_node(
+            "MatMul",
+            vec![],
+            &[&left_matrix, &right_matrix],
+            vec![],
+            matrix_dims(m, n),
+        );
+        Some(self.reshape(product, labels, dims))
+    }
+
+    /// Whether MatMul can multiply an MxK by a KxN matrix. The GPU implementation processes the matrices in blocks, and
+    /// requires that the dimensions are divisible by the block size (unless M is 1).
+    fn supports_matmul(&self, m: u64, k: u64, n: u64) -> bool {
+        let block_size = gcd(m, gcd(k, n));
+        self.backend == Backend::Cpu
+            || m == 1
+            || (block_size >= 2 && (block_size <= 4 || block_size.is_multiple_of(4)))
+    }
+}
+
 // Names associated with the inputs of the Split, Resize, Reshape and Clip operators (in positional order - see ONNX spec)
 static SPLIT_INPUT_NAMES: &[&str] = &["input", "split"];
 static RESIZE_INPUT_NAMES: &[&str] = &["X", "roi", "scales", "sizes"];
@@ -819,6 +1885,10 @@ static RESHAPE_INPUT_NAMES: &[&str] = &["data", "shape"];
 static CLIP_INPUT_NAMES: &[&str] = &["input", "min", "max"];
 static REDUCE_OPS_INPUT_NAMES: &[&str] = &["input", "axes"];
 static PAD_INPUT_NAMES: &[&str] = &["data", "pads", "constant_value"];
+static SLICE_INPUT_NAMES: &[&str] = &["data", "starts", "ends", "axes", "steps"];
+static EXPAND_INPUT_NAMES: &[&str] = &["input", "shape"];
+static TILE_INPUT_NAMES: &[&str] = &["input", "repeats"];
+static TOPK_INPUT_NAMES: &[&str] = &["X", "K"];
 
 /// Generate the output for a ConstantOfShape node
 pub fn constant_of_shape_output(
@@ -841,6 +1911,15 @@ pub fn constant_of_shape_output(
                 }
                 Ok(OutputTensor::F32(vec![fd[0]; element_count]))
             }
+            ScalarType::F16 => {
+                let fd = float16_data(&constant_value_tensor);
+                if fd.is_empty() {
+                    return Err(OptimizerError::InvalidNode(
+                        "value tensor for ConstantOfShape is empty".to_string(),
+                    ));
+                }
+                Ok(OutputTensor::F16(vec![fd[0]; element_count]))
+            }
             ScalarType::I64 => {
                 let fd = constant_value_tensor.get_int64_data();
                 if fd.is_empty() {
@@ -868,6 +1947,24 @@ pub fn constant_of_shape_output(
                 }
                 Ok(OutputTensor::U8(vec![fd[0]; element_count]))
             }
+            ScalarType::I8 => {
+                let fd = int8_data(&constant_value_tensor);
+                if fd.is_empty() {
+                    return Err(OptimizerError::InvalidNode(
+                        "value tensor for ConstantOfShape is empty".to_string(),
+                    ));
+                }
+                Ok(OutputTensor::I8(vec![fd[0]; element_count]))
+            }
+            ScalarType::Bool => {
+                let fd = bool_data(&constant_value_tensor);
+                if fd.is_empty() {
+                    return Err(OptimizerError::InvalidNode(
+                        "value tensor for ConstantOfShape is empty".to_string(),
+                    ));
+                }
+                Ok(OutputTensor::Bool(vec![fd[0]; element_count]))
+            }
         }
     } else {
         // The default value is a zero f32
@@ -882,7 +1979,12 @@ mod test {
     use crate::{
         ir::{self, Node, NodeDefinition},
         onnx::AttributeProto,
-        utils::{attribute, graph, initializer, model, node, tensor},
+        onnx::{TensorProto, TensorProto_DataType},
+        utils::{
+            attribute, graph, initializer, initializer_int64, model, node, tensor, tensor_of_type,
+            OutputTensor,
+        },
+        Backend,
     };
 
     use super::Optimizer;
@@ -1270,4 +2372,297 @@ mod test {
             assert_eq!(t.get_int64_data(), expected);
         });
     }
+
+    fn int_initializer(name: &str, data: OutputTensor, dims: Vec<i64>) -> TensorProto {
+        let mut tensor = TensorProto::from(data, dims);
+        tensor.set_name(name.to_string());
+        tensor
+    }
+
+    // Test: X -> [DequantizeLinear] -> [Conv] -> [QuantizeLinear] -> Y with quantized weights => X -> [QLinearConv] -> Y
+    #[test]
+    pub fn test_optimize_qdq_conv() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        pollster::block_on(async {
+            let m = model(graph(
+                vec![tensor_of_type(
+                    "X",
+                    &[1, 1, 3, 3],
+                    TensorProto_DataType::UINT8,
+                )],
+                vec![tensor_of_type(
+                    "Y",
+                    &[1, 2, 2, 2],
+                    TensorProto_DataType::UINT8,
+                )],
+                vec![
+                    tensor("XD", &[1, 1, 3, 3]),
+                    tensor("WD", &[2, 1, 2, 2]),
+                    tensor("C", &[1, 2, 2, 2]),
+                ],
+                vec![
+                    initializer("xs", vec![0.5], vec![]),
+                    int_initializer("xz", OutputTensor::U8(vec![2]), vec![]),
+                    int_initializer("W", OutputTensor::I8(vec![1; 8]), vec![2, 1, 2, 2]),
+                    initializer("ws", vec![0.25, 0.5], vec![2]),
+                    int_initializer("wz", OutputTensor::I8(vec![0, 0]), vec![2]),
+                    initializer("ys", vec![0.25], vec![]),
+                    int_initializer("yz", OutputTensor::U8(vec![10]), vec![]),
+                ],
+                vec![
+                    node(
+                        vec!["X", "xs", "xz"],
+                        vec!["XD"],
+                        "dx",
+                        "DequantizeLinear",
+                        vec![],
+                    ),
+                    node(
+                        vec!["W", "ws", "wz"],
+                        vec!["WD"],
+                        "dw",
+                        "DequantizeLinear",
+                        vec![attribute("axis", 0)],
+                    ),
+                    node(
+                        vec!["XD", "WD"],
+                        vec!["C"],
+                        "c",
+                        "Conv",
+                        vec![attribute("kernel_shape", vec![2, 2])],
+                    ),
+                    node(
+                        vec!["C", "ys", "yz"],
+                        vec!["Y"],
+                        "q",
+                        "QuantizeLinear",
+                        vec![],
+                    ),
+                ],
+            ));
+
+            let root = ir::Node::from_model(&m, None).unwrap();
+            let mut opt = Optimizer::new(13).with_backend(Backend::Cpu);
+            let new_root = opt.optimize(root).await.unwrap();
+            let mut new_pairs = vec![];
+            traverse(new_root, &mut new_pairs);
+            assert_eq!(
+                new_pairs[0],
+                ("QLinearConv_c+q".to_string(), "<outputs>".to_string())
+            );
+            assert_eq!(
+                new_pairs[1],
+                ("X".to_string(), "QLinearConv_c+q".to_string())
+            );
+            assert!(!new_pairs.iter().any(|(from, _)| from.contains("Linear_")));
+        });
+    }
+
+    // Test: X -> [ReduceMean] -> [Sub] -> [Pow] -> [ReduceMean] -> [Add] -> [Sqrt] -> [Div] -> [Mul] -> [Add] -> Y
+    // => X -> [LayerNormalization] -> Y
+    #[test]
+    pub fn test_optimize_decomposed_layer_normalization() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        pollster::block_on(async {
+            let m = model(graph(
+                vec![tensor("X", &[2, 4])],
+                vec![tensor("Y", &[2, 4])],
+                vec![
+                    tensor("M", &[2, 1]),
+                    tensor("D", &[2, 4]),
+                    tensor("P", &[2, 4]),
+                    tensor("V", &[2, 1]),
+                    tensor("VE", &[2, 1]),
+                    tensor("S", &[2, 1]),
+                    tensor("N", &[2, 4]),
+                    tensor("NS", &[2, 4]),
+                ],
+                vec![
+                    initializer("two", vec![2.0], vec![]),
+                    initializer("epsilon", vec![1e-5], vec![]),
+                    initializer("scale", vec![1.0, 2.0, 3.0, 4.0], vec![4]),
+                    initializer("bias", vec![0.5], vec![1]),
+                ],
+                vec![
+                    node(
+                        vec!["X"],
+                        vec!["M"],
+                        "mean",
+                        "ReduceMean",
+                        vec![attribute("axes", vec![-1])],
+                    ),
+                    node(vec!["X", "M"], vec!["D"], "sub", "Sub", vec![]),
+                    node(vec!["D", "two"], vec!["P"], "pow", "Pow", vec![]),
+                    node(
+                        vec!["P"],
+                        vec!["V"],
+                        "variance",
+                        "ReduceMean",
+                        vec![attribute("axes", vec![1])],
+                    ),
+                    node(vec!["V", "epsilon"], vec!["VE"], "add", "Add", vec![]),
+                    node(vec!["VE"], vec!["S"], "sqrt", "Sqrt", vec![]),
+                    node(vec!["D", "S"], vec!["N"], "div", "Div", vec![]),
+                    node(vec!["N", "scale"], vec!["NS"], "mul", "Mul", vec![]),
+                    node(vec!["bias", "NS"], vec!["Y"], "add_bias", "Add", vec![]),
+                ],
+            ));
+
+            let root = ir::Node::from_model(&m, None).unwrap();
+            let mut opt = Optimizer::new(13).with_backend(Backend::Cpu);
+            let new_root = opt.optimize(root).await.unwrap();
+            let mut new_pairs = vec![];
+            traverse(new_root, &mut new_pairs);
+            assert_eq!(
+                new_pairs,
+                vec![
+                    (
+                        "LayerNormalization_div+add_bias".to_string(),
+                        "<outputs>".to_string()
+                    ),
+                    (
+                        "X".to_string(),
+                        "LayerNormalization_div+add_bias".to_string()
+                    ),
+                    (
+                        "scale".to_string(),
+                        "LayerNormalization_div+add_bias".to_string()
+                    ),
+                    (
+                        "bias".to_string(),
+                        "LayerNormalization_div+add_bias".to_string()
+                    ),
+                ]
+            );
+        });
+    }
+
+    // Test: X -> [Neg] A -> [ScatterND] -> Y => ScatterND updates A in place, unless A is also read elsewhere
+    #[test]
+    pub fn test_optimize_scatter_nd_in_place() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        for (a_is_output, expected_op) in [(false, "ScatterNDInPlace"), (true, "ScatterND")] {
+            pollster::block_on(async {
+                let mut outputs = vec![tensor("Y", &[2, 2])];
+                if a_is_output {
+                    outputs.push(tensor("A", &[2, 2]));
+                }
+                let m = model(graph(
+                    vec![tensor("X", &[2, 2])],
+                    outputs,
+                    vec![tensor("A", &[2, 2])],
+                    vec![
+                        initializer_int64("I", vec![1], vec![1, 1]),
+                        initializer("U", vec![5.0, 6.0], vec![1, 2]),
+                    ],
+                    vec![
+                        node(vec!["X"], vec!["A"], "neg", "Neg", vec![]),
+                        node(
+                            vec!["A", "I", "U"],
+                            vec!["Y"],
+                            "scatter",
+                            "ScatterND",
+                            vec![],
+                        ),
+                    ],
+                ));
+
+                let root = ir::Node::from_model(&m, None).unwrap();
+                let mut opt = Optimizer::new(13);
+                let new_root = opt.optimize(root).await.unwrap();
+                let mut new_pairs = vec![];
+                traverse(new_root, &mut new_pairs);
+                let scatter_name = format!("{}_scatter", expected_op);
+                assert!(new_pairs.contains(&("Neg_neg".to_string(), scatter_name.clone())));
+                assert!(new_pairs.contains(&(scatter_name, "<outputs>".to_string())));
+            });
+        }
+    }
+
+    // Test: Einsum with equations that can be expressed using other operators is lowered to these operators
+    #[test]
+    pub fn test_optimize_einsum() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let cases: [(&str, Vec<Vec<i64>>, Vec<i64>, Vec<(&str, &str)>); 3] = [
+            // Batched matrix multiplication
+            (
+                "bij,bjk->bik",
+                vec![vec![2, 2, 4], vec![2, 4, 2]],
+                vec![2, 2, 2],
+                vec![
+                    ("X0", "MatMul_einsum"),
+                    ("X1", "MatMul_einsum"),
+                    ("MatMul_einsum", "<outputs>"),
+                ],
+            ),
+            // Trace: take the diagonal, then sum it
+            (
+                "ii->",
+                vec![vec![3, 3]],
+                vec![],
+                vec![
+                    ("X0", "Reshape_einsum_1"),
+                    ("Reshape_einsum_1", "Slice_einsum_2"),
+                    ("Slice_einsum_2", "ReduceSum_einsum"),
+                    ("ReduceSum_einsum", "<outputs>"),
+                ],
+            ),
+            // Outer product
+            (
+                "i,j->ij",
+                vec![vec![3], vec![4]],
+                vec![3, 4],
+                vec![
+                    ("X0", "Reshape_einsum_1"),
+                    ("X1", "Reshape_einsum_2"),
+                    ("Reshape_einsum_1", "Mul_einsum"),
+                    ("Reshape_einsum_2", "Mul_einsum"),
+                    ("Mul_einsum", "<outputs>"),
+                ],
+            ),
+        ];
+
+        for (equation, input_dims, output_dims, expected_pairs) in cases {
+            pollster::block_on(async {
+                let names: Vec<String> = (0..input_dims.len()).map(|i| format!("X{}", i)).collect();
+                let m = model(graph(
+                    names
+                        .iter()
+                        .zip(input_dims.iter())
+                        .map(|(name, dims)| tensor(name, dims))
+                        .collect(),
+                    vec![tensor("Y", &output_dims)],
+                    vec![],
+                    vec![],
+                    vec![node(
+                        names.iter().map(|name| name.as_str()).collect(),
+                        vec!["Y"],
+                        "einsum",
+                        "Einsum",
+                        vec![attribute("equation", equation)],
+                    )],
+                ));
+
+                let root = ir::Node::from_model(&m, None).unwrap();
+                let mut opt = Optimizer::new(13);
+                let new_root = opt.optimize(root).await.unwrap();
+                let mut new_pairs = vec![];
+                traverse(new_root, &mut new_pairs);
+                for (from, to) in expected_pairs {
+                    assert!(
+                        new_pairs.contains(&(from.to_string(), to.to_string())),
+                        "{}: {:?} does not contain {} -> {}",
+                        equation,
+                        new_pairs,
+                        from,
+                        to
+                    );
+                }
+            });
+        }
+    }
 }
@@ -0,0 +1,293 @@
+//! Tracking of the rows of padding in values that follow from the output of an op with a data-dependent number of
+//! results (see `op_has_data_dependent_output`). As the shapes of all tensors are fixed when the model is built, such an
+//! output has the maximum number of rows, of which only the first are produced and the rest are filled with -1. Nodes
+//! that use these values are executed as usual (computing results for the rows of padding as well), as long as the rows
+//! stay apart from each other. The axis that corresponds to the rows is tracked through these nodes, so that the results
+//! for the rows of padding can be removed when values are read back.
+use std::{collections::HashMap, sync::Arc};
+
+use thiserror::Error;
+
+use crate::{
+    compiler::slice_ranges,
+    gpu::{op_forwards_input, op_has_data_dependent_output},
+    ir::{Node, NodeDefinition, NodeIdentifier},
+    onnx::NodeProto,
+    optimizer::input_shape,
+    utils::{NodeAttributes, OutputTensor, Shape, TypedOutputTensor},
+};
+
+/// Identifies an output of a node by the node and the index of the output
+pub(crate) type NodeOutput<'model> = (NodeIdentifier<'model>, usize);
+
+/// Describes which part of a value consists of padding
+#[derive(Clone, Debug)]
+pub(crate) struct PaddedRows<'model> {
+    /// The output of the op with a data-dependent number of results from which the number of produced rows follows
+    pub(crate) source: NodeOutput<'model>,
+
+    /// The axis of the value along which there is one entry for each row of the source
+    pub(crate) axis: usize,
+}
+
+#[derive(Error, Debug)]
+pub enum PaddingError {
+    #[error("node '{0}' uses a value with a data-dependent number of rows (e.g. the output of NonMaxSuppression) in a way that mixes its rows, which is not supported")]
+    UnsupportedUse(String),
+}
+
+/// Determines which node outputs contain rows of padding, given the nodes of a graph in order of execution. Fails when a
+/// node combines the rows of such a value (e.g. reduces over them), as the results would then depend on the padding.
+#[allow(clippy::mutable_key_type)]
+pub(crate) fn padded_values<'model>(
+    nodes: &[Arc<Node<'model>>],
+    opset_version: i64,
+) -> Result<HashMap<NodeOutput<'model>, PaddedRows<'model>>, PaddingError> {
+    let mut padded: HashMap<NodeOutput, PaddedRows> = HashMap::new();
+    for node in nodes {
+        let op_def = match &node.definition {
+            NodeDefinition::Operator(op_def) => op_def,
+            _ => continue,
+        };
+        let proto = &op_def.proto;
+        let input_rows: Vec<Option<&PaddedRows>> = node
+            .inputs
+            .iter()
+            .map(|input| padded.get(&(input.source_node.identifier(), input.output_index)))
+            .collect();
+        let unsupported = || {
+            PaddingError::UnsupportedUse(if proto.has_name() {
+                proto.get_name().to_string()
+            } else {
+                proto.get_op_type().to_string()
+            })
+        };
+
+        if op_has_data_dependent_output(proto.get_op_type()) {
+            if input_rows.iter().any(Option::is_some) {
+                return Err(unsupported());
+            }
+            for output_index in 0..op_def.output_shapes.len() {
+                let output = (node.identifier(), output_index);
+                padded.insert(
+                    output.clone(),
+                    PaddedRows {
+                        source: output,
+                        axis: 0,
+                    },
+                );
+            }
+            continue;
+        }
+
+        // All padded inputs need to follow from the same source, as the number of rows may differ between sources
+        let mut source = None;
+        for rows in input_rows.iter().flatten() {
+            match &source {
+                None => source = Some(rows.source.clone()),
+                Some(source) if *source == rows.source => {}
+                Some(_) => return Err(unsupported()),
+            }
+        }
+        let source = match source {
+            Some(source) => source,
+            None => continue,
+        };
+
+        // Only the first output is tracked (the other outputs of the supported ops are optional and rarely used)
+        if proto
+            .get_output()
+            .iter()
+            .skip(1)
+            .any(|name| !name.is_empty())
+        {
+            return Err(unsupported());
+        }
+
+        let input_shapes: Vec<Option<Shape>> = node.inputs.iter().map(input_shape).collect();
+        let input_axes: Vec<Option<usize>> = input_rows
+            .iter()
+            .map(|rows| rows.map(|rows| rows.axis))
+            .collect();
+        let axis = output_axis(
+            proto,
+            &input_shapes,
+            &input_axes,
+            &op_def.output_shapes[0],
+            opset_version,
+        )
+        .ok_or_else(unsupported)?;
+        padded.insert((node.identifier(), 0), PaddedRows { source, axis });
+    }
+    Ok(padded)
+}
+
+/// Returns the axis of the output of a node that corresponds to the rows of its padded inputs (of which the axes are
+/// given), or None if the node mixes these rows or is not known to keep them apart
+fn output_axis(
+    proto: &NodeProto,
+    input_shapes: &[Option<Shape>],
+    input_axes: &[Option<usize>],
+    output_shape: &Shape,
+    opset_version: i64,
+) -> Option<usize> {
+    let op_type = proto.get_op_type();
+    match op_type {
+        _ if op_forwards_input(op_type) => {
+            // The rows are kept apart when the axis keeps its size and the axes before it have the same number of elements
+            let (input_shape, axis) = (input_shapes[0].as_ref()?, input_axes[0]?);
+            let elements_before: u64 = input_shape.dims[..axis].iter().product();
+            (0..output_shape.rank()).find(|output_axis| {
+                output_shape.dims[..*output_axis].iter().product::<u64>() == elements_before
+                    && output_shape.dims[*output_axis] == input_shape.dims[axis]
+            })
+        }
+
+        "Abs" | "Acos" | "Add" | "And" | "Asin" | "Atan" | "Cast" | "Ceil" | "Clip" | "Cos"
+        | "Div" | "Elu" | "Equal" | "Erf" | "Exp" | "Floor" | "Greater" | "GreaterOrEqual"
+        | "HardSigmoid" | "LeakyRelu" | "Less" | "LessOrEqual" | "Log" | "Mod" | "Mul" | "Neg"
+        | "Not" | "Or" | "Pow" | "Reciprocal" | "Relu" | "Round" | "Sigmoid" | "Sign" | "Sin"
+        | "Softplus" | "Softsign" | "Sqrt" | "Sub" | "Tan" | "Tanh" | "Where" | "Xor" => {
+            // Inputs are aligned to the right when broadcasting; the rows may not be broadcast
+            let mut result = None;
+            for (shape, axis) in input_shapes.iter().zip(input_axes) {
+                if let (Some(shape), Some(axis)) = (shape, axis) {
+                    let output_axis = axis + output_shape.rank() - shape.rank();
+                    if shape.dims[*axis] != output_shape.dims[output_axis]
+                        || result.unwrap_or(output_axis) != output_axis
+                    {
+                        return None;
+                    }
+                    result = Some(output_axis);
+                }
+            }
+            result
+        }
+
+        "Gather" => {
+            let data_rank = input_shapes[0].as_ref()?.rank();
+            let index_rank = input_shapes[1].as_ref()?.rank();
+            let gather_axis = proto.get_attribute_value("axis", Some(0)).ok()?;
+            let gather_axis = if gather_axis < 0 {
+                gather_axis + data_rank as i64
+            } else {
+                gather_axis
+            } as usize;
+            match (input_axes[0], input_axes[1]) {
+                (None, Some(axis)) => Some(gather_axis + axis),
+                (Some(axis), None) if axis < gather_axis => Some(axis),
+                (Some(axis), None) if axis > gather_axis => Some(axis + index_rank - 1),
+                _ => None,
+            }
+        }
+
+        "Slice" => {
+            // The rows are kept when the axis is not sliced
+            let axis = input_axes[0]?;
+            let ranges = slice_ranges(
+                proto,
+                input_shapes[0].as_ref()?,
+                output_shape,
+                opset_version,
+            )
+            .ok()?;
+            (ranges[axis] == (0, 1)
+                && input_shapes[0].as_ref()?.dims[axis] == output_shape.dims[axis])
+                .then_some(axis)
+        }
+
+        "Concat" => {
+            let rank = output_shape.rank() as i64;
+            let concat_axis: i64 = proto.get_attribute_value("axis", None).ok()?;
+            let concat_axis = if concat_axis < 0 {
+                concat_axis + rank
+            } else {
+                concat_axis
+            } as usize;
+            let mut axes = input_axes.iter().flatten();
+            let axis = *axes.next()?;
+            (axis != concat_axis && axes.all(|other| *other == axis)).then_some(axis)
+        }
+
+        "Transpose" => {
+            let rank = output_shape.rank() as i64;
+            let perm: Vec<i64> = proto
+                .get_attribute_value("perm", Some((0..rank).rev().collect()))
+                .ok()?;
+            let axis = input_axes[0]? as i64;
+            perm.iter().position(|input_axis| *input_axis == axis)
+        }
+
+        // Each region of interest results in one entry of the output
+        "RoiAlign"
+            if input_axes[0].is_none()
+                && input_axes[1..].iter().flatten().all(|axis| *axis == 0) =>
+        {
+            Some(0)
+        }
+
+        _ => None,
+    }
+}
+
+/// The number of rows that were produced by an op with a data-dependent number of results. The produced rows come first;
+/// rows of padding start with -1.
+pub(crate) fn produced_rows(data: &OutputTensor, shape: &Shape) -> usize {
+    let row_length = (shape.dims[1..].iter().product::<u64>() as usize).max(1);
+    match data {
+        OutputTensor::I64(v) => v
+            .iter()
+            .step_by(row_length)
+            .take_while(|x| **x >= 0)
+            .count(),
+        OutputTensor::I32(v) => v
+            .iter()
+            .step_by(row_length)
+            .take_while(|x| **x >= 0)
+            .count(),
+        // The ops with a data-dependent number of results produce integer indices
+        _ => shape.dims.first().copied().unwrap_or(0) as usize,
+    }
+}
+
+/// Removes the entries for rows of padding from a value in place, keeping the first `row_count` entries along `axis`
+pub(crate) fn remove_padding(
+    data: &mut OutputTensor,
+    shape: &Shape,
+    axis: usize,
+    row_count: usize,
+) {
+    let dim = shape.dims[axis] as usize;
+    let inner = shape.dims[(axis + 1)..].iter().product::<u64>() as usize;
+    fn retain<T>(values: &mut Vec<T>, dim: usize, inner: usize, row_count: usize) {
+        let mut index = 0;
+        values.retain(|_| {
+            let keep = (index / inner.max(1)) % dim.max(1) < row_count;
+            index += 1;
+            keep
+        });
+    }
+
+    match data {
+        OutputTensor::F32(v) => retain(v, dim, inner, row_count),
+        OutputTensor::F16(v) => retain(v, dim, inner, row_count),
+        OutputTensor::I32(v) => retain(v, dim, inner, row_count),
+        OutputTensor::I64(v) => retain(v, dim, inner, row_count),
+        OutputTensor::U8(v) => retain(v, dim, inner, row_count),
+        OutputTensor::I8(v) => retain(v, dim, inner, row_count),
+        OutputTensor::Bool(v) => retain(v, dim, inner, row_count),
+    }
+}
+
+/// Removes the entries for rows of padding from an output, adjusting its shape accordingly
+pub(crate) fn without_padding(
+    mut output: TypedOutputTensor,
+    axis: usize,
+    row_count: usize,
+) -> TypedOutputTensor {
+    remove_padding(&mut output.data, &output.shape, axis, row_count);
+    let mut dims: Vec<i64> = output.shape.dims.iter().map(|dim| *dim as i64).collect();
+    dims[axis] = row_count as i64;
+    output.shape = Shape::from(output.shape.data_type, &dims);
+    output
+}
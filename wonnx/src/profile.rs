@@ -0,0 +1,46 @@
+//! Per-node timing information obtained by profiling inference on the GPU
+use std::time::Duration;
+
+use crate::utils::Shape;
+
+/// The way in which the durations in a [ProfileReport] were measured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimingMethod {
+    /// Durations were measured on the GPU using timestamp queries written at the start and end of each compute pass
+    TimestampQueries,
+
+    /// Each step was submitted to the GPU separately and the time until completion was measured on the host. This
+    /// includes submission overhead and is therefore less accurate for short steps.
+    StepSubmission,
+}
+
+/// Timing information for a single node (GPU operator step) in a model
+#[derive(Clone, Debug)]
+pub struct NodeProfile {
+    pub node_name: String,
+    pub op_type: String,
+    pub output_shapes: Vec<Shape>,
+
+    /// The number of workgroups dispatched in each dimension (zero for nodes performed on the host)
+    pub dispatch: (u32, u32, u32),
+    pub duration: Duration,
+
+    /// Whether the node was performed on the host instead of the GPU (e.g. because its output shape depends on the data).
+    /// The duration of such a node is always measured on the host and includes reading back its inputs and writing its
+    /// outputs. It cannot be measured when running on WebAssembly, in which case it is zero.
+    pub on_host: bool,
+}
+
+/// The result of profiling inference on the GPU, listing the nodes in order of execution
+#[derive(Clone, Debug)]
+pub struct ProfileReport {
+    pub method: TimingMethod,
+    pub nodes: Vec<NodeProfile>,
+}
+
+impl ProfileReport {
+    /// The sum of the durations of all profiled nodes
+    pub fn total_duration(&self) -> Duration {
+        self.nodes.iter().map(|node| node.duration).sum()
+    }
+}
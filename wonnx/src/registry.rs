@@ -0,0 +1,193 @@
+//! Registration of custom (user-defined) operators
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use crate::{compiler::CompileError, onnx::NodeProto, utils::Shape};
+
+/// The source code of the shader for a custom operator
+#[derive(Clone, Debug)]
+pub enum ShaderSource {
+    /// Plain WGSL source code
+    Wgsl(String),
+
+    /// A Tera template that renders to WGSL. The template is rendered with the same context variables as the built-in
+    /// templates (e.g. `i_lens`, `o_lens`, `i_shape`, `o_shape`, `i_chunks`, `o_chunks`, `scalar_type`) and can include the
+    /// built-in templates (e.g. `{% include "structs.wgsl" %}`). The scalar type is that of the first output. For int64
+    /// it is `i32`, or `vec2<u32>` (low and high 32 bits) when [strict int64](crate::SessionConfig::strict_int64) mode is
+    /// enabled, in which case `scalar_type_is_int64` is set and `snippets/int64.wgsl` provides functions to work with it.
+    Template(String),
+}
+
+/// The compiled form of a custom operator for a specific node: the shader to run and the number of workgroups to dispatch
+#[derive(Clone, Debug)]
+pub struct CustomShader {
+    pub source: ShaderSource,
+    pub threads: (u32, u32, u32),
+}
+
+/// A user-defined operator that can be executed on the GPU.
+///
+/// The shader must follow the same binding layout as the built-in operators: input buffers first, followed by output
+/// buffers, numbered consecutively from zero, with at most four bindings per bind group (i.e. binding 0 of group 1 follows
+/// binding 3 of group 0). The entry point must be called `main`.
+pub trait CustomOperator: Send + Sync {
+    /// The op type of the nodes this operator implements (e.g. "MyOp")
+    fn op_type(&self) -> &str;
+
+    /// The domain of the nodes this operator implements (e.g. "com.example")
+    fn domain(&self) -> &str;
+
+    /// Determine the shapes of the outputs of the specified node, given the shapes of its inputs. This is used during
+    /// shape inference.
+    fn infer_output_shapes(
+        &self,
+        node: &NodeProto,
+        input_shapes: &[&Shape],
+    ) -> Result<Vec<Shape>, CompileError>;
+
+    /// Return the shader for the specified node and the number of workgroups to dispatch
+    fn compile(
+        &self,
+        node: &NodeProto,
+        input_shapes: &[&Shape],
+        output_shapes: &[&Shape],
+    ) -> Result<CustomShader, CompileError>;
+}
+
+/// A set of [custom operators](CustomOperator), identified by domain and op type. The registry can be passed to a
+/// [Session](crate::Session) through [SessionConfig::with_operator_registry](crate::SessionConfig::with_operator_registry).
+#[derive(Clone, Default)]
+pub struct OperatorRegistry {
+    operators: HashMap<(String, String), Arc<dyn CustomOperator>>,
+}
+
+impl OperatorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an operator to the registry. An operator registered earlier for the same domain and op type is replaced.
+    pub fn register<O: CustomOperator + 'static>(&mut self, operator: O) {
+        self.operators.insert(
+            (
+                operator.domain().to_string(),
+                operator.op_type().to_string(),
+            ),
+            Arc::new(operator),
+        );
+    }
+
+    /// Adds the specified operator to the registry and returns [Self].
+    pub fn with_operator<O: CustomOperator + 'static>(mut self, operator: O) -> Self {
+        self.register(operator);
+        self
+    }
+
+    /// Returns the custom operator that implements the specified node, if any
+    pub fn get(&self, node: &NodeProto) -> Option<&Arc<dyn CustomOperator>> {
+        self.operators.get(&(
+            node.get_domain().to_string(),
+            node.get_op_type().to_string(),
+        ))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operators.is_empty()
+    }
+}
+
+impl Debug for OperatorRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.operators.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CustomOperator, CustomShader, OperatorRegistry, ShaderSource};
+    use crate::{
+        compiler::{compile, CompileError},
+        onnx::NodeProto,
+        utils::{ScalarType, Shape},
+    };
+
+    struct Noop;
+
+    impl CustomOperator for Noop {
+        fn op_type(&self) -> &str {
+            "Noop"
+        }
+
+        fn domain(&self) -> &str {
+            "com.example"
+        }
+
+        fn infer_output_shapes(
+            &self,
+            _node: &NodeProto,
+            input_shapes: &[&Shape],
+        ) -> Result<Vec<Shape>, CompileError> {
+            Ok(input_shapes.iter().map(|s| (*s).clone()).collect())
+        }
+
+        fn compile(
+            &self,
+            _node: &NodeProto,
+            _input_shapes: &[&Shape],
+            _output_shapes: &[&Shape],
+        ) -> Result<CustomShader, CompileError> {
+            Ok(CustomShader {
+                source: ShaderSource::Template(
+                    "// {{ op_type }} {{ scalar_type }} {{ i_lens[0] }}".to_string(),
+                ),
+                threads: (1, 1, 1),
+            })
+        }
+    }
+
+    #[test]
+    fn test_registry_lookup() {
+        let registry = OperatorRegistry::new().with_operator(Noop);
+        assert!(!registry.is_empty());
+
+        let mut node = NodeProto::new();
+        node.set_op_type("Noop".to_string());
+        assert!(registry.get(&node).is_none());
+
+        node.set_domain("com.example".to_string());
+        assert_eq!(registry.get(&node).unwrap().op_type(), "Noop");
+
+        node.set_op_type("Relu".to_string());
+        assert!(registry.get(&node).is_none());
+    }
+
+    #[test]
+    fn test_compile_custom_template() {
+        let registry = OperatorRegistry::new().with_operator(Noop);
+        let mut node = NodeProto::new();
+        node.set_op_type("Noop".to_string());
+        node.set_domain("com.example".to_string());
+
+        let shape = Shape::from(ScalarType::F32, &[2, 3]);
+        let compiled = compile(&node, &[&shape], &[&shape], 13, &registry, false).unwrap();
+        assert_eq!(compiled.shader, "// Noop f32 6");
+        assert_eq!(compiled.threads, (1, 1, 1));
+
+        // Int64 values are truncated to i32, unless they are stored with all 64 bits in strict int64 mode
+        let shape = Shape::from(ScalarType::I64, &[2, 3]);
+        let compiled = compile(&node, &[&shape], &[&shape], 13, &registry, false).unwrap();
+        assert_eq!(compiled.shader, "// Noop i32 6");
+        let compiled = compile(&node, &[&shape], &[&shape], 13, &registry, true).unwrap();
+        assert_eq!(compiled.shader, "// Noop vec2<u32> 6");
+
+        // Without the registry the op is unknown
+        assert!(compile(
+            &node,
+            &[&shape],
+            &[&shape],
+            13,
+            &OperatorRegistry::default(),
+            false
+        )
+        .is_err());
+    }
+}
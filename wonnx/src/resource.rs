@@ -1,24 +1,149 @@
+use std::sync::Arc;
+use thiserror::Error;
 use wgpu::{util::DeviceExt, BufferUsages};
 
-// Get a device and a queue, honoring WGPU_ADAPTER_NAME and WGPU_BACKEND environment variables
-pub async fn request_device_queue() -> (wgpu::Device, wgpu::Queue) {
-    let backends = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::PRIMARY);
+/// Options that determine which GPU adapter is used when a new device is created. Options that are not set are taken from
+/// the `WGPU_BACKEND`, `WGPU_ADAPTER_NAME` and `WGPU_POWER_PREF` environment variables (when running natively).
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct AdapterOptions {
+    /// The power preference to use when selecting an adapter (ignored when an adapter name is set)
+    pub power_preference: Option<wgpu::PowerPreference>,
+
+    /// The set of backends (e.g. Vulkan, Metal) adapters may be selected from
+    pub backends: Option<wgpu::Backends>,
+
+    /// Select the first adapter whose name contains this string (case insensitive). Not supported on WebAssembly.
+    pub adapter_name: Option<String>,
+
+    /// When set, only a fallback (software) adapter is selected
+    pub force_fallback_adapter: bool,
+}
+
+impl AdapterOptions {
+    /// Creates a new [AdapterOptions] struct with the default options set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets [`AdapterOptions::power_preference`] to the specified value and returns [Self].
+    pub fn with_power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = Some(power_preference);
+        self
+    }
+
+    /// Sets [`AdapterOptions::backends`] to the specified value and returns [Self].
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = Some(backends);
+        self
+    }
+
+    /// Sets [`AdapterOptions::adapter_name`] to the specified value and returns [Self].
+    pub fn with_adapter_name(mut self, adapter_name: &str) -> Self {
+        self.adapter_name = Some(adapter_name.to_string());
+        self
+    }
+
+    /// Sets [`AdapterOptions::force_fallback_adapter`] to the specified value and returns [Self].
+    pub fn with_force_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DeviceError {
+    #[error("no GPU adapter found matching the adapter options")]
+    AdapterNotFound,
+
+    #[error("no GPU adapter found with a name containing '{0}'")]
+    NamedAdapterNotFound(String),
+
+    #[error("could not create device for GPU adapter: {0}")]
+    RequestDeviceFailed(#[from] wgpu::RequestDeviceError),
+}
+
+// Get a device and a queue using the specified adapter options, honoring WGPU_ADAPTER_NAME, WGPU_BACKEND and
+// WGPU_POWER_PREF environment variables for options that are not set
+pub async fn request_device_queue(
+    options: &AdapterOptions,
+) -> Result<(Arc<wgpu::Device>, Arc<wgpu::Queue>), DeviceError> {
+    let backends = options
+        .backends
+        .or_else(wgpu::util::backend_bits_from_env)
+        .unwrap_or(wgpu::Backends::PRIMARY);
     let instance_descriptor = wgpu::InstanceDescriptor {
         backends,
         dx12_shader_compiler: wgpu::Dx12Compiler::Fxc,
         ..Default::default()
     };
     let instance = wgpu::Instance::new(instance_descriptor);
-    let adapter = wgpu::util::initialize_adapter_from_env_or_default(&instance, None)
-        .await
-        .expect("No GPU found given preference");
+    let adapter = request_adapter(&instance, backends, options).await?;
 
     // `request_device` instantiates the feature specific connection to the GPU, defining some parameters,
     //  `features` being the available features.
-    adapter
-        .request_device(&wgpu::DeviceDescriptor::default(), None)
+    // Timestamp queries are used for profiling when the adapter supports them. SHADER_F16 is not requested, as the version
+    // of naga used by wgpu cannot parse f16 shaders yet (float16 models are widened to float32 instead). Some ops (e.g.
+    // LSTM, QLinearConv) bind more storage buffers than the default limit allows, so the adapter's limit is requested.
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+                required_limits: wgpu::Limits {
+                    max_storage_buffers_per_shader_stage: adapter
+                        .limits()
+                        .max_storage_buffers_per_shader_stage,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    Ok((Arc::new(device), Arc::new(queue)))
+}
+
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    backends: wgpu::Backends,
+    options: &AdapterOptions,
+) -> Result<wgpu::Adapter, DeviceError> {
+    // Adapters can only be enumerated (and hence selected by name) when running natively
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let adapter_name = options
+            .adapter_name
+            .clone()
+            .or_else(|| std::env::var("WGPU_ADAPTER_NAME").ok());
+        if let Some(adapter_name) = adapter_name {
+            let desired_name = adapter_name.to_lowercase();
+            return instance
+                .enumerate_adapters(backends)
+                .into_iter()
+                .find(|adapter| {
+                    let info = adapter.get_info();
+                    info.name.to_lowercase().contains(&desired_name)
+                        && (!options.force_fallback_adapter
+                            || info.device_type == wgpu::DeviceType::Cpu)
+                })
+                .ok_or(DeviceError::NamedAdapterNotFound(adapter_name));
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = backends;
+
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: options
+                .power_preference
+                .or_else(wgpu::util::power_preference_from_env)
+                .unwrap_or_default(),
+            force_fallback_adapter: options.force_fallback_adapter,
+            compatible_surface: None,
+        })
         .await
-        .expect("Could not create adapter for GPU device")
+        .ok_or(DeviceError::AdapterNotFound)
 }
 
 pub fn create_buffer_init<T: Clone + bytemuck::Pod>(
@@ -75,12 +200,28 @@ pub fn padding(data: &[u8], chunk_size: usize, padding_size: usize) -> Vec<u8> {
 mod tests {
     #[test]
     fn test_request_device_queue() {
-        pollster::block_on(crate::resource::request_device_queue());
+        pollster::block_on(crate::resource::request_device_queue(
+            &crate::resource::AdapterOptions::default(),
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn test_request_device_queue_unknown_adapter() {
+        let options = crate::resource::AdapterOptions::new().with_adapter_name("no such adapter");
+        let result = pollster::block_on(crate::resource::request_device_queue(&options));
+        assert!(matches!(
+            result,
+            Err(crate::resource::DeviceError::NamedAdapterNotFound(_))
+        ));
     }
 
     #[test]
     fn test_create_buffer_init() {
-        let (device, _) = pollster::block_on(crate::resource::request_device_queue());
+        let (device, _) = pollster::block_on(crate::resource::request_device_queue(
+            &crate::resource::AdapterOptions::default(),
+        ))
+        .unwrap();
         let data = [1.0, 2.0, 3.0, 4.0];
         let _ = crate::resource::create_buffer_init(
             &device,
@@ -14,8 +14,10 @@ use crate::onnx::TypeProto;
 use crate::onnx::TypeProto_Tensor;
 use crate::onnx::TypeProto_oneof_value;
 use crate::onnx::ValueInfoProto;
+use half::f16;
 use num::FromPrimitive;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::From;
 use std::convert::Into;
 use std::convert::TryFrom;
@@ -132,9 +134,44 @@ impl Shape {
 #[derive(Clone)]
 pub enum InputTensor<'a> {
     F32(Cow<'a, [f32]>),
+    F16(Cow<'a, [f16]>),
     I32(Cow<'a, [i32]>),
     I64(Cow<'a, [i64]>),
     U8(Cow<'a, [u8]>),
+    I8(Cow<'a, [i8]>),
+    Bool(Cow<'a, [bool]>),
+}
+
+impl<'a> InputTensor<'a> {
+    /// The data type of the elements in this tensor
+    pub fn data_type(&self) -> ScalarType {
+        match self {
+            InputTensor::F32(_) => ScalarType::F32,
+            InputTensor::F16(_) => ScalarType::F16,
+            InputTensor::I32(_) => ScalarType::I32,
+            InputTensor::I64(_) => ScalarType::I64,
+            InputTensor::U8(_) => ScalarType::U8,
+            InputTensor::I8(_) => ScalarType::I8,
+            InputTensor::Bool(_) => ScalarType::Bool,
+        }
+    }
+
+    /// The number of elements in this tensor
+    pub fn len(&self) -> usize {
+        match self {
+            InputTensor::F32(v) => v.len(),
+            InputTensor::F16(v) => v.len(),
+            InputTensor::I32(v) => v.len(),
+            InputTensor::I64(v) => v.len(),
+            InputTensor::U8(v) => v.len(),
+            InputTensor::I8(v) => v.len(),
+            InputTensor::Bool(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl<'a> From<&'a [f32]> for InputTensor<'a> {
@@ -143,6 +180,12 @@ impl<'a> From<&'a [f32]> for InputTensor<'a> {
     }
 }
 
+impl<'a> From<&'a [f16]> for InputTensor<'a> {
+    fn from(a: &'a [f16]) -> Self {
+        InputTensor::F16(Cow::Borrowed(a))
+    }
+}
+
 impl<'a> From<&'a [i32]> for InputTensor<'a> {
     fn from(a: &'a [i32]) -> Self {
         InputTensor::I32(Cow::Borrowed(a))
@@ -155,19 +198,88 @@ impl<'a> From<&'a [i64]> for InputTensor<'a> {
     }
 }
 
+impl<'a> From<&'a [u8]> for InputTensor<'a> {
+    fn from(a: &'a [u8]) -> Self {
+        InputTensor::U8(Cow::Borrowed(a))
+    }
+}
+
+impl<'a> From<&'a [i8]> for InputTensor<'a> {
+    fn from(a: &'a [i8]) -> Self {
+        InputTensor::I8(Cow::Borrowed(a))
+    }
+}
+
+impl<'a> From<&'a [bool]> for InputTensor<'a> {
+    fn from(a: &'a [bool]) -> Self {
+        InputTensor::Bool(Cow::Borrowed(a))
+    }
+}
+
 impl<'a> TryFrom<&'a TensorProto> for InputTensor<'a> {
     type Error = DataTypeError;
 
     fn try_from(value: &'a TensorProto) -> Result<Self, Self::Error> {
         Ok(match ScalarType::from_i32(value.get_data_type())? {
             ScalarType::F32 => InputTensor::F32(Cow::Borrowed(value.get_float_data())),
+            ScalarType::F16 => InputTensor::F16(Cow::Owned(float16_data(value))),
             ScalarType::I64 => InputTensor::I64(Cow::Borrowed(value.get_int64_data())),
             ScalarType::I32 => InputTensor::I32(Cow::Borrowed(value.get_int32_data())),
-            ScalarType::U8 => InputTensor::U8(Cow::Borrowed(value.get_raw_data())),
+            ScalarType::U8 => InputTensor::U8(Cow::Owned(uint8_data(value))),
+            ScalarType::I8 => InputTensor::I8(Cow::Owned(int8_data(value))),
+            ScalarType::Bool => InputTensor::Bool(Cow::Owned(bool_data(value))),
         })
     }
 }
 
+/// Read the elements of a FLOAT16 tensor, which are either stored as raw (little-endian) data or as bit patterns in the
+/// int32 data field
+pub(crate) fn float16_data(tensor: &TensorProto) -> Vec<f16> {
+    let int32_data = tensor.get_int32_data();
+    if !int32_data.is_empty() {
+        int32_data
+            .iter()
+            .map(|x| f16::from_bits(*x as u16))
+            .collect()
+    } else {
+        tensor
+            .get_raw_data()
+            .chunks_exact(2)
+            .map(|bytes| f16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect()
+    }
+}
+
+/// Read the elements of a UINT8 tensor, which are either stored as raw data or in the int32 data field
+pub(crate) fn uint8_data(tensor: &TensorProto) -> Vec<u8> {
+    let int32_data = tensor.get_int32_data();
+    if !int32_data.is_empty() {
+        int32_data.iter().map(|x| *x as u8).collect()
+    } else {
+        tensor.get_raw_data().to_vec()
+    }
+}
+
+/// Read the elements of an INT8 tensor, which are either stored as raw data or in the int32 data field
+pub(crate) fn int8_data(tensor: &TensorProto) -> Vec<i8> {
+    let int32_data = tensor.get_int32_data();
+    if !int32_data.is_empty() {
+        int32_data.iter().map(|x| *x as i8).collect()
+    } else {
+        tensor.get_raw_data().iter().map(|x| *x as i8).collect()
+    }
+}
+
+/// Read the elements of a BOOL tensor, which are either stored as raw data (one byte per element) or in the int32 data field
+pub(crate) fn bool_data(tensor: &TensorProto) -> Vec<bool> {
+    let int32_data = tensor.get_int32_data();
+    if !int32_data.is_empty() {
+        int32_data.iter().map(|x| *x != 0).collect()
+    } else {
+        tensor.get_raw_data().iter().map(|x| *x != 0).collect()
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum TensorConversionError {
     #[error("could not convert to the requested type becaue a value could not be represented in the target type")]
@@ -181,9 +293,12 @@ pub enum TensorConversionError {
 #[serde(untagged)]
 pub enum OutputTensor {
     F32(Vec<f32>),
+    F16(Vec<f16>),
     I32(Vec<i32>),
     I64(Vec<i64>),
     U8(Vec<u8>),
+    I8(Vec<i8>),
+    Bool(Vec<bool>),
 }
 
 impl TryFrom<OutputTensor> for Vec<f32> {
@@ -193,6 +308,7 @@ impl TryFrom<OutputTensor> for Vec<f32> {
     fn try_from(value: OutputTensor) -> Result<Self, Self::Error> {
         match value {
             OutputTensor::F32(floats) => Ok(floats),
+            OutputTensor::F16(halves) => Ok(halves.into_iter().map(f32::from).collect()),
             OutputTensor::I32(ints) => ints
                 .into_iter()
                 .map(|i| f32::from_i32(i).ok_or(TensorConversionError::OutOfBoundsError))
@@ -205,6 +321,8 @@ impl TryFrom<OutputTensor> for Vec<f32> {
                 .into_iter()
                 .map(|i| f32::from_u8(i).ok_or(TensorConversionError::OutOfBoundsError))
                 .collect::<Result<_, _>>(),
+            OutputTensor::I8(ints) => Ok(ints.into_iter().map(f32::from).collect()),
+            OutputTensor::Bool(bools) => Ok(bools.into_iter().map(|b| b as u8 as f32).collect()),
         }
     }
 }
@@ -217,20 +335,53 @@ impl<'a> TryFrom<&'a OutputTensor> for &'a [f32] {
     fn try_from(value: &'a OutputTensor) -> Result<Self, Self::Error> {
         match value {
             OutputTensor::F32(floats) => Ok(floats.as_slice()),
-            OutputTensor::I32(_) | OutputTensor::I64(_) | OutputTensor::U8(_) => {
-                Err(TensorConversionError::DataTypeError)
-            }
+            OutputTensor::F16(_)
+            | OutputTensor::I32(_)
+            | OutputTensor::I64(_)
+            | OutputTensor::U8(_)
+            | OutputTensor::I8(_)
+            | OutputTensor::Bool(_) => Err(TensorConversionError::DataTypeError),
         }
     }
 }
 
+/// The result of inference for a single output: the tensor data together with its shape (dimensions and data type)
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypedOutputTensor {
+    pub shape: Shape,
+    pub data: OutputTensor,
+}
+
+impl From<TypedOutputTensor> for OutputTensor {
+    fn from(typed: TypedOutputTensor) -> Self {
+        typed.data
+    }
+}
+
 impl<'a> From<&InputTensor<'a>> for OutputTensor {
     fn from(input: &InputTensor<'a>) -> Self {
         match input {
             InputTensor::F32(fs) => OutputTensor::F32(fs.to_vec()),
+            InputTensor::F16(fs) => OutputTensor::F16(fs.to_vec()),
             InputTensor::I32(fs) => OutputTensor::I32(fs.to_vec()),
             InputTensor::I64(fs) => OutputTensor::I64(fs.to_vec()),
             InputTensor::U8(fs) => OutputTensor::U8(fs.to_vec()),
+            InputTensor::I8(fs) => OutputTensor::I8(fs.to_vec()),
+            InputTensor::Bool(fs) => OutputTensor::Bool(fs.to_vec()),
+        }
+    }
+}
+
+impl<'a> From<&'a OutputTensor> for InputTensor<'a> {
+    fn from(output: &'a OutputTensor) -> Self {
+        match output {
+            OutputTensor::F32(fs) => InputTensor::F32(Cow::Borrowed(fs)),
+            OutputTensor::F16(fs) => InputTensor::F16(Cow::Borrowed(fs)),
+            OutputTensor::I32(fs) => InputTensor::I32(Cow::Borrowed(fs)),
+            OutputTensor::I64(fs) => InputTensor::I64(Cow::Borrowed(fs)),
+            OutputTensor::U8(fs) => InputTensor::U8(Cow::Borrowed(fs)),
+            OutputTensor::I8(fs) => InputTensor::I8(Cow::Borrowed(fs)),
+            OutputTensor::Bool(fs) => InputTensor::Bool(Cow::Borrowed(fs)),
         }
     }
 }
@@ -243,6 +394,10 @@ impl TensorProto {
                 tensor.set_data_type(ScalarType::F32.to_datatype().value());
                 tensor.set_float_data(v);
             }
+            OutputTensor::F16(v) => {
+                tensor.set_data_type(ScalarType::F16.to_datatype().value());
+                tensor.set_raw_data(v.iter().flat_map(|x| x.to_le_bytes()).collect());
+            }
             OutputTensor::I32(v) => {
                 tensor.set_data_type(ScalarType::I32.to_datatype().value());
                 tensor.set_int32_data(v);
@@ -255,6 +410,14 @@ impl TensorProto {
                 tensor.set_data_type(ScalarType::U8.to_datatype().value());
                 tensor.set_raw_data(v);
             }
+            OutputTensor::I8(v) => {
+                tensor.set_data_type(ScalarType::I8.to_datatype().value());
+                tensor.set_raw_data(v.iter().map(|x| *x as u8).collect());
+            }
+            OutputTensor::Bool(v) => {
+                tensor.set_data_type(ScalarType::Bool.to_datatype().value());
+                tensor.set_raw_data(v.iter().map(|x| *x as u8).collect());
+            }
         }
         tensor.set_dims(dims);
         tensor
@@ -280,9 +443,12 @@ pub enum DataTypeError {
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ScalarType {
     F32,
+    F16,
     I64,
     I32,
     U8,
+    I8,
+    Bool,
 }
 
 impl ScalarType {
@@ -295,9 +461,12 @@ impl ScalarType {
     pub fn from(onnx: TensorProto_DataType) -> Result<ScalarType, DataTypeError> {
         Ok(match onnx {
             TensorProto_DataType::FLOAT => ScalarType::F32,
+            TensorProto_DataType::FLOAT16 => ScalarType::F16,
             TensorProto_DataType::INT64 => ScalarType::I64,
             TensorProto_DataType::INT32 => ScalarType::I32,
             TensorProto_DataType::UINT8 => ScalarType::U8,
+            TensorProto_DataType::INT8 => ScalarType::I8,
+            TensorProto_DataType::BOOL => ScalarType::Bool,
             _ => return Err(DataTypeError::NotSupported(onnx)),
         })
     }
@@ -305,50 +474,68 @@ impl ScalarType {
     pub fn to_datatype(&self) -> TensorProto_DataType {
         match self {
             ScalarType::F32 => TensorProto_DataType::FLOAT,
+            ScalarType::F16 => TensorProto_DataType::FLOAT16,
             ScalarType::I64 => TensorProto_DataType::INT64,
             ScalarType::I32 => TensorProto_DataType::INT32,
             ScalarType::U8 => TensorProto_DataType::UINT8,
+            ScalarType::I8 => TensorProto_DataType::INT8,
+            ScalarType::Bool => TensorProto_DataType::BOOL,
         }
     }
 
     pub fn stride(&self) -> usize {
         match self {
             ScalarType::F32 => 4,
+            ScalarType::F16 => 2,
             ScalarType::I32 => 4,
             ScalarType::I64 => 8,
-            ScalarType::U8 => 1, // ! TODO check this
+            ScalarType::U8 | ScalarType::I8 => 1, // Packed four to a u32 on the GPU
+            ScalarType::Bool => 4,                // Stored as u32 on the GPU
         }
     }
 
     pub fn wgsl_supported(&self) -> bool {
         match self {
             ScalarType::F32 => true,
+            ScalarType::F16 => false, // Float16 models are widened to float32 on the GPU
             ScalarType::I32 => true,
             ScalarType::I64 => false,
-            ScalarType::U8 => false, // ! TODO check this
+            ScalarType::U8 | ScalarType::I8 => false, // Only accessible through the quantization shaders
+            ScalarType::Bool => true,
         }
     }
 
     pub fn wgsl_type_name(&self) -> &'static str {
         match self {
             ScalarType::F32 => "f32",
+            ScalarType::F16 => "f16",
             ScalarType::I32 => "i32",
             ScalarType::I64 => "i64",
-            ScalarType::U8 => "u8", // ! TODO check this
+            ScalarType::U8 | ScalarType::I8 => "u32", // Four 8-bit values packed in a single u32
+            ScalarType::Bool => "u32", // WGSL bools cannot be stored in buffers, so we use zero and one instead
         }
     }
 
     pub fn is_float(&self) -> bool {
         match self {
-            ScalarType::F32 => true,
-            ScalarType::I32 | ScalarType::I64 | ScalarType::U8 => false,
+            ScalarType::F32 | ScalarType::F16 => true,
+            ScalarType::I32
+            | ScalarType::I64
+            | ScalarType::U8
+            | ScalarType::I8
+            | ScalarType::Bool => false,
         }
     }
 }
 
 impl Display for ScalarType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.wgsl_type_name())
+        match self {
+            ScalarType::U8 => write!(f, "u8"),
+            ScalarType::I8 => write!(f, "i8"),
+            ScalarType::Bool => write!(f, "bool"),
+            _ => write!(f, "{}", self.wgsl_type_name()),
+        }
     }
 }
 
@@ -526,6 +713,134 @@ impl ValueInfoProto {
     }
 }
 
+/// A single dimension of a model input or output as declared in the model
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Dimension {
+    /// A dimension with a fixed size
+    Fixed(u64),
+
+    /// A dimension whose size is given by a named parameter (e.g. 'batch_size') and is only known at inference time
+    Symbolic(String),
+
+    /// A dimension for which the model does not specify a size
+    Unknown,
+}
+
+impl Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dimension::Fixed(size) => write!(f, "{}", size),
+            Dimension::Symbolic(name) => write!(f, "{}", name),
+            Dimension::Unknown => write!(f, "?"),
+        }
+    }
+}
+
+/// Describes an input or output of a model: its name, data type, dimensions and documentation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TensorInfo {
+    pub name: String,
+    pub data_type: ScalarType,
+    pub dimensions: Vec<Dimension>,
+    pub doc_string: String,
+}
+
+impl TensorInfo {
+    /// The shape of the tensor, or None when not all dimensions have a fixed size
+    pub fn shape(&self) -> Option<Shape> {
+        let dims = self
+            .dimensions
+            .iter()
+            .map(|d| match d {
+                Dimension::Fixed(size) => Some(*size),
+                Dimension::Symbolic(_) | Dimension::Unknown => None,
+            })
+            .collect::<Option<Vec<u64>>>()?;
+        Some(Shape {
+            dims,
+            data_type: self.data_type,
+        })
+    }
+
+    /// The names of the symbolic dimensions of this tensor, in order of appearance
+    pub fn symbolic_dimensions(&self) -> Vec<&str> {
+        self.dimensions
+            .iter()
+            .filter_map(|d| match d {
+                Dimension::Symbolic(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<&ValueInfoProto> for TensorInfo {
+    type Error = DataTypeError;
+
+    fn try_from(value_info: &ValueInfoProto) -> Result<Self, Self::Error> {
+        let tensor_type = match &value_info.get_field_type().value {
+            Some(TypeProto_oneof_value::tensor_type(tensor_type)) => tensor_type,
+            _ => return Err(DataTypeError::Undefined),
+        };
+
+        let dimensions = tensor_type
+            .get_shape()
+            .get_dim()
+            .iter()
+            .map(|dim| {
+                if dim.has_dim_value() {
+                    Dimension::Fixed(dim.get_dim_value() as u64)
+                } else if dim.has_dim_param() {
+                    Dimension::Symbolic(dim.get_dim_param().to_string())
+                } else {
+                    Dimension::Unknown
+                }
+            })
+            .collect();
+
+        Ok(TensorInfo {
+            name: value_info.get_name().to_string(),
+            data_type: ScalarType::from_i32(tensor_type.get_elem_type())?,
+            dimensions,
+            doc_string: value_info.get_doc_string().to_string(),
+        })
+    }
+}
+
+/// Model-level information taken from an ONNX model
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModelMetadata {
+    pub producer_name: String,
+    pub producer_version: String,
+    pub domain: String,
+    pub model_version: i64,
+    pub doc_string: String,
+
+    /// The version of the default ONNX opset the model uses
+    pub onnx_opset_version: i64,
+
+    /// Free-form key/value pairs stored in the model (`metadata_props`)
+    pub metadata_props: HashMap<String, String>,
+}
+
+impl ModelMetadata {
+    pub fn from(model: &ModelProto, onnx_opset_version: i64) -> Self {
+        ModelMetadata {
+            producer_name: model.get_producer_name().to_string(),
+            producer_version: model.get_producer_version().to_string(),
+            domain: model.get_domain().to_string(),
+            model_version: model.get_model_version(),
+            doc_string: model.get_doc_string().to_string(),
+            onnx_opset_version,
+            metadata_props: model
+                .get_metadata_props()
+                .iter()
+                .map(|prop| (prop.get_key().to_string(), prop.get_value().to_string()))
+                .collect(),
+        }
+    }
+}
+
 /// Shorthand method to define an ONNX tensor with the specified name and shape (data type is f32)
 pub fn tensor(name: &str, dimensions: &[i64]) -> onnx::ValueInfoProto {
     tensor_of_type(name, dimensions, TensorProto_DataType::FLOAT)
@@ -708,6 +1023,14 @@ impl From<&str> for onnx::AttributeProto {
     }
 }
 
+impl From<Vec<String>> for onnx::AttributeProto {
+    fn from(value: Vec<String>) -> Self {
+        let mut attributes = crate::onnx::AttributeProto::new();
+        attributes.set_strings(value.into_iter().map(|s| s.into_bytes()).collect());
+        attributes
+    }
+}
+
 impl From<TensorProto> for onnx::AttributeProto {
     fn from(value: TensorProto) -> Self {
         let mut attributes = crate::onnx::AttributeProto::new();
@@ -752,6 +1075,16 @@ impl From<onnx::AttributeProto> for String {
     }
 }
 
+impl From<onnx::AttributeProto> for Vec<String> {
+    fn from(value: onnx::AttributeProto) -> Self {
+        value
+            .get_strings()
+            .iter()
+            .map(|s| from_utf8(s).unwrap().to_string())
+            .collect()
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum OpsetError {
     #[error("more than one ONNX opset was specified: {0} and {1}")]
@@ -791,8 +1124,11 @@ pub fn get_opset_version(model: &ModelProto) -> Result<Option<i64>, OpsetError>
 
 #[cfg(test)]
 mod tests {
+    use std::convert::TryFrom;
+
     use crate::utils::{
-        attribute, graph, initializer, model, node, tensor, OutputTensor, ScalarType, Shape,
+        attribute, graph, initializer, model, node, tensor, Dimension, OutputTensor, ScalarType,
+        Shape, TensorInfo,
     };
 
     #[test]
@@ -885,4 +1221,28 @@ mod tests {
             Some(shape(&[1, 255, 768])),
         );
     }
+
+    #[test]
+    pub fn test_tensor_info_symbolic_dimensions() {
+        let mut value_info = tensor("X", &[1, 3]);
+        let dims = value_info
+            .mut_field_type()
+            .mut_tensor_type()
+            .mut_shape()
+            .mut_dim();
+        dims[0].set_dim_param("batch_size".to_string());
+        dims.push(crate::onnx::TensorShapeProto_Dimension::new());
+
+        let info = TensorInfo::try_from(&value_info).unwrap();
+        assert_eq!(
+            info.dimensions,
+            vec![
+                Dimension::Symbolic("batch_size".to_string()),
+                Dimension::Fixed(3),
+                Dimension::Unknown
+            ]
+        );
+        assert_eq!(info.symbolic_dimensions(), vec!["batch_size"]);
+        assert_eq!(info.shape(), None);
+    }
 }
@@ -1,13 +1,25 @@
 {%- include "structs.wgsl" -%}
+{# Expand and Tile only have a single input, which is broadcast or repeated to the output shape #}
+{%- set unary = op_type == "Expand" or op_type == "Tile" -%}
 
 @group(0) @binding(0)
 var<storage, read> input_0: Array;
 
+{% if not unary %}
 @group(0) @binding(1)
 var<storage, read> input_1: Array;
+{% endif %}
 
-@group(0) @binding(2)
-var<storage, read_write> output_0: Array;
+{% if comparison %}
+struct OutputArray {
+	data: array<{{ output_type }}>
+};
+{% else %}
+alias OutputArray = Array;
+{% endif %}
+
+@group(0) @binding({% if unary %}1{% else %}2{% endif %})
+var<storage, read_write> output_0: OutputArray;
 
 @compute @workgroup_size({{ workgroup_size_x }}, 1, 1)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
@@ -23,11 +35,17 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 			let out_index = rest / {{ o_chunks[0][loop.index0] }}u;
 
 			{% if lhs_padded_shape[loop.index0] > 1 %}
-				lhs_index = lhs_index + (out_index * {{ lhs_padded_chunks[loop.index0] }}u);
+				{% if op_type == "Tile" %}
+					lhs_index = lhs_index + ((out_index % {{ lhs_padded_shape[loop.index0] }}u) * {{ lhs_padded_chunks[loop.index0] }}u);
+				{% else %}
+					lhs_index = lhs_index + (out_index * {{ lhs_padded_chunks[loop.index0] }}u);
+				{% endif %}
 			{% endif %}
 
-			{% if rhs_padded_shape[loop.index0] > 1 %}
-				rhs_index = rhs_index + (out_index * {{ rhs_padded_chunks[loop.index0] }}u);
+			{% if not unary %}
+				{% if rhs_padded_shape[loop.index0] > 1 %}
+					rhs_index = rhs_index + (out_index * {{ rhs_padded_chunks[loop.index0] }}u);
+				{% endif %}
 			{% endif %}
 			rest = rest % {{ o_chunks[0][loop.index0] }}u;
 		}
@@ -35,13 +53,19 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 	{% endfor %}
 
 	let lhs = input_0.data[lhs_index];
+	{% if not unary %}
 	let rhs = input_1.data[rhs_index];
+	{% endif %}
 
-	{% if op_type == "Pow" %}
+	{% if unary %}
+		output_0.data[gidx] = lhs;
+	{% elif op_type == "Pow" %}
 		output_0.data[gidx] = pow(lhs, rhs);
 	{% elif op_type == "PRelu" %}
 		output_0.data[gidx] = max(lhs, Scalar())
 							+ min(lhs, Scalar()) * rhs;
+	{% elif comparison %}
+		output_0.data[gidx] = select({{ output_type }}(0), {{ output_type }}(1), lhs {{ op_type }} rhs);
 	{% else %}
 		output_0.data[gidx] = (lhs {{ op_type }} rhs);
 	{% endif %}
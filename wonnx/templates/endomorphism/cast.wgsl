@@ -12,5 +12,9 @@ var<storage, read_write> output_0: OutputArrayVector;
 @compute @workgroup_size({{ workgroup_size_x }})
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let gidx = global_id.x;
-    output_0.data[gidx] = vec4<{{ cast_to_type }}>(input_0.data[gidx]);
+    {% if cast_to_bool %}
+        output_0.data[gidx] = select(vec4<u32>(0u), vec4<u32>(1u), input_0.data[gidx] != Vec4(Scalar()));
+    {% else %}
+        output_0.data[gidx] = vec4<{{ cast_to_type }}>(input_0.data[gidx]);
+    {% endif %}
 }
\ No newline at end of file
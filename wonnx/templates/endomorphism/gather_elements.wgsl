@@ -0,0 +1,42 @@
+{%- if indices_are_int64 or data_is_int64 %}
+{%- include "snippets/int64.wgsl" -%}
+{%- endif %}
+
+@group(0) @binding(0)
+var<storage, read> input_0: array<{{ data_type }}>; // data
+
+@group(0) @binding(1)
+var<storage, read> input_1: array<{{ indices_type }}>; // indices
+
+@group(0) @binding(2)
+var<storage, read_write> output_0: array<{{ data_type }}>;
+
+// Reads the index at the specified position. Negative indices count back from the end of a dimension of the given size.
+fn index_at(position: u32, size: u32) -> u32 {
+	{%- if indices_are_int64 %}
+	let index = i64_to_i32(input_1[position]);
+	{%- else %}
+	let index = input_1[position];
+	{%- endif %}
+	return u32(select(index, index + i32(size), index < 0));
+}
+
+@compute @workgroup_size({{ workgroup_size_x }})
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	// The output has the shape of the indices. Each element is taken from the same position in the data, except along
+	// the axis, where the position is the index at the position of the element.
+	var offset = 0u;
+	{%- for dim in o_shape[0] %}
+	{%- if loop.index0 == axis %}
+	offset = offset + index_at(gidx, {{ i_shape[0][axis] }}u) * {{ i_chunks[0][axis] }}u;
+	{%- else %}
+	offset = offset + ((gidx / {{ o_chunks[0][loop.index0] }}u) % {{ dim }}u) * {{ i_chunks[0][loop.index0] }}u;
+	{%- endif %}
+	{%- endfor %}
+	output_0[gidx] = input_0[offset];
+}
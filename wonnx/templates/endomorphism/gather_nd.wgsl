@@ -0,0 +1,40 @@
+{%- if indices_are_int64 or data_is_int64 %}
+{%- include "snippets/int64.wgsl" -%}
+{%- endif %}
+
+@group(0) @binding(0)
+var<storage, read> input_0: array<{{ data_type }}>; // data
+
+@group(0) @binding(1)
+var<storage, read> input_1: array<{{ indices_type }}>; // indices
+
+@group(0) @binding(2)
+var<storage, read_write> output_0: array<{{ data_type }}>;
+
+// Reads the index at the specified position. Negative indices count back from the end of a dimension of the given size.
+fn index_at(position: u32, size: u32) -> u32 {
+	{%- if indices_are_int64 %}
+	let index = i64_to_i32(input_1[position]);
+	{%- else %}
+	let index = input_1[position];
+	{%- endif %}
+	return u32(select(index, index + i32(size), index < 0));
+}
+
+@compute @workgroup_size({{ workgroup_size_x }})
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	// The output holds a slice of the data for each tuple of indices. The tuple selects the slice within the data of the
+	// batch the tuple belongs to.
+	let tuple = gidx / {{ slice_size }}u;
+	let batch = tuple / {{ tuples_per_batch }}u;
+	var offset = batch * {{ batch_stride }}u + gidx % {{ slice_size }}u;
+	{%- for dim in index_dims %}
+	offset = offset + index_at(tuple * {{ index_depth }}u + {{ loop.index0 }}u, {{ dim }}u) * {{ index_strides[loop.index0] }}u;
+	{%- endfor %}
+	output_0[gidx] = input_0[offset];
+}
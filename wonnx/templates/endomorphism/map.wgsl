@@ -17,6 +17,9 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 		let zero_scalar = {{scalar_type}}(0);
 		let zeroes = Vec4(zero_scalar, zero_scalar, zero_scalar, zero_scalar);
 		output_0.data[gidx] = zeroes - (input_0.data[gidx]);
+	{% elif op_type == "Not" %}
+		{# Booleans are stored as zero (false) and one (true) #}
+		output_0.data[gidx] = select(Vec4(Scalar(1)), Vec4(), input_0.data[gidx] != Vec4());
 	{% elif op_type == "Tanh" %}
 		{# Tanh will produce NaNs when fed with inputs that are much larger than +10.0 or smaller than -10.0. As the output
 		for these inputs converges to 1.0 and -1.0 respectively, we clamp the inputs first. #}
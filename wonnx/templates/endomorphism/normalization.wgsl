@@ -0,0 +1,101 @@
+{%- include "structs.wgsl" -%}
+
+// X (input)
+@group(0) @binding(0)
+var<storage, read> input_0: Array;
+
+// Scale
+@group(0) @binding(1)
+var<storage, read> input_1: Array;
+
+{% if has_bias %}
+// B (bias)
+@group(0) @binding(2)
+var<storage, read> input_2: Array;
+{% endif %}
+
+{% set output_binding = i_lens | length -%}
+// Y (output)
+@group({{ output_binding / 4 | int }}) @binding({{ output_binding % 4 }})
+var<storage, read_write> output_0: Array;
+
+{% if o_lens | length > 1 %}
+struct StatisticsArray {
+	data: array<{{ statistics_type }}>
+};
+
+// Mean
+{% set mean_binding = output_binding + 1 -%}
+@group({{ mean_binding / 4 | int }}) @binding({{ mean_binding % 4 }})
+var<storage, read_write> output_1: StatisticsArray;
+{% endif %}
+
+{% if o_lens | length > 2 %}
+// Inverse standard deviation
+{% set inv_std_dev_binding = output_binding + 2 -%}
+@group({{ inv_std_dev_binding / 4 | int }}) @binding({{ inv_std_dev_binding % 4 }})
+var<storage, read_write> output_2: StatisticsArray;
+{% endif %}
+
+var<workgroup> partial_sums: array<{{ accumulator_type }}, {{ workgroup_size_x }}>;
+
+// Sums the values of all invocations in the workgroup (the workgroup size is a power of two)
+fn workgroup_sum(local_index: u32, value: {{ accumulator_type }}) -> {{ accumulator_type }} {
+	partial_sums[local_index] = value;
+	workgroupBarrier();
+
+	for(var stride: u32 = {{ workgroup_size_x }}u / 2u; stride > 0u; stride = stride / 2u) {
+		if (local_index < stride) {
+			partial_sums[local_index] = partial_sums[local_index] + partial_sums[local_index + stride];
+		}
+		workgroupBarrier();
+	}
+
+	let sum = partial_sums[0];
+	workgroupBarrier();
+	return sum;
+}
+
+{# Each workgroup normalizes one group, which is a contiguous range of elements in the input #}
+@compute @workgroup_size({{ workgroup_size_x }})
+fn main(@builtin(workgroup_id) workgroup_id: vec3<u32>, @builtin(local_invocation_index) local_index: u32) {
+	let group = workgroup_id.x;
+	let group_start = group * {{ group_size }}u;
+
+	// First determine the mean of the group
+	var sum = {{ accumulator_type }}();
+	for(var i: u32 = local_index; i < {{ group_size }}u; i = i + {{ workgroup_size_x }}u) {
+		sum = sum + {{ accumulator_type }}(input_0.data[group_start + i]);
+	}
+	let mean = workgroup_sum(local_index, sum) / {{ accumulator_type }}({{ group_size }});
+
+	// Then the variance (computed from the deviations to avoid cancellation)
+	var squared_deviations = {{ accumulator_type }}();
+	for(var i: u32 = local_index; i < {{ group_size }}u; i = i + {{ workgroup_size_x }}u) {
+		let deviation = {{ accumulator_type }}(input_0.data[group_start + i]) - mean;
+		squared_deviations = squared_deviations + deviation * deviation;
+	}
+	let variance = workgroup_sum(local_index, squared_deviations) / {{ accumulator_type }}({{ group_size }});
+	let inv_std_dev = {{ accumulator_type }}(1) / sqrt(variance + {{ accumulator_type }}({{ epsilon }}));
+
+	// Y = (X - mean) * inv_std_dev * scale + B. The scale (and bias) are selected per element or per channel.
+	let affine_group_start = (group % {{ affine_groups }}u) * {{ affine_group_stride }}u;
+	for(var i: u32 = local_index; i < {{ group_size }}u; i = i + {{ workgroup_size_x }}u) {
+		let affine_index = affine_group_start + i / {{ affine_divisor }}u;
+		let normalized = ({{ accumulator_type }}(input_0.data[group_start + i]) - mean) * inv_std_dev;
+		var value = {{ scalar_type }}(normalized) * input_1.data[affine_index % {{ scale_length }}u];
+		{% if has_bias %}
+			value = value + input_2.data[affine_index % {{ bias_length }}u];
+		{% endif %}
+		output_0.data[group_start + i] = value;
+	}
+
+	{% if o_lens | length > 1 %}
+	if (local_index == 0u) {
+		output_1.data[group] = {{ statistics_type }}(mean);
+		{% if o_lens | length > 2 %}
+			output_2.data[group] = {{ statistics_type }}(inv_std_dev);
+		{% endif %}
+	}
+	{% endif %}
+}
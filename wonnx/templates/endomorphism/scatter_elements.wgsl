@@ -0,0 +1,71 @@
+{%- if indices_are_int64 or data_is_int64 %}
+{%- include "snippets/int64.wgsl" -%}
+{%- endif %}
+
+@group(0) @binding(0)
+var<storage, read> input_0: array<{{ data_type }}>; // data
+
+@group(0) @binding(1)
+var<storage, read> input_1: array<{{ indices_type }}>; // indices
+
+@group(0) @binding(2)
+var<storage, read> input_2: array<{{ data_type }}>; // updates
+
+@group(0) @binding(3)
+var<storage, read_write> output_0: array<{{ data_type }}>;
+
+// Reads the index at the specified position. Negative indices count back from the end of a dimension of the given size.
+fn index_at(position: u32, size: u32) -> u32 {
+	{%- if indices_are_int64 %}
+	let index = i64_to_i32(input_1[position]);
+	{%- else %}
+	let index = input_1[position];
+	{%- endif %}
+	return u32(select(index, index + i32(size), index < 0));
+}
+
+fn combine(current: {{ data_type }}, update: {{ data_type }}) -> {{ data_type }} {
+	{%- if reduction == "add" %}
+	return current + update;
+	{%- elif reduction == "mul" %}
+	return current * update;
+	{%- elif reduction == "max" %}
+	return max(current, update);
+	{%- elif reduction == "min" %}
+	return min(current, update);
+	{%- else %}
+	return update;
+	{%- endif %}
+}
+
+@compute @workgroup_size({{ workgroup_size_x }})
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	// Only the updates at the same position as this element (except along the axis) can be scattered to it. These are
+	// applied in order, so that the last one wins when updates are not combined.
+	var value = input_0[gidx];
+	let position = (gidx / {{ o_chunks[0][axis] }}u) % {{ o_shape[0][axis] }}u;
+	var start = 0u;
+	{%- for dim in o_shape[0] %}
+	{%- if loop.index0 != axis %}
+	let coordinate_{{ loop.index0 }} = (gidx / {{ o_chunks[0][loop.index0] }}u) % {{ dim }}u;
+	if (coordinate_{{ loop.index0 }} >= {{ i_shape[2][loop.index0] }}u) {
+		output_0[gidx] = value;
+		return;
+	}
+	start = start + coordinate_{{ loop.index0 }} * {{ i_chunks[2][loop.index0] }}u;
+	{%- endif %}
+	{%- endfor %}
+
+	for(var j = 0u; j < {{ i_shape[2][axis] }}u; j = j + 1u) {
+		let update_index = start + j * {{ i_chunks[2][axis] }}u;
+		if (index_at(update_index, {{ o_shape[0][axis] }}u) == position) {
+			value = combine(value, input_2[update_index]);
+		}
+	}
+	output_0[gidx] = value;
+}
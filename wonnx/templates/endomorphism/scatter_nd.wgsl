@@ -0,0 +1,103 @@
+{%- if indices_are_int64 or data_is_int64 %}
+{%- include "snippets/int64.wgsl" -%}
+{%- endif %}
+
+{% if in_place %}
+@group(0) @binding(0)
+var<storage, read> input_1: array<{{ indices_type }}>; // indices
+
+@group(0) @binding(1)
+var<storage, read> input_2: array<{{ data_type }}>; // updates
+
+@group(0) @binding(2)
+var<storage, read_write> output_0: array<{{ data_type }}>; // data (updated in place)
+{% else %}
+@group(0) @binding(0)
+var<storage, read> input_0: array<{{ data_type }}>; // data
+
+@group(0) @binding(1)
+var<storage, read> input_1: array<{{ indices_type }}>; // indices
+
+@group(0) @binding(2)
+var<storage, read> input_2: array<{{ data_type }}>; // updates
+
+@group(0) @binding(3)
+var<storage, read_write> output_0: array<{{ data_type }}>;
+{% endif %}
+
+// Reads the index at the specified position. Negative indices count back from the end of a dimension of the given size.
+fn index_at(position: u32, size: u32) -> u32 {
+	{%- if indices_are_int64 %}
+	let index = i64_to_i32(input_1[position]);
+	{%- else %}
+	let index = input_1[position];
+	{%- endif %}
+	return u32(select(index, index + i32(size), index < 0));
+}
+
+// The offset in the data of the slice that is updated by the specified tuple of indices
+fn slice_offset(tuple: u32) -> u32 {
+	var offset = 0u;
+	{%- for dim in index_dims %}
+	offset = offset + index_at(tuple * {{ index_depth }}u + {{ loop.index0 }}u, {{ dim }}u) * {{ index_strides[loop.index0] }}u;
+	{%- endfor %}
+	return offset;
+}
+
+fn combine(current: {{ data_type }}, update: {{ data_type }}) -> {{ data_type }} {
+	{%- if reduction == "add" %}
+	return current + update;
+	{%- elif reduction == "mul" %}
+	return current * update;
+	{%- elif reduction == "max" %}
+	return max(current, update);
+	{%- elif reduction == "min" %}
+	return min(current, update);
+	{%- else %}
+	return update;
+	{%- endif %}
+}
+
+@compute @workgroup_size({{ workgroup_size_x }})
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+
+	{% if in_place -%}
+	// Each invocation updates an element of the slice of a tuple of indices. When multiple tuples refer to the same slice,
+	// the first of these applies the updates of all of them (in order, so that the last one wins when not combined).
+	if (gidx >= {{ tuple_count * slice_size }}u) {
+		return;
+	}
+	let tuple = gidx / {{ slice_size }}u;
+	let element = gidx % {{ slice_size }}u;
+	let offset = slice_offset(tuple);
+	for(var other = 0u; other < tuple; other = other + 1u) {
+		if (slice_offset(other) == offset) {
+			return;
+		}
+	}
+
+	var value = output_0[offset + element];
+	for(var other = tuple; other < {{ tuple_count }}u; other = other + 1u) {
+		if (slice_offset(other) == offset) {
+			value = combine(value, input_2[other * {{ slice_size }}u + element]);
+		}
+	}
+	output_0[offset + element] = value;
+	{%- else -%}
+	// Each invocation produces an element of the output, applying the updates of all tuples of indices that refer to the
+	// slice the element is in
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+	let element = gidx % {{ slice_size }}u;
+	let offset = gidx - element;
+	var value = input_0[gidx];
+	for(var tuple = 0u; tuple < {{ tuple_count }}u; tuple = tuple + 1u) {
+		if (slice_offset(tuple) == offset) {
+			value = combine(value, input_2[tuple * {{ slice_size }}u + element]);
+		}
+	}
+	output_0[gidx] = value;
+	{%- endif %}
+}
@@ -0,0 +1,53 @@
+{%- include "structs.wgsl" -%}
+
+struct ConditionArray {
+	data: array<u32>
+};
+
+@group(0) @binding(0)
+var<storage, read> input_0: ConditionArray; // condition
+
+@group(0) @binding(1)
+var<storage, read> input_1: Array; // X (selected where the condition is true)
+
+@group(0) @binding(2)
+var<storage, read> input_2: Array; // Y (selected where the condition is false)
+
+@group(0) @binding(3)
+var<storage, read_write> output_0: Array;
+
+@compute @workgroup_size({{ workgroup_size_x }}, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	{# Determine the corresponding indices in the (possibly broadcast) source tensors #}
+	var condition_index = 0u;
+	var x_index = 0u;
+	var y_index = 0u;
+	var rest = gidx;
+	{% for dim in o_shape[0] %}
+		{% if dim > 1 %}
+		{
+			let out_index = rest / {{ o_chunks[0][loop.index0] }}u;
+
+			{% if condition_padded_shape[loop.index0] > 1 %}
+				condition_index = condition_index + (out_index * {{ condition_padded_chunks[loop.index0] }}u);
+			{% endif %}
+
+			{% if x_padded_shape[loop.index0] > 1 %}
+				x_index = x_index + (out_index * {{ x_padded_chunks[loop.index0] }}u);
+			{% endif %}
+
+			{% if y_padded_shape[loop.index0] > 1 %}
+				y_index = y_index + (out_index * {{ y_padded_chunks[loop.index0] }}u);
+			{% endif %}
+			rest = rest % {{ o_chunks[0][loop.index0] }}u;
+		}
+		{% endif %}
+	{% endfor %}
+
+	output_0.data[gidx] = select(input_2.data[y_index], input_1.data[x_index], input_0.data[condition_index] != 0u);
+}
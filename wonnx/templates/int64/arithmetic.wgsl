@@ -0,0 +1,74 @@
+{%- include "snippets/int64.wgsl" -%}
+
+@group(0) @binding(0)
+var<storage, read> input_0: array<Int64>;
+
+@group(0) @binding(1)
+var<storage, read> input_1: array<Int64>;
+
+@group(0) @binding(2)
+var<storage, read_write> output_0: array<{{ output_type }}>;
+
+@compute @workgroup_size({{ workgroup_size_x }}, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	{# Determine the corresponding indices in the (possibly broadcast) source tensors #}
+	var lhs_index = 0u;
+	var rhs_index = 0u;
+	var rest = gidx;
+	{% for dim in o_shape[0] %}
+		{% if dim > 1 %}
+		{
+			let out_index = rest / {{ o_chunks[0][loop.index0] }}u;
+
+			{% if lhs_padded_shape[loop.index0] > 1 %}
+				lhs_index = lhs_index + (out_index * {{ lhs_padded_chunks[loop.index0] }}u);
+			{% endif %}
+
+			{% if rhs_padded_shape[loop.index0] > 1 %}
+				rhs_index = rhs_index + (out_index * {{ rhs_padded_chunks[loop.index0] }}u);
+			{% endif %}
+			rest = rest % {{ o_chunks[0][loop.index0] }}u;
+		}
+		{% endif %}
+	{% endfor %}
+
+	let lhs = input_0[lhs_index];
+	let rhs = input_1[rhs_index];
+
+	{% if op_type == "Add" %}
+		output_0[gidx] = i64_add(lhs, rhs);
+	{% elif op_type == "Sub" %}
+		output_0[gidx] = i64_sub(lhs, rhs);
+	{% elif op_type == "Mul" %}
+		output_0[gidx] = i64_mul(lhs, rhs);
+	{% elif op_type == "Div" %}
+		output_0[gidx] = i64_div(lhs, rhs);
+	{% elif op_type == "Mod" and fmod %}
+		output_0[gidx] = i64_fmod(lhs, rhs);
+	{% elif op_type == "Mod" %}
+		output_0[gidx] = i64_mod(lhs, rhs);
+	{% else %}
+		{% if op_type == "Equal" %}
+			let result = i64_eq(lhs, rhs);
+		{% elif op_type == "Greater" %}
+			let result = i64_lt(rhs, lhs);
+		{% elif op_type == "GreaterOrEqual" %}
+			let result = !i64_lt(lhs, rhs);
+		{% elif op_type == "Less" %}
+			let result = i64_lt(lhs, rhs);
+		{% elif op_type == "LessOrEqual" %}
+			let result = !i64_lt(rhs, lhs);
+		{% endif %}
+
+		{% if output_is_int64 %}
+			output_0[gidx] = i64_from_bool(result);
+		{% else %}
+			output_0[gidx] = {{ output_type }}(select(0, 1, result));
+		{% endif %}
+	{% endif %}
+}
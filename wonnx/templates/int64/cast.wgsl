@@ -0,0 +1,32 @@
+{%- include "snippets/int64.wgsl" -%}
+
+@group(0) @binding(0)
+var<storage, read> input_0: array<{{ input_type }}>;
+
+@group(0) @binding(1)
+var<storage, read_write> output_0: array<{{ output_type }}>;
+
+@compute @workgroup_size({{ workgroup_size_x }}, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	let value = input_0[gidx];
+	{% if input_is_int64 and output_is_int64 %}
+		output_0[gidx] = value;
+	{% elif input_is_int64 %}
+		{% if output_is_bool %}
+			output_0[gidx] = select(0u, 1u, !i64_eq(value, Int64(0u, 0u)));
+		{% elif output_is_float %}
+			output_0[gidx] = {{ output_type }}(i64_to_f32(value));
+		{% else %}
+			output_0[gidx] = {{ output_type }}(i64_to_i32(value));
+		{% endif %}
+	{% elif input_is_float %}
+		output_0[gidx] = i64_from_f32(f32(value));
+	{% else %}
+		output_0[gidx] = i64_from_i32(i32(value));
+	{% endif %}
+}
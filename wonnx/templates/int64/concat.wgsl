@@ -0,0 +1,31 @@
+{%- include "snippets/int64.wgsl" -%}
+
+{% for input in i_lens %}
+@group({{ loop.index0 / 4 | int }}) @binding({{ loop.index0 % 4 }})
+var<storage, read> input_{{ loop.index0 }}: array<Int64>;
+{% endfor %}
+
+{% set binding_len = i_lens | length %}
+@group({{ binding_len / 4 | int }}) @binding({{ binding_len % 4 }})
+var<storage, read_write> output_0: array<Int64>;
+
+@compute @workgroup_size({{ workgroup_size_x }}, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	{# Split the output index into the part before the concatenation axis, the position on the axis and the part after #}
+	let inner = gidx % {{ inner_size }}u;
+	let axis_index = (gidx / {{ inner_size }}u) % {{ axis_offsets | last }}u;
+	let outer = gidx / ({{ inner_size }}u * {{ axis_offsets | last }}u);
+
+	{% for input in i_lens %}
+		{% set start = axis_offsets | nth(n=loop.index0) %}
+		{% set end = axis_offsets | nth(n=loop.index) %}
+		if (axis_index >= {{ start }}u && axis_index < {{ end }}u) {
+			output_0[gidx] = input_{{ loop.index0 }}[(outer * {{ end - start }}u + (axis_index - {{ start }}u)) * {{ inner_size }}u + inner];
+		}
+	{% endfor %}
+}
@@ -0,0 +1,32 @@
+{%- include "snippets/int64.wgsl" -%}
+
+@group(0) @binding(0)
+var<storage, read> input_0: array<{{ data_type }}>; // data
+
+@group(0) @binding(1)
+var<storage, read> input_1: array<{{ indices_type }}>; // indices
+
+@group(0) @binding(2)
+var<storage, read_write> output_0: array<{{ data_type }}>;
+
+@compute @workgroup_size({{ workgroup_size_x }}, {{ workgroup_size_y }})
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let index_index = global_id.x; // Index of the index in the indices array that we are currently processing
+	let element_index = global_id.y; // Element that we are copying for this index
+	if (index_index >= {{ i_lens[1] }}u || element_index >= {{ i_chunks[0][0] }}u) {
+		return;
+	}
+
+	{% if indices_are_int64 %}
+		var index = i64_to_i32(input_1[index_index]);
+	{% else %}
+		var index = input_1[index_index];
+	{% endif %}
+
+	// Negative indexing is allowed; see https://github.com/onnx/onnx/blob/main/docs/Operators.md#inputs-38
+	if (index < 0) {
+		index = {{ i_shape[0][0] }} + index;
+	}
+
+	output_0[index_index * {{ i_chunks[0][0] }}u + element_index] = input_0[u32(index) * {{ i_chunks[0][0] }}u + element_index];
+}
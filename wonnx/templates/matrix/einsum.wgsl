@@ -0,0 +1,57 @@
+{%- include "structs.wgsl" -%}
+
+{% for input in i_lens %}
+@group({{ loop.index0 / 4 | int }}) @binding({{ loop.index0 % 4 }})
+var<storage, read> input_{{ loop.index0 }}: Array;
+{% endfor %}
+
+{% set binding_len = i_lens | length %}
+@group({{ binding_len / 4 | int }}) @binding({{ binding_len % 4 }})
+var<storage, read_write> output_0: Array;
+
+@compute @workgroup_size({{ workgroup_size_x }})
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	// Determine the offset of the first element to multiply in each input from the labels in the output
+	var rest = gidx;
+	{%- for input in i_lens %}
+	var base_{{ loop.index0 }} = 0u;
+	{%- endfor %}
+	{%- for chunk in output_chunks %}
+	{%- set axis = loop.index0 %}
+	let output_label_{{ axis }} = rest / {{ chunk }}u;
+	rest = rest % {{ chunk }}u;
+	{%- for strides in output_strides %}
+	{%- if strides[axis] != 0 %}
+	base_{{ loop.index0 }} = base_{{ loop.index0 }} + output_label_{{ axis }} * {{ strides[axis] }}u;
+	{%- endif %}
+	{%- endfor %}
+	{%- endfor %}
+
+	// Sum the products of the inputs over all combinations of values of the labels that are not in the output
+	var sum = Scalar();
+	for (var index = 0u; index < {{ sum_count }}u; index = index + 1u) {
+		var remaining = index;
+		{%- for input in i_lens %}
+		var offset_{{ loop.index0 }} = base_{{ loop.index0 }};
+		{%- endfor %}
+		{%- for chunk in sum_chunks %}
+		{%- set axis = loop.index0 %}
+		let summed_label_{{ axis }} = remaining / {{ chunk }}u;
+		remaining = remaining % {{ chunk }}u;
+		{%- for strides in sum_strides %}
+		{%- if strides[axis] != 0 %}
+		offset_{{ loop.index0 }} = offset_{{ loop.index0 }} + summed_label_{{ axis }} * {{ strides[axis] }}u;
+		{%- endif %}
+		{%- endfor %}
+		{%- endfor %}
+
+		sum = sum + {% for input in i_lens %}{% if not loop.first %} * {% endif %}input_{{ loop.index0 }}.data[offset_{{ loop.index0 }}]{% endfor %};
+	}
+
+	output_0.data[gidx] = sum;
+}
@@ -0,0 +1,30 @@
+{%- include "structs.wgsl" -%}
+
+@group(0) @binding(0)
+var<storage, read> input_0: Array;
+
+@group(0) @binding(1)
+var<storage, read_write> output_0: Array;
+
+@compute @workgroup_size(256, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+
+	if (gidx < {{ o_lens[0] }}u) {
+		{# The input index is the index of the first selected element plus, for each output dimension, the position along
+		that dimension multiplied by the (possibly negative) distance between selected elements in the input #}
+		var rest = gidx;
+		var index: i32 = {{ slice_offset }};
+
+		{%- for chunk in o_chunks[0] %}
+			{%- if loop.last %}
+				index = index + i32(rest) * ({{ slice_strides[loop.index0] }});
+			{%- else %}
+				index = index + i32(rest / {{ chunk }}u) * ({{ slice_strides[loop.index0] }});
+				rest = rest % {{ chunk }}u;
+			{%- endif %}
+		{%- endfor %}
+
+		output_0.data[gidx] = input_0.data[u32(index)];
+	}
+}
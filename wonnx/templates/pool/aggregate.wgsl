@@ -1,4 +1,5 @@
 {%- include "structs.wgsl" -%}
+{%- set spatial_rank = kernel_shape | length -%}
 
 {# 
 // The smallest floating point number that can be represented in IEEE-754. This should be -3.40282347E+38. However, Google 
@@ -25,8 +26,11 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 		let m = rest / {{ o_chunks[0][1] }}u;
 		rest = rest % {{ o_chunks[0][1] }}u;
 	
-		let y = rest / {{ o_chunks[0][2] }}u;
-		let x = rest % {{ o_chunks[0][2] }}u;
+		let spatial_index = rest;
+		{% for d in range(end = spatial_rank) -%}
+		let o_{{ d }} = rest / {{ o_spatial_chunks[d] }}u;
+		rest = rest % {{ o_spatial_chunks[d] }}u;
+		{% endfor %}
 		
 		{% if op_type == "AveragePool" -%}
 		var result = Vec4(Scalar(), Scalar(), Scalar(), Scalar());
@@ -41,37 +45,33 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 		var value = result;
 
 		let base_index = batch * {{ i_chunks[0][0] }}u + m * {{ i_chunks[0][1] * 4 }}u ;
-		var tmp_y = 0u;
-		var tmp_x = 0u;
 		var tmp_index = 0u;
 		var counter = Scalar();
 
-		for(var i: u32 = 0u; i < {{ kernel_shape[0] }}u; i = i + 1u) {
-			tmp_y = y * {{ stride[0] }}u + i * {{ dilation[0] }}u - {{ pad[0] }}u; 
-
-			if ((tmp_y < {{ original_height }}u) && (tmp_y >= 0u)) {
-				for(var j: u32 = 0u; j < {{ kernel_shape[1] }}u; j = j + 1u) { 
-					tmp_x = x * {{ stride[1] }}u + j * {{ dilation[1] }}u - {{ pad[1] }}u;
-						if ((tmp_x < {{ original_width }}u) && (tmp_x >= 0u)) {
-
-							tmp_index  = base_index + tmp_y * {{ original_width }}u + tmp_x;
-							value = Vec4(
-								input_0.data[tmp_index],
-								input_0.data[tmp_index + {{ i_chunks[0][1] }}u],
-								input_0.data[tmp_index + {{ 2 * i_chunks[0][1] }}u],
-								input_0.data[tmp_index + {{ 3 * i_chunks[0][1] }}u],
-							);
-							
-							{%- if op_type == "MaxPool" -%}
-								result = max(result, value);
-							{%- elif op_type == "AveragePool" -%}
-								result = result + value;
-								counter = counter + {{ scalar_type }}(1);
-							{%- endif -%}
-						}
-				}
-			}
-		}	
+		// One nested loop over the kernel per spatial dimension. Positions in the padding wrap around and fail the bounds check.
+		{% for d in range(end = spatial_rank) -%}
+		for(var k_{{ d }}: u32 = 0u; k_{{ d }} < {{ kernel_shape[d] }}u; k_{{ d }} = k_{{ d }} + 1u) {
+		let i_{{ d }} = o_{{ d }} * {{ stride[d] }}u + k_{{ d }} * {{ dilation[d] }}u - {{ pad[d] }}u;
+		if (i_{{ d }} < {{ original_shape[d] }}u) {
+		{% endfor %}
+			tmp_index = base_index {%- for d in range(end = spatial_rank) %} + i_{{ d }} * {{ i_spatial_chunks[d] }}u {%- endfor %};
+			value = Vec4(
+				input_0.data[tmp_index],
+				input_0.data[tmp_index + {{ i_chunks[0][1] }}u],
+				input_0.data[tmp_index + {{ 2 * i_chunks[0][1] }}u],
+				input_0.data[tmp_index + {{ 3 * i_chunks[0][1] }}u],
+			);
+
+			{%- if op_type == "MaxPool" -%}
+				result = max(result, value);
+			{%- elif op_type == "AveragePool" -%}
+				result = result + value;
+				counter = counter + {{ scalar_type }}(1);
+			{%- endif -%}
+		{% for d in range(end = spatial_rank) -%}
+		}
+		}
+		{% endfor %}
 
 		{% if op_type == "AveragePool" -%}
 			{% if count_include_pad == 0 %}
@@ -81,7 +81,7 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 			{% endif %}
 		{%- endif %}
 
-		let base_index_2 = batch * {{ o_chunks[0][0] }}u + m * {{ o_chunks[0][1] * 4 }}u + y * {{ width }}u + x;
+		let base_index_2 = batch * {{ o_chunks[0][0] }}u + m * {{ o_chunks[0][1] * 4 }}u + spatial_index;
 
 		for(var index_vec: u32 = 0u; index_vec < 4u; index_vec = index_vec + 1u) {
 			let index = base_index_2 + index_vec * {{ o_chunks[0][1] }}u;
@@ -98,8 +98,10 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 		let m = rest / {{ o_chunks[0][1] }}u;
 		rest = rest % {{ o_chunks[0][1] }}u;
 	
-		let y = rest / {{ o_chunks[0][2] }}u;
-		let x = rest % {{ o_chunks[0][2] }}u;
+		{% for d in range(end = spatial_rank) -%}
+		let o_{{ d }} = rest / {{ o_spatial_chunks[d] }}u;
+		rest = rest % {{ o_spatial_chunks[d] }}u;
+		{% endfor %}
 		
 		{% if op_type == "AveragePool" -%}
 		var result = Scalar();
@@ -110,32 +112,28 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 		var value = result;
 		
 		let base_index = batch * {{ i_chunks[0][0] }}u + m * {{ i_chunks[0][1] }}u;
-		var tmp_y = 0u;
-		var tmp_x = 0u;
 		var tmp_index = 0u;
 		var counter = Scalar();
 
-		for(var i: u32 = 0u; i < {{ kernel_shape[0] }}u; i = i + 1u) {
-			tmp_y = y * {{ stride[0] }}u + i * {{ dilation[0] }}u - {{ pad[0] }}u; 
-
-			if ((tmp_y < {{ original_height }}u) && (tmp_y >= 0u)) {
-				for(var j: u32 = 0u; j < {{ kernel_shape[1] }}u; j = j + 1u) { 
-					tmp_x = x * {{ stride[1] }}u + j * {{ dilation[1] }}u - {{ pad[1] }}u;
-						if ((tmp_x < {{ original_width }}u) && (tmp_x >= 0u)) {
-							tmp_index  = base_index + tmp_y * {{ original_width }}u + tmp_x;
-
-							value = input_0.data[tmp_index];
-							
-							{%- if op_type == "MaxPool" -%}
-								result = max(result, value);
-							{%- elif op_type == "AveragePool" -%}
-								result = result + value;
-								counter = counter + {{ scalar_type }}(1);
-							{%- endif -%}
-						}
-				}
-			}
+		// One nested loop over the kernel per spatial dimension. Positions in the padding wrap around and fail the bounds check.
+		{% for d in range(end = spatial_rank) -%}
+		for(var k_{{ d }}: u32 = 0u; k_{{ d }} < {{ kernel_shape[d] }}u; k_{{ d }} = k_{{ d }} + 1u) {
+		let i_{{ d }} = o_{{ d }} * {{ stride[d] }}u + k_{{ d }} * {{ dilation[d] }}u - {{ pad[d] }}u;
+		if (i_{{ d }} < {{ original_shape[d] }}u) {
+		{% endfor %}
+			tmp_index = base_index {%- for d in range(end = spatial_rank) %} + i_{{ d }} * {{ i_spatial_chunks[d] }}u {%- endfor %};
+			value = input_0.data[tmp_index];
+
+			{%- if op_type == "MaxPool" -%}
+				result = max(result, value);
+			{%- elif op_type == "AveragePool" -%}
+				result = result + value;
+				counter = counter + {{ scalar_type }}(1);
+			{%- endif -%}
+		{% for d in range(end = spatial_rank) -%}
+		}
 		}
+		{% endfor %}
 
 		{% if op_type == "AveragePool" -%}
 			{% if count_include_pad == 0 %}
@@ -1,4 +1,5 @@
 {%- include "structs.wgsl" -%}
+{%- set spatial_rank = kernel_shape | length -%}
 
 @group(0) @binding(0)
 var<storage, read> input_0: Array;
@@ -29,9 +30,10 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 		let m = rest / {{ o_chunks[0][1] }}u;
 		rest = rest % {{ o_chunks[0][1] }}u;
 
-		let y = rest / {{ o_chunks[0][2] }}u;
-		let x = rest % {{ o_chunks[0][2] }}u;
-
+		{% for d in range(end = spatial_rank) -%}
+		let o_{{ d }} = rest / {{ o_spatial_chunks[d] }}u;
+		rest = rest % {{ o_spatial_chunks[d] }}u;
+		{% endfor %}
 		let M = {{ o_shape[0][1] }}u;
 		let current_group: u32 = m * {{ groups }}u / M;
 
@@ -44,21 +46,19 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 			let base_index = root_index + c * {{ i_chunks[0][1] }}u;
 			let base_kernel_index = root_kernel_index + c % {{ channels_per_group }}u * {{ kernel_length }}u;
 
-			for(var i: u32 = 0u; i < {{ kernel_shape[0] }}u; i = i + 1u) {
-				let tmp_y = i32(y) * {{ stride[0] }}i + i32(i) * {{ dilation[0] }}i - {{ pad[0] }}i; 
-
-				if ((tmp_y < {{ original_height }}i) && (tmp_y >= 0i)) {
-					for(var j: u32 = 0u; j < {{ kernel_shape[1] }}u; j = j + 1u) { 
-						let tmp_x = i32(x) * {{ stride[1] }}i + i32(j) * {{ dilation[1] }}i - {{ pad[1] }}i;
-
-						if ((tmp_x < {{ original_width }}i) && (tmp_x >= 0i)) {
-							let tmp_index = base_index + u32(tmp_y) * {{ original_width }}u + u32(tmp_x);
-							let index_kernel = base_kernel_index + i * {{ kernel_shape[1] }}u + j;
-							result = input_0.data[tmp_index] * input_1.data[index_kernel] + result;
-						}
-					}
-				}
+			// One nested loop over the kernel per spatial dimension
+			{% for d in range(end = spatial_rank) -%}
+			for(var k_{{ d }}: u32 = 0u; k_{{ d }} < {{ kernel_shape[d] }}u; k_{{ d }} = k_{{ d }} + 1u) {
+			let i_{{ d }} = i32(o_{{ d }}) * {{ stride[d] }}i + i32(k_{{ d }}) * {{ dilation[d] }}i - {{ pad[d] }}i;
+			if ((i_{{ d }} < {{ original_shape[d] }}i) && (i_{{ d }} >= 0i)) {
+			{% endfor %}
+				let tmp_index = base_index {%- for d in range(end = spatial_rank) %} + u32(i_{{ d }}) * {{ i_spatial_chunks[d] }}u {%- endfor %};
+				let index_kernel = base_kernel_index {%- for d in range(end = spatial_rank) %} + k_{{ d }} * {{ kernel_chunks[d] }}u {%- endfor %};
+				result = input_0.data[tmp_index] * input_1.data[index_kernel] + result;
+			{% for d in range(end = spatial_rank) -%}
+			}
 			}
+			{% endfor %}
 		}
 
 		{% if i_lens | length == 3 -%}
@@ -0,0 +1,122 @@
+{%- include "structs.wgsl" -%}
+{%- if indices_are_int64 %}
+{%- include "snippets/int64.wgsl" -%}
+{%- endif %}
+
+@group(0) @binding(0)
+var<storage, read> input_0: Array; // X
+
+@group(0) @binding(1)
+var<storage, read> input_1: Array; // rois
+
+@group(0) @binding(2)
+var<storage, read> input_2: array<{{ indices_type }}>; // batch_indices
+
+@group(0) @binding(3)
+var<storage, read_write> output_0: Array;
+
+// Returns the value at (y, x) in the channel starting at the specified offset, interpolated bilinearly from the four
+// surrounding pixels (or zero when the point lies outside of the image). In 'max' mode, the largest of the weighted values
+// of the surrounding pixels is returned instead of their sum.
+fn sample(channel_start: u32, sample_y: f32, sample_x: f32) -> f32 {
+	if (sample_y < -1.0 || sample_y > f32({{ height }}) || sample_x < -1.0 || sample_x > f32({{ width }})) {
+		return 0.0;
+	}
+
+	var y = max(sample_y, 0.0);
+	var x = max(sample_x, 0.0);
+	var y_low = u32(y);
+	var x_low = u32(x);
+	var y_high = y_low + 1u;
+	var x_high = x_low + 1u;
+	if (y_low >= {{ height - 1 }}u) {
+		y_low = {{ height - 1 }}u;
+		y_high = y_low;
+		y = f32(y_low);
+	}
+	if (x_low >= {{ width - 1 }}u) {
+		x_low = {{ width - 1 }}u;
+		x_high = x_low;
+		x = f32(x_low);
+	}
+
+	let ly = y - f32(y_low);
+	let lx = x - f32(x_low);
+	let hy = 1.0 - ly;
+	let hx = 1.0 - lx;
+	let v1 = hy * hx * f32(input_0.data[channel_start + y_low * {{ width }}u + x_low]);
+	let v2 = hy * lx * f32(input_0.data[channel_start + y_low * {{ width }}u + x_high]);
+	let v3 = ly * hx * f32(input_0.data[channel_start + y_high * {{ width }}u + x_low]);
+	let v4 = ly * lx * f32(input_0.data[channel_start + y_high * {{ width }}u + x_high]);
+	{%- if max %}
+	return max(max(v1, v2), max(v3, v4));
+	{%- else %}
+	return v1 + v2 + v3 + v4;
+	{%- endif %}
+}
+
+@compute @workgroup_size({{ workgroup_size_x }})
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	// Each invocation calculates the value of one output bin (roi, channel, bin_y, bin_x)
+	let roi = gidx / {{ o_chunks[0][0] }}u;
+	let channel = (gidx / {{ o_chunks[0][1] }}u) % {{ channels }}u;
+	let bin_y = (gidx / {{ output_width }}u) % {{ output_height }}u;
+	let bin_x = gidx % {{ output_width }}u;
+
+	{%- if indices_are_int64 %}
+	let batch = u32(i64_to_i32(input_2[roi]));
+	{%- else %}
+	let batch = u32(input_2[roi]);
+	{%- endif %}
+	let channel_start = (batch * {{ channels }}u + channel) * {{ height * width }}u;
+
+	// The RoI is specified as (x1, y1, x2, y2) in the coordinates of the original image
+	let offset = {% if half_pixel %}0.5{% else %}0.0{% endif %};
+	let start_x = f32(input_1.data[roi * 4u]) * f32({{ spatial_scale }}) - offset;
+	let start_y = f32(input_1.data[roi * 4u + 1u]) * f32({{ spatial_scale }}) - offset;
+	let end_x = f32(input_1.data[roi * 4u + 2u]) * f32({{ spatial_scale }}) - offset;
+	let end_y = f32(input_1.data[roi * 4u + 3u]) * f32({{ spatial_scale }}) - offset;
+	{%- if half_pixel %}
+	let roi_width = end_x - start_x;
+	let roi_height = end_y - start_y;
+	{%- else %}
+	// Malformed RoIs are forced to be at least 1x1
+	let roi_width = max(end_x - start_x, 1.0);
+	let roi_height = max(end_y - start_y, 1.0);
+	{%- endif %}
+	let bin_height = roi_height / f32({{ output_height }});
+	let bin_width = roi_width / f32({{ output_width }});
+
+	{%- if sampling_ratio > 0 %}
+	let grid_y = {{ sampling_ratio }}u;
+	let grid_x = {{ sampling_ratio }}u;
+	{%- else %}
+	let grid_y = u32(max(ceil(bin_height), 0.0));
+	let grid_x = u32(max(ceil(bin_width), 0.0));
+	{%- endif %}
+
+	// Sample the bin at regularly spaced points
+	var result = 0.0;
+	for (var iy = 0u; iy < grid_y; iy = iy + 1u) {
+		let y = start_y + f32(bin_y) * bin_height + (f32(iy) + 0.5) * bin_height / f32(grid_y);
+		for (var ix = 0u; ix < grid_x; ix = ix + 1u) {
+			let x = start_x + f32(bin_x) * bin_width + (f32(ix) + 0.5) * bin_width / f32(grid_x);
+			let value = sample(channel_start, y, x);
+			{%- if max %}
+			result = select(max(result, value), value, iy == 0u && ix == 0u);
+			{%- else %}
+			result = result + value;
+			{%- endif %}
+		}
+	}
+
+	{%- if not max %}
+	result = result / f32(max(grid_y * grid_x, 1u));
+	{%- endif %}
+	output_0.data[gidx] = Scalar(result);
+}
@@ -0,0 +1,73 @@
+{%- include "structs.wgsl" -%}
+{%- if indices_are_int64 %}
+{%- include "snippets/int64.wgsl" -%}
+{%- endif %}
+
+@group(0) @binding(0)
+var<storage, read> input_0: Array;
+
+{% if has_values %}
+@group(0) @binding(1)
+var<storage, read_write> output_0: Array;
+
+@group(0) @binding(2)
+var<storage, read_write> output_1: array<{{ indices_type }}>;
+{% else %}
+@group(0) @binding(1)
+var<storage, read_write> output_0: array<{{ indices_type }}>;
+{% endif %}
+
+// Whether element a comes before element b in the output order. Elements with equal values are ordered by index.
+fn precedes(a_value: Scalar, a_index: i32, b_value: Scalar, b_index: i32) -> bool {
+	if (a_value == b_value) {
+		return a_index {% if prefer_last_index %}>{% else %}<{% endif %} b_index;
+	}
+	return a_value {% if largest %}>{% else %}<{% endif %} b_value;
+}
+
+@compute @workgroup_size({{ workgroup_size_x }}, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+
+	{# Each invocation selects the first k elements (in output order) along the axis for one position in the other dimensions #}
+	if (gidx < {{ row_count }}u) {
+		let outer = gidx / {{ inner_size }}u;
+		let inner = gidx % {{ inner_size }}u;
+		let input_start = outer * {{ axis_length * inner_size }}u + inner;
+		let output_start = outer * {{ k * inner_size }}u + inner;
+
+		var previous_value = Scalar();
+		var previous_index = -1;
+		for(var j = 0u; j < {{ k }}u; j = j + 1u) {
+			// Find the first element in output order that comes after the previously selected element
+			var best_value = Scalar();
+			var best_index = -1;
+			for(var i = 0u; i < {{ axis_length }}u; i = i + 1u) {
+				let value = input_0.data[input_start + i * {{ inner_size }}u];
+				let index = i32(i);
+				if (previous_index >= 0 && !precedes(previous_value, previous_index, value, index)) {
+					continue;
+				}
+				if (best_index < 0 || precedes(value, index, best_value, best_index)) {
+					best_value = value;
+					best_index = index;
+				}
+			}
+
+			let output_index = output_start + j * {{ inner_size }}u;
+			{%- if has_values %}
+			output_0.data[output_index] = best_value;
+			{%- set indices_output = "output_1" %}
+			{%- else %}
+			{%- set indices_output = "output_0" %}
+			{%- endif %}
+			{%- if indices_are_int64 %}
+			{{ indices_output }}[output_index] = i64_from_i32(best_index);
+			{%- else %}
+			{{ indices_output }}[output_index] = best_index;
+			{%- endif %}
+			previous_value = best_value;
+			previous_index = best_index;
+		}
+	}
+}
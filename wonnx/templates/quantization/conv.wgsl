@@ -0,0 +1,114 @@
+{%- include "structs.wgsl" -%}
+{%- include "snippets/quantization.wgsl" -%}
+
+@group(0) @binding(0)
+var<storage, read> input_0: PackedArray; // X
+
+@group(0) @binding(1)
+var<storage, read> input_1: Array; // X scale
+
+@group(0) @binding(2)
+var<storage, read> input_2: PackedArray; // X zero point
+
+@group(0) @binding(3)
+var<storage, read> input_3: PackedArray; // W
+
+@group(1) @binding(0)
+var<storage, read> input_4: Array; // W scale
+
+@group(1) @binding(1)
+var<storage, read> input_5: PackedArray; // W zero point
+
+@group(1) @binding(2)
+var<storage, read> input_6: Array; // Y scale
+
+@group(1) @binding(3)
+var<storage, read> input_7: PackedArray; // Y zero point
+
+{% if i_lens | length == 9 -%}
+	@group(2) @binding(0)
+	var<storage, read> input_8: IntArray; // Bias
+
+	@group(2) @binding(1)
+	var<storage, read_write> output_0: PackedArray;
+{%- else -%}
+	@group(2) @binding(0)
+	var<storage, read_write> output_0: PackedArray;
+{%- endif %}
+
+@compute @workgroup_size(256, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ packed_length }}u) {
+		return;
+	}
+
+	let x_scale = input_1.data[0];
+	let x_zero_point = unpack_{{ x_type }}(input_2.data[0], 0u);
+	let y_scale = input_6.data[0];
+	let y_zero_point = unpack_{{ output_type }}(input_7.data[0], 0u);
+
+	{# Each invocation calculates the four values that are packed together in a single output element #}
+	var values = vec4<i32>();
+	for(var lane: u32 = 0u; lane < 4u; lane = lane + 1u) {
+		let index = gidx * 4u + lane;
+		if (index < {{ o_lens[0] }}u) {
+			let batch = index / {{ o_chunks[0][0] }}u;
+			var rest = index % {{ o_chunks[0][0] }}u;
+
+			let m = rest / {{ o_chunks[0][1] }}u;
+			rest = rest % {{ o_chunks[0][1] }}u;
+
+			let y = rest / {{ o_chunks[0][2] }}u;
+			let x = rest % {{ o_chunks[0][2] }}u;
+
+			{% if per_channel_weights -%}
+				let w_scale = input_4.data[m];
+				let w_zero_point = unpack_{{ w_type }}(input_5.data[m / 4u], m);
+			{%- else -%}
+				let w_scale = input_4.data[0];
+				let w_zero_point = unpack_{{ w_type }}(input_5.data[0], 0u);
+			{%- endif %}
+
+			let M = {{ o_shape[0][1] }}u;
+			let current_group: u32 = m * {{ groups }}u / M;
+
+			var result = 0i;
+
+			let root_index = batch * {{ i_chunks[0][0] }}u;
+			let root_kernel_index = m * {{ kernel_channel_len }}u;
+
+			for(var c: u32 = current_group * {{ channels_per_group }}u; c < (current_group + 1u) * {{ channels_per_group }}u; c = c + 1u) {
+				let base_index = root_index + c * {{ i_chunks[0][1] }}u;
+				let base_kernel_index = root_kernel_index + c % {{ channels_per_group }}u * {{ kernel_length }}u;
+
+				for(var i: u32 = 0u; i < {{ kernel_shape[0] }}u; i = i + 1u) {
+					let tmp_y = i32(y) * {{ stride[0] }}i + i32(i) * {{ dilation[0] }}i - {{ pad[0] }}i;
+
+					{# Padding is equivalent to the zero point of the input, so padded positions do not contribute #}
+					if ((tmp_y < {{ original_height }}i) && (tmp_y >= 0i)) {
+						for(var j: u32 = 0u; j < {{ kernel_shape[1] }}u; j = j + 1u) {
+							let tmp_x = i32(x) * {{ stride[1] }}i + i32(j) * {{ dilation[1] }}i - {{ pad[1] }}i;
+
+							if ((tmp_x < {{ original_width }}i) && (tmp_x >= 0i)) {
+								let tmp_index = base_index + u32(tmp_y) * {{ original_width }}u + u32(tmp_x);
+								let index_kernel = base_kernel_index + i * {{ kernel_shape[1] }}u + j;
+								let input_value = unpack_{{ x_type }}(input_0.data[tmp_index / 4u], tmp_index) - x_zero_point;
+								let weight_value = unpack_{{ w_type }}(input_3.data[index_kernel / 4u], index_kernel) - w_zero_point;
+								result = result + input_value * weight_value;
+							}
+						}
+					}
+				}
+			}
+
+			{% if i_lens | length == 9 -%}
+				result = result + input_8.data[m];
+			{%- endif %}
+
+			values[lane] = quantize_value(f32(result) * x_scale * w_scale, y_scale, y_zero_point, {{ lowest }}.0, {{ highest }}.0);
+		}
+	}
+
+	output_0.data[gidx] = pack_8bit(values);
+}
@@ -0,0 +1,51 @@
+{%- include "structs.wgsl" -%}
+{%- include "snippets/quantization.wgsl" -%}
+
+@group(0) @binding(0)
+var<storage, read> input_0: {% if input_type == "i32" %}IntArray{% else %}PackedArray{% endif %}; // X
+
+@group(0) @binding(1)
+var<storage, read> input_1: Array; // Scale
+
+{% if i_lens | length == 3 -%}
+	@group(0) @binding(2)
+	var<storage, read> input_2: {% if input_type == "i32" %}IntArray{% else %}PackedArray{% endif %}; // Zero point
+
+	@group(0) @binding(3)
+	var<storage, read_write> output_0: Array;
+{%- else -%}
+	@group(0) @binding(2)
+	var<storage, read_write> output_0: Array;
+{%- endif %}
+
+@compute @workgroup_size({{ workgroup_size_x }}, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ o_lens[0] }}u) {
+		return;
+	}
+
+	{% if per_axis -%}
+		let channel = (gidx / {{ axis_chunk }}u) % {{ axis_dim }}u;
+	{%- else -%}
+		let channel = 0u;
+	{%- endif %}
+
+	{% if input_type == "i32" -%}
+		let value = input_0.data[gidx];
+	{%- else -%}
+		let value = unpack_{{ input_type }}(input_0.data[gidx / 4u], gidx);
+	{%- endif %}
+
+	{% if i_lens | length == 3 -%}
+		{% if input_type == "i32" -%}
+			let zero_point = input_2.data[channel];
+		{%- else -%}
+			let zero_point = unpack_{{ input_type }}(input_2.data[channel / 4u], channel);
+		{%- endif %}
+	{%- else -%}
+		let zero_point = 0i;
+	{%- endif %}
+
+	output_0.data[gidx] = Scalar(value - zero_point) * input_1.data[channel];
+}
@@ -0,0 +1,114 @@
+{%- include "structs.wgsl" -%}
+{%- include "snippets/quantization.wgsl" -%}
+
+{% if op_type == "QLinearMatMul" -%}
+	@group(0) @binding(0)
+	var<storage, read> input_0: PackedArray; // A
+
+	@group(0) @binding(1)
+	var<storage, read> input_1: Array; // A scale
+
+	@group(0) @binding(2)
+	var<storage, read> input_2: PackedArray; // A zero point
+
+	@group(0) @binding(3)
+	var<storage, read> input_3: PackedArray; // B
+
+	@group(1) @binding(0)
+	var<storage, read> input_4: Array; // B scale
+
+	@group(1) @binding(1)
+	var<storage, read> input_5: PackedArray; // B zero point
+
+	@group(1) @binding(2)
+	var<storage, read> input_6: Array; // Y scale
+
+	@group(1) @binding(3)
+	var<storage, read> input_7: PackedArray; // Y zero point
+
+	@group(2) @binding(0)
+	var<storage, read_write> output_0: PackedArray;
+{%- else -%}
+	@group(0) @binding(0)
+	var<storage, read> input_0: PackedArray; // A
+
+	@group(0) @binding(1)
+	var<storage, read> input_1: PackedArray; // B
+
+	{% if i_lens | length > 2 -%}
+		@group(0) @binding(2)
+		var<storage, read> input_2: PackedArray; // A zero point
+	{%- endif %}
+
+	{% if i_lens | length > 3 -%}
+		@group(0) @binding(3)
+		var<storage, read> input_3: PackedArray; // B zero point
+	{%- endif %}
+
+	{% set output_binding = i_lens | length -%}
+	@group({{ output_binding / 4 | int }}) @binding({{ output_binding % 4 }})
+	var<storage, read_write> output_0: IntArray;
+{%- endif %}
+
+@compute @workgroup_size({{ workgroup_size_x }}, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ packed_length }}u) {
+		return;
+	}
+
+	{% if op_type == "QLinearMatMul" -%}
+		let a_zero_point = unpack_{{ a_type }}(input_2.data[0], 0u);
+		let b_zero_point = unpack_{{ b_type }}(input_5.data[0], 0u);
+		let y_zero_point = unpack_{{ output_type }}(input_7.data[0], 0u);
+		let scale = input_1.data[0] * input_4.data[0];
+		{% set right = "input_3" %}
+	{%- else -%}
+		{% if i_lens | length > 2 -%}
+			let a_zero_point = unpack_{{ a_type }}(input_2.data[0], 0u);
+		{%- else -%}
+			let a_zero_point = 0i;
+		{%- endif %}
+		{% if i_lens | length > 3 -%}
+			let b_zero_point = unpack_{{ b_type }}(input_3.data[0], 0u);
+		{%- else -%}
+			let b_zero_point = 0i;
+		{%- endif %}
+		{% set right = "input_1" %}
+	{%- endif %}
+
+	{# Each invocation calculates four consecutive output elements (which are packed together for 8-bit outputs) #}
+	{% if op_type == "QLinearMatMul" -%}
+		var values = vec4<i32>();
+	{%- endif %}
+	for(var lane: u32 = 0u; lane < 4u; lane = lane + 1u) {
+		let index = gidx * 4u + lane;
+		if (index < {{ o_lens[0] }}u) {
+			let stack_index = index / {{ dim_m * dim_n }}u;
+			let row = (index % {{ dim_m * dim_n }}u) / {{ dim_n }}u;
+			let column = index % {{ dim_n }}u;
+
+			let left_offset = stack_index * {{ stack_left_stride }}u + row * {{ dim_k }}u;
+			let right_offset = stack_index * {{ stack_right_stride }}u + column;
+
+			var result = 0i;
+			for(var k: u32 = 0u; k < {{ dim_k }}u; k = k + 1u) {
+				let index_left = left_offset + k;
+				let index_right = right_offset + k * {{ dim_n }}u;
+				let left = unpack_{{ a_type }}(input_0.data[index_left / 4u], index_left) - a_zero_point;
+				let right = unpack_{{ b_type }}({{ right }}.data[index_right / 4u], index_right) - b_zero_point;
+				result = result + left * right;
+			}
+
+			{% if op_type == "QLinearMatMul" -%}
+				values[lane] = quantize_value(f32(result) * scale, input_6.data[0], y_zero_point, {{ lowest }}.0, {{ highest }}.0);
+			{%- else -%}
+				output_0.data[index] = result;
+			{%- endif %}
+		}
+	}
+
+	{% if op_type == "QLinearMatMul" -%}
+		output_0.data[gidx] = pack_8bit(values);
+	{%- endif %}
+}
@@ -0,0 +1,50 @@
+{%- include "structs.wgsl" -%}
+{%- include "snippets/quantization.wgsl" -%}
+
+@group(0) @binding(0)
+var<storage, read> input_0: Array; // X
+
+@group(0) @binding(1)
+var<storage, read> input_1: Array; // Scale
+
+{% if i_lens | length == 3 -%}
+	@group(0) @binding(2)
+	var<storage, read> input_2: PackedArray; // Zero point
+
+	@group(0) @binding(3)
+	var<storage, read_write> output_0: PackedArray;
+{%- else -%}
+	@group(0) @binding(2)
+	var<storage, read_write> output_0: PackedArray;
+{%- endif %}
+
+@compute @workgroup_size({{ workgroup_size_x }}, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ packed_length }}u) {
+		return;
+	}
+
+	{# Each invocation calculates the four values that are packed together in a single output element #}
+	var values = vec4<i32>();
+	for(var lane: u32 = 0u; lane < 4u; lane = lane + 1u) {
+		let index = gidx * 4u + lane;
+		if (index < {{ o_lens[0] }}u) {
+			{% if per_axis -%}
+				let channel = (index / {{ axis_chunk }}u) % {{ axis_dim }}u;
+			{%- else -%}
+				let channel = 0u;
+			{%- endif %}
+
+			{% if i_lens | length == 3 -%}
+				let zero_point = unpack_{{ output_type }}(input_2.data[channel / 4u], channel);
+			{%- else -%}
+				let zero_point = 0i;
+			{%- endif %}
+
+			values[lane] = quantize_value(input_0.data[index], input_1.data[channel], zero_point, {{ lowest }}.0, {{ highest }}.0);
+		}
+	}
+
+	output_0.data[gidx] = pack_8bit(values);
+}
@@ -0,0 +1,235 @@
+{%- include "structs.wgsl" -%}
+
+struct IntArray {
+	data: array<i32>
+};
+
+// The step of the sequence that is processed by this invocation (the shader is invoked once per step and phase)
+struct Step {
+	index: u32,
+	phase: u32,
+};
+
+{% for binding in bindings %}
+@group({{ binding.group }}) @binding({{ binding.binding }})
+var<storage, {{ binding.access }}> {{ binding.name }}: {{ binding.array_type }};
+{% endfor %}
+
+@group({{ step_group }}) @binding(0)
+var<uniform> step: Step;
+
+{# The state buffer holds the hidden state of the previous and current step, followed by the cell state (LSTM) or the
+reset hidden state (GRU, when linear_before_reset is 0). Each part has a value for every direction, batch and unit. #}
+{% set cell_offset = state_length * 2 %}
+
+{% for slot_activations in activations %}
+fn activation_{{ loop.index0 }}(direction: u32, x: Scalar) -> Scalar {
+	{%- for activation in slot_activations %}
+	if (direction == {{ loop.index0 }}u) {
+		{%- if activation.name == "Relu" %}
+		return max(x, Scalar());
+		{%- elif activation.name == "Tanh" %}
+		return tanh(x);
+		{%- elif activation.name == "Sigmoid" %}
+		return Scalar(1) / (Scalar(1) + exp(-x));
+		{%- elif activation.name == "Affine" %}
+		return Scalar({{ activation.alpha }}) * x + Scalar({{ activation.beta }});
+		{%- elif activation.name == "LeakyRelu" %}
+		return select(Scalar({{ activation.alpha }}) * x, x, x >= Scalar());
+		{%- elif activation.name == "ThresholdedRelu" %}
+		return select(Scalar(), x, x > Scalar({{ activation.alpha }}));
+		{%- elif activation.name == "ScaledTanh" %}
+		return Scalar({{ activation.alpha }}) * tanh(Scalar({{ activation.beta }}) * x);
+		{%- elif activation.name == "HardSigmoid" %}
+		return clamp(Scalar({{ activation.alpha }}) * x + Scalar({{ activation.beta }}), Scalar(), Scalar(1));
+		{%- elif activation.name == "Elu" %}
+		return select(Scalar({{ activation.alpha }}) * (exp(x) - Scalar(1)), x, x >= Scalar());
+		{%- elif activation.name == "Softsign" %}
+		return x / (Scalar(1) + abs(x));
+		{%- elif activation.name == "Softplus" %}
+		return log(Scalar(1) + exp(x));
+		{%- endif %}
+	}
+	{%- endfor %}
+	return x;
+}
+{% endfor %}
+
+fn clipped(x: Scalar) -> Scalar {
+	{%- if has_clip %}
+	return clamp(x, Scalar(-{{ clip }}), Scalar({{ clip }}));
+	{%- else %}
+	return x;
+	{%- endif %}
+}
+
+// The hidden state after the previous step, indexed by direction, batch and unit
+fn previous_hidden(index: u32) -> Scalar {
+	if (step.index == 0u) {
+		{%- if has_initial_h %}
+		return input_initial_h.data[index];
+		{%- else %}
+		return Scalar();
+		{%- endif %}
+	}
+	return state.data[(step.index % 2u) * {{ state_length }}u + index];
+}
+
+{% if op_type == "LSTM" %}
+fn previous_cell(index: u32) -> Scalar {
+	if (step.index == 0u) {
+		{%- if has_initial_c %}
+		return input_initial_c.data[index];
+		{%- else %}
+		return Scalar();
+		{%- endif %}
+	}
+	return state.data[{{ cell_offset }}u + index];
+}
+{% endif %}
+
+// Sum of the input at time t multiplied by a row of the input weights, plus the input bias of that row
+fn input_gate(direction: u32, t: u32, batch: u32, row: u32) -> Scalar {
+	let x_start = (t * {{ batch_size }}u + batch) * {{ input_size }}u;
+	let w_start = (direction * {{ gate_count * hidden_size }}u + row) * {{ input_size }}u;
+	var sum = Scalar();
+	for(var k: u32 = 0u; k < {{ input_size }}u; k = k + 1u) {
+		sum = sum + input_x.data[x_start + k] * input_w.data[w_start + k];
+	}
+	{%- if has_b %}
+	sum = sum + input_b.data[direction * {{ 2 * gate_count * hidden_size }}u + row];
+	{%- endif %}
+	return sum;
+}
+
+// Sum of a hidden state multiplied by a row of the recurrence weights, plus the recurrence bias of that row. The hidden
+// state is either that of the previous step or the one starting at the specified index in the state buffer.
+fn recurrence_gate(direction: u32, batch: u32, row: u32, state_start: u32, use_previous: bool) -> Scalar {
+	let h_start = (direction * {{ batch_size }}u + batch) * {{ hidden_size }}u;
+	let r_start = (direction * {{ gate_count * hidden_size }}u + row) * {{ hidden_size }}u;
+	var sum = Scalar();
+	for(var k: u32 = 0u; k < {{ hidden_size }}u; k = k + 1u) {
+		var h = Scalar();
+		if (use_previous) {
+			h = previous_hidden(h_start + k);
+		} else {
+			h = state.data[state_start + h_start + k];
+		}
+		sum = sum + h * input_r.data[r_start + k];
+	}
+	{%- if has_b %}
+	sum = sum + input_b.data[direction * {{ 2 * gate_count * hidden_size }}u + {{ gate_count * hidden_size }}u + row];
+	{%- endif %}
+	return sum;
+}
+
+@compute @workgroup_size(256, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+	let gidx = global_id.x;
+	if (gidx >= {{ state_length }}u) {
+		return;
+	}
+
+	let direction = gidx / {{ batch_size * hidden_size }}u;
+	let batch = (gidx / {{ hidden_size }}u) % {{ batch_size }}u;
+	let unit = gidx % {{ hidden_size }}u;
+	let next_hidden_index = ((step.index + 1u) % 2u) * {{ state_length }}u + gidx;
+
+	{% if has_sequence_lens -%}
+	let sequence_length = u32(input_sequence_lens.data[batch]);
+	{%- else -%}
+	let sequence_length = {{ sequence_length }}u;
+	{%- endif %}
+
+	// Steps beyond the length of a sequence do not change the state and produce zero output
+	if (step.index >= sequence_length) {
+		if (step.phase == {{ phases - 1 }}u) {
+			let hidden = previous_hidden(gidx);
+			state.data[next_hidden_index] = hidden;
+			{%- if has_y %}
+			output_y.data[step.index * {{ state_length }}u + gidx] = Scalar();
+			{%- endif %}
+			{%- if has_y_h %}
+			output_y_h.data[gidx] = hidden;
+			{%- endif %}
+			{%- if op_type == "LSTM" %}
+			let cell = previous_cell(gidx);
+			state.data[{{ cell_offset }}u + gidx] = cell;
+			{%- if has_y_c %}
+			output_y_c.data[gidx] = cell;
+			{%- endif %}
+			{%- endif %}
+		}
+		return;
+	}
+
+	// A reversed sequence is processed from its last element (as determined by its length) to the first
+	{% if direction == "forward" -%}
+	let t = step.index;
+	{%- elif direction == "reverse" -%}
+	let t = sequence_length - 1u - step.index;
+	{%- else -%}
+	let t = select(step.index, sequence_length - 1u - step.index, direction == 1u);
+	{%- endif %}
+
+	{% if op_type == "LSTM" %}
+	// Gates are ordered i, o, f, c (peephole weights i, o, f)
+	let cell_before = previous_cell(gidx);
+	{%- if has_p %}
+	let p_start = direction * {{ 3 * hidden_size }}u + unit;
+	let p_i = input_p.data[p_start];
+	let p_o = input_p.data[p_start + {{ hidden_size }}u];
+	let p_f = input_p.data[p_start + {{ 2 * hidden_size }}u];
+	{%- else %}
+	let p_i = Scalar();
+	let p_o = Scalar();
+	let p_f = Scalar();
+	{%- endif %}
+
+	let gate_i = activation_0(direction, clipped(input_gate(direction, t, batch, unit) + recurrence_gate(direction, batch, unit, 0u, true) + p_i * cell_before));
+	{%- if input_forget == 1 %}
+	let gate_f = Scalar(1) - gate_i;
+	{%- else %}
+	let gate_f = activation_0(direction, clipped(input_gate(direction, t, batch, unit + {{ 2 * hidden_size }}u) + recurrence_gate(direction, batch, unit + {{ 2 * hidden_size }}u, 0u, true) + p_f * cell_before));
+	{%- endif %}
+	let gate_c = activation_1(direction, clipped(input_gate(direction, t, batch, unit + {{ 3 * hidden_size }}u) + recurrence_gate(direction, batch, unit + {{ 3 * hidden_size }}u, 0u, true)));
+	let cell = gate_f * cell_before + gate_i * gate_c;
+	let gate_o = activation_0(direction, clipped(input_gate(direction, t, batch, unit + {{ hidden_size }}u) + recurrence_gate(direction, batch, unit + {{ hidden_size }}u, 0u, true) + p_o * cell));
+	let hidden = gate_o * activation_2(direction, cell);
+
+	state.data[{{ cell_offset }}u + gidx] = cell;
+	{%- if has_y_c %}
+	output_y_c.data[gidx] = cell;
+	{%- endif %}
+
+	{% elif op_type == "GRU" %}
+	// Gates are ordered z, r, h
+	{%- if linear_before_reset == 0 %}
+	// The reset gate is applied to the hidden state before it is multiplied with the recurrence weights, so the reset
+	// hidden state for all units is calculated in a first phase
+	if (step.phase == 0u) {
+		let gate_r = activation_0(direction, clipped(input_gate(direction, t, batch, unit + {{ hidden_size }}u) + recurrence_gate(direction, batch, unit + {{ hidden_size }}u, 0u, true)));
+		state.data[{{ cell_offset }}u + gidx] = gate_r * previous_hidden(gidx);
+		return;
+	}
+	let gate_h = activation_1(direction, clipped(input_gate(direction, t, batch, unit + {{ 2 * hidden_size }}u) + recurrence_gate(direction, batch, unit + {{ 2 * hidden_size }}u, {{ cell_offset }}u, false)));
+	{%- else %}
+	let gate_r = activation_0(direction, clipped(input_gate(direction, t, batch, unit + {{ hidden_size }}u) + recurrence_gate(direction, batch, unit + {{ hidden_size }}u, 0u, true)));
+	let gate_h = activation_1(direction, clipped(input_gate(direction, t, batch, unit + {{ 2 * hidden_size }}u) + gate_r * recurrence_gate(direction, batch, unit + {{ 2 * hidden_size }}u, 0u, true)));
+	{%- endif %}
+	let gate_z = activation_0(direction, clipped(input_gate(direction, t, batch, unit) + recurrence_gate(direction, batch, unit, 0u, true)));
+	let hidden = (Scalar(1) - gate_z) * gate_h + gate_z * previous_hidden(gidx);
+
+	{% else %}
+	let hidden = activation_0(direction, clipped(input_gate(direction, t, batch, unit) + recurrence_gate(direction, batch, unit, 0u, true)));
+	{% endif %}
+
+	state.data[next_hidden_index] = hidden;
+	{%- if has_y %}
+	// Y has shape [seq_length, num_directions, batch_size, hidden_size]
+	output_y.data[t * {{ state_length }}u + gidx] = hidden;
+	{%- endif %}
+	{%- if has_y_h %}
+	output_y_h.data[gidx] = hidden;
+	{%- endif %}
+}
@@ -47,6 +47,10 @@
 		)
 	);
 
+{%- elif activation_type == "LeakyRelu" -%}
+	{{ activation_output }} = max({{ activation_input }}, Scalar())
+	                         + min({{ scalar_type }}({{ alpha }}) * {{ activation_input }}, Scalar());
+
 {%- elif activation_output != activation_input -%}
 	{{ activation_output }} = {{ activation_input }};
 
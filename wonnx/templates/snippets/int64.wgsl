@@ -0,0 +1,145 @@
+{# 
+// WGSL does not (yet) support 64-bit integers. In strict int64 mode, 64-bit integers are represented as vec2<u32>, where x
+// holds the low 32 bits and y the high 32 bits of the value in two's complement (this is the same as the little-endian
+// memory layout of an i64). The functions below implement the operations needed on such values.
+#}
+alias Int64 = vec2<u32>;
+
+struct Int64DivMod {
+	quotient: Int64,
+	remainder: Int64,
+};
+
+fn i64_from_bool(x: bool) -> Int64 {
+	return Int64(select(0u, 1u, x), 0u);
+}
+
+fn i64_from_i32(x: i32) -> Int64 {
+	return Int64(bitcast<u32>(x), select(0u, 0xFFFFFFFFu, x < 0));
+}
+
+fn i64_to_i32(x: Int64) -> i32 {
+	// Wraps around when the value does not fit, like a C cast would
+	return bitcast<i32>(x.x);
+}
+
+fn i64_to_f32(x: Int64) -> f32 {
+	// Convert the magnitude and apply the sign afterwards, as adding the (positive) low part to the negative high part
+	// loses precision, and cancels out completely for small negative values
+	let negative = i64_is_negative(x);
+	let magnitude = select(x, i64_neg(x), negative);
+	let result = f32(magnitude.y) * 4294967296.0 + f32(magnitude.x);
+	return select(result, -result, negative);
+}
+
+fn i64_is_negative(x: Int64) -> bool {
+	return (x.y >> 31u) == 1u;
+}
+
+fn i64_add(a: Int64, b: Int64) -> Int64 {
+	let low = a.x + b.x;
+	let carry = select(0u, 1u, low < a.x);
+	return Int64(low, a.y + b.y + carry);
+}
+
+fn i64_neg(x: Int64) -> Int64 {
+	return i64_add(~x, Int64(1u, 0u));
+}
+
+fn i64_sub(a: Int64, b: Int64) -> Int64 {
+	return i64_add(a, i64_neg(b));
+}
+
+fn i64_abs(x: Int64) -> Int64 {
+	return select(x, i64_neg(x), i64_is_negative(x));
+}
+
+fn i64_from_f32(x: f32) -> Int64 {
+	// Truncates towards zero
+	let magnitude = abs(trunc(x));
+	let high = floor(magnitude / 4294967296.0);
+	let low = magnitude - high * 4294967296.0;
+	let result = Int64(u32(low), u32(high));
+	return select(result, i64_neg(result), x < 0.0);
+}
+
+// Multiplies two 32-bit unsigned integers into a 64-bit result, using 16-bit limbs so that no intermediate overflows
+fn u32_mul_wide(a: u32, b: u32) -> Int64 {
+	let a_low = a & 0xFFFFu;
+	let a_high = a >> 16u;
+	let b_low = b & 0xFFFFu;
+	let b_high = b >> 16u;
+
+	let low_low = a_low * b_low;
+	let high_low = a_high * b_low;
+	let low_high = a_low * b_high;
+	let high_high = a_high * b_high;
+
+	let middle = (low_low >> 16u) + (high_low & 0xFFFFu) + (low_high & 0xFFFFu);
+	let low = (low_low & 0xFFFFu) | (middle << 16u);
+	let high = high_high + (high_low >> 16u) + (low_high >> 16u) + (middle >> 16u);
+	return Int64(low, high);
+}
+
+fn i64_mul(a: Int64, b: Int64) -> Int64 {
+	// The low 64 bits of the product are the same for signed and unsigned (two's complement) values
+	let low = u32_mul_wide(a.x, b.x);
+	return Int64(low.x, low.y + a.x * b.y + a.y * b.x);
+}
+
+fn i64_eq(a: Int64, b: Int64) -> bool {
+	return all(a == b);
+}
+
+fn i64_lt(a: Int64, b: Int64) -> bool {
+	let a_high = bitcast<i32>(a.y);
+	let b_high = bitcast<i32>(b.y);
+	return a_high < b_high || (a_high == b_high && a.x < b.x);
+}
+
+fn u64_lt(a: Int64, b: Int64) -> bool {
+	return a.y < b.y || (a.y == b.y && a.x < b.x);
+}
+
+fn u64_shift_left_one(x: Int64) -> Int64 {
+	return Int64(x.x << 1u, (x.y << 1u) | (x.x >> 31u));
+}
+
+// Unsigned division using binary long division
+fn u64_divmod(n: Int64, d: Int64) -> Int64DivMod {
+	var quotient = Int64(0u, 0u);
+	var remainder = Int64(0u, 0u);
+	for (var i = 63; i >= 0; i = i - 1) {
+		let word = select(n.x, n.y, i >= 32);
+		let bit = (word >> (u32(i) % 32u)) & 1u;
+		remainder = u64_shift_left_one(remainder);
+		remainder.x = remainder.x | bit;
+		quotient = u64_shift_left_one(quotient);
+		if (!u64_lt(remainder, d)) {
+			remainder = i64_sub(remainder, d);
+			quotient.x = quotient.x | 1u;
+		}
+	}
+	return Int64DivMod(quotient, remainder);
+}
+
+// Division that truncates towards zero
+fn i64_div(a: Int64, b: Int64) -> Int64 {
+	let quotient = u64_divmod(i64_abs(a), i64_abs(b)).quotient;
+	return select(quotient, i64_neg(quotient), i64_is_negative(a) != i64_is_negative(b));
+}
+
+// Remainder that has the sign of the dividend (like the ONNX Mod operator with fmod=1)
+fn i64_fmod(a: Int64, b: Int64) -> Int64 {
+	let remainder = u64_divmod(i64_abs(a), i64_abs(b)).remainder;
+	return select(remainder, i64_neg(remainder), i64_is_negative(a));
+}
+
+// Remainder that has the sign of the divisor (like the ONNX Mod operator with fmod=0)
+fn i64_mod(a: Int64, b: Int64) -> Int64 {
+	var remainder = i64_fmod(a, b);
+	if (!i64_eq(remainder, Int64(0u, 0u)) && i64_is_negative(remainder) != i64_is_negative(b)) {
+		remainder = i64_add(remainder, b);
+	}
+	return remainder;
+}
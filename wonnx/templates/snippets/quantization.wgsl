@@ -0,0 +1,33 @@
+{#
+// Helpers for working with 8-bit integer tensors. WGSL does not have 8-bit types, so these tensors are stored with four
+// values packed in each u32 (the value at index i occupies bits 8 * (i % 4) up to and including 8 * (i % 4) + 7).
+#}
+struct PackedArray {
+	data: array<u32>
+};
+
+struct IntArray {
+	data: array<i32>
+};
+
+{# 
+// The packed values are passed as 'word' rather than 'packed', which is a reserved word in GLSL
+#}
+fn unpack_u8(word: u32, index: u32) -> i32 {
+	return i32((word >> ((index % 4u) * 8u)) & 255u);
+}
+
+fn unpack_i8(word: u32, index: u32) -> i32 {
+	{# Shift the value to the most significant byte, then shift it back (which extends the sign) #}
+	return bitcast<i32>(word << ((3u - index % 4u) * 8u)) >> 24u;
+}
+
+fn pack_8bit(values: vec4<i32>) -> u32 {
+	let bytes = bitcast<vec4<u32>>(values) & vec4<u32>(255u);
+	return bytes.x | (bytes.y << 8u) | (bytes.z << 16u) | (bytes.w << 24u);
+}
+
+fn quantize_value(value: f32, scale: f32, zero_point: i32, lowest: f32, highest: f32) -> i32 {
+	{# WGSL round() rounds halfway cases to even, as required by QuantizeLinear #}
+	return i32(clamp(round(value / scale) + f32(zero_point), lowest, highest));
+}
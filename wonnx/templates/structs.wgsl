@@ -3,16 +3,22 @@
 // 'scalar_type' variable. Here we define several other useful data types that shader code can use to make it more portable.
 #}
 alias Scalar = {{ scalar_type }};
-alias Vec3 = vec3<{{ scalar_type }}>;
-alias Vec4 = vec4<{{ scalar_type }}>;
 
 struct Array {
 	data: array<Scalar>
 };
 
-struct ArrayVector {
-	data: array<Vec4>
-};
+{# 
+// In strict int64 mode, int64 scalars are themselves vectors (vec2<u32>), of which no vectors can be made
+#}
+{% if not scalar_type_is_int64 %}
+	alias Vec3 = vec3<{{ scalar_type }}>;
+	alias Vec4 = vec4<{{ scalar_type }}>;
+
+	struct ArrayVector {
+		data: array<Vec4>
+	};
+{% endif %}
 
 {# 
 // WGSL only supports matrixes for floating point types at this point 
@@ -1,6 +1,7 @@
 use approx::assert_abs_diff_eq;
 use std::{collections::HashMap, convert::TryInto};
 use wonnx::{
+    half::f16,
     onnx::TensorProto_DataType,
     utils::{
         graph, initializer, initializer_int64, model, node, tensor, tensor_of_type, InputTensor,
@@ -147,6 +148,129 @@ fn test_int64_initializers() {
     assert_eq!(result["Z"], OutputTensor::I64(sum))
 }
 
+#[test]
+fn test_int64_strict() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    // Values that do not fit in 32 bits
+    let left: Vec<i64> = vec![
+        5_000_000_000,
+        -7_000_000_000,
+        1 << 40,
+        -3,
+        123_456_789_012,
+        0,
+    ];
+    let right: Vec<i64> = vec![3, 1_000_000, -(1 << 20), 2, -5, i64::MAX];
+    let dims = vec![left.len() as i64];
+
+    // Z = (X * Y - X) / Y
+    let model = model(graph(
+        vec![tensor_of_type("X", &dims, TensorProto_DataType::INT64)],
+        vec![tensor_of_type("Z", &dims, TensorProto_DataType::INT64)],
+        vec![
+            tensor_of_type("P", &dims, TensorProto_DataType::INT64),
+            tensor_of_type("D", &dims, TensorProto_DataType::INT64),
+        ],
+        vec![initializer_int64("Y", right.clone(), dims.clone())],
+        vec![
+            node(vec!["X", "Y"], vec!["P"], "mul", "Mul", vec![]),
+            node(vec!["P", "X"], vec!["D"], "sub", "Sub", vec![]),
+            node(vec!["D", "Y"], vec!["Z"], "div", "Div", vec![]),
+        ],
+    ));
+
+    let config = wonnx::SessionConfig::new().with_strict_int64(true);
+    let session = pollster::block_on(wonnx::Session::from_model_with_config(model, &config))
+        .expect("Session did not create");
+
+    let mut input_data: HashMap<String, InputTensor> = HashMap::new();
+    input_data.insert("X".to_string(), left.as_slice().into());
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    let expected: Vec<i64> = left
+        .iter()
+        .zip(right.iter())
+        .map(|(x, y)| (x.wrapping_mul(*y).wrapping_sub(*x)) / y)
+        .collect();
+    assert_eq!(result["Z"], OutputTensor::I64(expected))
+}
+
+#[test]
+fn test_int64_strict_mod() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let left: Vec<i64> = vec![-7, 7, -7, 7, -5_000_000_000, 5_000_000_000, -6];
+    let right: Vec<i64> = vec![3, -3, -3, 3, 3, -7, 3];
+    let dims = vec![left.len() as i64];
+
+    // With fmod=1 the result has the sign of the dividend, otherwise that of the divisor
+    for (fmod, expected) in [
+        (0, vec![2, -2, -1, 1, 1, -5, 0]),
+        (1, vec![-1, 1, -1, 1, -2, 2, 0]),
+    ] {
+        // Model: X, Y -> Mod -> Z
+        let model = model(graph(
+            vec![tensor_of_type("X", &dims, TensorProto_DataType::INT64)],
+            vec![tensor_of_type("Z", &dims, TensorProto_DataType::INT64)],
+            vec![],
+            vec![initializer_int64("Y", right.clone(), dims.clone())],
+            vec![node(
+                vec!["X", "Y"],
+                vec!["Z"],
+                "mod",
+                "Mod",
+                vec![wonnx::utils::attribute("fmod", fmod)],
+            )],
+        ));
+
+        let config = wonnx::SessionConfig::new().with_strict_int64(true);
+        let session = pollster::block_on(wonnx::Session::from_model_with_config(model, &config))
+            .expect("Session did not create");
+
+        let mut input_data: HashMap<String, InputTensor> = HashMap::new();
+        input_data.insert("X".to_string(), left.as_slice().into());
+        let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+        assert_eq!(result["Z"], OutputTensor::I64(expected), "fmod={}", fmod);
+    }
+}
+
+#[test]
+fn test_int64_strict_cast() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let data: Vec<i64> = vec![-7, -1, 0, 3, 5_000_000_000, -5_000_000_000];
+    let dims = vec![data.len() as i64];
+
+    // Model: X -> Cast -> Y
+    let model = model(graph(
+        vec![tensor_of_type("X", &dims, TensorProto_DataType::INT64)],
+        vec![tensor("Y", &dims)],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["X"],
+            vec!["Y"],
+            "cast",
+            "Cast",
+            vec![wonnx::utils::attribute(
+                "to",
+                TensorProto_DataType::FLOAT as i64,
+            )],
+        )],
+    ));
+
+    let config = wonnx::SessionConfig::new().with_strict_int64(true);
+    let session = pollster::block_on(wonnx::Session::from_model_with_config(model, &config))
+        .expect("Session did not create");
+
+    let mut input_data: HashMap<String, InputTensor> = HashMap::new();
+    input_data.insert("X".to_string(), data.as_slice().into());
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    // Negative values keep their sign and magnitude
+    let expected: Vec<f32> = data.iter().map(|x| *x as f32).collect();
+    common::assert_eq_vector((&result["Y"]).try_into().unwrap(), &expected);
+}
+
 pub fn assert_eq_vector_weak(xs: &[f32], ys: &[f32]) {
     assert_eq!(xs.len(), ys.len());
     for i in 0..xs.len() {
@@ -354,3 +478,95 @@ fn test_clip() {
     let result = pollster::block_on(session.run(&input_data)).unwrap();
     assert_eq!(result["Y"], OutputTensor::F32(vec![0.0, 0.0, 1.0, 1.0]));
 }
+
+#[test]
+fn test_add_float16() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let shape = vec![6];
+    let a: Vec<f16> = (0..6).map(|x| f16::from_f32(x as f32 * 0.5)).collect();
+    let b: Vec<f16> = (0..6).map(|x| f16::from_f32(x as f32 - 2.0)).collect();
+    let mut input_data = HashMap::new();
+    input_data.insert("A".to_string(), InputTensor::from(a.as_slice()));
+    input_data.insert("B".to_string(), InputTensor::from(b.as_slice()));
+
+    // Model: A + B -> C (float16). On the GPU this runs in single precision, with conversion of inputs and outputs.
+    let model = model(graph(
+        vec![
+            tensor_of_type("A", &shape, TensorProto_DataType::FLOAT16),
+            tensor_of_type("B", &shape, TensorProto_DataType::FLOAT16),
+        ],
+        vec![tensor_of_type("C", &shape, TensorProto_DataType::FLOAT16)],
+        vec![],
+        vec![],
+        vec![node(vec!["A", "B"], vec!["C"], "add", "Add", vec![])],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    match &result["C"] {
+        OutputTensor::F16(values) => {
+            let values: Vec<f32> = values.iter().map(|x| x.to_f32()).collect();
+            common::assert_eq_vector(&values, &[-2.0, -0.5, 1.0, 2.5, 4.0, 5.5]);
+        }
+        _ => panic!("expected float16 output"),
+    }
+}
+
+#[test]
+fn test_float16_widened() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let shape = vec![2, 2];
+    let x: Vec<f16> = [-1.5, 0.5, 2.0, -0.25]
+        .iter()
+        .map(|x| f16::from_f32(*x))
+        .collect();
+    let bias: Vec<f16> = [1.0, 1.0, -1.0, 1.0]
+        .iter()
+        .map(|x| f16::from_f32(*x))
+        .collect();
+    let mut bias_initializer =
+        wonnx::onnx::TensorProto::from(OutputTensor::F16(bias), shape.clone());
+    bias_initializer.set_name("B".to_string());
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), InputTensor::from(x.as_slice()));
+
+    // Model: (X + B) -> Relu -> Y (float16), Y -> Cast -> Z (float32). The float16 initializer, the activation and the
+    // cast are all calculated in single precision on the GPU.
+    let model = model(graph(
+        vec![tensor_of_type("X", &shape, TensorProto_DataType::FLOAT16)],
+        vec![
+            tensor_of_type("Y", &shape, TensorProto_DataType::FLOAT16),
+            tensor("Z", &shape),
+        ],
+        vec![tensor_of_type("S", &shape, TensorProto_DataType::FLOAT16)],
+        vec![bias_initializer],
+        vec![
+            node(vec!["X", "B"], vec!["S"], "add", "Add", vec![]),
+            node(vec!["S"], vec!["Y"], "relu", "Relu", vec![]),
+            node(
+                vec!["Y"],
+                vec!["Z"],
+                "cast",
+                "Cast",
+                vec![wonnx::utils::attribute(
+                    "to",
+                    TensorProto_DataType::FLOAT as i64,
+                )],
+            ),
+        ],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    let expected = [0.0, 1.5, 1.0, 0.75];
+    match &result["Y"] {
+        OutputTensor::F16(values) => {
+            let values: Vec<f32> = values.iter().map(|x| x.to_f32()).collect();
+            common::assert_eq_vector(&values, &expected);
+        }
+        _ => panic!("expected float16 output"),
+    }
+    common::assert_eq_vector((&result["Z"]).try_into().unwrap(), &expected);
+}
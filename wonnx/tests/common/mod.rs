@@ -1,9 +1,19 @@
 use approx::assert_ulps_eq;
 
 /// Assert two vectors are equal up to a specific number of units in last place (ULPS)
+#[allow(dead_code)]
 pub fn assert_eq_vector(xs: &[f32], ys: &[f32]) {
     assert_eq!(xs.len(), ys.len());
     for i in 0..xs.len() {
         assert_ulps_eq!(xs[i], ys[i], max_ulps = 2);
     }
 }
+
+/// Assert two vectors are equal up to the specified absolute difference (for results of longer calculations)
+#[allow(dead_code)]
+pub fn assert_approx_eq_vector(xs: &[f32], ys: &[f32], epsilon: f32) {
+    assert_eq!(xs.len(), ys.len());
+    for i in 0..xs.len() {
+        approx::assert_abs_diff_eq!(xs[i], ys[i], epsilon = epsilon);
+    }
+}
@@ -275,3 +275,147 @@ fn _conv_kernel_3() {
     let mut model = crate::onnx::ModelProto::new();
     model.set_graph(graph);
 }
+
+#[test]
+fn conv_transpose_stride() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..9).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let data_w: Vec<f32> = vec![1.0; 18];
+    let model = model(graph(
+        vec![tensor("X", &[1, 1, 3, 3])],
+        vec![tensor("Y", &[1, 2, 7, 3])],
+        vec![],
+        vec![initializer("W", data_w, vec![1, 2, 3, 3])],
+        vec![node(
+            vec!["X", "W"],
+            vec!["Y"],
+            "conv_transpose",
+            "ConvTranspose",
+            vec![
+                attribute("strides", vec![3, 2]),
+                attribute("pads", vec![1, 2, 1, 2]),
+            ],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    // Both output channels have the same (all-ones) weights
+    let channel = [
+        1., 1., 3., 1., 1., 3., 7., 4., 9., 7., 4., 9., 7., 4., 9., 13., 7., 15., 13., 7., 15.,
+    ];
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[channel, channel].concat(),
+    );
+}
+
+#[test]
+fn conv_transpose_dilations_relu() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![3., 8., 1., 9., 5., 7., 3., 2., 6.];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // ConvTranspose followed by Relu is fused into ConvTransposeRelu by the optimizer
+    let model = model(graph(
+        vec![tensor("X", &[1, 1, 3, 3])],
+        vec![tensor("Z", &[1, 1, 5, 5])],
+        vec![tensor("Y", &[1, 1, 5, 5])],
+        vec![
+            initializer("W", vec![7., 2., 1., 9.], vec![1, 1, 2, 2]),
+            initializer("B", vec![-50.], vec![1]),
+        ],
+        vec![
+            node(
+                vec!["X", "W", "B"],
+                vec!["Y"],
+                "conv_transpose",
+                "ConvTranspose",
+                vec![attribute("dilations", vec![2, 2])],
+            ),
+            node(vec!["Y"], vec!["Z"], "relu", "Relu", vec![]),
+        ],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["Z"]).try_into().unwrap(),
+        &[
+            0., 6., 0., 0., 0., 13., 0., 17., 0., 0., 0., 0., 26., 26., 0., 0., 0., 38., 0., 13.,
+            0., 0., 0., 0., 4.,
+        ],
+    );
+}
+
+#[test]
+fn conv_1d() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..7).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // A moving sum and a difference filter
+    let data_w: Vec<f32> = vec![1.0, 1.0, 1.0, 1.0, 0.0, -1.0];
+
+    let model = model(graph(
+        vec![tensor("X", &[1, 1, 7])],
+        vec![tensor("Y", &[1, 2, 4])],
+        vec![],
+        vec![initializer("W", data_w, vec![2, 1, 3])],
+        vec![node(
+            vec!["X", "W"],
+            vec!["Y"],
+            "conv",
+            "Conv",
+            vec![
+                attribute("strides", vec![2]),
+                attribute("pads", vec![1, 1]),
+                attribute("kernel_shape", vec![3]),
+            ],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[1., 6., 12., 11., -1., -2., -2., 5.],
+    )
+}
+
+#[test]
+fn conv_3d() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..27).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let data_w: Vec<f32> = vec![1.0; 8];
+
+    let model = model(graph(
+        vec![tensor("X", &[1, 1, 3, 3, 3])],
+        vec![tensor("Y", &[1, 1, 2, 2, 2])],
+        vec![],
+        vec![initializer("W", data_w, vec![1, 1, 2, 2, 2])],
+        vec![node(
+            vec!["X", "W"],
+            vec!["Y"],
+            "conv",
+            "Conv",
+            vec![attribute("kernel_shape", vec![2, 2, 2])],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[52., 60., 76., 84., 124., 132., 148., 156.],
+    )
+}
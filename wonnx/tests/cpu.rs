@@ -0,0 +1,1631 @@
+use protobuf::ProtobufEnum;
+use std::{collections::HashMap, convert::TryInto};
+use wonnx::{
+    half::f16,
+    onnx::{self, ModelProto, TensorProto, TensorProto_DataType},
+    utils::{
+        attribute, graph, initializer, initializer_int64, model, model_with_opset, node, tensor,
+        tensor_of_type, Dimension, InputTensor, OutputTensor, ScalarType,
+    },
+    Backend, CpuError, PaddingError, Session, SessionConfig, SessionError,
+};
+mod common;
+
+fn cpu_session(model: ModelProto) -> Session {
+    let config = SessionConfig::new().with_backend(Backend::Cpu);
+    pollster::block_on(Session::from_model_with_config(model, &config))
+        .expect("Session did not create")
+}
+
+#[test]
+fn test_cpu_conv_pad() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..25).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Four output channels and a 3x3 kernel would trigger the GPU-specific weight padding in the optimizer
+    let data_w: Vec<f32> = (0..4 * 9).map(|x| (x / 9 + 1) as f32).collect();
+    let conv_model = model(graph(
+        vec![tensor("X", &[1, 1, 5, 5])],
+        vec![tensor("Y", &[1, 4, 5, 5])],
+        vec![],
+        vec![
+            initializer("W", data_w, vec![4, 1, 3, 3]),
+            initializer("B", vec![0.0, 0.0, 0.0, 1.0], vec![4]),
+        ],
+        vec![node(
+            vec!["X", "W", "B"],
+            vec!["Y"],
+            "conv",
+            "Conv",
+            vec![
+                attribute("kernel_shape", vec![3, 3]),
+                attribute("pads", vec![1, 1, 1, 1]),
+            ],
+        )],
+    ));
+
+    let session = cpu_session(conv_model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    let result: &[f32] = (&result["Y"]).try_into().unwrap();
+
+    let first_channel = vec![
+        12.0, 21.0, 27.0, 33.0, 24.0, 33.0, 54.0, 63.0, 72.0, 51.0, 63.0, 99.0, 108.0, 117.0, 81.0,
+        93.0, 144.0, 153.0, 162.0, 111.0, 72.0, 111.0, 117.0, 123.0, 84.0,
+    ];
+    common::assert_eq_vector(&result[0..25], &first_channel);
+    let last_channel: Vec<f32> = first_channel.iter().map(|x| x * 4.0 + 1.0).collect();
+    common::assert_eq_vector(&result[75..100], &last_channel);
+}
+
+#[test]
+fn test_cpu_conv_relu_fused() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1.0, -2.0, 3.0, -4.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Conv followed by Relu is fused into ConvRelu by the optimizer
+    let model = model(graph(
+        vec![tensor("X", &[1, 1, 2, 2])],
+        vec![tensor("Z", &[1, 1, 2, 2])],
+        vec![tensor("Y", &[1, 1, 2, 2])],
+        vec![initializer("W", vec![1.0], vec![1, 1, 1, 1])],
+        vec![
+            node(
+                vec!["X", "W"],
+                vec!["Y"],
+                "conv",
+                "Conv",
+                vec![attribute("kernel_shape", vec![1, 1])],
+            ),
+            node(vec!["Y"], vec!["Z"], "relu", "Relu", vec![]),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Z"], OutputTensor::F32(vec![1.0, 0.0, 3.0, 0.0]));
+}
+
+#[test]
+fn test_cpu_matmul_broadcast() {
+    let mut input_data = HashMap::new();
+    let data_a: Vec<f32> = (0..12).map(|x| x as f32).collect();
+    let data_b: Vec<f32> = vec![1.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+    input_data.insert("A".to_string(), data_a.as_slice().into());
+    input_data.insert("B".to_string(), data_b.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("A", &[2, 2, 3]), tensor("B", &[3, 2])],
+        vec![tensor("C", &[2, 2, 2])],
+        vec![],
+        vec![],
+        vec![node(vec!["A", "B"], vec!["C"], "MatMul", "MatMul", vec![])],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["C"],
+        OutputTensor::F32(vec![2.0, 3.0, 8.0, 9.0, 14.0, 15.0, 20.0, 21.0])
+    );
+}
+
+#[test]
+fn test_cpu_gemm() {
+    let mut input_data = HashMap::new();
+    let data_a: Vec<f32> = vec![1.0, 2.0, 3.0, 4.0];
+    input_data.insert("A".to_string(), data_a.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("A", &[2, 2])],
+        vec![tensor("Y", &[2, 2])],
+        vec![],
+        vec![
+            initializer("B", vec![1.0, 2.0, 3.0, 4.0], vec![2, 2]),
+            initializer("C", vec![10.0, 20.0], vec![2]),
+        ],
+        vec![node(
+            vec!["A", "B", "C"],
+            vec!["Y"],
+            "gemm",
+            "Gemm",
+            vec![attribute("transB", 1), attribute("alpha", 2.0)],
+        )],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Y"], OutputTensor::F32(vec![20.0, 42.0, 32.0, 70.0]));
+}
+
+#[test]
+fn test_cpu_softmax() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1.0, 2.0, 3.0, 1.0, 1.0, 1.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("X", &[2, 3])],
+        vec![tensor("Y", &[2, 3])],
+        vec![],
+        vec![],
+        vec![node(vec!["X"], vec!["Y"], "softmax", "Softmax", vec![])],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[
+            0.09003057,
+            0.24472848,
+            0.66524094,
+            1.0 / 3.0,
+            1.0 / 3.0,
+            1.0 / 3.0,
+        ],
+    );
+}
+
+#[test]
+fn test_cpu_reduce_mean() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (1..=12).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("X", &[2, 2, 3])],
+        vec![tensor("Y", &[2, 1, 1])],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["X"],
+            vec!["Y"],
+            "reduce",
+            "ReduceMean",
+            vec![attribute("axes", vec![1, -1])],
+        )],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Y"], OutputTensor::F32(vec![3.5, 9.5]));
+}
+
+#[test]
+fn test_cpu_gather_axis() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..6).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("X", &[2, 3])],
+        vec![tensor("Y", &[2, 2])],
+        vec![],
+        vec![initializer_int64("I", vec![2, -3], vec![2])],
+        vec![node(
+            vec!["X", "I"],
+            vec!["Y"],
+            "gather",
+            "Gather",
+            vec![attribute("axis", 1)],
+        )],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Y"], OutputTensor::F32(vec![2.0, 0.0, 5.0, 3.0]));
+}
+
+#[test]
+fn test_cpu_concat_axis() {
+    let mut input_data = HashMap::new();
+    let data_a: Vec<f32> = vec![1.0, 2.0, 3.0, 4.0];
+    let data_b: Vec<f32> = vec![5.0, 6.0];
+    input_data.insert("A".to_string(), data_a.as_slice().into());
+    input_data.insert("B".to_string(), data_b.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("A", &[2, 2]), tensor("B", &[2, 1])],
+        vec![tensor("Y", &[2, 3])],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["A", "B"],
+            vec!["Y"],
+            "concat",
+            "Concat",
+            vec![attribute("axis", 1)],
+        )],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["Y"],
+        OutputTensor::F32(vec![1.0, 2.0, 5.0, 3.0, 4.0, 6.0])
+    );
+}
+
+#[test]
+fn test_cpu_transpose() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..6).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("X", &[2, 3])],
+        vec![tensor("Y", &[3, 2])],
+        vec![],
+        vec![],
+        vec![node(vec!["X"], vec!["Y"], "transpose", "Transpose", vec![])],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["Y"],
+        OutputTensor::F32(vec![0.0, 3.0, 1.0, 4.0, 2.0, 5.0])
+    );
+}
+
+#[test]
+fn test_cpu_split() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..6).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("X", &[2, 3])],
+        vec![tensor("Y", &[2, 1]), tensor("Z", &[2, 2])],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["X"],
+            vec!["Y", "Z"],
+            "split",
+            "Split",
+            vec![attribute("axis", 1), attribute("split", vec![1, 2])],
+        )],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Y"], OutputTensor::F32(vec![0.0, 3.0]));
+    assert_eq!(result["Z"], OutputTensor::F32(vec![1.0, 2.0, 4.0, 5.0]));
+}
+
+#[test]
+fn test_cpu_constant_folding() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1.0, 2.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // A + B only depends on initializers and is folded into a single initializer without a GPU
+    let model = model(graph(
+        vec![tensor("X", &[2])],
+        vec![tensor("Y", &[2])],
+        vec![tensor("C", &[2])],
+        vec![
+            initializer("A", vec![1.0, 2.0], vec![2]),
+            initializer("B", vec![3.0, 4.0], vec![2]),
+        ],
+        vec![
+            node(vec!["A", "B"], vec!["C"], "add", "Add", vec![]),
+            node(vec!["X", "C"], vec!["Y"], "mul", "Mul", vec![]),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Y"], OutputTensor::F32(vec![4.0, 12.0]));
+}
+
+#[test]
+fn test_cpu_integer_division_by_zero() {
+    let mut input_data = HashMap::new();
+    let data_a: Vec<i64> = vec![4, 5];
+    let data_b: Vec<i64> = vec![2, 0];
+    input_data.insert("A".to_string(), InputTensor::from(data_a.as_slice()));
+    input_data.insert("B".to_string(), InputTensor::from(data_b.as_slice()));
+
+    let model = model(graph(
+        vec![
+            tensor_of_type("A", &[2], wonnx::onnx::TensorProto_DataType::INT64),
+            tensor_of_type("B", &[2], wonnx::onnx::TensorProto_DataType::INT64),
+        ],
+        vec![tensor_of_type(
+            "Y",
+            &[2],
+            wonnx::onnx::TensorProto_DataType::INT64,
+        )],
+        vec![],
+        vec![],
+        vec![node(vec!["A", "B"], vec!["Y"], "div", "Div", vec![])],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data));
+    assert!(matches!(
+        result,
+        Err(SessionError::CpuError(CpuError::DivisionByZero(_)))
+    ));
+}
+
+#[test]
+fn test_cpu_run_typed() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..6).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("X", &[2, 3])],
+        vec![tensor("Y", &[3, 2])],
+        vec![],
+        vec![],
+        vec![node(vec!["X"], vec!["Y"], "transpose", "Transpose", vec![])],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run_typed(&input_data)).unwrap();
+    assert_eq!(result["Y"].shape.dims, vec![3, 2]);
+    assert_eq!(result["Y"].shape.data_type, ScalarType::F32);
+    assert_eq!(
+        result["Y"].data,
+        OutputTensor::F32(vec![0.0, 3.0, 1.0, 4.0, 2.0, 5.0])
+    );
+}
+
+#[test]
+fn test_cpu_session_introspection() {
+    let mut input_x = tensor("X", &[1, 3]);
+    input_x.set_doc_string("the input".to_string());
+
+    // Older models also list initializers as graph inputs; these should not be reported as inputs
+    let mut model = model(graph(
+        vec![input_x, tensor("B", &[1, 3])],
+        vec![tensor("Y", &[1, 3])],
+        vec![],
+        vec![initializer("B", vec![1.0, 2.0, 3.0], vec![1, 3])],
+        vec![node(vec!["X", "B"], vec!["Y"], "add", "Add", vec![])],
+    ));
+    model.set_producer_name("wonnx-test".to_string());
+    let mut prop = wonnx::onnx::StringStringEntryProto::new();
+    prop.set_key("author".to_string());
+    prop.set_value("someone".to_string());
+    model.mut_metadata_props().push(prop);
+
+    let session = cpu_session(model);
+
+    let inputs = session.inputs();
+    assert_eq!(inputs.len(), 1);
+    assert_eq!(inputs[0].name, "X");
+    assert_eq!(inputs[0].doc_string, "the input");
+    assert_eq!(inputs[0].data_type, ScalarType::F32);
+    assert_eq!(
+        inputs[0].dimensions,
+        vec![Dimension::Fixed(1), Dimension::Fixed(3)]
+    );
+    assert_eq!(inputs[0].shape().unwrap().dims, vec![1, 3]);
+
+    let outputs = session.outputs();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].name, "Y");
+
+    let metadata = session.metadata();
+    assert_eq!(metadata.producer_name, "wonnx-test");
+    assert_eq!(metadata.onnx_opset_version, 13);
+    assert_eq!(metadata.metadata_props["author"], "someone");
+}
+
+#[test]
+fn test_cpu_input_validation() {
+    let model = model(graph(
+        vec![tensor("X", &[2, 3])],
+        vec![tensor("Y", &[2, 3])],
+        vec![],
+        vec![],
+        vec![node(vec!["X"], vec!["Y"], "relu", "Relu", vec![])],
+    ));
+    let session = cpu_session(model);
+
+    let data: Vec<f32> = vec![1.0; 5];
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), InputTensor::from(data.as_slice()));
+    assert!(matches!(
+        pollster::block_on(session.run(&input_data)),
+        Err(SessionError::InputSizeMismatch { ref name, actual: 5, .. }) if name == "X"
+    ));
+
+    let data: Vec<i32> = vec![1; 6];
+    input_data.insert("X".to_string(), InputTensor::from(data.as_slice()));
+    assert!(matches!(
+        pollster::block_on(session.run(&input_data)),
+        Err(SessionError::InputTypeMismatch {
+            expected: ScalarType::F32,
+            actual: ScalarType::I32,
+            ..
+        })
+    ));
+
+    let data: Vec<f32> = vec![1.0; 6];
+    input_data.insert("X".to_string(), InputTensor::from(data.as_slice()));
+    input_data.insert("Z".to_string(), InputTensor::from(data.as_slice()));
+    assert!(matches!(
+        pollster::block_on(session.run(&input_data)),
+        Err(SessionError::InvalidInput(ref name)) if name == "Z"
+    ));
+}
+
+#[test]
+fn test_cpu_io_binding_unsupported() {
+    let model = model(graph(
+        vec![tensor("X", &[4])],
+        vec![tensor("Y", &[4])],
+        vec![],
+        vec![],
+        vec![node(vec!["X"], vec!["Y"], "relu", "Relu", vec![])],
+    ));
+    let session = cpu_session(model);
+    assert!(matches!(
+        session.create_io_binding(),
+        Err(SessionError::IoBindingUnsupported)
+    ));
+}
+
+#[test]
+fn test_cpu_profile_unsupported() {
+    let model = model(graph(
+        vec![tensor("X", &[4])],
+        vec![tensor("Y", &[4])],
+        vec![],
+        vec![],
+        vec![node(vec!["X"], vec!["Y"], "relu", "Relu", vec![])],
+    ));
+    let session = cpu_session(model);
+    let data: Vec<f32> = vec![1.0; 4];
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), InputTensor::from(data.as_slice()));
+    assert!(matches!(
+        pollster::block_on(session.profile(&input_data)),
+        Err(SessionError::ProfilingUnsupported)
+    ));
+}
+
+#[test]
+fn test_cpu_capture_intermediates() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![-2.0, -1.0, 4.0, 9.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Model: X -> Neg -> A -> Relu -> B -> Sqrt -> Y
+    let model = model(graph(
+        vec![tensor("X", &[4])],
+        vec![tensor("Y", &[4])],
+        vec![tensor("A", &[4]), tensor("B", &[4])],
+        vec![],
+        vec![
+            node(vec!["X"], vec!["A"], "neg", "Neg", vec![]),
+            node(vec!["A"], vec!["B"], "relu", "Relu", vec![]),
+            node(vec!["B"], vec!["Y"], "sqrt", "Sqrt", vec![]),
+        ],
+    ));
+
+    let config = SessionConfig::new()
+        .with_backend(Backend::Cpu)
+        .with_capture_intermediates(true);
+    let session = pollster::block_on(Session::from_model_with_config(model.clone(), &config))
+        .expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["A"], OutputTensor::F32(vec![2.0, 1.0, -4.0, -9.0]));
+    assert_eq!(result["B"], OutputTensor::F32(vec![2.0, 1.0, 0.0, 0.0]));
+    assert_eq!(result.len(), 3);
+
+    // Taking the square root of negative values (skipping the Relu) yields NaN
+    let mut nan_model = model;
+    nan_model.mut_graph().mut_node()[2].set_input(vec!["A".to_string()].into());
+    let config = config.with_check_non_finite(true);
+    let session = pollster::block_on(Session::from_model_with_config(nan_model, &config))
+        .expect("Session did not create");
+    assert!(matches!(
+        pollster::block_on(session.run(&input_data)),
+        Err(SessionError::NonFiniteValue { ref node, ref output }) if node == "sqrt" && output == "Y"
+    ));
+
+    // Without capturing intermediates, the node that first produced a non-finite value is still reported
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![-1.0, 1.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+    let log_model = wonnx::utils::model(graph(
+        vec![tensor("X", &[2])],
+        vec![tensor("Y", &[2])],
+        vec![tensor("L", &[2])],
+        vec![],
+        vec![
+            node(vec!["X"], vec!["L"], "log", "Log", vec![]),
+            node(vec!["L"], vec!["Y"], "sigmoid", "Sigmoid", vec![]),
+        ],
+    ));
+    let config = SessionConfig::new()
+        .with_backend(Backend::Cpu)
+        .with_check_non_finite(true);
+    let session = pollster::block_on(Session::from_model_with_config(log_model, &config))
+        .expect("Session did not create");
+    assert!(matches!(
+        pollster::block_on(session.run(&input_data)),
+        Err(SessionError::NonFiniteValue { ref node, ref output }) if node == "log" && output == "L"
+    ));
+
+    // Intermediate values are not returned unless they are to be captured
+    let data: Vec<f32> = vec![1.0, 2.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result.len(), 1);
+    assert!(result.contains_key("Y"));
+}
+
+#[test]
+fn test_cpu_float16() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let shape = vec![2, 2];
+    let data: Vec<f16> = [-1.5, 0.5, 2.0, -0.25]
+        .iter()
+        .map(|x| f16::from_f32(*x))
+        .collect();
+    let bias: Vec<f16> = [1.0, 1.0, -1.0, 1.0]
+        .iter()
+        .map(|x| f16::from_f32(*x))
+        .collect();
+    let mut bias_initializer =
+        wonnx::onnx::TensorProto::from(OutputTensor::F16(bias), shape.clone());
+    bias_initializer.set_name("B".to_string());
+
+    // Model: (X + B) -> Relu -> Y (float16), Y -> Cast -> Z (float32)
+    let model = model(graph(
+        vec![tensor_of_type("X", &shape, TensorProto_DataType::FLOAT16)],
+        vec![
+            tensor_of_type("Y", &shape, TensorProto_DataType::FLOAT16),
+            tensor("Z", &shape),
+        ],
+        vec![tensor_of_type("S", &shape, TensorProto_DataType::FLOAT16)],
+        vec![bias_initializer],
+        vec![
+            node(vec!["X", "B"], vec!["S"], "add", "Add", vec![]),
+            node(vec!["S"], vec!["Y"], "relu", "Relu", vec![]),
+            node(
+                vec!["Y"],
+                vec!["Z"],
+                "cast",
+                "Cast",
+                vec![attribute("to", TensorProto_DataType::FLOAT.value() as i64)],
+            ),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    assert_eq!(session.inputs()[0].data_type, ScalarType::F16);
+
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), InputTensor::from(data.as_slice()));
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    let expected = [0.0, 1.5, 1.0, 0.75];
+    match &result["Y"] {
+        OutputTensor::F16(values) => {
+            let values: Vec<f32> = values.iter().map(|x| x.to_f32()).collect();
+            common::assert_eq_vector(&values, &expected);
+        }
+        _ => panic!("expected float16 output"),
+    }
+    let z: &[f32] = (&result["Z"]).try_into().unwrap();
+    common::assert_eq_vector(z, &expected);
+}
+
+#[test]
+fn test_cpu_bool() {
+    let mut input_data = HashMap::new();
+    let x: Vec<f32> = vec![1.0, 5.0, 3.0, -1.0];
+    let y: Vec<f32> = vec![2.0, 4.0, 3.0, 0.0];
+    input_data.insert("X".to_string(), x.as_slice().into());
+    input_data.insert("Y".to_string(), y.as_slice().into());
+
+    // Model: Z = Where(Not(X < Y), X, Y), i.e. the element-wise maximum; M = X < Y
+    let bool_type = TensorProto_DataType::BOOL;
+    let model = model(graph(
+        vec![tensor("X", &[4]), tensor("Y", &[4])],
+        vec![tensor("Z", &[4]), tensor_of_type("M", &[4], bool_type)],
+        vec![tensor_of_type("N", &[4], bool_type)],
+        vec![],
+        vec![
+            node(vec!["X", "Y"], vec!["M"], "less", "Less", vec![]),
+            node(vec!["M"], vec!["N"], "not", "Not", vec![]),
+            node(vec!["N", "X", "Y"], vec!["Z"], "where", "Where", vec![]),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["M"],
+        OutputTensor::Bool(vec![true, false, false, true])
+    );
+    common::assert_eq_vector((&result["Z"]).try_into().unwrap(), &[2.0, 5.0, 3.0, 0.0]);
+}
+
+fn int_initializer(name: &str, data: OutputTensor, dims: Vec<i64>) -> TensorProto {
+    let mut tensor = TensorProto::from(data, dims);
+    tensor.set_name(name.to_string());
+    tensor
+}
+
+#[test]
+fn test_cpu_quantize_linear() {
+    let mut input_data = HashMap::new();
+    let x: Vec<f32> = vec![-1.0, 0.0, 0.3, 1.25, 2.5, 100.0];
+    input_data.insert("X".to_string(), x.as_slice().into());
+
+    // Model: Y = QuantizeLinear(X), Z = DequantizeLinear(Y)
+    let model = model(graph(
+        vec![tensor("X", &[6])],
+        vec![
+            tensor_of_type("Y", &[6], TensorProto_DataType::INT8),
+            tensor("Z", &[6]),
+        ],
+        vec![],
+        vec![
+            initializer("scale", vec![0.5], vec![]),
+            int_initializer("zero_point", OutputTensor::I8(vec![-1]), vec![]),
+        ],
+        vec![
+            node(
+                vec!["X", "scale", "zero_point"],
+                vec!["Y"],
+                "quantize",
+                "QuantizeLinear",
+                vec![],
+            ),
+            node(
+                vec!["Y", "scale", "zero_point"],
+                vec!["Z"],
+                "dequantize",
+                "DequantizeLinear",
+                vec![],
+            ),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Y"], OutputTensor::I8(vec![-3, -1, 0, 1, 4, 127]));
+    common::assert_eq_vector(
+        (&result["Z"]).try_into().unwrap(),
+        &[-1.0, 0.0, 0.5, 1.0, 2.5, 64.0],
+    );
+}
+
+#[test]
+fn test_cpu_qlinear_matmul() {
+    let mut input_data = HashMap::new();
+    let a: Vec<u8> = vec![1, 2, 3, 4, 5, 6];
+    input_data.insert("A".to_string(), a.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor_of_type("A", &[2, 3], TensorProto_DataType::UINT8)],
+        vec![
+            tensor_of_type("Y", &[2, 2], TensorProto_DataType::INT32),
+            tensor_of_type("Q", &[2, 2], TensorProto_DataType::INT8),
+        ],
+        vec![],
+        vec![
+            int_initializer("B", OutputTensor::I8(vec![1, -1, 2, 0, -3, 1]), vec![3, 2]),
+            initializer("a_scale", vec![0.5], vec![]),
+            int_initializer("a_zero_point", OutputTensor::U8(vec![1]), vec![]),
+            initializer("b_scale", vec![0.25], vec![]),
+            int_initializer("b_zero_point", OutputTensor::I8(vec![-1]), vec![]),
+            initializer("y_scale", vec![0.0625], vec![]),
+            int_initializer("y_zero_point", OutputTensor::I8(vec![0]), vec![]),
+        ],
+        vec![
+            node(
+                vec!["A", "B", "a_zero_point", "b_zero_point"],
+                vec!["Y"],
+                "matmul_integer",
+                "MatMulInteger",
+                vec![],
+            ),
+            node(
+                vec![
+                    "A",
+                    "a_scale",
+                    "a_zero_point",
+                    "B",
+                    "b_scale",
+                    "b_zero_point",
+                    "y_scale",
+                    "y_zero_point",
+                ],
+                vec!["Q"],
+                "qlinear_matmul",
+                "QLinearMatMul",
+                vec![],
+            ),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Y"], OutputTensor::I32(vec![-1, 5, 8, 14]));
+    assert_eq!(result["Q"], OutputTensor::I8(vec![-2, 10, 16, 28]));
+}
+
+#[test]
+fn test_cpu_qdq_conv() {
+    let mut input_data = HashMap::new();
+    let x: Vec<u8> = (0..9).collect();
+    input_data.insert("X".to_string(), x.as_slice().into());
+
+    // Model: Y = QuantizeLinear(Conv(DequantizeLinear(X), DequantizeLinear(W), DequantizeLinear(B))), which the
+    // optimizer fuses into a single QLinearConv
+    let model = model(graph(
+        vec![tensor_of_type(
+            "X",
+            &[1, 1, 3, 3],
+            TensorProto_DataType::UINT8,
+        )],
+        vec![tensor_of_type(
+            "Y",
+            &[1, 2, 2, 2],
+            TensorProto_DataType::UINT8,
+        )],
+        vec![
+            tensor("XD", &[1, 1, 3, 3]),
+            tensor("WD", &[2, 1, 2, 2]),
+            tensor("BD", &[2]),
+            tensor("C", &[1, 2, 2, 2]),
+        ],
+        vec![
+            initializer("x_scale", vec![0.5], vec![]),
+            int_initializer("x_zero_point", OutputTensor::U8(vec![2]), vec![]),
+            int_initializer(
+                "W",
+                OutputTensor::I8(vec![1, -1, 2, 0, 3, 1, -2, -1]),
+                vec![2, 1, 2, 2],
+            ),
+            initializer("w_scale", vec![0.25, 0.5], vec![2]),
+            int_initializer("w_zero_point", OutputTensor::I8(vec![0, 0]), vec![2]),
+            int_initializer("B", OutputTensor::I32(vec![4, -2]), vec![2]),
+            initializer("b_scale", vec![0.125, 0.25], vec![2]),
+            initializer("y_scale", vec![0.25], vec![]),
+            int_initializer("y_zero_point", OutputTensor::U8(vec![20]), vec![]),
+        ],
+        vec![
+            node(
+                vec!["X", "x_scale", "x_zero_point"],
+                vec!["XD"],
+                "dequantize_x",
+                "DequantizeLinear",
+                vec![],
+            ),
+            node(
+                vec!["W", "w_scale", "w_zero_point"],
+                vec!["WD"],
+                "dequantize_w",
+                "DequantizeLinear",
+                vec![attribute("axis", 0)],
+            ),
+            node(
+                vec!["B", "b_scale"],
+                vec!["BD"],
+                "dequantize_b",
+                "DequantizeLinear",
+                vec![attribute("axis", 0)],
+            ),
+            node(
+                vec!["XD", "WD", "BD"],
+                vec!["C"],
+                "conv",
+                "Conv",
+                vec![attribute("kernel_shape", vec![2, 2])],
+            ),
+            node(
+                vec!["C", "y_scale", "y_zero_point"],
+                vec!["Y"],
+                "quantize_y",
+                "QuantizeLinear",
+                vec![],
+            ),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["Y"],
+        OutputTensor::U8(vec![22, 24, 26, 26, 7, 8, 10, 11])
+    );
+}
+
+#[test]
+fn test_cpu_slice() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..12).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Rows in reverse order and every other column
+    let model = model(graph(
+        vec![tensor("X", &[3, 4])],
+        vec![tensor("Y", &[3, 2])],
+        vec![],
+        vec![
+            initializer_int64("starts", vec![i64::MAX, 0], vec![2]),
+            initializer_int64("ends", vec![i64::MIN, 4], vec![2]),
+            initializer_int64("steps", vec![-1, 2], vec![2]),
+        ],
+        vec![node(
+            vec!["X", "starts", "ends", "", "steps"],
+            vec!["Y"],
+            "slice",
+            "Slice",
+            vec![],
+        )],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[8.0, 10.0, 4.0, 6.0, 0.0, 2.0],
+    );
+}
+
+#[test]
+fn test_cpu_expand_tile() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1.0, 2.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Model: E = Expand(X, [2, 1, 1]), T = Tile(X, [1, 2])
+    let model = model(graph(
+        vec![tensor("X", &[2, 1])],
+        vec![tensor("E", &[2, 2, 1]), tensor("T", &[2, 2])],
+        vec![],
+        vec![
+            initializer_int64("shape", vec![2, 1, 1], vec![3]),
+            initializer_int64("repeats", vec![1, 2], vec![2]),
+        ],
+        vec![
+            node(vec!["X", "shape"], vec!["E"], "expand", "Expand", vec![]),
+            node(vec!["X", "repeats"], vec!["T"], "tile", "Tile", vec![]),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector((&result["E"]).try_into().unwrap(), &[1.0, 2.0, 1.0, 2.0]);
+    common::assert_eq_vector((&result["T"]).try_into().unwrap(), &[1.0, 1.0, 2.0, 2.0]);
+}
+
+#[test]
+fn test_cpu_normalization() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![0.0, 0.0, 3.0, 3.0, 2.0, 2.0, -1.0, -1.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // The same data normalized per row (LayerNormalization), per channel (InstanceNormalization) and per group of
+    // channels (GroupNormalization). Each group has mean 1.5 or 0.5 and variance 2.25, so with an epsilon of 1.75 the
+    // standard deviation is exactly 2.
+    let model = model(graph(
+        vec![tensor("X", &[2, 4])],
+        vec![
+            tensor("L", &[2, 4]),
+            tensor("M", &[2, 1]),
+            tensor("D", &[2, 1]),
+            tensor("I", &[1, 2, 4]),
+            tensor("G", &[1, 4, 2]),
+        ],
+        vec![tensor("XI", &[1, 2, 4]), tensor("XG", &[1, 4, 2])],
+        vec![
+            initializer("layer_scale", vec![1.0, 2.0, 1.0, 2.0], vec![4]),
+            initializer("layer_bias", vec![0.0, 0.0, 1.0, 1.0], vec![4]),
+            initializer("channel_scale", vec![1.0, 2.0], vec![2]),
+            initializer("channel_bias", vec![0.0, 1.0], vec![2]),
+            initializer("group_scale", vec![1.0, 1.0, 2.0, 2.0], vec![4]),
+            initializer("group_bias", vec![0.0, 0.0, 0.0, 1.0], vec![4]),
+            initializer_int64("instance_shape", vec![1, 2, 4], vec![3]),
+            initializer_int64("group_shape", vec![1, 4, 2], vec![3]),
+        ],
+        vec![
+            node(
+                vec!["X", "layer_scale", "layer_bias"],
+                vec!["L", "M", "D"],
+                "layer_norm",
+                "LayerNormalization",
+                vec![attribute("epsilon", 1.75)],
+            ),
+            node(
+                vec!["X", "instance_shape"],
+                vec!["XI"],
+                "reshape_instance",
+                "Reshape",
+                vec![],
+            ),
+            node(
+                vec!["XI", "channel_scale", "channel_bias"],
+                vec!["I"],
+                "instance_norm",
+                "InstanceNormalization",
+                vec![attribute("epsilon", 1.75)],
+            ),
+            node(
+                vec!["X", "group_shape"],
+                vec!["XG"],
+                "reshape_group",
+                "Reshape",
+                vec![],
+            ),
+            node(
+                vec!["XG", "group_scale", "group_bias"],
+                vec!["G"],
+                "group_norm",
+                "GroupNormalization",
+                vec![attribute("epsilon", 1.75), attribute("num_groups", 2)],
+            ),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["L"]).try_into().unwrap(),
+        &[-0.75, -1.5, 1.75, 2.5, 0.75, 1.5, 0.25, -0.5],
+    );
+    common::assert_eq_vector((&result["M"]).try_into().unwrap(), &[1.5, 0.5]);
+    common::assert_eq_vector((&result["D"]).try_into().unwrap(), &[0.5, 0.5]);
+    common::assert_eq_vector(
+        (&result["I"]).try_into().unwrap(),
+        &[-0.75, -0.75, 0.75, 0.75, 2.5, 2.5, -0.5, -0.5],
+    );
+    common::assert_eq_vector(
+        (&result["G"]).try_into().unwrap(),
+        &[-0.75, -0.75, 0.75, 0.75, 1.5, 1.5, -0.5, -0.5],
+    );
+}
+
+#[test]
+fn test_cpu_decomposed_layer_normalization() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![0.0, 0.0, 3.0, 3.0, 2.0, 2.0, -1.0, -1.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // The subgraph older exporters emit for LayerNormalization, which the optimizer fuses into a single node
+    let model = model(graph(
+        vec![tensor("X", &[2, 4])],
+        vec![tensor("Y", &[2, 4])],
+        vec![
+            tensor("M", &[2, 1]),
+            tensor("D", &[2, 4]),
+            tensor("P", &[2, 4]),
+            tensor("V", &[2, 1]),
+            tensor("VE", &[2, 1]),
+            tensor("S", &[2, 1]),
+            tensor("N", &[2, 4]),
+            tensor("NS", &[2, 4]),
+        ],
+        vec![
+            initializer("two", vec![2.0], vec![]),
+            initializer("epsilon", vec![1.75], vec![]),
+            initializer("scale", vec![1.0, 2.0, 3.0, 4.0], vec![4]),
+            initializer("bias", vec![0.5], vec![1]),
+        ],
+        vec![
+            node(
+                vec!["X"],
+                vec!["M"],
+                "mean",
+                "ReduceMean",
+                vec![attribute("axes", vec![-1])],
+            ),
+            node(vec!["X", "M"], vec!["D"], "sub", "Sub", vec![]),
+            node(vec!["D", "two"], vec!["P"], "pow", "Pow", vec![]),
+            node(
+                vec!["P"],
+                vec!["V"],
+                "variance",
+                "ReduceMean",
+                vec![attribute("axes", vec![-1])],
+            ),
+            node(vec!["V", "epsilon"], vec!["VE"], "add", "Add", vec![]),
+            node(vec!["VE"], vec!["S"], "sqrt", "Sqrt", vec![]),
+            node(vec!["D", "S"], vec!["N"], "div", "Div", vec![]),
+            node(vec!["N", "scale"], vec!["NS"], "mul", "Mul", vec![]),
+            node(vec!["NS", "bias"], vec!["Y"], "add_bias", "Add", vec![]),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[-0.25, -1.0, 2.75, 3.5, 1.25, 2.0, -1.75, -2.5],
+    );
+}
+
+fn conv_transpose_model(
+    input_shape: &[i64],
+    weights: Vec<f32>,
+    weight_shape: &[i64],
+    output_shape: &[i64],
+    attributes: Vec<wonnx::onnx::AttributeProto>,
+) -> ModelProto {
+    model(graph(
+        vec![tensor("X", input_shape)],
+        vec![tensor("Y", output_shape)],
+        vec![],
+        vec![initializer("W", weights, weight_shape.to_vec())],
+        vec![node(
+            vec!["X", "W"],
+            vec!["Y"],
+            "conv_transpose",
+            "ConvTranspose",
+            attributes,
+        )],
+    ))
+}
+
+#[test]
+fn test_cpu_conv_transpose() {
+    // The expected values are those of the ONNX backend tests for ConvTranspose
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..9).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let ones = vec![1.0; 18];
+    let cases = [
+        (
+            vec![],
+            vec![1, 2, 5, 5],
+            vec![
+                0., 1., 3., 3., 2., 3., 8., 15., 12., 7., 9., 21., 36., 27., 15., 9., 20., 33.,
+                24., 13., 6., 13., 21., 15., 8.,
+            ],
+        ),
+        (
+            vec![
+                attribute("strides", vec![3, 2]),
+                attribute("pads", vec![1, 2, 1, 2]),
+            ],
+            vec![1, 2, 7, 3],
+            vec![
+                1., 1., 3., 1., 1., 3., 7., 4., 9., 7., 4., 9., 7., 4., 9., 13., 7., 15., 13., 7.,
+                15.,
+            ],
+        ),
+        (
+            vec![
+                attribute("strides", vec![3, 2]),
+                attribute("output_shape", vec![10, 8]),
+            ],
+            vec![1, 2, 10, 8],
+            [
+                [0., 0., 1., 1., 3., 2., 2., 0.].repeat(3),
+                [3., 3., 7., 4., 9., 5., 5., 0.].repeat(3),
+                [6., 6., 13., 7., 15., 8., 8., 0.].repeat(3),
+                vec![0.; 8],
+            ]
+            .concat(),
+        ),
+        (
+            vec![
+                attribute("strides", vec![2, 2]),
+                attribute("auto_pad", "SAME_UPPER"),
+            ],
+            vec![1, 2, 6, 6],
+            vec![
+                0., 0., 1., 1., 3., 2., 0., 0., 1., 1., 3., 2., 3., 3., 8., 5., 12., 7., 3., 3.,
+                7., 4., 9., 5., 9., 9., 20., 11., 24., 13., 6., 6., 13., 7., 15., 8.,
+            ],
+        ),
+    ];
+
+    for (attributes, output_shape, first_channel) in cases {
+        let model = conv_transpose_model(
+            &[1, 1, 3, 3],
+            ones.clone(),
+            &[1, 2, 3, 3],
+            &output_shape,
+            attributes,
+        );
+        let session = cpu_session(model);
+        let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+        // Both output channels have the same (all-ones) weights
+        let expected: Vec<f32> = first_channel
+            .iter()
+            .chain(first_channel.iter())
+            .copied()
+            .collect();
+        common::assert_eq_vector((&result["Y"]).try_into().unwrap(), &expected);
+    }
+}
+
+#[test]
+fn test_cpu_conv_transpose_dilations() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![3., 8., 1., 9., 5., 7., 3., 2., 6.];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = conv_transpose_model(
+        &[1, 1, 3, 3],
+        vec![7., 2., 1., 9.],
+        &[1, 1, 2, 2],
+        &[1, 1, 5, 5],
+        vec![attribute("dilations", vec![2, 2])],
+    );
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[
+            21., 56., 13., 16., 2., 63., 35., 67., 10., 14., 24., 22., 76., 76., 21., 9., 5., 88.,
+            45., 63., 3., 2., 33., 18., 54.,
+        ],
+    );
+}
+
+#[test]
+fn test_cpu_conv_transpose_group_relu_fused() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1.0, -2.0, 3.0, -4.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Each of the two groups maps one input channel to two output channels. ConvTranspose followed by LeakyRelu is fused
+    // into ConvTransposeLeakyRelu by the optimizer.
+    let model = model(graph(
+        vec![tensor("X", &[1, 2, 1, 2])],
+        vec![tensor("Z", &[1, 4, 1, 2])],
+        vec![tensor("Y", &[1, 4, 1, 2])],
+        vec![
+            initializer("W", vec![1.0, -1.0, 2.0, 0.5], vec![2, 2, 1, 1]),
+            initializer("B", vec![0.0, 0.0, 1.0, 0.0], vec![4]),
+        ],
+        vec![
+            node(
+                vec!["X", "W", "B"],
+                vec!["Y"],
+                "conv_transpose",
+                "ConvTranspose",
+                vec![attribute("group", 2)],
+            ),
+            node(
+                vec!["Y"],
+                vec!["Z"],
+                "leaky_relu",
+                "LeakyRelu",
+                vec![attribute("alpha", 0.5)],
+            ),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["Z"],
+        OutputTensor::F32(vec![1.0, -1.0, -0.5, 2.0, 7.0, -3.5, 1.5, -1.0])
+    );
+}
+
+#[test]
+fn test_cpu_lstm() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (1..=6).map(|x| x as f32 * 0.1).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let data_w: Vec<f32> = (0..16).map(|x| (x % 5) as f32 * 0.1 - 0.2).collect();
+    let data_r: Vec<f32> = (0..16).map(|x| (x % 3) as f32 * 0.1 - 0.1).collect();
+    let data_b: Vec<f32> = (0..16).map(|x| (x % 4) as f32 * 0.05).collect();
+    let model = model(graph(
+        vec![tensor("X", &[3, 1, 2])],
+        vec![
+            tensor("Y", &[3, 1, 1, 2]),
+            tensor("Y_h", &[1, 1, 2]),
+            tensor("Y_c", &[1, 1, 2]),
+        ],
+        vec![],
+        vec![
+            initializer("W", data_w, vec![1, 8, 2]),
+            initializer("R", data_r, vec![1, 8, 2]),
+            initializer("B", data_b, vec![1, 16]),
+        ],
+        vec![node(
+            vec!["X", "W", "R", "B"],
+            vec!["Y", "Y_h", "Y_c"],
+            "lstm",
+            "LSTM",
+            vec![attribute("hidden_size", 2)],
+        )],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_approx_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[0.057593, 0.082156, 0.089348, 0.120979, 0.109127, 0.137969],
+        1e-6,
+    );
+    common::assert_approx_eq_vector(
+        (&result["Y_h"]).try_into().unwrap(),
+        &[0.109127, 0.137969],
+        1e-6,
+    );
+    common::assert_approx_eq_vector(
+        (&result["Y_c"]).try_into().unwrap(),
+        &[0.20188, 0.25156],
+        1e-6,
+    );
+}
+
+#[test]
+fn test_cpu_gru_sequence_lens() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1.0, -1.0, 0.5, 0.5, -0.5, 1.0];
+    let lengths: Vec<i32> = vec![3, 2];
+    input_data.insert("X".to_string(), data.as_slice().into());
+    input_data.insert("L".to_string(), lengths.as_slice().into());
+
+    // The (optional) bias is not provided. The second sequence is one element shorter than the first.
+    let model = model(graph(
+        vec![
+            tensor("X", &[3, 2, 1]),
+            tensor_of_type("L", &[2], TensorProto_DataType::INT32),
+        ],
+        vec![tensor("Y", &[3, 1, 2, 1]), tensor("Y_h", &[1, 2, 1])],
+        vec![],
+        vec![
+            initializer("W", vec![0.5, -0.3, 0.8], vec![1, 3, 1]),
+            initializer("R", vec![0.2, 0.4, -0.6], vec![1, 3, 1]),
+        ],
+        vec![node(
+            vec!["X", "W", "R", "", "L"],
+            vec!["Y", "Y_h"],
+            "gru",
+            "GRU",
+            vec![attribute("hidden_size", 1)],
+        )],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_approx_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[0.250701, -0.413336, 0.278281, -0.010489, -0.125326, 0.0],
+        1e-6,
+    );
+    common::assert_approx_eq_vector(
+        (&result["Y_h"]).try_into().unwrap(),
+        &[-0.125326, -0.010489],
+        1e-6,
+    );
+}
+
+#[test]
+fn test_cpu_top_k_arg_max() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1., 4., 4., 2., 0., 7.];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("X", &[2, 3])],
+        vec![
+            tensor("Values", &[2, 2]),
+            tensor_of_type("Indices", &[2, 2], TensorProto_DataType::INT64),
+            tensor_of_type("ArgMax", &[2], TensorProto_DataType::INT64),
+        ],
+        vec![],
+        vec![initializer_int64("K", vec![2], vec![1])],
+        vec![
+            node(
+                vec!["X", "K"],
+                vec!["Values", "Indices"],
+                "top_k",
+                "TopK",
+                vec![],
+            ),
+            node(
+                vec!["X"],
+                vec!["ArgMax"],
+                "arg_max",
+                "ArgMax",
+                vec![
+                    attribute("axis", 1),
+                    attribute("keepdims", 0),
+                    attribute("select_last_index", 1),
+                ],
+            ),
+        ],
+    ));
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Values"], OutputTensor::F32(vec![4., 4., 7., 2.]));
+    assert_eq!(result["Indices"], OutputTensor::I64(vec![1, 2, 2, 0]));
+    assert_eq!(result["ArgMax"], OutputTensor::I64(vec![2, 2]));
+}
+
+#[test]
+fn test_cpu_gather_scatter_elements_nd() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1., 2., 3., 4., 5., 6.];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = model_with_opset(
+        graph(
+            vec![tensor("X", &[2, 3])],
+            vec![
+                tensor("Elements", &[2, 2]),
+                tensor("Slices", &[1, 3]),
+                tensor("Scattered", &[2, 3]),
+                tensor("ScatteredND", &[2, 3]),
+            ],
+            vec![],
+            vec![
+                initializer_int64("I", vec![2, 0, -1, 1], vec![2, 2]),
+                initializer_int64("J", vec![-1], vec![1, 1]),
+                initializer("U", vec![10., 20., 30., 40.], vec![2, 2]),
+                initializer("V", vec![2., 2., 2., 3., 3., 3.], vec![2, 3]),
+                initializer_int64("K", vec![0, 0], vec![2, 1]),
+            ],
+            vec![
+                node(
+                    vec!["X", "I"],
+                    vec!["Elements"],
+                    "gather_elements",
+                    "GatherElements",
+                    vec![attribute("axis", 1)],
+                ),
+                node(
+                    vec!["X", "J"],
+                    vec!["Slices"],
+                    "gather_nd",
+                    "GatherND",
+                    vec![],
+                ),
+                node(
+                    vec!["X", "I", "U"],
+                    vec!["Scattered"],
+                    "scatter_elements",
+                    "ScatterElements",
+                    vec![attribute("axis", 1), attribute("reduction", "max")],
+                ),
+                node(
+                    vec!["X", "K", "V"],
+                    vec!["ScatteredND"],
+                    "scatter_nd",
+                    "ScatterND",
+                    vec![attribute("reduction", "mul")],
+                ),
+            ],
+        ),
+        18,
+    );
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Elements"], OutputTensor::F32(vec![3., 1., 6., 5.]));
+    assert_eq!(result["Slices"], OutputTensor::F32(vec![4., 5., 6.]));
+    assert_eq!(
+        result["Scattered"],
+        OutputTensor::F32(vec![20., 2., 10., 4., 40., 30.])
+    );
+    assert_eq!(
+        result["ScatteredND"],
+        OutputTensor::F32(vec![6., 12., 18., 4., 5., 6.])
+    );
+}
+
+#[test]
+fn test_cpu_einsum() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..8).map(|x| x as f32).collect();
+    let ints: Vec<i32> = (0..6).collect();
+    input_data.insert("X".to_string(), data[0..6].into());
+    input_data.insert("C".to_string(), data.as_slice().into());
+    input_data.insert("I".to_string(), ints.as_slice().into());
+
+    let model = model_with_opset(
+        graph(
+            vec![
+                tensor("X", &[2, 3]),
+                tensor("C", &[2, 2, 2]),
+                tensor_of_type("I", &[2, 3], TensorProto_DataType::INT32),
+            ],
+            vec![
+                tensor("Contracted", &[2, 2]),
+                tensor("Diagonal", &[2]),
+                tensor_of_type("Product", &[2, 3], TensorProto_DataType::INT32),
+            ],
+            vec![],
+            vec![],
+            vec![
+                node(
+                    vec!["X", "X"],
+                    vec!["Contracted"],
+                    "contract",
+                    "Einsum",
+                    vec![attribute("equation", "ij,kj->ik")],
+                ),
+                node(
+                    vec!["C"],
+                    vec!["Diagonal"],
+                    "diagonal",
+                    "Einsum",
+                    vec![attribute("equation", "ijj->i")],
+                ),
+                node(
+                    vec!["I", "I"],
+                    vec!["Product"],
+                    "product",
+                    "Einsum",
+                    vec![attribute("equation", "ij,ij->ij")],
+                ),
+            ],
+        ),
+        12,
+    );
+
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["Contracted"],
+        OutputTensor::F32(vec![5., 14., 14., 50.])
+    );
+    assert_eq!(result["Diagonal"], OutputTensor::F32(vec![3., 11.]));
+    assert_eq!(
+        result["Product"],
+        OutputTensor::I32(vec![0, 1, 4, 9, 16, 25])
+    );
+}
+
+#[test]
+fn test_cpu_non_max_suppression_roi_align() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    let boxes = [
+        0.0, 0.0, 1.0, 1.0, 0.0, 0.1, 1.0, 1.1, 0.0, -0.1, 1.0, 0.9, 0.0, 10.0, 1.0, 11.0, 0.0,
+        10.1, 1.0, 11.1, 0.0, 100.0, 1.0, 101.0,
+    ];
+    let scores = [0.9, 0.75, 0.6, 0.95, 0.5, 0.3];
+    let image: Vec<f32> = (0..16).map(|x| x as f32).collect();
+    let batch_indices: Vec<i64> = vec![0];
+    input_data.insert("B".to_string(), boxes[..].into());
+    input_data.insert("S".to_string(), scores[..].into());
+    input_data.insert("X".to_string(), image.as_slice().into());
+    input_data.insert("R".to_string(), boxes[0..4].into());
+    input_data.insert("I".to_string(), batch_indices.as_slice().into());
+
+    let model = model_with_opset(
+        graph(
+            vec![
+                tensor("B", &[1, 6, 4]),
+                tensor("S", &[1, 1, 6]),
+                tensor("X", &[1, 1, 4, 4]),
+                tensor("R", &[1, 4]),
+                tensor_of_type("I", &[1], TensorProto_DataType::INT64),
+            ],
+            vec![
+                tensor_of_type("Selected", &[6, 3], TensorProto_DataType::INT64),
+                tensor("Aligned", &[1, 1, 1, 1]),
+            ],
+            vec![],
+            vec![
+                initializer_int64("M", vec![6], vec![1]),
+                initializer("T", vec![0.5], vec![1]),
+            ],
+            vec![
+                node(
+                    vec!["B", "S", "M", "T"],
+                    vec!["Selected"],
+                    "nms",
+                    "NonMaxSuppression",
+                    vec![],
+                ),
+                node(
+                    vec!["X", "R", "I"],
+                    vec!["Aligned"],
+                    "roi_align",
+                    "RoiAlign",
+                    vec![
+                        attribute("output_height", 1),
+                        attribute("output_width", 1),
+                        attribute("sampling_ratio", 2),
+                        attribute("spatial_scale", 2.0),
+                        attribute("coordinate_transformation_mode", "output_half_pixel"),
+                    ],
+                ),
+            ],
+        ),
+        16,
+    );
+
+    // The selected indices are trimmed to the boxes that were actually selected
+    let session = cpu_session(model);
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["Selected"],
+        OutputTensor::I64(vec![0, 0, 3, 0, 0, 0, 0, 0, 5])
+    );
+    assert_eq!(result["Aligned"], OutputTensor::F32(vec![5.]));
+}
+
+/// Builds a model in which NonMaxSuppression selects up to six of six boxes, of which the selected indices (N) are used by
+/// the specified consumer node, which produces Y
+fn non_max_suppression_consumer_model(
+    consumer: onnx::NodeProto,
+    output: onnx::ValueInfoProto,
+    initializers: Vec<TensorProto>,
+) -> ModelProto {
+    let mut initializers = initializers;
+    initializers.push(initializer_int64("M", vec![6], vec![1]));
+    model_with_opset(
+        graph(
+            vec![tensor("B", &[1, 6, 4]), tensor("S", &[1, 1, 6])],
+            vec![output],
+            vec![tensor_of_type("N", &[6, 3], TensorProto_DataType::INT64)],
+            initializers,
+            vec![
+                node(
+                    vec!["B", "S", "M"],
+                    vec!["N"],
+                    "nms",
+                    "NonMaxSuppression",
+                    vec![],
+                ),
+                consumer,
+            ],
+        ),
+        13,
+    )
+}
+
+// Values that follow from the padded output of NonMaxSuppression are trimmed to the rows that were selected; ops that
+// combine these rows are not supported
+#[test]
+fn test_cpu_non_max_suppression_gather() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    let boxes = [
+        0.0, 0.0, 1.0, 1.0, 0.0, 0.1, 1.0, 1.1, 0.0, -0.1, 1.0, 0.9, 0.0, 10.0, 1.0, 11.0, 0.0,
+        10.1, 1.0, 11.1, 0.0, 100.0, 1.0, 101.0,
+    ];
+    let scores = [0.9, 0.75, 0.6, 0.95, 0.5, 0.3];
+    input_data.insert("B".to_string(), boxes[..].into());
+    input_data.insert("S".to_string(), scores[..].into());
+
+    // The third column of the selected indices holds the index of the box
+    let model = non_max_suppression_consumer_model(
+        node(
+            vec!["N", "C"],
+            vec!["Y"],
+            "gather",
+            "Gather",
+            vec![attribute("axis", 1)],
+        ),
+        tensor_of_type("Y", &[6], TensorProto_DataType::INT64),
+        vec![initializer_int64("C", vec![2], vec![])],
+    );
+    let result = pollster::block_on(cpu_session(model).run(&input_data)).unwrap();
+    assert_eq!(result["Y"], OutputTensor::I64(vec![3, 0, 5]));
+
+    // Summing over all rows would include the rows of padding
+    let model = non_max_suppression_consumer_model(
+        node(vec!["N"], vec!["Y"], "sum", "ReduceSum", vec![]),
+        tensor_of_type("Y", &[1, 1], TensorProto_DataType::INT64),
+        vec![],
+    );
+    let config = SessionConfig::new().with_backend(Backend::Cpu);
+    assert!(matches!(
+        pollster::block_on(Session::from_model_with_config(model, &config)),
+        Err(SessionError::CpuError(CpuError::PaddingError(PaddingError::UnsupportedUse(ref node))))
+            if node == "sum"
+    ));
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use wonnx::{
+    onnx::NodeProto,
+    utils::{graph, model, node, tensor, NodeAttributes, OutputTensor, Shape},
+    Backend, CompileError, CpuError, CustomOperator, CustomShader, OperatorRegistry, SessionConfig,
+    SessionError, ShaderSource,
+};
+
+mod common;
+
+/// Squares each element of its (float) input, using a plain WGSL shader
+struct Square;
+
+impl CustomOperator for Square {
+    fn op_type(&self) -> &str {
+        "Square"
+    }
+
+    fn domain(&self) -> &str {
+        "com.example"
+    }
+
+    fn infer_output_shapes(
+        &self,
+        _node: &NodeProto,
+        input_shapes: &[&Shape],
+    ) -> Result<Vec<Shape>, CompileError> {
+        Ok(vec![input_shapes[0].clone()])
+    }
+
+    fn compile(
+        &self,
+        _node: &NodeProto,
+        input_shapes: &[&Shape],
+        _output_shapes: &[&Shape],
+    ) -> Result<CustomShader, CompileError> {
+        Ok(CustomShader {
+            source: ShaderSource::Wgsl(
+                r#"
+                @group(0) @binding(0)
+                var<storage, read> input_0: array<f32>;
+
+                @group(0) @binding(1)
+                var<storage, read_write> output_0: array<f32>;
+
+                @compute @workgroup_size(1)
+                fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+                    let gidx = global_id.x;
+                    output_0[gidx] = input_0[gidx] * input_0[gidx];
+                }
+                "#
+                .to_string(),
+            ),
+            threads: (input_shapes[0].element_count() as u32, 1, 1),
+        })
+    }
+}
+
+/// Multiplies each element of its input by the value of the 'factor' attribute, using a shader template
+struct Scale;
+
+impl CustomOperator for Scale {
+    fn op_type(&self) -> &str {
+        "Scale"
+    }
+
+    fn domain(&self) -> &str {
+        "com.example"
+    }
+
+    fn infer_output_shapes(
+        &self,
+        _node: &NodeProto,
+        input_shapes: &[&Shape],
+    ) -> Result<Vec<Shape>, CompileError> {
+        Ok(vec![input_shapes[0].clone()])
+    }
+
+    fn compile(
+        &self,
+        node: &NodeProto,
+        input_shapes: &[&Shape],
+        _output_shapes: &[&Shape],
+    ) -> Result<CustomShader, CompileError> {
+        let factor: f32 = node.get_attribute_value("factor", Some(1.0))?;
+        Ok(CustomShader {
+            source: ShaderSource::Template(format!(
+                r#"
+                {{%- include "structs.wgsl" -%}}
+                @group(0) @binding(0)
+                var<storage, read> input_0: Array;
+
+                @group(0) @binding(1)
+                var<storage, read_write> output_0: Array;
+
+                @compute @workgroup_size(1)
+                fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {{
+                    let gidx = global_id.x;
+                    if (gidx < {{{{ i_lens[0] }}}}u) {{
+                        output_0.data[gidx] = input_0.data[gidx] * {{{{ scalar_type }}}}({factor:?});
+                    }}
+                }}
+                "#
+            )),
+            threads: (input_shapes[0].element_count() as u32, 1, 1),
+        })
+    }
+}
+
+#[test]
+fn test_custom_op_wgsl() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let data: Vec<f32> = vec![-2.0, -1.0, 0.0, 1.0, 2.0, 3.0];
+    let shape = vec![2, 3];
+
+    let mut square = node(vec!["X"], vec!["Y"], "square", "Square", vec![]);
+    square.set_domain("com.example".to_string());
+    let model = model(graph(
+        vec![tensor("X", &shape)],
+        vec![tensor("Y", &shape)],
+        vec![],
+        vec![],
+        vec![square],
+    ));
+
+    // Without the custom operator the model cannot be compiled
+    assert!(pollster::block_on(wonnx::Session::from_model(model.clone())).is_err());
+
+    let config =
+        SessionConfig::new().with_operator_registry(OperatorRegistry::new().with_operator(Square));
+    let session = pollster::block_on(wonnx::Session::from_model_with_config(model, &config))
+        .expect("Session did not create");
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), data.as_slice().into());
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    let expected: Vec<f32> = data.iter().map(|x| x * x).collect();
+    match &result["Y"] {
+        OutputTensor::F32(output) => common::assert_eq_vector(output, &expected),
+        _ => panic!("expected float output"),
+    }
+}
+
+#[test]
+fn test_custom_op_template() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let data: Vec<f32> = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+    let shape = vec![5];
+
+    // Model: X -> Scale -> Relu -> Y, to check that custom ops mix with built-in ones
+    let mut scale = node(
+        vec!["X"],
+        vec!["S"],
+        "scale",
+        "Scale",
+        vec![wonnx::utils::attribute("factor", 2.5)],
+    );
+    scale.set_domain("com.example".to_string());
+    let model = model(graph(
+        vec![tensor("X", &shape)],
+        vec![tensor("Y", &shape)],
+        vec![tensor("S", &shape)],
+        vec![],
+        vec![scale, node(vec!["S"], vec!["Y"], "relu", "Relu", vec![])],
+    ));
+
+    let config =
+        SessionConfig::new().with_operator_registry(OperatorRegistry::new().with_operator(Scale));
+    let session = pollster::block_on(wonnx::Session::from_model_with_config(model, &config))
+        .expect("Session did not create");
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), data.as_slice().into());
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    let expected: Vec<f32> = data.iter().map(|x| x * 2.5).collect();
+    match &result["Y"] {
+        OutputTensor::F32(output) => common::assert_eq_vector(output, &expected),
+        _ => panic!("expected float output"),
+    }
+}
+
+#[test]
+fn test_custom_op_cpu_unsupported() {
+    let shape = vec![2, 3];
+    let mut square = node(vec!["X"], vec!["Y"], "square", "Square", vec![]);
+    square.set_domain("com.example".to_string());
+    let model = model(graph(
+        vec![tensor("X", &shape)],
+        vec![tensor("Y", &shape)],
+        vec![],
+        vec![],
+        vec![square],
+    ));
+
+    // Custom operators only provide a shader, so they cannot be executed by the CPU backend
+    let config = SessionConfig::new()
+        .with_backend(Backend::Cpu)
+        .with_operator_registry(OperatorRegistry::new().with_operator(Square));
+    assert!(matches!(
+        pollster::block_on(wonnx::Session::from_model_with_config(model, &config)),
+        Err(SessionError::CpuError(CpuError::CustomOperatorUnsupported { ref node, ref op_type }))
+            if node == "square" && op_type == "Square"
+    ));
+}
@@ -0,0 +1,334 @@
+use std::{collections::HashMap, convert::TryInto};
+use wonnx::{
+    onnx::{AttributeProto, TensorProto_DataType},
+    utils::{
+        attribute, graph, initializer, initializer_int64, model_with_opset, node, tensor,
+        tensor_of_type, OutputTensor, Shape,
+    },
+    SessionConfig,
+};
+mod common;
+
+// Boxes and scores from the test cases at https://github.com/onnx/onnx/blob/main/docs/Operators.md#NonMaxSuppression
+const BOXES: [f32; 24] = [
+    0.0, 0.0, 1.0, 1.0, 0.0, 0.1, 1.0, 1.1, 0.0, -0.1, 1.0, 0.9, 0.0, 10.0, 1.0, 11.0, 0.0, 10.1,
+    1.0, 11.1, 0.0, 100.0, 1.0, 101.0,
+];
+const SCORES: [f32; 6] = [0.9, 0.75, 0.6, 0.95, 0.5, 0.3];
+
+/// Runs NonMaxSuppression on the specified boxes and scores (of one batch), returning the selected indices
+fn non_max_suppression(
+    boxes: &[f32],
+    scores: &[f32],
+    class_count: i64,
+    max_output_boxes_per_class: i64,
+    score_threshold: f32,
+    attributes: Vec<AttributeProto>,
+    config: &SessionConfig,
+) -> (Shape, OutputTensor) {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    input_data.insert("B".to_string(), boxes.into());
+    input_data.insert("S".to_string(), scores.into());
+    let box_count = boxes.len() as i64 / 4;
+    let max_selected = class_count * max_output_boxes_per_class;
+
+    // Model: (B, S) -> NonMaxSuppression -> Y
+    let model = model_with_opset(
+        graph(
+            vec![
+                tensor("B", &[1, box_count, 4]),
+                tensor("S", &[1, class_count, box_count]),
+            ],
+            vec![tensor_of_type(
+                "Y",
+                &[max_selected, 3],
+                TensorProto_DataType::INT64,
+            )],
+            vec![],
+            vec![
+                initializer_int64("M", vec![max_output_boxes_per_class], vec![1]),
+                initializer("I", vec![0.5], vec![1]),
+                initializer("T", vec![score_threshold], vec![1]),
+            ],
+            vec![node(
+                vec!["B", "S", "M", "I", "T"],
+                vec!["Y"],
+                "nms",
+                "NonMaxSuppression",
+                attributes,
+            )],
+        ),
+        11,
+    );
+
+    let session = pollster::block_on(wonnx::Session::from_model_with_config(model, config))
+        .expect("Session did not create");
+    let mut result = pollster::block_on(session.run_typed(&input_data)).unwrap();
+    let output = result.remove("Y").unwrap();
+    (output.shape, output.data)
+}
+
+#[test]
+fn test_non_max_suppression() {
+    for config in [
+        SessionConfig::new(),
+        SessionConfig::new().with_strict_int64(true),
+    ] {
+        // Suppress by IoU
+        let (shape, selected) = non_max_suppression(&BOXES, &SCORES, 1, 3, 0.0, vec![], &config);
+        assert_eq!(shape.dims, vec![3, 3]);
+        assert_eq!(selected, OutputTensor::I64(vec![0, 0, 3, 0, 0, 0, 0, 0, 5]));
+
+        // Boxes with a score below the threshold are not selected, so fewer boxes than the maximum are returned
+        let (shape, selected) = non_max_suppression(&BOXES, &SCORES, 1, 3, 0.4, vec![], &config);
+        assert_eq!(shape.dims, vec![2, 3]);
+        assert_eq!(selected, OutputTensor::I64(vec![0, 0, 3, 0, 0, 0]));
+
+        // Limited number of boxes per class, with two classes
+        let scores: Vec<f32> = SCORES.iter().chain(SCORES.iter()).copied().collect();
+        let (_, selected) = non_max_suppression(&BOXES, &scores, 2, 2, 0.0, vec![], &config);
+        assert_eq!(
+            selected,
+            OutputTensor::I64(vec![0, 0, 3, 0, 0, 0, 0, 1, 3, 0, 1, 0])
+        );
+    }
+}
+
+#[test]
+fn test_non_max_suppression_box_formats() {
+    let config = SessionConfig::new();
+
+    // Corners may be specified in any order
+    let flipped = [
+        1.0, 1.0, 0.0, 0.0, 0.0, 0.1, 1.0, 1.1, 0.0, 0.9, 1.0, -0.1, 0.0, 10.0, 1.0, 11.0, 1.0,
+        10.1, 0.0, 11.1, 1.0, 101.0, 0.0, 100.0,
+    ];
+    let (_, selected) = non_max_suppression(&flipped, &SCORES, 1, 3, 0.0, vec![], &config);
+    assert_eq!(selected, OutputTensor::I64(vec![0, 0, 3, 0, 0, 0, 0, 0, 5]));
+
+    // Boxes specified as (x center, y center, width, height)
+    let centered = [
+        0.5, 0.5, 1.0, 1.0, 0.5, 0.6, 1.0, 1.0, 0.5, 0.4, 1.0, 1.0, 0.5, 10.5, 1.0, 1.0, 0.5, 10.6,
+        1.0, 1.0, 0.5, 100.5, 1.0, 1.0,
+    ];
+    let (_, selected) = non_max_suppression(
+        &centered,
+        &SCORES,
+        1,
+        3,
+        0.0,
+        vec![attribute("center_point_box", 1)],
+        &config,
+    );
+    assert_eq!(selected, OutputTensor::I64(vec![0, 0, 3, 0, 0, 0, 0, 0, 5]));
+}
+
+// The output of NonMaxSuppression is padded with -1 to its maximum size. Ops that use it compute results for the rows of
+// padding as well, which are removed from the outputs that follow from it.
+#[test]
+fn test_non_max_suppression_gather() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    input_data.insert("B".to_string(), BOXES[..].into());
+    input_data.insert("S".to_string(), SCORES[..].into());
+
+    // Model: (B, S) -> NonMaxSuppression -> N -> Slice -> Reshape -> Gather(Reshape(B)) -> Y
+    let model = model_with_opset(
+        graph(
+            vec![tensor("B", &[1, 6, 4]), tensor("S", &[1, 1, 6])],
+            vec![
+                tensor_of_type("N", &[3, 3], TensorProto_DataType::INT64),
+                tensor("Y", &[3, 4]),
+            ],
+            vec![
+                tensor_of_type("C", &[3, 1], TensorProto_DataType::INT64),
+                tensor_of_type("I", &[3], TensorProto_DataType::INT64),
+                tensor("R", &[6, 4]),
+            ],
+            vec![
+                initializer_int64("M", vec![3], vec![1]),
+                initializer("O", vec![0.5], vec![1]),
+                initializer("T", vec![0.4], vec![1]),
+                initializer_int64("starts", vec![2], vec![1]),
+                initializer_int64("ends", vec![3], vec![1]),
+                initializer_int64("axes", vec![1], vec![1]),
+                initializer_int64("index_shape", vec![3], vec![1]),
+                initializer_int64("box_shape", vec![6, 4], vec![2]),
+            ],
+            vec![
+                node(
+                    vec!["B", "S", "M", "O", "T"],
+                    vec!["N"],
+                    "nms",
+                    "NonMaxSuppression",
+                    vec![],
+                ),
+                node(
+                    vec!["N", "starts", "ends", "axes"],
+                    vec!["C"],
+                    "slice",
+                    "Slice",
+                    vec![],
+                ),
+                node(
+                    vec!["C", "index_shape"],
+                    vec!["I"],
+                    "reshape_indices",
+                    "Reshape",
+                    vec![],
+                ),
+                node(
+                    vec!["B", "box_shape"],
+                    vec!["R"],
+                    "reshape_boxes",
+                    "Reshape",
+                    vec![],
+                ),
+                node(vec!["R", "I"], vec!["Y"], "gather", "Gather", vec![]),
+            ],
+        ),
+        11,
+    );
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let mut result = pollster::block_on(session.run_typed(&input_data)).unwrap();
+    let selected = result.remove("N").unwrap();
+    assert_eq!(selected.shape.dims, vec![2, 3]);
+    assert_eq!(selected.data, OutputTensor::I64(vec![0, 0, 3, 0, 0, 0]));
+    let boxes = result.remove("Y").unwrap();
+    assert_eq!(boxes.shape.dims, vec![2, 4]);
+    assert_eq!(
+        boxes.data,
+        OutputTensor::F32(vec![0., 10., 1., 11., 0., 0., 1., 1.])
+    );
+}
+
+// The padding is also removed from outputs read back through an IO binding
+#[test]
+fn test_non_max_suppression_io_binding() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    // Model: (B, S) -> NonMaxSuppression -> Y
+    let model = model_with_opset(
+        graph(
+            vec![tensor("B", &[1, 6, 4]), tensor("S", &[1, 1, 6])],
+            vec![tensor_of_type("Y", &[3, 3], TensorProto_DataType::INT64)],
+            vec![],
+            vec![
+                initializer_int64("M", vec![3], vec![1]),
+                initializer("I", vec![0.5], vec![1]),
+                initializer("T", vec![0.4], vec![1]),
+            ],
+            vec![node(
+                vec!["B", "S", "M", "I", "T"],
+                vec!["Y"],
+                "nms",
+                "NonMaxSuppression",
+                vec![],
+            )],
+        ),
+        11,
+    );
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let mut binding = session.create_io_binding().unwrap();
+    binding.bind_output_to_host("Y").unwrap();
+    binding.bind_input("B", &BOXES[..].into()).unwrap();
+    binding.bind_input("S", &SCORES[..].into()).unwrap();
+    pollster::block_on(session.run_with_binding(&mut binding)).unwrap();
+    assert_eq!(binding.output_shape("Y").unwrap().dims, vec![3, 3]);
+    assert_eq!(
+        binding.output("Y"),
+        Some(&OutputTensor::I64(vec![0, 0, 3, 0, 0, 0]))
+    );
+}
+
+/// Runs RoiAlign on a 4x4 image for each of two batches, where each pixel has the value 4 * y + x (plus 16 for the
+/// second batch). As the image is linear, the average of a bin is the value at its center.
+fn roi_align(
+    rois: &[f32],
+    batch_indices: &[i64],
+    output_size: i64,
+    attributes: Vec<AttributeProto>,
+    expected: &[f32],
+) {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    let x: Vec<f32> = (0..32).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), x.as_slice().into());
+    input_data.insert("R".to_string(), rois.into());
+    input_data.insert("I".to_string(), batch_indices.into());
+    let roi_count = batch_indices.len() as i64;
+
+    // Model: (X, R, I) -> RoiAlign -> Y
+    let mut attributes = attributes;
+    attributes.push(attribute("output_height", output_size));
+    attributes.push(attribute("output_width", output_size));
+    let model = model_with_opset(
+        graph(
+            vec![
+                tensor("X", &[2, 1, 4, 4]),
+                tensor("R", &[roi_count, 4]),
+                tensor_of_type("I", &[roi_count], TensorProto_DataType::INT64),
+            ],
+            vec![tensor("Y", &[roi_count, 1, output_size, output_size])],
+            vec![],
+            vec![],
+            vec![node(
+                vec!["X", "R", "I"],
+                vec!["Y"],
+                "roi_align",
+                "RoiAlign",
+                attributes,
+            )],
+        ),
+        16,
+    );
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector((&result["Y"]).try_into().unwrap(), expected);
+}
+
+#[test]
+fn test_roi_align() {
+    // Without half pixel offset; the third RoI extends beyond the image, where values are clamped to the edge
+    roi_align(
+        &[0., 0., 2., 2., 0., 0., 2., 2., 2., 2., 4., 4.],
+        &[0, 1, 0],
+        2,
+        vec![
+            attribute("coordinate_transformation_mode", "output_half_pixel"),
+            attribute("sampling_ratio", 2),
+        ],
+        &[
+            2.5, 3.5, 6.5, 7.5, 18.5, 19.5, 22.5, 23.5, 12.5, 13., 14.5, 15.,
+        ],
+    );
+
+    // With half pixel offset (the default) and adaptive sampling ratio, scaled
+    roi_align(
+        &[2., 2., 6., 6.],
+        &[0],
+        2,
+        vec![attribute("spatial_scale", 0.5)],
+        &[5., 6., 9., 10.],
+    );
+
+    // The maximum of the samples, each of which is the largest of the weighted values of the surrounding pixels
+    roi_align(
+        &[0., 0., 2., 2.],
+        &[0],
+        1,
+        vec![
+            attribute("coordinate_transformation_mode", "output_half_pixel"),
+            attribute("sampling_ratio", 2),
+            attribute("mode", "max"),
+        ],
+        &[2.5],
+    );
+}
@@ -0,0 +1,176 @@
+use std::{collections::HashMap, convert::TryInto};
+use wonnx::{
+    onnx::TensorProto_DataType,
+    utils::{attribute, graph, model_with_opset, node, tensor, tensor_of_type, OutputTensor},
+};
+mod common;
+
+/// Returns the values start, start + 1, ... for a tensor of the specified dimensions
+fn range(start: f32, dims: &[i64]) -> Vec<f32> {
+    (0..dims.iter().product::<i64>())
+        .map(|x| start + x as f32)
+        .collect()
+}
+
+fn assert_einsum(
+    equation: &str,
+    inputs: &[(Vec<f32>, &[i64])],
+    output_dims: &[i64],
+    expected: &[f32],
+) {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let names: Vec<String> = (0..inputs.len()).map(|i| format!("X{}", i)).collect();
+    let mut input_data = HashMap::new();
+    for (name, (data, _)) in names.iter().zip(inputs.iter()) {
+        input_data.insert(name.clone(), data.as_slice().into());
+    }
+
+    // Model: (X0, X1, ...) -> Einsum -> Y
+    let model = model_with_opset(
+        graph(
+            names
+                .iter()
+                .zip(inputs.iter())
+                .map(|(name, (_, dims))| tensor(name, dims))
+                .collect(),
+            vec![tensor("Y", output_dims)],
+            vec![],
+            vec![],
+            vec![node(
+                names.iter().map(|name| name.as_str()).collect(),
+                vec!["Y"],
+                "einsum",
+                "Einsum",
+                vec![attribute("equation", equation)],
+            )],
+        ),
+        12,
+    );
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector((&result["Y"]).try_into().unwrap(), expected);
+}
+
+#[test]
+fn test_einsum_batched_matmul() {
+    assert_einsum(
+        "bij,bjk->bik",
+        &[
+            (range(0., &[2, 2, 4]), &[2, 2, 4]),
+            (range(0., &[2, 4, 2]), &[2, 4, 2]),
+        ],
+        &[2, 2, 2],
+        &[28., 34., 76., 98., 428., 466., 604., 658.],
+    );
+
+    // The same, with implicit output and an ellipsis for the batch dimension
+    assert_einsum(
+        "...ij,...jk",
+        &[
+            (range(0., &[2, 2, 4]), &[2, 2, 4]),
+            (range(0., &[2, 4, 2]), &[2, 4, 2]),
+        ],
+        &[2, 2, 2],
+        &[28., 34., 76., 98., 428., 466., 604., 658.],
+    );
+}
+
+#[test]
+fn test_einsum_outer_product() {
+    assert_einsum(
+        "i,j->ij",
+        &[(range(1., &[3]), &[3]), (range(1., &[4]), &[4])],
+        &[3, 4],
+        &[1., 2., 3., 4., 2., 4., 6., 8., 3., 6., 9., 12.],
+    );
+}
+
+#[test]
+fn test_einsum_trace_diagonal() {
+    assert_einsum("ii->", &[(range(0., &[3, 3]), &[3, 3])], &[], &[12.]);
+    assert_einsum(
+        "ii->i",
+        &[(range(0., &[4, 4]), &[4, 4])],
+        &[4],
+        &[0., 5., 10., 15.],
+    );
+}
+
+#[test]
+fn test_einsum_transpose_contract() {
+    assert_einsum(
+        "ijk,jl->lki",
+        &[
+            (range(0., &[2, 4, 2]), &[2, 4, 2]),
+            (range(0., &[4, 2]), &[4, 2]),
+        ],
+        &[2, 2, 2],
+        &[56., 152., 68., 164., 68., 196., 84., 212.],
+    );
+    assert_einsum(
+        "ij->ji",
+        &[(range(0., &[2, 3]), &[2, 3])],
+        &[3, 2],
+        &[0., 3., 1., 4., 2., 5.],
+    );
+}
+
+#[test]
+fn test_einsum_three_inputs() {
+    assert_einsum(
+        "ij,jk,kl->il",
+        &[
+            (range(0., &[2, 2]), &[2, 2]),
+            (range(1., &[2, 2]), &[2, 2]),
+            (range(2., &[2, 2]), &[2, 2]),
+        ],
+        &[2, 2],
+        &[22., 29., 86., 113.],
+    );
+}
+
+// Equations that are not lowered to other ops are executed by the generic Einsum shader
+#[test]
+fn test_einsum_generic() {
+    // Broadcasting
+    assert_einsum(
+        "ij,ij->ij",
+        &[(range(0., &[2, 3]), &[2, 3]), (range(1., &[1, 3]), &[1, 3])],
+        &[2, 3],
+        &[0., 2., 6., 3., 8., 15.],
+    );
+
+    // Integers
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    let a: Vec<i32> = (0..6).collect();
+    input_data.insert("A".to_string(), a.as_slice().into());
+    input_data.insert("B".to_string(), a.as_slice().into());
+
+    let model = model_with_opset(
+        graph(
+            vec![
+                tensor_of_type("A", &[2, 3], TensorProto_DataType::INT32),
+                tensor_of_type("B", &[3, 2], TensorProto_DataType::INT32),
+            ],
+            vec![tensor_of_type("Y", &[2, 2], TensorProto_DataType::INT32)],
+            vec![],
+            vec![],
+            vec![node(
+                vec!["A", "B"],
+                vec!["Y"],
+                "einsum",
+                "Einsum",
+                vec![attribute("equation", "ij,jk->ik")],
+            )],
+        ),
+        12,
+    );
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["Y"], OutputTensor::I32(vec![10, 13, 28, 40]));
+}
@@ -1,5 +1,12 @@
 use std::{collections::HashMap, convert::TryInto};
-use wonnx::utils::{attribute, graph, model, node, tensor};
+use wonnx::{
+    onnx::{AttributeProto, TensorProto_DataType},
+    utils::{
+        attribute, graph, initializer, initializer_int64, model, model_with_opset, node, tensor,
+        tensor_of_type, InputTensor, OutputTensor,
+    },
+    SessionConfig,
+};
 mod common;
 
 fn assert_gather(
@@ -18,7 +25,10 @@ fn assert_gather(
 
     // Model: (X, I) -> Gather -> Y
     let bn_model = model(graph(
-        vec![tensor("X", data_shape), tensor("I", indices_shape)],
+        vec![
+            tensor("X", data_shape),
+            tensor_of_type("I", indices_shape, TensorProto_DataType::INT32),
+        ],
         vec![tensor("Y", output_shape)],
         vec![],
         vec![],
@@ -88,3 +98,414 @@ fn gather() {
         0,
     );
 }
+
+#[test]
+fn gather_int64_strict() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let data: Vec<i64> = vec![1 << 40, -(1 << 35), 3, i64::MIN, 5, 6];
+    let indices: Vec<i64> = vec![2, -3, 0];
+
+    let mut input_data: HashMap<String, InputTensor> = HashMap::new();
+    input_data.insert("X".to_string(), data.as_slice().into());
+    input_data.insert("I".to_string(), indices.as_slice().into());
+
+    // Model: (X, I) -> Gather -> Y, all int64
+    let gather_model = model(graph(
+        vec![
+            tensor_of_type("X", &[3, 2], TensorProto_DataType::INT64),
+            tensor_of_type("I", &[3], TensorProto_DataType::INT64),
+        ],
+        vec![tensor_of_type("Y", &[3, 2], TensorProto_DataType::INT64)],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["X", "I"],
+            vec!["Y"],
+            "myGather",
+            "Gather",
+            vec![attribute("axis", 0)],
+        )],
+    ));
+
+    let config = SessionConfig::new().with_strict_int64(true);
+    let session = pollster::block_on(wonnx::Session::from_model_with_config(
+        gather_model,
+        &config,
+    ))
+    .expect("Session did not create");
+
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["Y"],
+        OutputTensor::I64(vec![5, 6, 1 << 40, -(1 << 35), 1 << 40, -(1 << 35)])
+    );
+}
+
+fn assert_gather_elements_nd(
+    op: &str,
+    data: &[f32],
+    data_shape: &[i64],
+    indices: &[i64],
+    indices_shape: &[i64],
+    output: &[f32],
+    output_shape: &[i64],
+    attributes: Vec<AttributeProto>,
+) {
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), data.into());
+
+    // Model: (X, I) -> GatherElements/GatherND -> Y
+    let model = model(graph(
+        vec![tensor("X", data_shape)],
+        vec![tensor("Y", output_shape)],
+        vec![],
+        vec![initializer_int64(
+            "I",
+            indices.to_vec(),
+            indices_shape.to_vec(),
+        )],
+        vec![node(vec!["X", "I"], vec!["Y"], "myGather", op, attributes)],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector((&result["Y"]).try_into().unwrap(), output);
+}
+
+// Test cases adapted from https://github.com/onnx/onnx/blob/main/docs/Operators.md#GatherElements
+#[test]
+fn gather_elements() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    assert_gather_elements_nd(
+        "GatherElements",
+        &[1., 2., 3., 4.],
+        &[2, 2],
+        &[0, 0, 1, 0],
+        &[2, 2],
+        &[1., 1., 4., 3.],
+        &[2, 2],
+        vec![attribute("axis", 1)],
+    );
+
+    assert_gather_elements_nd(
+        "GatherElements",
+        &[1., 2., 3., 4., 5., 6., 7., 8., 9.],
+        &[3, 3],
+        &[1, 2, 0, 2, 0, 0],
+        &[2, 3],
+        &[4., 8., 3., 7., 2., 3.],
+        &[2, 3],
+        vec![attribute("axis", 0)],
+    );
+
+    // With negative indices
+    assert_gather_elements_nd(
+        "GatherElements",
+        &[1., 2., 3., 4., 5., 6., 7., 8., 9.],
+        &[3, 3],
+        &[-1, -2, 0, -2, 0, 0],
+        &[2, 3],
+        &[7., 5., 3., 4., 2., 3.],
+        &[2, 3],
+        vec![attribute("axis", 0)],
+    );
+}
+
+// Test cases adapted from https://github.com/onnx/onnx/blob/main/docs/Operators.md#GatherND
+#[test]
+fn gather_nd() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    assert_gather_elements_nd(
+        "GatherND",
+        &[0., 1., 2., 3.],
+        &[2, 2],
+        &[0, 0, 1, 1],
+        &[2, 2],
+        &[0., 3.],
+        &[2],
+        vec![],
+    );
+
+    assert_gather_elements_nd(
+        "GatherND",
+        &[0., 1., 2., 3.],
+        &[2, 2],
+        &[1, 0],
+        &[2, 1],
+        &[2., 3., 0., 1.],
+        &[2, 2],
+        vec![],
+    );
+
+    assert_gather_elements_nd(
+        "GatherND",
+        &[0., 1., 2., 3., 4., 5., 6., 7.],
+        &[2, 2, 2],
+        &[0, 1, 1, 0],
+        &[2, 2],
+        &[2., 3., 4., 5.],
+        &[2, 2],
+        vec![],
+    );
+
+    assert_gather_elements_nd(
+        "GatherND",
+        &[0., 1., 2., 3., 4., 5., 6., 7.],
+        &[2, 2, 2],
+        &[1, 0],
+        &[2, 1],
+        &[2., 3., 4., 5.],
+        &[2, 2],
+        vec![attribute("batch_dims", 1)],
+    );
+
+    // With negative indices
+    assert_gather_elements_nd(
+        "GatherND",
+        &[0., 1., 2., 3.],
+        &[2, 2],
+        &[-1, 0],
+        &[1, 2],
+        &[2.],
+        &[1],
+        vec![],
+    );
+}
+
+fn assert_scatter(
+    op: &str,
+    data: &[f32],
+    data_shape: &[i64],
+    indices: &[i64],
+    indices_shape: &[i64],
+    updates: &[f32],
+    updates_shape: &[i64],
+    output: &[f32],
+    attributes: Vec<AttributeProto>,
+) {
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), data.into());
+
+    // Model: (X, I, U) -> ScatterElements/ScatterND -> Y
+    let model = model_with_opset(
+        graph(
+            vec![tensor("X", data_shape)],
+            vec![tensor("Y", data_shape)],
+            vec![],
+            vec![
+                initializer_int64("I", indices.to_vec(), indices_shape.to_vec()),
+                initializer("U", updates.to_vec(), updates_shape.to_vec()),
+            ],
+            vec![node(
+                vec!["X", "I", "U"],
+                vec!["Y"],
+                "myScatter",
+                op,
+                attributes,
+            )],
+        ),
+        18,
+    );
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector((&result["Y"]).try_into().unwrap(), output);
+}
+
+// Test cases adapted from https://github.com/onnx/onnx/blob/main/docs/Operators.md#ScatterElements
+#[test]
+fn scatter_elements() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    assert_scatter(
+        "ScatterElements",
+        &[0.; 9],
+        &[3, 3],
+        &[1, 0, 2, 0, 2, 1],
+        &[2, 3],
+        &[1.0, 1.1, 1.2, 2.0, 2.1, 2.2],
+        &[2, 3],
+        &[2.0, 1.1, 0.0, 1.0, 0.0, 2.2, 0.0, 2.1, 1.2],
+        vec![attribute("axis", 0)],
+    );
+
+    // With negative indices
+    assert_scatter(
+        "ScatterElements",
+        &[1., 2., 3., 4., 5.],
+        &[1, 5],
+        &[1, -3],
+        &[1, 2],
+        &[1.1, 2.1],
+        &[1, 2],
+        &[1., 1.1, 2.1, 4., 5.],
+        vec![attribute("axis", 1)],
+    );
+
+    // Updates for the same element are combined
+    assert_scatter(
+        "ScatterElements",
+        &[1., 2., 3., 4., 5.],
+        &[1, 5],
+        &[1, 1],
+        &[1, 2],
+        &[1.1, 2.1],
+        &[1, 2],
+        &[1., 5.2, 3., 4., 5.],
+        vec![attribute("axis", 1), attribute("reduction", "add")],
+    );
+    assert_scatter(
+        "ScatterElements",
+        &[1., 2., 3., 4., 5.],
+        &[1, 5],
+        &[1, 1, 3],
+        &[1, 3],
+        &[1.5, 0.5, 9.],
+        &[1, 3],
+        &[1., 0.5, 3., 4., 5.],
+        vec![attribute("axis", 1), attribute("reduction", "min")],
+    );
+}
+
+// Test cases adapted from https://github.com/onnx/onnx/blob/main/docs/Operators.md#ScatterND
+#[test]
+fn scatter_nd() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    assert_scatter(
+        "ScatterND",
+        &[1., 2., 3., 4., 5., 6., 7., 8.],
+        &[8],
+        &[4, 3, 1, 7],
+        &[4, 1],
+        &[9., 10., 11., 12.],
+        &[4],
+        &[1., 11., 3., 10., 9., 6., 7., 12.],
+        vec![],
+    );
+
+    // Slices, negative indices and combined updates
+    assert_scatter(
+        "ScatterND",
+        &[1., 2., 3., 4., 5., 6.],
+        &[2, 3],
+        &[1, -2, 0],
+        &[3, 1],
+        &[2., 2., 2., 3., 3., 3., 10., 10., 10.],
+        &[3, 3],
+        &[30., 60., 90., 8., 10., 12.],
+        vec![attribute("reduction", "mul")],
+    );
+}
+
+// When the data is an intermediate value that is not used elsewhere, ScatterND updates it in place
+#[test]
+fn scatter_nd_in_place() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    let data: &[f32] = &[1., 2., 3., 4., 5., 6.];
+    input_data.insert("X".to_string(), data.into());
+
+    for a_is_output in [false, true] {
+        let mut outputs = vec![tensor("Y", &[2, 3])];
+        if a_is_output {
+            outputs.push(tensor("A", &[2, 3]));
+        }
+
+        // Model: X -> Neg -> A, (A, I, U) -> ScatterND -> Y
+        let model = model_with_opset(
+            graph(
+                vec![tensor("X", &[2, 3])],
+                outputs,
+                vec![tensor("A", &[2, 3])],
+                vec![
+                    initializer_int64("I", vec![1, -2, 0], vec![3, 1]),
+                    initializer("U", vec![2., 2., 2., 3., 3., 3., 10., 10., 10.], vec![3, 3]),
+                ],
+                vec![
+                    node(vec!["X"], vec!["A"], "myNeg", "Neg", vec![]),
+                    node(
+                        vec!["A", "I", "U"],
+                        vec!["Y"],
+                        "myScatter",
+                        "ScatterND",
+                        vec![attribute("reduction", "mul")],
+                    ),
+                ],
+            ),
+            16,
+        );
+
+        let session =
+            pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+
+        let result = pollster::block_on(session.run(&input_data)).unwrap();
+        common::assert_eq_vector(
+            (&result["Y"]).try_into().unwrap(),
+            &[-30., -60., -90., -8., -10., -12.],
+        );
+        if a_is_output {
+            common::assert_eq_vector(
+                (&result["A"]).try_into().unwrap(),
+                &[-1., -2., -3., -4., -5., -6.],
+            );
+        }
+    }
+}
+
+#[test]
+fn gather_scatter_int64_strict() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let data: Vec<i64> = vec![1 << 40, 2, 3, -(1 << 35)];
+
+    let mut input_data: HashMap<String, InputTensor> = HashMap::new();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Model: (X, I) -> GatherElements -> G, (X, J, U) -> ScatterND -> S, all int64
+    let model = model(graph(
+        vec![tensor_of_type("X", &[2, 2], TensorProto_DataType::INT64)],
+        vec![
+            tensor_of_type("G", &[1, 2], TensorProto_DataType::INT64),
+            tensor_of_type("S", &[2, 2], TensorProto_DataType::INT64),
+        ],
+        vec![],
+        vec![
+            initializer_int64("I", vec![1, -2], vec![1, 2]),
+            initializer_int64("J", vec![1, 0, 0, 0], vec![2, 2]),
+            initializer_int64("U", vec![7, -(1 << 36)], vec![2]),
+        ],
+        vec![
+            node(
+                vec!["X", "I"],
+                vec!["G"],
+                "myGather",
+                "GatherElements",
+                vec![attribute("axis", 0)],
+            ),
+            node(
+                vec!["X", "J", "U"],
+                vec!["S"],
+                "myScatter",
+                "ScatterND",
+                vec![],
+            ),
+        ],
+    ));
+
+    let config = SessionConfig::new().with_strict_int64(true);
+    let session = pollster::block_on(wonnx::Session::from_model_with_config(model, &config))
+        .expect("Session did not create");
+
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(result["G"], OutputTensor::I64(vec![3, 2]));
+    assert_eq!(
+        result["S"],
+        OutputTensor::I64(vec![-(1 << 36), 2, 7, -(1 << 35)])
+    );
+}
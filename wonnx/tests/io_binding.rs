@@ -0,0 +1,177 @@
+use std::{collections::HashMap, convert::TryInto};
+use wonnx::{
+    utils::{graph, model, node, tensor, InputTensor, OutputTensor},
+    SessionConfig, SessionError,
+};
+
+mod common;
+
+#[test]
+fn test_io_binding_host_output() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let shape = vec![2, 4];
+
+    // Model: X -> Relu -> Y
+    let model = model(graph(
+        vec![tensor("X", &shape)],
+        vec![tensor("Y", &shape)],
+        vec![],
+        vec![],
+        vec![node(vec!["X"], vec!["Y"], "relu", "Relu", vec![])],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let mut binding = session.create_io_binding().unwrap();
+    binding.bind_output_to_host("Y").unwrap();
+
+    // Running before all inputs are bound is an error
+    assert!(pollster::block_on(session.run_with_binding(&mut binding)).is_err());
+
+    // The same binding can be used for multiple runs with updated inputs
+    for i in 0..3 {
+        let data: Vec<f32> = (0..8).map(|x| (x - 4 + i) as f32).collect();
+        binding
+            .bind_input("X", &InputTensor::from(data.as_slice()))
+            .unwrap();
+        pollster::block_on(session.run_with_binding(&mut binding)).unwrap();
+
+        let expected: Vec<f32> = data.iter().map(|x| x.max(0.0)).collect();
+        match binding.output("Y") {
+            Some(OutputTensor::F32(output)) => common::assert_eq_vector(output, &expected),
+            _ => panic!("expected float output"),
+        }
+    }
+
+    let data: Vec<f32> = vec![1.0; 3];
+    assert!(matches!(
+        binding.bind_input("X", &InputTensor::from(data.as_slice())),
+        Err(SessionError::InputSizeMismatch { actual: 3, .. })
+    ));
+    assert!(matches!(
+        binding.bind_output_to_host("Z"),
+        Err(SessionError::InvalidOutput(_))
+    ));
+}
+
+#[test]
+fn test_io_binding_interleaved_runs() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let shape = vec![4];
+
+    // Model: X -> Add(X, X) -> Y
+    let model = model(graph(
+        vec![tensor("X", &shape)],
+        vec![tensor("Y", &shape)],
+        vec![],
+        vec![],
+        vec![node(vec!["X", "X"], vec!["Y"], "add", "Add", vec![])],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let mut first = session.create_io_binding().unwrap();
+    let mut second = session.create_io_binding().unwrap();
+    first.bind_output_to_host("Y").unwrap();
+    second.bind_output_to_host("Y").unwrap();
+    first
+        .bind_input("X", &InputTensor::from(&[1.0f32, 2.0, 3.0, 4.0][..]))
+        .unwrap();
+    second
+        .bind_input("X", &InputTensor::from(&[5.0f32, 6.0, 7.0, 8.0][..]))
+        .unwrap();
+
+    // Neither running the session without a binding nor another binding affects the inputs bound to a binding
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), InputTensor::from(&[100.0f32; 4][..]));
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector((&result["Y"]).try_into().unwrap(), &[200.0; 4]);
+
+    for _ in 0..2 {
+        pollster::block_on(session.run_with_binding(&mut first)).unwrap();
+        pollster::block_on(session.run_with_binding(&mut second)).unwrap();
+        match (first.output("Y"), second.output("Y")) {
+            (Some(OutputTensor::F32(first)), Some(OutputTensor::F32(second))) => {
+                common::assert_eq_vector(first, &[2.0, 4.0, 6.0, 8.0]);
+                common::assert_eq_vector(second, &[10.0, 12.0, 14.0, 16.0]);
+            }
+            _ => panic!("expected float output"),
+        }
+        pollster::block_on(session.run(&input_data)).unwrap();
+    }
+}
+
+#[test]
+fn test_io_binding_chain_sessions() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let shape = vec![8];
+
+    // First model: X -> Neg -> Y, second model: A -> Relu -> B
+    let first_model = model(graph(
+        vec![tensor("X", &shape)],
+        vec![tensor("Y", &shape)],
+        vec![],
+        vec![],
+        vec![node(vec!["X"], vec!["Y"], "neg", "Neg", vec![])],
+    ));
+    let second_model = model(graph(
+        vec![tensor("A", &shape)],
+        vec![tensor("B", &shape)],
+        vec![],
+        vec![],
+        vec![node(vec!["A"], vec!["B"], "relu", "Relu", vec![])],
+    ));
+
+    let first = pollster::block_on(wonnx::Session::from_model(first_model))
+        .expect("Session did not create");
+    let (device, queue) = first.device_queue().unwrap();
+    let config = SessionConfig::new().with_device_queue(device, queue);
+    let second = pollster::block_on(wonnx::Session::from_model_with_config(
+        second_model,
+        &config,
+    ))
+    .expect("Session did not create");
+
+    let mut first_binding = first.create_io_binding().unwrap();
+    let mut second_binding = second.create_io_binding().unwrap();
+    first_binding
+        .bind_output_to_input("Y", &mut second_binding, "A")
+        .unwrap();
+    second_binding.bind_output_to_host("B").unwrap();
+
+    let data: Vec<f32> = (0..8).map(|x| (x - 4) as f32).collect();
+    first_binding
+        .bind_input("X", &InputTensor::from(data.as_slice()))
+        .unwrap();
+    pollster::block_on(first.run_with_binding(&mut first_binding)).unwrap();
+    pollster::block_on(second.run_with_binding(&mut second_binding)).unwrap();
+
+    let expected: Vec<f32> = data.iter().map(|x| (-x).max(0.0)).collect();
+    match second_binding.output("B") {
+        Some(OutputTensor::F32(output)) => common::assert_eq_vector(output, &expected),
+        _ => panic!("expected float output"),
+    }
+
+    // Running the second session (directly or with another binding) does not affect the chained input
+    let mut other_binding = second.create_io_binding().unwrap();
+    other_binding
+        .bind_input("A", &InputTensor::from(&[100.0f32; 8][..]))
+        .unwrap();
+    let mut input_data = HashMap::new();
+    input_data.insert("A".to_string(), InputTensor::from(&[200.0f32; 8][..]));
+
+    let data: Vec<f32> = (0..8).map(|x| (4 - x) as f32).collect();
+    first_binding
+        .bind_input("X", &InputTensor::from(data.as_slice()))
+        .unwrap();
+    pollster::block_on(first.run_with_binding(&mut first_binding)).unwrap();
+    pollster::block_on(second.run(&input_data)).unwrap();
+    pollster::block_on(second.run_with_binding(&mut other_binding)).unwrap();
+    pollster::block_on(second.run_with_binding(&mut second_binding)).unwrap();
+
+    let expected: Vec<f32> = data.iter().map(|x| (-x).max(0.0)).collect();
+    match second_binding.output("B") {
+        Some(OutputTensor::F32(output)) => common::assert_eq_vector(output, &expected),
+        _ => panic!("expected float output"),
+    }
+}
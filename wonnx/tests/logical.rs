@@ -0,0 +1,153 @@
+use std::{collections::HashMap, convert::TryInto};
+use wonnx::{
+    onnx::TensorProto_DataType,
+    utils::{attribute, graph, model, node, tensor, tensor_of_type, InputTensor, OutputTensor},
+};
+mod common;
+
+#[test]
+fn test_comparison_bool_output() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let x: Vec<f32> = vec![1.0, 5.0, 3.0, -1.0, 2.0, 2.0];
+    let y: Vec<f32> = vec![2.0, 4.0, 3.0];
+
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), x.as_slice().into());
+    input_data.insert("Y".to_string(), y.as_slice().into());
+
+    // Model: (X, Y) -> Greater -> Z (with broadcasting of Y)
+    let model = model(graph(
+        vec![tensor("X", &[2, 3]), tensor("Y", &[3])],
+        vec![tensor_of_type("Z", &[2, 3], TensorProto_DataType::BOOL)],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["X", "Y"],
+            vec!["Z"],
+            "greater",
+            "Greater",
+            vec![],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["Z"],
+        OutputTensor::Bool(vec![false, true, false, false, false, false])
+    );
+}
+
+#[test]
+fn test_not_xor() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let a = vec![true, true, false, false];
+    let b = vec![true, false, true, false];
+
+    let mut input_data: HashMap<String, InputTensor> = HashMap::new();
+    input_data.insert("A".to_string(), a.as_slice().into());
+    input_data.insert("B".to_string(), b.as_slice().into());
+
+    // Model: Z = Not(Xor(A, B))
+    let bool_type = TensorProto_DataType::BOOL;
+    let model = model(graph(
+        vec![
+            tensor_of_type("A", &[4], bool_type),
+            tensor_of_type("B", &[4], bool_type),
+        ],
+        vec![tensor_of_type("Z", &[4], bool_type)],
+        vec![tensor_of_type("X", &[4], bool_type)],
+        vec![],
+        vec![
+            node(vec!["A", "B"], vec!["X"], "xor", "Xor", vec![]),
+            node(vec!["X"], vec!["Z"], "not", "Not", vec![]),
+        ],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    assert_eq!(
+        result["Z"],
+        OutputTensor::Bool(vec![true, false, false, true])
+    );
+}
+
+#[test]
+fn test_where() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let condition = vec![true, false];
+    let x: Vec<f32> = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+    let y: Vec<f32> = vec![-1.0, -2.0, -3.0];
+
+    let mut input_data: HashMap<String, InputTensor> = HashMap::new();
+    input_data.insert("C".to_string(), condition.as_slice().into());
+    input_data.insert("X".to_string(), x.as_slice().into());
+    input_data.insert("Y".to_string(), y.as_slice().into());
+
+    // Model: (C, X, Y) -> Where -> Z, where C is broadcast over the columns and Y over the rows
+    let model = model(graph(
+        vec![
+            tensor_of_type("C", &[2, 1], TensorProto_DataType::BOOL),
+            tensor("X", &[2, 3]),
+            tensor("Y", &[3]),
+        ],
+        vec![tensor("Z", &[2, 3])],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["C", "X", "Y"],
+            vec!["Z"],
+            "where",
+            "Where",
+            vec![],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["Z"]).try_into().unwrap(),
+        &[1.0, 2.0, 3.0, -1.0, -2.0, -3.0],
+    );
+}
+
+#[test]
+fn test_cast_bool() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let x: Vec<f32> = vec![0.0, 0.5, -2.0, 0.0];
+
+    let mut input_data = HashMap::new();
+    input_data.insert("X".to_string(), x.as_slice().into());
+
+    // Model: X -> Cast(bool) -> B -> Cast(float) -> Y
+    let model = model(graph(
+        vec![tensor("X", &[4])],
+        vec![tensor("Y", &[4])],
+        vec![tensor_of_type("B", &[4], TensorProto_DataType::BOOL)],
+        vec![],
+        vec![
+            node(
+                vec!["X"],
+                vec!["B"],
+                "to_bool",
+                "Cast",
+                vec![attribute("to", TensorProto_DataType::BOOL as i64)],
+            ),
+            node(
+                vec!["B"],
+                vec!["Y"],
+                "to_float",
+                "Cast",
+                vec![attribute("to", TensorProto_DataType::FLOAT as i64)],
+            ),
+        ],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector((&result["Y"]).try_into().unwrap(), &[0.0, 1.0, 1.0, 0.0]);
+}
@@ -338,6 +338,142 @@ fn test_pad_complex() {
     assert_eq!(actual, &test_y);
 }
 
+#[test]
+fn test_slice() {
+    let mut input_data = HashMap::new();
+    let data = (0..12).map(|x| x as f32).collect::<Vec<f32>>();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Every other row in reverse order (the end is clamped to before the first row), and every other column starting
+    // at the second one (the end is clamped to the number of columns)
+    let model = model(graph(
+        vec![tensor("X", &[3, 4])],
+        vec![tensor("Y", &[2, 2])],
+        vec![],
+        vec![
+            initializer_int64("starts", vec![-1, 1], vec![2]),
+            initializer_int64("ends", vec![-10, 1000], vec![2]),
+            initializer_int64("axes", vec![0, -1], vec![2]),
+            initializer_int64("steps", vec![-2, 2], vec![2]),
+        ],
+        vec![node(
+            vec!["X", "starts", "ends", "axes", "steps"],
+            vec!["Y"],
+            "slice",
+            "Slice",
+            vec![],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    let actual: &[_] = (&result["Y"]).try_into().unwrap();
+    assert_eq!(actual, &[9.0, 11.0, 1.0, 3.0]);
+}
+
+#[test]
+fn test_slice_attributes() {
+    let mut input_data = HashMap::new();
+    let data = (0..24).map(|x| x as f32).collect::<Vec<f32>>();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Before opset 10, the starts, ends and axes are specified as attributes
+    let model = model(graph(
+        vec![tensor("X", &[2, 3, 4])],
+        vec![tensor("Y", &[2, 1, 2])],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["X"],
+            vec!["Y"],
+            "slice",
+            "Slice",
+            vec![
+                attribute("starts", vec![1, 1]),
+                attribute("ends", vec![2, -1]),
+                attribute("axes", vec![1, 2]),
+            ],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    let actual: &[_] = (&result["Y"]).try_into().unwrap();
+    assert_eq!(actual, &[5.0, 6.0, 17.0, 18.0]);
+}
+
+#[test]
+fn test_expand() {
+    let mut input_data = HashMap::new();
+    let data = [1.0, 2.0, 3.0].to_vec();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // The shape is broadcast in both directions: [3, 1] and [2, 1, 4] result in [2, 3, 4]
+    let model = model(graph(
+        vec![tensor("X", &[3, 1])],
+        vec![tensor("Y", &[2, 3, 4])],
+        vec![],
+        vec![initializer_int64("shape", vec![2, 1, 4], vec![3])],
+        vec![node(
+            vec!["X", "shape"],
+            vec!["Y"],
+            "expand",
+            "Expand",
+            vec![],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    let row: Vec<f32> = [1.0, 2.0, 3.0].iter().flat_map(|x| [*x; 4]).collect();
+    let test_y = [row.clone(), row].concat();
+    let actual: &[_] = (&result["Y"]).try_into().unwrap();
+    assert_eq!(actual, &test_y);
+}
+
+#[test]
+fn test_tile() {
+    let mut input_data = HashMap::new();
+    #[rustfmt::skip]
+    let data = [
+        1.0, 2.0,
+        3.0, 4.0,
+    ].to_vec();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let model = model(graph(
+        vec![tensor("X", &[2, 2])],
+        vec![tensor("Y", &[4, 6])],
+        vec![],
+        vec![initializer_int64("repeats", vec![2, 3], vec![2])],
+        vec![node(
+            vec!["X", "repeats"],
+            vec!["Y"],
+            "tile",
+            "Tile",
+            vec![],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    #[rustfmt::skip]
+    let test_y = vec![
+        1.0, 2.0, 1.0, 2.0, 1.0, 2.0,
+        3.0, 4.0, 3.0, 4.0, 3.0, 4.0,
+        1.0, 2.0, 1.0, 2.0, 1.0, 2.0,
+        3.0, 4.0, 3.0, 4.0, 3.0, 4.0,
+    ];
+    let actual: &[_] = (&result["Y"]).try_into().unwrap();
+    assert_eq!(actual, &test_y);
+}
+
 #[test]
 fn test_resize() {
     let _ = env_logger::builder().is_test(true).try_init();
@@ -0,0 +1,157 @@
+use std::{collections::HashMap, convert::TryInto};
+use wonnx::utils::{attribute, graph, initializer, model, model_with_opset, node, tensor};
+mod common;
+
+#[test]
+fn layer_normalization() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![0.0, 0.0, 3.0, 3.0, 2.0, 2.0, -1.0, -1.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Each row has mean 1.5 or 0.5 and variance 2.25, so with an epsilon of 1.75 the standard deviation is exactly 2
+    let ln_model = model_with_opset(
+        graph(
+            vec![tensor("X", &[2, 4])],
+            vec![
+                tensor("Y", &[2, 4]),
+                tensor("Mean", &[2, 1]),
+                tensor("InvStdDev", &[2, 1]),
+            ],
+            vec![],
+            vec![
+                initializer("scale", vec![1.0, 2.0, 1.0, 2.0], vec![4]),
+                initializer("B", vec![0.0, 0.0, 1.0, 1.0], vec![4]),
+            ],
+            vec![node(
+                vec!["X", "scale", "B"],
+                vec!["Y", "Mean", "InvStdDev"],
+                "ln",
+                "LayerNormalization",
+                vec![attribute("epsilon", 1.75)],
+            )],
+        ),
+        17,
+    );
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(ln_model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    // Y = (X - mean) / sqrt(variance + epsilon) * scale + B
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[-0.75, -1.5, 1.75, 2.5, 0.75, 1.5, 0.25, -0.5],
+    );
+    common::assert_eq_vector((&result["Mean"]).try_into().unwrap(), &[1.5, 0.5]);
+    common::assert_eq_vector((&result["InvStdDev"]).try_into().unwrap(), &[0.5, 0.5]);
+}
+
+#[test]
+fn layer_normalization_large() {
+    // Each group is larger than a workgroup, so every invocation sums several values
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..1200)
+        .map(|x| if x % 2 == 0 { 8.0 } else { 2.0 })
+        .collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // The mean is 5 and the variance 9, so with an epsilon of 16 the standard deviation is exactly 5
+    let ln_model = model_with_opset(
+        graph(
+            vec![tensor("X", &[2, 2, 300])],
+            vec![tensor("Y", &[2, 2, 300])],
+            vec![],
+            vec![initializer("scale", vec![1.0; 600], vec![2, 300])],
+            vec![node(
+                vec!["X", "scale"],
+                vec!["Y"],
+                "ln",
+                "LayerNormalization",
+                vec![attribute("axis", 1), attribute("epsilon", 16.0)],
+            )],
+        ),
+        17,
+    );
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(ln_model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    let expected: Vec<f32> = (0..1200)
+        .map(|x| if x % 2 == 0 { 0.6 } else { -0.6 })
+        .collect();
+    common::assert_eq_vector((&result["Y"]).try_into().unwrap(), &expected);
+}
+
+#[test]
+fn instance_normalization() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![0.0, 0.0, 3.0, 3.0, 2.0, 2.0, -1.0, -1.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let in_model = model(graph(
+        vec![tensor("X", &[1, 2, 2, 2])],
+        vec![tensor("Y", &[1, 2, 2, 2])],
+        vec![],
+        vec![
+            initializer("scale", vec![1.0, 2.0], vec![2]),
+            initializer("B", vec![0.0, 1.0], vec![2]),
+        ],
+        vec![node(
+            vec!["X", "scale", "B"],
+            vec!["Y"],
+            "in",
+            "InstanceNormalization",
+            vec![attribute("epsilon", 1.75)],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(in_model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[-0.75, -0.75, 0.75, 0.75, 2.5, 2.5, -0.5, -0.5],
+    );
+}
+
+#[test]
+fn group_normalization() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![0.0, 0.0, 3.0, 3.0, 2.0, 2.0, -1.0, -1.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Opset 18 specifies the scale and bias per group, opset 21 per channel
+    for (opset_version, scale, bias) in [
+        (18, vec![1.0, 2.0], vec![0.0, 1.0]),
+        (21, vec![1.0, 1.0, 2.0, 2.0], vec![0.0, 0.0, 1.0, 1.0]),
+    ] {
+        let scale_length = scale.len() as i64;
+        let gn_model = model_with_opset(
+            graph(
+                vec![tensor("X", &[1, 4, 2])],
+                vec![tensor("Y", &[1, 4, 2])],
+                vec![],
+                vec![
+                    initializer("scale", scale, vec![scale_length]),
+                    initializer("B", bias, vec![scale_length]),
+                ],
+                vec![node(
+                    vec!["X", "scale", "B"],
+                    vec!["Y"],
+                    "gn",
+                    "GroupNormalization",
+                    vec![attribute("epsilon", 1.75), attribute("num_groups", 2)],
+                )],
+            ),
+            opset_version,
+        );
+
+        let session = pollster::block_on(wonnx::Session::from_model(gn_model))
+            .expect("Session did not create");
+        let result = pollster::block_on(session.run(&input_data)).unwrap();
+        common::assert_eq_vector(
+            (&result["Y"]).try_into().unwrap(),
+            &[-0.75, -0.75, 0.75, 0.75, 2.5, 2.5, -0.5, -0.5],
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use std::{collections::HashMap, convert::TryInto};
 use wonnx::{
-    onnx::AttributeProto,
-    utils::{attribute, graph, model, node, tensor, InputTensor},
+    onnx::{AttributeProto, TensorProto_DataType},
+    utils::{attribute, graph, model, node, tensor, tensor_of_type, InputTensor},
 };
 mod common;
 
@@ -29,8 +29,8 @@ fn test_onehot(
     // Model: I, D, V -> OneHot -> Y
     let model = model(graph(
         vec![
-            tensor("I", indexes_shape),
-            tensor("D", &[]),
+            tensor_of_type("I", indexes_shape, TensorProto_DataType::INT32),
+            tensor_of_type("D", &[], TensorProto_DataType::INT32),
             tensor("V", &[values.len() as i64]),
         ],
         vec![tensor("Y", output_shape)],
@@ -0,0 +1,70 @@
+use std::{collections::HashMap, convert::TryInto};
+use wonnx::utils::{attribute, graph, model, node, tensor};
+mod common;
+
+#[test]
+fn max_pool_1d() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1., 3., 2., 5., 4., -1., -3., -2., -5., -4.];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let pool_model = model(graph(
+        vec![tensor("X", &[1, 2, 5])],
+        vec![tensor("Y", &[1, 2, 6])],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["X"],
+            vec!["Y"],
+            "pool",
+            "MaxPool",
+            vec![
+                attribute("kernel_shape", vec![2]),
+                attribute("pads", vec![1, 1]),
+            ],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(pool_model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    // Padded positions are skipped, so the first and last windows only contain a single value
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[1., 3., 3., 5., 5., 4., -1., -1., -2., -2., -4., -4.],
+    );
+}
+
+#[test]
+fn average_pool_3d() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..64).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Four channels, so that the vectorized implementation is used
+    let pool_model = model(graph(
+        vec![tensor("X", &[1, 4, 2, 2, 4])],
+        vec![tensor("Y", &[1, 4, 2, 1, 2])],
+        vec![],
+        vec![],
+        vec![node(
+            vec!["X"],
+            vec!["Y"],
+            "pool",
+            "AveragePool",
+            vec![
+                attribute("kernel_shape", vec![1, 2, 2]),
+                attribute("strides", vec![1, 1, 2]),
+            ],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(pool_model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    let expected: Vec<f32> = (0..4)
+        .flat_map(|c| [2.5, 4.5, 10.5, 12.5].map(|x| x + 16. * c as f32))
+        .collect();
+    common::assert_eq_vector((&result["Y"]).try_into().unwrap(), &expected);
+}
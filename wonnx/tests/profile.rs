@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use wonnx::{
+    onnx::TensorProto_DataType,
+    utils::{graph, initializer_int64, model, model_with_opset, node, tensor, tensor_of_type},
+};
+
+#[test]
+fn test_profile() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = (0..16).map(|x| x as f32).collect();
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Model: X -> Relu -> Y -> Neg -> Z
+    let model = model(graph(
+        vec![tensor("X", &[4, 4])],
+        vec![tensor("Z", &[4, 4])],
+        vec![tensor("Y", &[4, 4])],
+        vec![],
+        vec![
+            node(vec!["X"], vec!["Y"], "relu", "Relu", vec![]),
+            node(vec!["Y"], vec!["Z"], "neg", "Neg", vec![]),
+        ],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let report = pollster::block_on(session.profile(&input_data)).unwrap();
+
+    let nodes: Vec<(&str, &str)> = report
+        .nodes
+        .iter()
+        .map(|node| (node.node_name.as_str(), node.op_type.as_str()))
+        .collect();
+    assert_eq!(nodes, vec![("relu", "Relu"), ("neg", "Neg")]);
+    assert_eq!(report.nodes[1].output_shapes[0].dims, vec![4, 4]);
+}
+
+#[test]
+fn test_profile_host_step() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    let boxes: Vec<f32> = vec![0.0, 0.0, 1.0, 1.0, 0.0, 0.1, 1.0, 1.1];
+    let scores: Vec<f32> = vec![0.9, 0.8];
+    input_data.insert("B".to_string(), boxes.as_slice().into());
+    input_data.insert("S".to_string(), scores.as_slice().into());
+
+    // Model: (B, S) -> Relu -> NonMaxSuppression -> Y
+    let model = model_with_opset(
+        graph(
+            vec![tensor("B", &[1, 2, 4]), tensor("S", &[1, 1, 2])],
+            vec![tensor_of_type("Y", &[2, 3], TensorProto_DataType::INT64)],
+            vec![tensor("R", &[1, 2, 4])],
+            vec![initializer_int64("M", vec![2], vec![1])],
+            vec![
+                node(vec!["B"], vec!["R"], "relu", "Relu", vec![]),
+                node(
+                    vec!["R", "S", "M"],
+                    vec!["Y"],
+                    "nms",
+                    "NonMaxSuppression",
+                    vec![],
+                ),
+            ],
+        ),
+        11,
+    );
+
+    // Steps performed on the host are included in the report
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    let report = pollster::block_on(session.profile(&input_data)).unwrap();
+    let nodes: Vec<(&str, bool)> = report
+        .nodes
+        .iter()
+        .map(|node| (node.node_name.as_str(), node.on_host))
+        .collect();
+    assert_eq!(nodes, vec![("relu", false), ("nms", true)]);
+    assert_eq!(report.nodes[1].output_shapes[0].dims, vec![2, 3]);
+}
@@ -0,0 +1,252 @@
+use std::{collections::HashMap, convert::TryInto};
+use wonnx::{
+    onnx::{ModelProto, TensorProto, TensorProto_DataType},
+    utils::{attribute, graph, initializer, model, node, tensor, tensor_of_type, OutputTensor},
+};
+mod common;
+
+/// Create an initializer with integer data
+fn int_initializer(name: &str, data: OutputTensor, dims: Vec<i64>) -> TensorProto {
+    let mut tensor = TensorProto::from(data, dims);
+    tensor.set_name(name.to_string());
+    tensor
+}
+
+fn run(
+    model: ModelProto,
+    input_data: &HashMap<String, wonnx::utils::InputTensor>,
+) -> HashMap<String, OutputTensor> {
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+    pollster::block_on(session.run(input_data)).unwrap()
+}
+
+#[test]
+fn test_quantize_dequantize() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![-1.0, 0.0, 0.3, 1.25, 2.5, 100.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Model: X -> QuantizeLinear -> Y (uint8 and int8) -> DequantizeLinear -> Z
+    let model = model(graph(
+        vec![tensor("X", &[6])],
+        vec![
+            tensor_of_type("Y", &[6], TensorProto_DataType::UINT8),
+            tensor_of_type("S", &[6], TensorProto_DataType::INT8),
+            tensor("Z", &[6]),
+        ],
+        vec![],
+        vec![
+            initializer("scale", vec![0.5], vec![]),
+            int_initializer("zero_point", OutputTensor::U8(vec![3]), vec![]),
+            int_initializer("signed_zero_point", OutputTensor::I8(vec![-1]), vec![]),
+        ],
+        vec![
+            node(
+                vec!["X", "scale", "zero_point"],
+                vec!["Y"],
+                "quantize",
+                "QuantizeLinear",
+                vec![],
+            ),
+            node(
+                vec!["X", "scale", "signed_zero_point"],
+                vec!["S"],
+                "quantize_signed",
+                "QuantizeLinear",
+                vec![],
+            ),
+            node(
+                vec!["Y", "scale", "zero_point"],
+                vec!["Z"],
+                "dequantize",
+                "DequantizeLinear",
+                vec![],
+            ),
+        ],
+    ));
+
+    let result = run(model, &input_data);
+
+    // Values are rounded half to even and saturated
+    assert_eq!(result["Y"], OutputTensor::U8(vec![1, 3, 4, 5, 8, 203]));
+    assert_eq!(result["S"], OutputTensor::I8(vec![-3, -1, 0, 1, 4, 127]));
+    common::assert_eq_vector(
+        (&result["Z"]).try_into().unwrap(),
+        &[-1.0, 0.0, 0.5, 1.0, 2.5, 100.0],
+    );
+}
+
+#[test]
+fn test_dequantize_per_axis() {
+    let mut input_data = HashMap::new();
+    let data: Vec<i8> = vec![-4, 0, 4, -4, 0, 4];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    // Model: X (int8) -> DequantizeLinear (scale and zero point per row) -> Y
+    let model = model(graph(
+        vec![tensor_of_type("X", &[2, 3], TensorProto_DataType::INT8)],
+        vec![tensor("Y", &[2, 3])],
+        vec![],
+        vec![
+            initializer("scale", vec![0.5, 2.0], vec![2]),
+            int_initializer("zero_point", OutputTensor::I8(vec![0, 4]), vec![2]),
+        ],
+        vec![node(
+            vec!["X", "scale", "zero_point"],
+            vec!["Y"],
+            "dequantize",
+            "DequantizeLinear",
+            vec![attribute("axis", 0)],
+        )],
+    ));
+
+    let result = run(model, &input_data);
+    common::assert_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[-2.0, 0.0, 2.0, -16.0, -8.0, 0.0],
+    );
+}
+
+#[test]
+fn test_matmul_integer() {
+    let mut input_data = HashMap::new();
+    let a: Vec<u8> = vec![1, 2, 3, 4, 5, 6];
+    input_data.insert("A".to_string(), a.as_slice().into());
+
+    // Model: (A, B) -> MatMulInteger -> Y, (A, B) -> QLinearMatMul -> Q
+    let model = model(graph(
+        vec![tensor_of_type("A", &[2, 3], TensorProto_DataType::UINT8)],
+        vec![
+            tensor_of_type("Y", &[2, 2], TensorProto_DataType::INT32),
+            tensor_of_type("Q", &[2, 2], TensorProto_DataType::INT8),
+        ],
+        vec![],
+        vec![
+            int_initializer("B", OutputTensor::I8(vec![1, -1, 2, 0, -3, 1]), vec![3, 2]),
+            initializer("a_scale", vec![0.5], vec![]),
+            int_initializer("a_zero_point", OutputTensor::U8(vec![1]), vec![]),
+            initializer("b_scale", vec![0.25], vec![]),
+            int_initializer("b_zero_point", OutputTensor::I8(vec![-1]), vec![]),
+            initializer("y_scale", vec![0.0625], vec![]),
+            int_initializer("y_zero_point", OutputTensor::I8(vec![0]), vec![]),
+        ],
+        vec![
+            node(
+                vec!["A", "B", "a_zero_point", "b_zero_point"],
+                vec!["Y"],
+                "matmul_integer",
+                "MatMulInteger",
+                vec![],
+            ),
+            node(
+                vec![
+                    "A",
+                    "a_scale",
+                    "a_zero_point",
+                    "B",
+                    "b_scale",
+                    "b_zero_point",
+                    "y_scale",
+                    "y_zero_point",
+                ],
+                vec!["Q"],
+                "qlinear_matmul",
+                "QLinearMatMul",
+                vec![],
+            ),
+        ],
+    ));
+
+    let result = run(model, &input_data);
+    assert_eq!(result["Y"], OutputTensor::I32(vec![-1, 5, 8, 14]));
+    assert_eq!(result["Q"], OutputTensor::I8(vec![-2, 10, 16, 28]));
+}
+
+#[test]
+fn test_qdq_conv() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+    let x: Vec<u8> = (0..9).collect();
+    input_data.insert("X".to_string(), x.as_slice().into());
+
+    // Model: X -> DequantizeLinear -> Conv (with dequantized weights and bias) -> QuantizeLinear -> Y. The optimizer
+    // fuses this into a single QLinearConv.
+    let model = model(graph(
+        vec![tensor_of_type(
+            "X",
+            &[1, 1, 3, 3],
+            TensorProto_DataType::UINT8,
+        )],
+        vec![tensor_of_type(
+            "Y",
+            &[1, 2, 2, 2],
+            TensorProto_DataType::UINT8,
+        )],
+        vec![
+            tensor("XD", &[1, 1, 3, 3]),
+            tensor("WD", &[2, 1, 2, 2]),
+            tensor("BD", &[2]),
+            tensor("C", &[1, 2, 2, 2]),
+        ],
+        vec![
+            initializer("x_scale", vec![0.5], vec![]),
+            int_initializer("x_zero_point", OutputTensor::U8(vec![2]), vec![]),
+            int_initializer(
+                "W",
+                OutputTensor::I8(vec![1, -1, 2, 0, 3, 1, -2, -1]),
+                vec![2, 1, 2, 2],
+            ),
+            initializer("w_scale", vec![0.25, 0.5], vec![2]),
+            int_initializer("w_zero_point", OutputTensor::I8(vec![0, 0]), vec![2]),
+            int_initializer("B", OutputTensor::I32(vec![4, -2]), vec![2]),
+            initializer("b_scale", vec![0.125, 0.25], vec![2]),
+            initializer("y_scale", vec![0.25], vec![]),
+            int_initializer("y_zero_point", OutputTensor::U8(vec![20]), vec![]),
+        ],
+        vec![
+            node(
+                vec!["X", "x_scale", "x_zero_point"],
+                vec!["XD"],
+                "dequantize_x",
+                "DequantizeLinear",
+                vec![],
+            ),
+            node(
+                vec!["W", "w_scale", "w_zero_point"],
+                vec!["WD"],
+                "dequantize_w",
+                "DequantizeLinear",
+                vec![attribute("axis", 0)],
+            ),
+            node(
+                vec!["B", "b_scale"],
+                vec!["BD"],
+                "dequantize_b",
+                "DequantizeLinear",
+                vec![attribute("axis", 0)],
+            ),
+            node(
+                vec!["XD", "WD", "BD"],
+                vec!["C"],
+                "conv",
+                "Conv",
+                vec![attribute("kernel_shape", vec![2, 2])],
+            ),
+            node(
+                vec!["C", "y_scale", "y_zero_point"],
+                vec!["Y"],
+                "quantize_y",
+                "QuantizeLinear",
+                vec![],
+            ),
+        ],
+    ));
+
+    let result = run(model, &input_data);
+    assert_eq!(
+        result["Y"],
+        OutputTensor::U8(vec![22, 24, 26, 26, 7, 8, 10, 11])
+    );
+}
@@ -0,0 +1,163 @@
+use std::{collections::HashMap, convert::TryInto};
+use wonnx::{
+    onnx::TensorProto_DataType,
+    utils::{attribute, graph, initializer, model, node, tensor, tensor_of_type},
+};
+mod common;
+
+#[test]
+fn lstm_initial_state() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![0.5, -0.5, 1.0, 0.25];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let data_w: Vec<f32> = (0..8).map(|x| (x % 3) as f32 * 0.2 - 0.2).collect();
+    let data_r: Vec<f32> = (0..16).map(|x| (x % 4) as f32 * 0.1 - 0.15).collect();
+    let data_b: Vec<f32> = (0..16).map(|x| (x % 3) as f32 * 0.05).collect();
+
+    // The sequence_lens input is not provided
+    let lstm_model = model(graph(
+        vec![tensor("X", &[2, 2, 1])],
+        vec![
+            tensor("Y", &[2, 1, 2, 2]),
+            tensor("Y_h", &[1, 2, 2]),
+            tensor("Y_c", &[1, 2, 2]),
+        ],
+        vec![],
+        vec![
+            initializer("W", data_w, vec![1, 8, 1]),
+            initializer("R", data_r, vec![1, 8, 2]),
+            initializer("B", data_b, vec![1, 16]),
+            initializer("H", vec![0.1, -0.1, 0.2, 0.0], vec![1, 2, 2]),
+            initializer("C", vec![0.5, -0.5, 0.0, 1.0], vec![1, 2, 2]),
+        ],
+        vec![node(
+            vec!["X", "W", "R", "B", "", "H", "C"],
+            vec!["Y", "Y_h", "Y_c"],
+            "lstm",
+            "LSTM",
+            vec![attribute("hidden_size", 2)],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(lstm_model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_approx_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[
+            0.137097, -0.126264, 0.045994, 0.274723, 0.042851, -0.06201, 0.033311, 0.178266,
+        ],
+        1e-5,
+    );
+    common::assert_approx_eq_vector(
+        (&result["Y_h"]).try_into().unwrap(),
+        &[0.042851, -0.06201, 0.033311, 0.178266],
+        1e-5,
+    );
+    common::assert_approx_eq_vector(
+        (&result["Y_c"]).try_into().unwrap(),
+        &[0.073614, -0.132139, 0.061229, 0.354792],
+        1e-5,
+    );
+}
+
+#[test]
+fn gru_bidirectional_sequence_lens() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1.0, 0.5, -1.0, 0.25, 0.5, -0.5];
+    let lengths: Vec<i32> = vec![3, 2];
+    input_data.insert("X".to_string(), data.as_slice().into());
+    input_data.insert("L".to_string(), lengths.as_slice().into());
+
+    let data_w: Vec<f32> = (0..12).map(|x| (x % 5) as f32 * 0.2 - 0.4).collect();
+    let data_r: Vec<f32> = (0..24).map(|x| (x % 7) as f32 * 0.1 - 0.3).collect();
+    let data_b: Vec<f32> = (0..24).map(|x| (x % 3) as f32 * 0.1 + 0.05).collect();
+
+    let gru_model = model(graph(
+        vec![
+            tensor("X", &[3, 2, 1]),
+            tensor_of_type("L", &[2], TensorProto_DataType::INT32),
+        ],
+        vec![tensor("Y", &[3, 2, 2, 2]), tensor("Y_h", &[2, 2, 2])],
+        vec![],
+        vec![
+            initializer("W", data_w, vec![2, 6, 1]),
+            initializer("R", data_r, vec![2, 6, 2]),
+            initializer("B", data_b, vec![2, 12]),
+        ],
+        vec![node(
+            vec!["X", "W", "R", "B", "L"],
+            vec!["Y", "Y_h"],
+            "gru",
+            "GRU",
+            vec![
+                attribute("hidden_size", 2),
+                attribute("direction", "bidirectional"),
+            ],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(gru_model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    // The second sequence has only two elements, so the output for the last step is zero (in both directions), and
+    // the reverse direction starts at the second element
+    common::assert_approx_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[
+            0.347175, 0.047344, 0.242602, 0.131139, 0.08666, 0.298255, 0.091691, 0.259292,
+            0.147942, 0.305824, 0.297402, 0.244272, 0.274637, 0.351646, 0.096221, 0.179542,
+            0.305524, 0.306039, 0.0, 0.0, 0.049834, 0.16169, 0.0, 0.0,
+        ],
+        1e-5,
+    );
+    common::assert_approx_eq_vector(
+        (&result["Y_h"]).try_into().unwrap(),
+        &[
+            0.305524, 0.306039, 0.297402, 0.244272, 0.08666, 0.298255, 0.091691, 0.259292,
+        ],
+        1e-5,
+    );
+}
+
+#[test]
+fn rnn_reverse() {
+    let mut input_data = HashMap::new();
+    let data: Vec<f32> = vec![1.0, 2.0, -1.0, 0.5, 0.0, 1.0];
+    input_data.insert("X".to_string(), data.as_slice().into());
+
+    let rnn_model = model(graph(
+        vec![tensor("X", &[3, 1, 2])],
+        vec![tensor("Y", &[3, 1, 1, 2]), tensor("Y_h", &[1, 1, 2])],
+        vec![],
+        vec![
+            initializer("W", vec![0.5, -0.25, 0.25, 0.5], vec![1, 2, 2]),
+            initializer("R", vec![0.5, 0.0, -0.5, 1.0], vec![1, 2, 2]),
+        ],
+        vec![node(
+            vec!["X", "W", "R"],
+            vec!["Y", "Y_h"],
+            "rnn",
+            "RNN",
+            vec![
+                attribute("hidden_size", 2),
+                attribute("direction", "reverse"),
+                attribute("activations", vec!["Relu".to_string()]),
+            ],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(rnn_model)).expect("Session did not create");
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+
+    // The sequence is processed from the last element to the first, so Y_h is the output for the first element
+    common::assert_approx_eq_vector(
+        (&result["Y"]).try_into().unwrap(),
+        &[0.0, 1.75, 0.0, 0.5, 0.0, 0.5],
+        1e-6,
+    );
+    common::assert_approx_eq_vector((&result["Y_h"]).try_into().unwrap(), &[0.0, 1.75], 1e-6);
+}
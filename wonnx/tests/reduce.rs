@@ -1,7 +1,9 @@
 use std::{collections::HashMap, convert::TryInto};
 use wonnx::{
-    onnx::AttributeProto,
-    utils::{attribute, graph, initializer_int64, model, node, tensor},
+    onnx::{AttributeProto, TensorProto_DataType},
+    utils::{
+        attribute, graph, initializer_int64, model, node, tensor, tensor_of_type, OutputTensor,
+    },
 };
 mod common;
 
@@ -282,3 +284,149 @@ fn test_reduce_sum_with_axes_as_input() {
         &[4., 6., 12., 14., 20., 22.],
     );
 }
+
+#[test]
+fn test_arg_max_arg_min() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+
+    #[rustfmt::skip]
+    let data: &[f32] = &[
+        2., 1., 2.,
+        3., 3., 0.,
+    ];
+    input_data.insert("X".to_string(), data.into());
+
+    let cases: Vec<(&str, Vec<AttributeProto>, Vec<i64>, Vec<i64>)> = vec![
+        ("ArgMax", vec![], vec![1, 3], vec![1, 1, 0]),
+        ("ArgMax", vec![attribute("axis", 1)], vec![2, 1], vec![0, 0]),
+        (
+            "ArgMax",
+            vec![attribute("axis", -1), attribute("select_last_index", 1)],
+            vec![2, 1],
+            vec![2, 1],
+        ),
+        (
+            "ArgMin",
+            vec![attribute("axis", 1), attribute("keepdims", 0)],
+            vec![2],
+            vec![1, 2],
+        ),
+    ];
+
+    for (op, attributes, output_shape, expected) in cases {
+        // Model: X -> ArgMax/ArgMin -> Y
+        let model = model(graph(
+            vec![tensor("X", &[2, 3])],
+            vec![tensor_of_type(
+                "Y",
+                &output_shape,
+                TensorProto_DataType::INT64,
+            )],
+            vec![],
+            vec![],
+            vec![node(vec!["X"], vec!["Y"], "myArg", op, attributes)],
+        ));
+
+        let session =
+            pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+
+        let result = pollster::block_on(session.run(&input_data)).unwrap();
+        assert_eq!(result["Y"], OutputTensor::I64(expected));
+    }
+}
+
+// Test case adapted from https://github.com/onnx/onnx/blob/main/docs/Operators.md#TopK
+#[test]
+fn test_top_k() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+
+    #[rustfmt::skip]
+    let data: &[f32] = &[
+        0., 1., 2., 3.,
+        4., 5., 6., 7.,
+        11., 10., 9., 8.,
+    ];
+    input_data.insert("X".to_string(), data.into());
+
+    let cases: Vec<(Vec<AttributeProto>, Vec<f32>, Vec<i64>)> = vec![
+        (
+            vec![],
+            vec![3., 2., 1., 7., 6., 5., 11., 10., 9.],
+            vec![3, 2, 1, 3, 2, 1, 0, 1, 2],
+        ),
+        (
+            vec![attribute("largest", 0)],
+            vec![0., 1., 2., 4., 5., 6., 8., 9., 10.],
+            vec![0, 1, 2, 0, 1, 2, 3, 2, 1],
+        ),
+    ];
+
+    for (attributes, expected_values, expected_indices) in cases {
+        // Model: X, K -> TopK -> Values, Indices
+        let model = model(graph(
+            vec![tensor("X", &[3, 4])],
+            vec![
+                tensor("Values", &[3, 3]),
+                tensor_of_type("Indices", &[3, 3], TensorProto_DataType::INT64),
+            ],
+            vec![],
+            vec![initializer_int64("K", vec![3], vec![1])],
+            vec![node(
+                vec!["X", "K"],
+                vec!["Values", "Indices"],
+                "myTopK",
+                "TopK",
+                attributes,
+            )],
+        ));
+
+        let session =
+            pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+
+        let result = pollster::block_on(session.run(&input_data)).unwrap();
+        common::assert_eq_vector((&result["Values"]).try_into().unwrap(), &expected_values);
+        assert_eq!(result["Indices"], OutputTensor::I64(expected_indices));
+    }
+}
+
+// TopK along the first axis of a tensor with equal values, which must be ordered by their index
+#[test]
+fn test_top_k_axis_ties() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut input_data = HashMap::new();
+
+    #[rustfmt::skip]
+    let data: &[f32] = &[
+        1., 5.,
+        3., 5.,
+        3., 2.,
+    ];
+    input_data.insert("X".to_string(), data.into());
+
+    // Model: X, K -> TopK -> Values, Indices
+    let model = model(graph(
+        vec![tensor("X", &[3, 2])],
+        vec![
+            tensor("Values", &[2, 2]),
+            tensor_of_type("Indices", &[2, 2], TensorProto_DataType::INT64),
+        ],
+        vec![],
+        vec![initializer_int64("K", vec![2], vec![1])],
+        vec![node(
+            vec!["X", "K"],
+            vec!["Values", "Indices"],
+            "myTopK",
+            "TopK",
+            vec![attribute("axis", 0)],
+        )],
+    ));
+
+    let session =
+        pollster::block_on(wonnx::Session::from_model(model)).expect("Session did not create");
+
+    let result = pollster::block_on(session.run(&input_data)).unwrap();
+    common::assert_eq_vector((&result["Values"]).try_into().unwrap(), &[3., 5., 3., 5.]);
+    assert_eq!(result["Indices"], OutputTensor::I64(vec![1, 0, 2, 1]));
+}